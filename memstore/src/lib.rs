@@ -4,17 +4,25 @@
 mod test;
 
 use std::cmp::max;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::hash::Hash;
+use std::hash::Hasher;
 use std::io::Cursor;
 use std::ops::RangeBounds;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
 
 use openraft::async_trait::async_trait;
 use openraft::raft::Entry;
 use openraft::raft::EntryPayload;
+use openraft::storage::CheckpointHandle;
 use openraft::storage::HardState;
 use openraft::storage::InitialState;
 use openraft::storage::Snapshot;
@@ -27,10 +35,12 @@ use openraft::LogId;
 use openraft::NodeId;
 use openraft::RaftStorage;
 use openraft::RaftStorageDebug;
+use openraft::SnapshotId;
 use openraft::SnapshotMeta;
 use openraft::StateMachineChanges;
 use openraft::StorageError;
 use openraft::StorageIOError;
+use openraft::TransientStorageError;
 use serde::Deserialize;
 use serde::Serialize;
 use tokio::sync::RwLock;
@@ -54,10 +64,19 @@ pub struct ClientRequest {
 impl AppData for ClientRequest {}
 
 /// The application data response type which the `MemStore` works with.
+///
+/// It is the previous status of the client, or an error message if the request is rejected.
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct ClientResponse(Option<String>);
+pub struct ClientResponse(Result<Option<String>, String>);
 
-impl AppDataResponse for ClientResponse {}
+impl AppDataResponse for ClientResponse {
+    fn application_error(&self) -> Option<Box<dyn std::error::Error + Send + Sync>> {
+        match &self.0 {
+            Ok(_) => None,
+            Err(msg) => Some(msg.clone().into()),
+        }
+    }
+}
 
 /// The application snapshot type which the `MemStore` works with.
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -87,10 +106,39 @@ pub struct MemStore {
     id: NodeId,
     /// The Raft log.
     log: RwLock<BTreeMap<u64, Entry<ClientRequest>>>,
+    /// The size of the logs in `log`, updated along with it for `log_size_bytes()`.
+    log_bytes: AtomicU64,
     /// The Raft state machine.
     sm: RwLock<MemStoreStateMachine>,
     /// The current hard state.
     hs: RwLock<Option<HardState>>,
+    /// The application defined node-local metadata.
+    node_metadata: RwLock<Option<Vec<u8>>>,
+    /// The time it takes to append logs, to simulate a slow disk.
+    append_delay: Mutex<Duration>,
+    /// The time it takes to apply logs to the state machine, to simulate a slow state machine.
+    apply_delay: Mutex<Duration>,
+    /// The time applying logs blocks the calling thread, to simulate a CPU heavy state machine.
+    apply_busy: Mutex<Duration>,
+    /// The max size of `ClientRequest::status` accepted by `validate_entry()`. `None` means no limit.
+    max_status_size: Mutex<Option<usize>>,
+    /// The index of a log `get_log_entries()` leaves out of its result, to simulate a buggy store.
+    hidden_log_index: Mutex<Option<u64>>,
+    /// The number of calls to read log entries, i.e., `get_log_entries()`, `try_get_log_entries()`,
+    /// `try_get_log_entry()` and `get_log_id()`.
+    log_read_count: AtomicU64,
+    /// The number of logs written by `append_to_log()` and `append_and_save_hard_state()`.
+    log_append_count: AtomicU64,
+    /// The time it takes to build a snapshot from a checkpoint, to simulate a large state machine.
+    snapshot_build_delay: Mutex<Duration>,
+    /// The number of the following calls to `apply_to_state_machine()` that fail with a transient error.
+    apply_faults: AtomicU64,
+    /// Whether `apply_to_state_machine()` panics, to simulate a buggy state machine.
+    apply_panic: AtomicBool,
+    /// The number of logs applied to the state machine.
+    apply_count: AtomicU64,
+    /// The number of calls to `apply_to_state_machine()` that apply logs.
+    apply_call_count: AtomicU64,
 
     snapshot_idx: Arc<Mutex<u64>>,
     /// The current snapshot.
@@ -110,20 +158,191 @@ impl MemStore {
             let mut l = log.write().await;
             l.insert(0, Entry {
                 log_id: LogId::default(),
+                timestamp_ms: 0,
                 payload: EntryPayload::Blank,
             });
         }
+        let log_bytes = AtomicU64::new(log.read().await.values().map(entry_size).sum());
 
         Self {
             id,
             log,
+            log_bytes,
             sm,
             hs,
+            node_metadata: RwLock::new(None),
+            append_delay: Mutex::new(Duration::from_millis(0)),
+            apply_delay: Mutex::new(Duration::from_millis(0)),
+            apply_busy: Mutex::new(Duration::from_millis(0)),
+            max_status_size: Mutex::new(None),
+            hidden_log_index: Mutex::new(None),
+            log_read_count: AtomicU64::new(0),
+            log_append_count: AtomicU64::new(0),
+            snapshot_build_delay: Mutex::new(Duration::from_millis(0)),
+            apply_faults: AtomicU64::new(0),
+            apply_panic: AtomicBool::new(false),
+            apply_count: AtomicU64::new(0),
+            apply_call_count: AtomicU64::new(0),
             snapshot_idx: Arc::new(Mutex::new(0)),
             current_snapshot,
         }
     }
 
+    /// Delay every call to `append_to_log()` and `append_and_save_hard_state()` by `delay`, to simulate a slow disk
+    /// (for testing).
+    pub fn set_append_delay(&self, delay: Duration) {
+        *self.append_delay.lock().unwrap() = delay;
+    }
+
+    /// Delay every call to `apply_to_state_machine()` by `delay`, to simulate a slow state machine (for testing).
+    pub fn set_apply_delay(&self, delay: Duration) {
+        *self.apply_delay.lock().unwrap() = delay;
+    }
+
+    /// Block the calling thread for `busy` in every call to `apply_to_state_machine()`, to simulate a CPU heavy
+    /// state machine (for testing).
+    pub fn set_apply_busy(&self, busy: Duration) {
+        *self.apply_busy.lock().unwrap() = busy;
+    }
+
+    /// Reject a client request whose `status` is larger than `size` bytes in `validate_entry()` (for testing).
+    pub fn set_max_status_size(&self, size: Option<usize>) {
+        *self.max_status_size.lock().unwrap() = size;
+    }
+
+    /// Make `get_log_entries()` leave out the log at `index`, to simulate a store that drops entries (for testing).
+    pub fn set_hidden_log_index(&self, index: Option<u64>) {
+        *self.hidden_log_index.lock().unwrap() = index;
+    }
+
+    /// Returns the number of calls to read log entries so far (for testing).
+    pub fn log_read_count(&self) -> u64 {
+        self.log_read_count.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of logs appended so far, including logs that override existing ones (for testing).
+    pub fn log_append_count(&self) -> u64 {
+        self.log_append_count.load(Ordering::Relaxed)
+    }
+
+    /// Delay building every snapshot by `delay`, to simulate a large state machine (for testing).
+    pub fn set_snapshot_build_delay(&self, delay: Duration) {
+        *self.snapshot_build_delay.lock().unwrap() = delay;
+    }
+
+    /// Make the next `n` calls to `apply_to_state_machine()` fail with a transient error, without applying any log
+    /// (for testing).
+    pub fn set_apply_faults(&self, n: u64) {
+        self.apply_faults.store(n, Ordering::Relaxed);
+    }
+
+    /// Make every call to `apply_to_state_machine()` panic (for testing).
+    pub fn set_apply_panic(&self, panic: bool) {
+        self.apply_panic.store(panic, Ordering::Relaxed);
+    }
+
+    /// Returns the number of logs applied to the state machine so far (for testing).
+    pub fn apply_count(&self) -> u64 {
+        self.apply_count.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of calls to `apply_to_state_machine()` that applied logs so far (for testing).
+    pub fn apply_call_count(&self) -> u64 {
+        self.apply_call_count.load(Ordering::Relaxed)
+    }
+
+    /// Returns the hard state and the last log id as they are at this instant, i.e., what a crash would leave in the
+    /// store (for testing).
+    pub async fn crash_state(&self) -> (Option<HardState>, LogId) {
+        let h = self.hs.read().await;
+        let log = self.log.read().await;
+
+        let last = log.values().next_back().map(|x| x.log_id).unwrap_or_default();
+        (h.clone(), last)
+    }
+
+    /// Overwrite the status of a client in the state machine without a log, to simulate a diverged replica (for
+    /// testing).
+    pub async fn set_client_status(&self, client: &str, status: &str) {
+        let mut sm = self.sm.write().await;
+        sm.client_status.insert(client.to_string(), status.to_string());
+    }
+
+    /// Sleep for the delay set by `set_append_delay()`.
+    async fn delay_append(&self) {
+        let delay = *self.append_delay.lock().unwrap();
+        if delay > Duration::from_millis(0) {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Insert a log into `log`, replacing the one at the same index if there is one, and account its size.
+    fn insert_log(&self, log: &mut BTreeMap<u64, Entry<ClientRequest>>, entry: Entry<ClientRequest>) {
+        self.log_bytes.fetch_add(entry_size(&entry), Ordering::Relaxed);
+        if let Some(prev) = log.insert(entry.log_id.index, entry) {
+            self.log_bytes.fetch_sub(entry_size(&prev), Ordering::Relaxed);
+        }
+    }
+
+    /// Take a consistent copy of the state machine to build a snapshot from.
+    ///
+    /// The state machine is locked only while it is copied, thus applying logs is not blocked by building a snapshot.
+    pub async fn checkpoint(&self) -> MemStoreStateMachine {
+        self.sm.read().await.clone()
+    }
+
+    /// Serialize `sm`, a checkpoint of the state machine, into a snapshot and make it the current snapshot.
+    async fn build_snapshot(&self, sm: MemStoreStateMachine) -> Result<Snapshot<Cursor<Vec<u8>>>, StorageError> {
+        let (data, last_applied_log);
+
+        {
+            let delay = *self.snapshot_build_delay.lock().unwrap();
+            if delay > Duration::from_millis(0) {
+                tokio::time::sleep(delay).await;
+            }
+
+            // Serialize the data of the state machine.
+            data = serde_json::to_vec(&sm)
+                .map_err(|e| StorageIOError::new(ErrorSubject::StateMachine, ErrorVerb::Read, e.into()))?;
+
+            last_applied_log = sm.last_applied_log;
+        }
+
+        let snapshot_size = data.len();
+
+        let snapshot_idx = {
+            let mut l = self.snapshot_idx.lock().unwrap();
+            *l += 1;
+            *l
+        };
+
+        let meta;
+        {
+            let mut current_snapshot = self.current_snapshot.write().await;
+
+            let snapshot_id = SnapshotId::new(last_applied_log, snapshot_idx, self.id);
+
+            meta = SnapshotMeta {
+                last_log_id: last_applied_log,
+                snapshot_id,
+                base_snapshot_id: None,
+            };
+
+            let snapshot = MemStoreSnapshot {
+                meta: meta.clone(),
+                data: data.clone(),
+            };
+
+            *current_snapshot = Some(snapshot);
+        } // Release log & snapshot write locks.
+
+        tracing::info!({ snapshot_size = snapshot_size }, "log compaction complete");
+        Ok(Snapshot {
+            meta,
+            snapshot: Box::new(Cursor::new(data)),
+        })
+    }
+
     /// Create a new `MemStore` instance with some existing state (for testing).
     #[cfg(test)]
     pub fn new_with_state(
@@ -133,6 +352,7 @@ impl MemStore {
         hs: Option<HardState>,
         current_snapshot: Option<MemStoreSnapshot>,
     ) -> Self {
+        let log_bytes = AtomicU64::new(log.values().map(entry_size).sum());
         let log = RwLock::new(log);
         let sm = RwLock::new(sm);
         let hs = RwLock::new(hs);
@@ -140,8 +360,22 @@ impl MemStore {
         Self {
             id,
             log,
+            log_bytes,
             sm,
             hs,
+            node_metadata: RwLock::new(None),
+            append_delay: Mutex::new(Duration::from_millis(0)),
+            apply_delay: Mutex::new(Duration::from_millis(0)),
+            apply_busy: Mutex::new(Duration::from_millis(0)),
+            max_status_size: Mutex::new(None),
+            hidden_log_index: Mutex::new(None),
+            log_read_count: AtomicU64::new(0),
+            log_append_count: AtomicU64::new(0),
+            snapshot_build_delay: Mutex::new(Duration::from_millis(0)),
+            apply_faults: AtomicU64::new(0),
+            apply_panic: AtomicBool::new(false),
+            apply_count: AtomicU64::new(0),
+            apply_call_count: AtomicU64::new(0),
             snapshot_idx: Arc::new(Mutex::new(0)),
             current_snapshot,
         }
@@ -206,14 +440,68 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
         Ok(self.hs.read().await.clone())
     }
 
+    #[tracing::instrument(level = "trace", skip(self, meta))]
+    async fn save_node_metadata(&self, meta: &[u8]) -> Result<(), StorageError> {
+        let mut m = self.node_metadata.write().await;
+        *m = Some(meta.to_vec());
+        Ok(())
+    }
+
+    async fn read_node_metadata(&self) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.node_metadata.read().await.clone())
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, data))]
+    async fn validate_entry(&self, data: &ClientRequest) -> Result<(), StorageError> {
+        let max = *self.max_status_size.lock().unwrap();
+
+        if let Some(max) = max {
+            if data.status.len() > max {
+                return Err(StorageIOError::new(
+                    ErrorSubject::Store,
+                    ErrorVerb::Write,
+                    anyhow::anyhow!("status too large: {} > {}", data.status.len(), max),
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
     async fn get_log_entries<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
         &self,
         range: RNG,
     ) -> Result<Vec<Entry<ClientRequest>>, StorageError> {
+        self.log_read_count.fetch_add(1, Ordering::Relaxed);
+        let hidden = *self.hidden_log_index.lock().unwrap();
+
         let res = {
             let log = self.log.read().await;
-            log.range(range.clone()).map(|(_, val)| val.clone()).collect::<Vec<_>>()
+            log.range(range.clone())
+                .filter(|(index, _)| Some(**index) != hidden)
+                .map(|(_, val)| val.clone())
+                .collect::<Vec<_>>()
+        };
+
+        Ok(res)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn get_log_entries_rev<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
+        &self,
+        range: RNG,
+    ) -> Result<Vec<Entry<ClientRequest>>, StorageError> {
+        self.log_read_count.fetch_add(1, Ordering::Relaxed);
+        let hidden = *self.hidden_log_index.lock().unwrap();
+
+        let res = {
+            let log = self.log.read().await;
+            log.range(range.clone())
+                .rev()
+                .filter(|(index, _)| Some(**index) != hidden)
+                .map(|(_, val)| val.clone())
+                .collect::<Vec<_>>()
         };
 
         Ok(res)
@@ -223,6 +511,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
         &self,
         range: RNG,
     ) -> Result<Vec<Entry<ClientRequest>>, StorageError> {
+        self.log_read_count.fetch_add(1, Ordering::Relaxed);
         let res = {
             let log = self.log.read().await;
             log.range(range.clone()).map(|(_, val)| val.clone()).collect::<Vec<_>>()
@@ -233,10 +522,18 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
 
     #[tracing::instrument(level = "trace", skip(self))]
     async fn try_get_log_entry(&self, log_index: u64) -> Result<Option<Entry<ClientRequest>>, StorageError> {
+        self.log_read_count.fetch_add(1, Ordering::Relaxed);
         let log = self.log.read().await;
         Ok(log.get(&log_index).cloned())
     }
 
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn get_log_id(&self, log_index: u64) -> Result<Option<LogId>, StorageError> {
+        self.log_read_count.fetch_add(1, Ordering::Relaxed);
+        let log = self.log.read().await;
+        Ok(log.get(&log_index).map(|ent| ent.log_id))
+    }
+
     async fn first_id_in_log(&self) -> Result<Option<LogId>, StorageError> {
         let log = self.log.read().await;
         let first = log.iter().next().map(|(_, ent)| ent.log_id);
@@ -260,6 +557,40 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
         Ok(last)
     }
 
+    async fn log_size_bytes(&self) -> Result<Option<u64>, StorageError> {
+        Ok(Some(self.log_bytes.load(Ordering::Relaxed)))
+    }
+
+    async fn state_checksum_at(&self, log_id: LogId) -> Result<Option<u64>, StorageError> {
+        let sm = self.sm.read().await;
+        if sm.last_applied_log != log_id {
+            return Ok(None);
+        }
+
+        // The client states are kept in `HashMap`s, sort them to get the same checksum on every replica.
+        let mut hasher = DefaultHasher::new();
+        sm.last_applied_log.term.hash(&mut hasher);
+        sm.last_applied_log.index.hash(&mut hasher);
+        sm.client_serial_responses.iter().collect::<BTreeMap<_, _>>().hash(&mut hasher);
+        sm.client_status.iter().collect::<BTreeMap<_, _>>().hash(&mut hasher);
+
+        Ok(Some(hasher.finish()))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, payload))]
+    async fn rewrite_log_entry(&self, log_id: LogId, payload: ClientRequest) -> Result<bool, StorageError> {
+        let mut log = self.log.write().await;
+        let entry = match log.get(&log_id.index) {
+            Some(ent) if ent.log_id == log_id => Entry {
+                payload: EntryPayload::Normal(payload),
+                ..ent.clone()
+            },
+            _ => return Ok(false),
+        };
+        self.insert_log(&mut log, entry);
+        Ok(true)
+    }
+
     async fn last_applied_state(&self) -> Result<(LogId, Option<EffectiveMembership>), StorageError> {
         let sm = self.sm.read().await;
         Ok((sm.last_applied_log, sm.last_membership.clone()))
@@ -277,7 +608,9 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
 
             let keys = log.range(range).map(|(k, _v)| *k).collect::<Vec<_>>();
             for key in keys {
-                log.remove(&key);
+                if let Some(ent) = log.remove(&key) {
+                    self.log_bytes.fetch_sub(entry_size(&ent), Ordering::Relaxed);
+                }
             }
         }
 
@@ -286,10 +619,33 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
 
     #[tracing::instrument(level = "trace", skip(self, entries))]
     async fn append_to_log(&self, entries: &[&Entry<ClientRequest>]) -> Result<(), StorageError> {
+        self.delay_append().await;
+
         let mut log = self.log.write().await;
         for entry in entries {
-            log.insert(entry.log_id.index, (*entry).clone());
+            self.insert_log(&mut log, (*entry).clone());
         }
+        self.log_append_count.fetch_add(entries.len() as u64, Ordering::Relaxed);
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, entries))]
+    async fn append_and_save_hard_state(
+        &self,
+        entries: &[&Entry<ClientRequest>],
+        hs: &HardState,
+    ) -> Result<(), StorageError> {
+        self.delay_append().await;
+
+        // Hold both locks so that no one sees one write without the other, just like a transaction.
+        let mut h = self.hs.write().await;
+        let mut log = self.log.write().await;
+
+        *h = Some(hs.clone());
+        for entry in entries {
+            self.insert_log(&mut log, (*entry).clone());
+        }
+        self.log_append_count.fetch_add(entries.len() as u64, Ordering::Relaxed);
         Ok(())
     }
 
@@ -298,33 +654,63 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
         &self,
         entries: &[&Entry<ClientRequest>],
     ) -> Result<Vec<ClientResponse>, StorageError> {
+        let delay = *self.apply_delay.lock().unwrap();
+        if delay > Duration::from_millis(0) {
+            tokio::time::sleep(delay).await;
+        }
+
+        let busy = *self.apply_busy.lock().unwrap();
+        if busy > Duration::from_millis(0) {
+            std::thread::sleep(busy);
+        }
+
+        let faults = self.apply_faults.load(Ordering::Relaxed);
+        if faults > 0 {
+            self.apply_faults.store(faults - 1, Ordering::Relaxed);
+            let err = TransientStorageError(anyhow::anyhow!("injected apply fault"));
+            return Err(StorageIOError::new(ErrorSubject::StateMachine, ErrorVerb::Write, err.into()).into());
+        }
+
+        if self.apply_panic.load(Ordering::Relaxed) {
+            panic!("injected apply panic on node {}", self.id);
+        }
+
+        self.apply_call_count.fetch_add(1, Ordering::Relaxed);
+
         let mut sm = self.sm.write().await;
         let mut res = Vec::with_capacity(entries.len());
 
         for entry in entries {
             tracing::debug!("id:{} replicate to sm index:{}", self.id, entry.log_id.index);
 
+            self.apply_count.fetch_add(1, Ordering::Relaxed);
+
             sm.last_applied_log = entry.log_id;
 
             match entry.payload {
-                EntryPayload::Blank => res.push(ClientResponse(None)),
+                EntryPayload::Blank => res.push(ClientResponse(Ok(None))),
                 EntryPayload::Normal(ref data) => {
+                    // An empty status is a client error. It is rejected without changing the state machine.
+                    if data.status.is_empty() {
+                        res.push(ClientResponse(Err(format!("empty status from client {}", data.client))));
+                        continue;
+                    }
                     if let Some((serial, r)) = sm.client_serial_responses.get(&data.client) {
                         if serial == &data.serial {
-                            res.push(ClientResponse(r.clone()));
+                            res.push(ClientResponse(Ok(r.clone())));
                             continue;
                         }
                     }
                     let previous = sm.client_status.insert(data.client.clone(), data.status.clone());
                     sm.client_serial_responses.insert(data.client.clone(), (data.serial, previous.clone()));
-                    res.push(ClientResponse(previous));
+                    res.push(ClientResponse(Ok(previous)));
                 }
                 EntryPayload::Membership(ref mem) => {
                     sm.last_membership = Some(EffectiveMembership {
                         log_id: entry.log_id,
                         membership: mem.clone(),
                     });
-                    res.push(ClientResponse(None))
+                    res.push(ClientResponse(Ok(None)))
                 }
             };
         }
@@ -333,49 +719,26 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
 
     #[tracing::instrument(level = "trace", skip(self))]
     async fn do_log_compaction(&self) -> Result<Snapshot<Self::SnapshotData>, StorageError> {
-        let (data, last_applied_log);
+        // Build the snapshot from a checkpoint, so that logs are applied while serializing.
+        let sm = self.checkpoint().await;
+        self.build_snapshot(sm).await
+    }
 
-        {
-            // Serialize the data of the state machine.
-            let sm = self.sm.read().await;
-            data = serde_json::to_vec(&*sm)
-                .map_err(|e| StorageIOError::new(ErrorSubject::StateMachine, ErrorVerb::Read, e.into()))?;
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn begin_checkpoint(&self) -> Result<CheckpointHandle, StorageError> {
+        let sm = self.checkpoint().await;
+        Ok(CheckpointHandle::new(sm.last_applied_log, sm))
+    }
 
-            last_applied_log = sm.last_applied_log;
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn do_log_compaction_from(
+        &self,
+        checkpoint: CheckpointHandle,
+    ) -> Result<Snapshot<Self::SnapshotData>, StorageError> {
+        match checkpoint.into_view::<MemStoreStateMachine>() {
+            Some(sm) => self.build_snapshot(sm).await,
+            None => self.do_log_compaction().await,
         }
-
-        let snapshot_size = data.len();
-
-        let snapshot_idx = {
-            let mut l = self.snapshot_idx.lock().unwrap();
-            *l += 1;
-            *l
-        };
-
-        let meta;
-        {
-            let mut current_snapshot = self.current_snapshot.write().await;
-
-            let snapshot_id = format!("{}-{}-{}", last_applied_log.term, last_applied_log.index, snapshot_idx);
-
-            meta = SnapshotMeta {
-                last_log_id: last_applied_log,
-                snapshot_id,
-            };
-
-            let snapshot = MemStoreSnapshot {
-                meta: meta.clone(),
-                data: data.clone(),
-            };
-
-            *current_snapshot = Some(snapshot);
-        } // Release log & snapshot write locks.
-
-        tracing::info!({ snapshot_size = snapshot_size }, "log compaction complete");
-        Ok(Snapshot {
-            meta,
-            snapshot: Box::new(Cursor::new(data)),
-        })
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
@@ -394,7 +757,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
             "decoding snapshot for installation"
         );
 
-        let new_snapshot = MemStoreSnapshot {
+        let mut new_snapshot = MemStoreSnapshot {
             meta: meta.clone(),
             data: snapshot.into_inner(),
         };
@@ -406,6 +769,9 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
             tracing::debug!("JSON SNAP DATA:{}", y);
         }
 
+        // Hold the snapshot lock until the state machine is updated, so that the base snapshot does not change.
+        let mut current_snapshot = self.current_snapshot.write().await;
+
         // Update the state machine.
         {
             let new_sm: MemStoreStateMachine = serde_json::from_slice(&new_snapshot.data).map_err(|e| {
@@ -416,15 +782,44 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
                 )
             })?;
             let mut sm = self.sm.write().await;
-            *sm = new_sm;
+
+            match &meta.base_snapshot_id {
+                None => {
+                    *sm = new_sm;
+                }
+                Some(base) => {
+                    // A delta snapshot contains only the client states changed since the base snapshot.
+                    let current_id = current_snapshot.as_ref().map(|x| &x.meta.snapshot_id);
+                    if current_id != Some(base) {
+                        return Err(StorageIOError::new(
+                            ErrorSubject::Snapshot(meta.clone()),
+                            ErrorVerb::Write,
+                            anyhow::anyhow!("base snapshot {} not found, current: {:?}", base, current_id),
+                        )
+                        .into());
+                    }
+
+                    sm.last_applied_log = new_sm.last_applied_log;
+                    if new_sm.last_membership.is_some() {
+                        sm.last_membership = new_sm.last_membership;
+                    }
+                    sm.client_serial_responses.extend(new_sm.client_serial_responses);
+                    sm.client_status.extend(new_sm.client_status);
+
+                    // The stored snapshot is always a full one.
+                    new_snapshot.meta.base_snapshot_id = None;
+                    new_snapshot.data = serde_json::to_vec(&*sm)
+                        .map_err(|e| StorageIOError::new(ErrorSubject::StateMachine, ErrorVerb::Read, e.into()))?;
+                }
+            }
         }
 
         // Update current snapshot.
-        let mut current_snapshot = self.current_snapshot.write().await;
         *current_snapshot = Some(new_snapshot);
         Ok(StateMachineChanges {
             last_applied: Some(meta.last_log_id),
             is_snapshot: true,
+            purge_upto: Some(meta.last_log_id),
         })
     }
 
@@ -443,4 +838,15 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
             None => Ok(None),
         }
     }
+
+    async fn list_snapshots(&self) -> Result<Vec<SnapshotMeta>, StorageError> {
+        // Only the current snapshot is kept: a new one replaces it.
+        let current_snapshot = self.current_snapshot.read().await;
+        Ok(current_snapshot.iter().map(|x| x.meta.clone()).collect())
+    }
+}
+
+/// The size of a log entry serialized with serde_json, as it is counted in `MemStore::log_bytes`.
+fn entry_size(entry: &Entry<ClientRequest>) -> u64 {
+    serde_json::to_vec(entry).map(|x| x.len() as u64).unwrap_or_default()
 }
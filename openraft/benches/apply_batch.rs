@@ -0,0 +1,78 @@
+//! Benchmark of `Config::max_apply_batch`: the throughput of a burst of concurrent writes to a leader whose state
+//! machine takes a fixed time for every call to apply logs, with and without adaptive batching.
+//!
+//! Run it with `make bench`, or `cargo bench -p openraft --bench apply_batch`.
+
+#![feature(test)]
+extern crate test;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::Wrapper;
+use test::Bencher;
+use tokio::runtime::Runtime;
+
+#[allow(unused_macros)]
+#[macro_use]
+#[path = "../tests/fixtures/mod.rs"]
+mod fixtures;
+
+const N_BURST: u64 = 64;
+
+#[bench]
+fn burst_unbatched(b: &mut Bencher) {
+    bench_burst(b, Config::default());
+}
+
+#[bench]
+fn burst_batched(b: &mut Bencher) {
+    bench_burst(b, Config {
+        max_apply_batch: Some(64),
+        ..Default::default()
+    });
+}
+
+fn bench_burst(b: &mut Bencher, config: Config) {
+    let rt = tokio::runtime::Builder::new_multi_thread().worker_threads(6).enable_all().build().unwrap();
+
+    let router = rt.block_on(new_cluster(config)).unwrap();
+
+    b.iter(|| burst(&rt, &router, N_BURST).unwrap());
+}
+
+/// Bring on a cluster of 3 voters, whose leader, node 0, takes 1 ms for every call to apply logs.
+async fn new_cluster(config: Config) -> Result<Arc<RaftRouter>> {
+    let router = Arc::new(RaftRouter::new(Arc::new(config.validate()?)));
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.get_storage_handle(&0).await?.inner().set_apply_delay(Duration::from_millis(1));
+
+    Ok(router)
+}
+
+/// Send `n` concurrent writes to the leader and wait until all of them are applied.
+fn burst(rt: &Runtime, router: &Arc<RaftRouter>, n: u64) -> Result<()> {
+    rt.block_on(async {
+        let raft = router.get_raft_handle(&0).await?;
+
+        let writes = (0..n).map(|serial| {
+            raft.client_write(ClientWriteRequest::new(ClientRequest {
+                client: "0".to_string(),
+                serial,
+                status: format!("request-{}", serial),
+            }))
+        });
+        for res in futures::future::join_all(writes).await {
+            res?;
+        }
+
+        Ok(())
+    })
+}
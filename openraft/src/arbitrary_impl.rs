@@ -0,0 +1,77 @@
+//! `Arbitrary` implementations of core types, enabled by the `test-utils` feature.
+//!
+//! They are meant for property testing and fuzzing a `RaftStorage` implementation. A generated value is a valid
+//! value of the type, e.g., a membership has one or two non-empty configs, but a sequence of generated values is not
+//! a valid log: building one is up to the test.
+
+use std::collections::BTreeSet;
+
+use arbitrary::Arbitrary;
+use arbitrary::Unstructured;
+
+use crate::raft::Entry;
+use crate::raft::EntryPayload;
+use crate::raft::Membership;
+use crate::storage::HardState;
+use crate::AppData;
+use crate::LogId;
+use crate::NodeId;
+
+/// Node ids are picked from a small range, so that the configs of a joint membership overlap.
+const MAX_NODE_ID: NodeId = 9;
+
+impl<'a> Arbitrary<'a> for LogId {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(LogId::new(u.arbitrary()?, u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for HardState {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(HardState {
+            current_term: u.arbitrary()?,
+            voted_for: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Membership {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let n_configs = u.int_in_range(1..=2)?;
+
+        let mut configs = Vec::with_capacity(n_configs);
+        for _ in 0..n_configs {
+            let mut config = BTreeSet::new();
+            config.insert(u.int_in_range(0..=MAX_NODE_ID)?);
+
+            let n_more = u.int_in_range(0..=4)?;
+            for _ in 0..n_more {
+                config.insert(u.int_in_range(0..=MAX_NODE_ID)?);
+            }
+            configs.push(config);
+        }
+
+        Ok(Membership::new_multi(configs))
+    }
+}
+
+impl<'a, D: AppData + Arbitrary<'a>> Arbitrary<'a> for EntryPayload<D> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let payload = match u.int_in_range(0..=2)? {
+            0 => EntryPayload::Blank,
+            1 => EntryPayload::Normal(u.arbitrary()?),
+            _ => EntryPayload::Membership(u.arbitrary()?),
+        };
+        Ok(payload)
+    }
+}
+
+impl<'a, D: AppData + Arbitrary<'a>> Arbitrary<'a> for Entry<D> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Entry {
+            log_id: u.arbitrary()?,
+            timestamp_ms: 0,
+            payload: u.arbitrary()?,
+        })
+    }
+}
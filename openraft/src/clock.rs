@@ -0,0 +1,21 @@
+use tokio::time::Instant;
+
+/// A source of monotonic time for the election timer.
+///
+/// Raft reads the time from it to decide when an election timeout is due and to detect a clock jump, e.g., when the
+/// process is paused and resumed by a VM migration. The default clock is `tokio::time::Instant::now()`. An
+/// application or a test may provide its own through `Config::clock`.
+pub trait Clock: Send + Sync + 'static {
+    /// Returns the current instant. It must never go backward.
+    fn now(&self) -> Instant;
+}
+
+/// The default `Clock`, i.e., the monotonic clock of the tokio runtime.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioClock;
+
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
@@ -0,0 +1,118 @@
+//! Serialization formats for log entries, snapshots and RPC messages.
+//!
+//! Openraft itself never serializes anything: a `RaftStorage` and a `RaftNetwork` implementation decide how to store
+//! logs and how to transmit messages. A `Codec` gives them a common way to do it, and `Config::codec` lets the
+//! application choose the format.
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::error::CodecError;
+
+/// Encodes a value into bytes and decodes it back.
+///
+/// Every node in a cluster has to use the same format for RPC messages, and a store has to read its data with the
+/// format it is written with.
+pub trait Codec: Send + Sync + 'static {
+    /// Encode a value into bytes.
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, CodecError>;
+
+    /// Decode a value from bytes built by `encode()`.
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T, CodecError>;
+
+    /// The size in bytes of a value encoded by `encode()`.
+    ///
+    /// The default implementation encodes the value. A codec should override it if it can tell the size without
+    /// building the bytes.
+    fn encoded_size<T: Serialize>(&self, value: &T) -> Result<u64, CodecError> {
+        self.encode(value).map(|x| x.len() as u64)
+    }
+}
+
+/// A human readable codec in JSON, for debugging.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        serde_json::to_vec(value).map_err(|e| CodecError::new(CodecType::Json, e))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T, CodecError> {
+        serde_json::from_slice(data).map_err(|e| CodecError::new(CodecType::Json, e))
+    }
+
+    fn encoded_size<T: Serialize>(&self, value: &T) -> Result<u64, CodecError> {
+        let mut counter = ByteCounter(0);
+        serde_json::to_writer(&mut counter, value).map_err(|e| CodecError::new(CodecType::Json, e))?;
+        Ok(counter.0)
+    }
+}
+
+/// A writer that discards data and counts the bytes written.
+struct ByteCounter(u64);
+
+impl std::io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A compact binary codec with bincode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BincodeCodec;
+
+impl Codec for BincodeCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        bincode::serialize(value).map_err(|e| CodecError::new(CodecType::Bincode, e))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T, CodecError> {
+        bincode::deserialize(data).map_err(|e| CodecError::new(CodecType::Bincode, e))
+    }
+
+    fn encoded_size<T: Serialize>(&self, value: &T) -> Result<u64, CodecError> {
+        bincode::serialized_size(value).map_err(|e| CodecError::new(CodecType::Bincode, e))
+    }
+}
+
+/// The built-in codecs, to choose one with `Config::codec`.
+///
+/// It is a `Codec` itself that dispatches to the chosen one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CodecType {
+    /// See `JsonCodec`.
+    Json,
+
+    /// See `BincodeCodec`.
+    Bincode,
+}
+
+impl Codec for CodecType {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        match self {
+            CodecType::Json => JsonCodec.encode(value),
+            CodecType::Bincode => BincodeCodec.encode(value),
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T, CodecError> {
+        match self {
+            CodecType::Json => JsonCodec.decode(data),
+            CodecType::Bincode => BincodeCodec.decode(data),
+        }
+    }
+
+    fn encoded_size<T: Serialize>(&self, value: &T) -> Result<u64, CodecError> {
+        match self {
+            CodecType::Json => JsonCodec.encoded_size(value),
+            CodecType::Bincode => BincodeCodec.encoded_size(value),
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::fmt::Debug;
+
+use maplit::btreeset;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::codec::BincodeCodec;
+use crate::codec::Codec;
+use crate::codec::CodecType;
+use crate::codec::JsonCodec;
+use crate::raft::AppendEntriesRequest;
+use crate::raft::AppendEntriesResponse;
+use crate::raft::Entry;
+use crate::raft::EntryPayload;
+use crate::raft::InstallSnapshotRequest;
+use crate::raft::InstallSnapshotResponse;
+use crate::raft::LogAuditResponse;
+use crate::raft::Membership;
+use crate::raft::VoteRejection;
+use crate::raft::VoteRequest;
+use crate::raft::VoteResponse;
+use crate::AppData;
+use crate::LogId;
+use crate::SnapshotId;
+use crate::SnapshotMeta;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Data {
+    key: String,
+    value: Option<u64>,
+}
+
+impl AppData for Data {}
+
+fn entries() -> Vec<Entry<Data>> {
+    vec![
+        Entry {
+            log_id: LogId::new(1, 1),
+            timestamp_ms: 0,
+            payload: EntryPayload::Blank,
+        },
+        Entry {
+            log_id: LogId::new(1, 2),
+            timestamp_ms: 1_640_995_200_000,
+            payload: EntryPayload::Normal(Data {
+                key: "foo".to_string(),
+                value: Some(3),
+            }),
+        },
+        Entry {
+            log_id: LogId::new(2, 3),
+            timestamp_ms: 0,
+            payload: EntryPayload::Membership(Membership::new_multi(vec![btreeset! {1,2}, btreeset! {2,3}])),
+        },
+    ]
+}
+
+/// Encode and decode a value, and compare their debug strings, since not every RPC type implements `PartialEq`.
+fn round_trip<C: Codec, T: Serialize + DeserializeOwned + Debug>(codec: &C, value: &T) -> anyhow::Result<()> {
+    let data = codec.encode(value)?;
+    let got: T = codec.decode(&data)?;
+    assert_eq!(format!("{:?}", value), format!("{:?}", got));
+    Ok(())
+}
+
+fn round_trip_all<C: Codec>(codec: &C) -> anyhow::Result<()> {
+    for ent in entries() {
+        let data = codec.encode(&ent)?;
+        let got: Entry<Data> = codec.decode(&data)?;
+        assert_eq!(ent, got);
+    }
+
+    round_trip(codec, &AppendEntriesRequest {
+        term: 2,
+        leader_id: 1,
+        prev_log_id: LogId::new(1, 1),
+        entries: entries(),
+        leader_commit: LogId::new(1, 2),
+        relay_to: vec![],
+    })?;
+
+    round_trip(codec, &AppendEntriesResponse {
+        term: 2,
+        matched: None,
+        conflict: Some(LogId::new(1, 5)),
+        last_log_id: Some(LogId::new(1, 7)),
+        instance_uuid: Some(u128::MAX),
+    })?;
+
+    round_trip(codec, &VoteRequest::new(3, 2, LogId::new(2, 3)))?;
+
+    round_trip(codec, &VoteResponse {
+        term: 3,
+        vote_granted: true,
+        last_log_id: LogId::new(2, 3),
+        reject_reason: None,
+    })?;
+
+    round_trip(codec, &VoteResponse {
+        term: 3,
+        vote_granted: false,
+        last_log_id: LogId::new(2, 3),
+        reject_reason: Some(VoteRejection::StaleLog),
+    })?;
+
+    let data = b"snapshot-data".to_vec();
+    round_trip(codec, &InstallSnapshotRequest {
+        term: 3,
+        leader_id: 2,
+        meta: SnapshotMeta {
+            last_log_id: LogId::new(2, 3),
+            snapshot_id: SnapshotId::new(LogId::new(2, 3), 4, 2),
+            base_snapshot_id: Some(SnapshotId::new(LogId::new(1, 2), 3, 2)),
+        },
+        offset: 10,
+        checksum: InstallSnapshotRequest::checksum_of(&data),
+        data,
+        done: true,
+    })?;
+
+    round_trip(codec, &InstallSnapshotResponse {
+        term: 3,
+        need_full_snapshot: true,
+    })?;
+
+    Ok(())
+}
+
+#[test]
+fn test_json_codec_round_trip() -> anyhow::Result<()> {
+    round_trip_all(&JsonCodec)?;
+    round_trip_all(&CodecType::Json)?;
+    Ok(())
+}
+
+#[test]
+fn test_bincode_codec_round_trip() -> anyhow::Result<()> {
+    round_trip_all(&BincodeCodec)?;
+    round_trip_all(&CodecType::Bincode)?;
+    Ok(())
+}
+
+#[test]
+fn test_encoded_size() -> anyhow::Result<()> {
+    for ent in entries().iter() {
+        for codec in [CodecType::Json, CodecType::Bincode] {
+            let size = codec.encode(&ent.payload)?.len() as u64;
+            assert_eq!(size, codec.encoded_size(&ent.payload)?, "codec: {:?}", codec);
+            assert_eq!(size, ent.payload_size_hint(&codec), "codec: {:?}", codec);
+        }
+    }
+
+    // The size differs between the codecs.
+    let ent = &entries()[1];
+    assert_ne!(ent.payload_size_hint(&JsonCodec), ent.payload_size_hint(&BincodeCodec));
+
+    Ok(())
+}
+
+#[test]
+fn test_codec_type_dispatch() -> anyhow::Result<()> {
+    let ent = &entries()[1];
+
+    assert_eq!(JsonCodec.encode(ent)?, CodecType::Json.encode(ent)?);
+    assert_eq!(BincodeCodec.encode(ent)?, CodecType::Bincode.encode(ent)?);
+
+    // Data encoded with one codec can not be decoded with the other.
+    let data = BincodeCodec.encode(ent)?;
+    let res: Result<Entry<Data>, _> = JsonCodec.decode(&data);
+    let err = res.unwrap_err();
+    assert_eq!(CodecType::Json, err.codec);
+
+    Ok(())
+}
+
+#[test]
+fn test_json_entry_without_timestamp() -> anyhow::Result<()> {
+    // An entry stored before `timestamp_ms` is added.
+    let data = br#"{"log_id":{"term":1,"index":2},"payload":"Blank"}"#;
+
+    let got: Entry<Data> = JsonCodec.decode(data)?;
+    assert_eq!(
+        Entry {
+            log_id: LogId::new(1, 2),
+            timestamp_ms: 0,
+            payload: EntryPayload::Blank,
+        },
+        got
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_checksum_of_logs() -> anyhow::Result<()> {
+    let ents = entries();
+
+    for codec in [CodecType::Json, CodecType::Bincode] {
+        let checksum = LogAuditResponse::checksum_of_logs(&ents, &codec)?;
+        assert_eq!(checksum, LogAuditResponse::checksum_of_logs(&entries(), &codec)?);
+
+        // The timestamp and the payload are covered.
+        let mut changed = entries();
+        changed[1].timestamp_ms += 1;
+        assert_ne!(checksum, LogAuditResponse::checksum_of_logs(&changed, &codec)?);
+
+        let mut changed = entries();
+        changed[1].payload = EntryPayload::Blank;
+        assert_ne!(checksum, LogAuditResponse::checksum_of_logs(&changed, &codec)?);
+    }
+
+    Ok(())
+}
+
+/// A payload that can not be encoded.
+#[derive(Clone, Debug, Deserialize)]
+struct Unencodable;
+
+impl Serialize for Unencodable {
+    fn serialize<S: serde::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+        Err(serde::ser::Error::custom("unencodable"))
+    }
+}
+
+impl AppData for Unencodable {}
+
+#[test]
+fn test_checksum_of_logs_codec_error() -> anyhow::Result<()> {
+    let ents = vec![Entry {
+        log_id: LogId::new(1, 1),
+        timestamp_ms: 0,
+        payload: EntryPayload::Normal(Unencodable),
+    }];
+
+    let res = LogAuditResponse::checksum_of_logs(&ents, &CodecType::Json);
+    let err = res.unwrap_err();
+    assert_eq!(CodecType::Json, err.codec);
+
+    Ok(())
+}
@@ -1,12 +1,20 @@
 //! Raft runtime configuration.
 
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::sync::Arc;
+
 use rand::thread_rng;
 use rand::Rng;
 use serde::Deserialize;
 use serde::Serialize;
 use structopt::StructOpt;
 
+use crate::clock::Clock;
+use crate::codec::CodecType;
 use crate::error::ConfigError;
+use crate::State;
+use crate::StorageError;
 
 /// Log compaction and snapshot policy.
 ///
@@ -19,6 +27,14 @@ pub enum SnapshotPolicy {
     /// A snapshot will be generated once the log has grown the specified number of logs since
     /// the last snapshot.
     LogsSinceLast(u64),
+
+    /// A snapshot is never generated automatically, but only by `Raft::trigger_snapshot()`, e.g., when the
+    /// application snapshots on its own schedule.
+    ///
+    /// Only logs included in a snapshot are purged, thus the log grows without bound unless snapshots are triggered
+    /// explicitly. A leader still builds a snapshot on demand when a follower needs logs that are already
+    /// purged, and it sends the current snapshot to a lagging follower no matter how old it is.
+    Never,
 }
 
 /// Parse number with unit such as 5.3 KB
@@ -29,16 +45,20 @@ fn parse_bytes_with_unit(src: &str) -> anyhow::Result<u64> {
 }
 
 fn parse_snapshot_policy(src: &str) -> anyhow::Result<SnapshotPolicy> {
+    if src == "never" {
+        return Ok(SnapshotPolicy::Never);
+    }
+
     let elts = src.split(':').collect::<Vec<_>>();
     if elts.len() != 2 {
         return Err(anyhow::anyhow!(
-            "snapshot policy should be in form of 'since_last:<num>'"
+            "snapshot policy should be 'never' or in form of 'since_last:<num>'"
         ));
     }
 
     if elts[0] != "since_last" {
         return Err(anyhow::anyhow!(
-            "snapshot policy should be in form of 'since_last:<num>'"
+            "snapshot policy should be 'never' or in form of 'since_last:<num>'"
         ));
     }
 
@@ -46,6 +66,196 @@ fn parse_snapshot_policy(src: &str) -> anyhow::Result<SnapshotPolicy> {
     Ok(SnapshotPolicy::LogsSinceLast(n_logs))
 }
 
+/// When a leader responds to a client write request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AckOn {
+    /// Respond once the log is committed, i.e., it is durable on a quorum, before it is applied to the state machine.
+    ///
+    /// This is a weaker guarantee than `Apply`: the response carries no application data, a rejection by the state
+    /// machine is not reported, and a read right after the response may not observe the write.
+    Commit,
+
+    /// Respond once the log is applied to the state machine, with the response of the state machine.
+    Apply,
+}
+
+fn parse_ack_on(src: &str) -> anyhow::Result<AckOn> {
+    match src {
+        "commit" => Ok(AckOn::Commit),
+        "apply" => Ok(AckOn::Apply),
+        _ => Err(anyhow::anyhow!("client write ack should be one of 'commit' or 'apply'")),
+    }
+}
+
+/// When a leader advances the commit index with the acknowledgements from the targets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommitAdvance {
+    /// The commit index is advanced as soon as a quorum acknowledges a log, and is sent to every target at once.
+    ///
+    /// A write is committed and applied with the least latency, at the cost of one more AppendEntries RPC to every
+    /// target for every advancement.
+    Eager,
+
+    /// The commit index is advanced once every heartbeat interval, with the acknowledgements received so far.
+    ///
+    /// Fewer RPCs are sent when writes are frequent, but a write waits up to a heartbeat interval more to be
+    /// committed. A leader without any other voter commits at once anyway.
+    Batched,
+}
+
+fn parse_commit_advance(src: &str) -> anyhow::Result<CommitAdvance> {
+    match src {
+        "eager" => Ok(CommitAdvance::Eager),
+        "batched" => Ok(CommitAdvance::Batched),
+        _ => Err(anyhow::anyhow!("commit advance should be one of 'eager' or 'batched'")),
+    }
+}
+
+/// Whether to retry applying logs to the state machine when it fails with a transient storage error.
+///
+/// See `StorageError::is_transient()`. Any other storage error always makes the node shut down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApplyRetry {
+    /// A failure to apply logs makes the node shut down at once.
+    Never,
+
+    /// Apply the same logs again up to `max_retries` times, waiting `backoff_ms` milliseconds before the first retry
+    /// and twice as long before every following one. The node shuts down if the last retry still fails.
+    ///
+    /// `RaftStorage::apply_to_state_machine()` has to be idempotent: the logs of a failed call may be partially
+    /// applied, and are applied again with the same log ids.
+    Backoff { max_retries: u64, backoff_ms: u64 },
+}
+
+fn parse_apply_retry(src: &str) -> anyhow::Result<ApplyRetry> {
+    if src == "never" {
+        return Ok(ApplyRetry::Never);
+    }
+
+    let elts = src.split(':').collect::<Vec<_>>();
+    if elts.len() != 3 || elts[0] != "backoff" {
+        return Err(anyhow::anyhow!(
+            "apply retry should be 'never' or in form of 'backoff:<max_retries>:<backoff_ms>'"
+        ));
+    }
+
+    let max_retries = elts[1].parse::<u64>()?;
+    let backoff_ms = elts[2].parse::<u64>()?;
+    Ok(ApplyRetry::Backoff {
+        max_retries,
+        backoff_ms,
+    })
+}
+
+/// Which membership config a follower or learner reports in `RaftMetrics::membership_config`.
+///
+/// It affects only what is reported. For elections, votes and quorums a node always uses the latest config in its log,
+/// as standard Raft does: acting on a committed config while a newer one is in the log may let two leaders be elected
+/// in the same term, with quorums from the old and the new config that do not overlap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReportMembershipOn {
+    /// The latest config in the log is reported as soon as it is appended.
+    ///
+    /// An uncommitted config may be removed by a new leader later, and the node falls back to the previous one.
+    Append,
+
+    /// The latest config in the log is reported only when the node learns it is committed.
+    ///
+    /// Until then it is reported as `RaftMetrics::pending_membership`, and `RaftMetrics::membership_config` is the
+    /// last committed one, i.e., an application never sees a config that may be rolled back, but it lags behind
+    /// the leader by one round trip. The node itself already uses the pending config, e.g., a learner being added
+    /// becomes a follower at once. On startup the commit index is unknown, and the latest config in storage is
+    /// reported as in `Append`.
+    Commit,
+}
+
+fn parse_report_membership_on(src: &str) -> anyhow::Result<ReportMembershipOn> {
+    match src {
+        "append" => Ok(ReportMembershipOn::Append),
+        "commit" => Ok(ReportMembershipOn::Commit),
+        _ => Err(anyhow::anyhow!(
+            "report membership on should be one of 'append' or 'commit'"
+        )),
+    }
+}
+
+fn parse_codec(src: &str) -> anyhow::Result<CodecType> {
+    match src {
+        "json" => Ok(CodecType::Json),
+        "bincode" => Ok(CodecType::Bincode),
+        _ => Err(anyhow::anyhow!("codec should be one of 'json' or 'bincode'")),
+    }
+}
+
+/// A deployment profile to build a `Config` for, with `Config::preset()`.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    /// Nodes in one datacenter or in availability zones with low latency between them.
+    LAN,
+
+    /// Nodes in different regions, with a round trip time up to several hundred milliseconds.
+    WAN,
+
+    /// Nodes in one process or on one host, e.g., in a test: short timeouts and frequent snapshots, to exercise
+    /// elections and snapshot replication quickly.
+    Testing,
+}
+
+/// A callback invoked with the storage error that makes a Raft node shut down.
+///
+/// It is called synchronously by the Raft core, before the shutdown begins, and at most once per node.
+/// It has no way to cancel the shutdown.
+#[derive(Clone)]
+pub struct FatalStorageErrorHandler(pub Arc<dyn Fn(&StorageError) + Send + Sync>);
+
+impl FatalStorageErrorHandler {
+    pub fn new(f: impl Fn(&StorageError) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+impl Debug for FatalStorageErrorHandler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FatalStorageErrorHandler")
+    }
+}
+
+/// A callback invoked with the old and the new state every time the state of a Raft node changes.
+///
+/// It is called synchronously by the Raft core at the transition, thus it should return quickly, e.g., by sending a
+/// message to a task of the application.
+#[derive(Clone)]
+pub struct StateChangeHandler(pub Arc<dyn Fn(State, State) + Send + Sync>);
+
+impl StateChangeHandler {
+    pub fn new(f: impl Fn(State, State) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+impl Debug for StateChangeHandler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "StateChangeHandler")
+    }
+}
+
+/// A `Clock` provided by the application, for the election timer.
+#[derive(Clone)]
+pub struct ClockSource(pub Arc<dyn Clock>);
+
+impl ClockSource {
+    pub fn new(clock: impl Clock) -> Self {
+        Self(Arc::new(clock))
+    }
+}
+
+impl Debug for ClockSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ClockSource")
+    }
+}
+
 /// The runtime configuration for a Raft node.
 ///
 /// The default values used by this type should generally work well for Raft clusters which will
@@ -99,6 +309,31 @@ pub struct Config {
     #[structopt(long, env = "RAFT_MAX_PAYLOAD_ENTRIES", default_value = "300")]
     pub max_payload_entries: u64,
 
+    /// The maximum total payload size in bytes of the entries in an AppendEntries RPC
+    ///
+    /// Entries are measured with `Entry::payload_size_hint()` with `codec`. A batch stops growing once adding the next
+    /// entry would exceed this limit, but at least one entry is always sent so that a single large entry does not
+    /// stall replication. By default batches are limited only by `max_payload_entries`.
+    #[structopt(long, env = "RAFT_MAX_PAYLOAD_BYTES", parse(try_from_str=parse_bytes_with_unit))]
+    pub max_payload_bytes: Option<u64>,
+
+    /// The maximum size in bytes of the entry of a client write
+    ///
+    /// The entry is measured with `EntryPayload::size_hint()`, i.e., its size encoded with `codec`. A larger entry is
+    /// rejected by the leader with `ClientWriteError::EntryTooLarge` before it is appended, thus it never reaches
+    /// replication or a snapshot. By default the size of an entry is not limited.
+    #[structopt(long, env = "RAFT_MAX_ENTRY_SIZE_BYTES", parse(try_from_str=parse_bytes_with_unit))]
+    pub max_entry_size_bytes: Option<u64>,
+
+    /// The maximum number of AppendEntries RPCs in flight to one target
+    ///
+    /// Once the matching log on a target is found, and the target lags behind by more than one payload, up to this
+    /// many consecutive payloads are sent concurrently, to make use of a fast link with a long round trip time.
+    /// The target still appends them in order: a payload that arrives before the preceding one is rejected and sent
+    /// again. By default one RPC is sent at a time.
+    #[structopt(long, env = "RAFT_REPLICATION_STREAMS_PER_FOLLOWER", default_value = "1")]
+    pub replication_streams_per_follower: u64,
+
     /// The distance behind in log replication a follower must fall before it is considered lagging
     ///
     /// Once a replication stream transition into line-rate state, the target node will be considered safe to join a
@@ -106,7 +341,15 @@ pub struct Config {
     #[structopt(long, env = "RAFT_REPLICATION_LAG_THRESHOLD", default_value = "1000")]
     pub replication_lag_threshold: u64,
 
-    /// The snapshot policy to use for a Raft node.
+    /// How long a learner may go without responding to the leader before it is evicted, in millisecond
+    ///
+    /// When it is set, a leader stops replicating to a learner that has not responded to any replication RPC for this
+    /// long, and forgets about it as if it was never added. Voters are never evicted this way, since removing a voter
+    /// is a membership change that has to be decided by the application. By default learners are never evicted.
+    #[structopt(long, env = "RAFT_LEARNER_EVICTION_TIMEOUT")]
+    pub learner_eviction_timeout: Option<u64>,
+
+    /// The snapshot policy to use for a Raft node: `since_last:<num>` or `never`
     #[structopt(
         long,
         env = "RAFT_SNAPSHOT_POLICY",
@@ -119,9 +362,187 @@ pub struct Config {
     #[structopt(long, env = "RAFT_SNAPSHOT_MAX_CHUNK_SIZE", default_value = "3MiB", parse(try_from_str=parse_bytes_with_unit))]
     pub snapshot_max_chunk_size: u64,
 
+    /// The maximum rate in bytes per second at which a leader sends snapshot data to a target
+    ///
+    /// It throttles snapshot transfer so that it does not starve normal replication and client writes.
+    /// When it is set, a snapshot chunk is made small enough to be sent at least once every heartbeat interval, so
+    /// that the target does not time out waiting for the leader. Time spent waiting for the throttle is not
+    /// counted in `install_snapshot_timeout`. By default snapshot transfer is not throttled.
+    #[structopt(long, env = "RAFT_SNAPSHOT_TRANSFER_BYTES_PER_SEC", parse(try_from_str=parse_bytes_with_unit))]
+    pub snapshot_transfer_bytes_per_sec: Option<u64>,
+
+    /// When to respond to a client write request: `commit` or `apply`
+    ///
+    /// See `AckOn` for the guarantee each of them provides.
+    #[structopt(
+        long,
+        env = "RAFT_CLIENT_WRITE_ACK",
+        default_value = "apply",
+        parse(try_from_str=parse_ack_on)
+    )]
+    pub client_write_ack: AckOn,
+
+    /// When a leader advances the commit index: `eager` or `batched`
+    ///
+    /// See `CommitAdvance` for the tradeoffs of each of them.
+    #[structopt(
+        long,
+        env = "RAFT_COMMIT_ADVANCE",
+        default_value = "eager",
+        parse(try_from_str=parse_commit_advance)
+    )]
+    pub commit_advance: CommitAdvance,
+
+    /// The maximum number of uncommitted entries a leader accepts
+    ///
+    /// When the last log index of the leader is ahead of the committed index by this many entries, a client write is
+    /// rejected with `ClientWriteError::Throttled` until replication catches up. It keeps the log of a leader from
+    /// growing without bound when it can not reach a quorum. By default there is no limit.
+    #[structopt(long, env = "RAFT_MAX_UNCOMMITTED_ENTRIES")]
+    pub max_uncommitted_entries: Option<u64>,
+
+    /// Whether a leader that lost the quorum keeps serving stale reads
+    ///
+    /// When a leader fails to confirm its leadership with a quorum, e.g., in `Raft::client_read()`, it enters a
+    /// read-only mode: `Raft::stale_read()` is served with the local state and flagged as stale, client writes are
+    /// refused with `ClientWriteError::QuorumLost`, and `RaftMetrics::is_stale` is set. Linearizable reads always
+    /// fail. The mode is left once the leader confirms its leadership again, or when it is no longer a leader.
+    /// By default a stale read fails like a linearizable read when the quorum is lost.
+    #[structopt(
+        long,
+        env = "RAFT_ALLOW_STALE_READS_ON_QUORUM_LOSS",
+        default_value = "false",
+        parse(try_from_str)
+    )]
+    pub allow_stale_reads_on_quorum_loss: bool,
+
+    /// Whether to collect timing stats of the internals of a Raft node
+    ///
+    /// When it is set, the time each iteration of the core loop takes, the latency of replication RPCs and the time
+    /// applying a batch of logs takes are reported in `Raft::perf_metrics()`. It is for performance debugging, e.g.,
+    /// to find a storage call that blocks the core loop. By default nothing is measured.
+    #[structopt(long, env = "RAFT_ENABLE_TICK_METRICS", default_value = "false", parse(try_from_str))]
+    pub enable_tick_metrics: bool,
+
+    /// The format to serialize log entries and RPC messages with: `json` or `bincode`
+    ///
+    /// Openraft does not serialize anything itself. It is for a `RaftStorage` or a `RaftNetwork` implementation that
+    /// opts in to encode and decode data with `Codec`, thus the format can be chosen without changing them.
+    /// Every node in a cluster has to use the same codec.
+    #[structopt(long, env = "RAFT_CODEC", default_value = "json", parse(try_from_str=parse_codec))]
+    pub codec: CodecType,
+
+    /// When a follower or learner reports a membership config received from the leader: `append` or `commit`
+    ///
+    /// It only affects metrics: a node always uses the latest config in its log. See `ReportMembershipOn`.
+    #[structopt(
+        long,
+        env = "RAFT_REPORT_MEMBERSHIP_ON",
+        default_value = "append",
+        parse(try_from_str=parse_report_membership_on)
+    )]
+    pub report_membership_on: ReportMembershipOn,
+
     /// The maximum number of applied logs to keep before purging
     #[structopt(long, env = "RAFT_MAX_APPLIED_LOG_TO_KEEP", default_value = "1000")]
     pub max_applied_log_to_keep: u64,
+
+    /// The seed of the random number generator for election timeouts
+    ///
+    /// With a seed, the election timeouts of a node are reproducible, which is useful in testing.
+    /// The node id is mixed into the seed thus nodes sharing a config still get different timeouts.
+    /// By default the generator is seeded from the OS.
+    #[structopt(long, env = "RAFT_ELECTION_RNG_SEED")]
+    pub election_rng_seed: Option<u64>,
+
+    /// Whether to retry applying logs on a transient storage error: `never` or `backoff:<max_retries>:<backoff_ms>`
+    ///
+    /// See `ApplyRetry`. While retrying, the node does nothing else.
+    #[structopt(
+        long,
+        env = "RAFT_APPLY_RETRY",
+        default_value = "never",
+        parse(try_from_str=parse_apply_retry)
+    )]
+    pub apply_retry: ApplyRetry,
+
+    /// Whether to call `RaftStorage::apply_to_state_machine()` on the blocking thread pool of the tokio runtime
+    ///
+    /// Set it if applying logs is CPU heavy, e.g., it compresses or indexes data, thus it does not occupy a runtime
+    /// worker thread that other tasks, such as the replication streams that send heartbeats, have to run on.
+    ///
+    /// Committed logs are then applied by a dedicated task, which the Raft task sends them to in order and does not
+    /// wait for: it keeps handling messages during an apply, thus a follower answers AppendEntries requests and does
+    /// not start an election however long an apply takes. `RaftMetrics::last_applied` is updated, and a leader
+    /// answers a client write, when the task reports the logs applied. Installing a snapshot waits for the logs being
+    /// applied. By default logs are applied on the Raft task, which handles no message until an apply is done.
+    #[structopt(
+        long,
+        env = "RAFT_APPLY_ON_BLOCKING_POOL",
+        default_value = "false",
+        parse(try_from_str)
+    )]
+    pub apply_on_blocking_pool: bool,
+
+    /// Whether the leader slows down heartbeats to idle learners
+    ///
+    /// When it is `true`, the heartbeat to a learner that has caught up slows down to 10 times `heartbeat_interval`,
+    /// and speeds up again as soon as there are new logs or a new committed log id to send to it. By default
+    /// heartbeats to learners are not slowed down.
+    ///
+    /// Only the heartbeats from the leader to learners are slowed down. Voters are not idled: they are always sent
+    /// heartbeats at `heartbeat_interval`, and a follower still waits for them with its election timeout. Thus a
+    /// cluster with only voters, or a single node, wakes up as often as without it.
+    #[structopt(
+        long,
+        env = "RAFT_SLOW_IDLE_LEARNER_HEARTBEAT",
+        default_value = "false",
+        parse(try_from_str)
+    )]
+    pub slow_idle_learner_heartbeat: bool,
+
+    /// How long the leader remembers a client write by its request id, in millisecond
+    ///
+    /// When it is set, a write with `ClientWriteRequest::with_request_id()` that is received again by the same leader
+    /// within this window, e.g., a client retries after a timeout, is not appended again: it is answered with the
+    /// response of the first one once it is applied. The writes are remembered only in memory by the leader, thus a
+    /// retry sent to a new leader is appended again. By default writes are not deduplicated.
+    #[structopt(long, env = "RAFT_DEDUP_WINDOW")]
+    pub dedup_window: Option<u64>,
+
+    /// The maximum number of logs applied to the state machine in one call, with the batch size adapted to the load
+    ///
+    /// When it is set, committed logs are applied in batches that start with 1 log and double while logs are committed
+    /// faster than they are applied, upto this limit, and shrink when a batch takes longer than `heartbeat_interval`.
+    /// A batch never waits for more logs to be committed, thus a trickle of writes is applied at once. By default the
+    /// leader applies logs one at a time and a follower applies all committed logs at once.
+    #[structopt(long, env = "RAFT_MAX_APPLY_BATCH")]
+    pub max_apply_batch: Option<u64>,
+
+    /// A callback invoked with the storage error that makes this node shut down
+    ///
+    /// It gives the application a chance to alert or flush diagnostics before the node stops.
+    /// It can only be set in code, not from the command line or a config file.
+    #[structopt(skip)]
+    #[serde(skip)]
+    pub on_fatal_storage_error: Option<FatalStorageErrorHandler>,
+
+    /// A callback invoked with `(old, new)` every time the state of this node changes
+    ///
+    /// E.g., to pause background jobs when a node is no longer the leader, without polling the metrics.
+    /// It can only be set in code, not from the command line or a config file.
+    #[structopt(skip)]
+    #[serde(skip)]
+    pub on_state_change: Option<StateChangeHandler>,
+
+    /// The clock the election timer reads the time from, or None for the monotonic clock of the tokio runtime
+    ///
+    /// A follower that sees this clock move further than the clock of the tokio runtime, e.g., when it jumps after a
+    /// VM pause, re-bases its election timer by the difference instead of starting an election at once.
+    /// It can only be set in code, not from the command line or a config file.
+    #[structopt(skip)]
+    #[serde(skip)]
+    pub clock: Option<ClockSource>,
 }
 
 impl Default for Config {
@@ -136,6 +557,44 @@ impl Config {
         thread_rng().gen_range(self.election_timeout_min..self.election_timeout_max)
     }
 
+    /// Build a valid config with the timing and snapshot settings suitable for a deployment profile.
+    ///
+    /// Other fields have the default values. A field can still be changed before calling `validate()` again, e.g.:
+    /// `Config { cluster_name: "foo".to_string(), ..Config::preset(Profile::WAN) }`.
+    pub fn preset(profile: Profile) -> Config {
+        let default = Config::default();
+
+        let config = match profile {
+            Profile::LAN => Config {
+                heartbeat_interval: 50,
+                election_timeout_min: 150,
+                election_timeout_max: 300,
+                install_snapshot_timeout: 200,
+                ..default
+            },
+            Profile::WAN => Config {
+                heartbeat_interval: 500,
+                election_timeout_min: 2500,
+                election_timeout_max: 5000,
+                install_snapshot_timeout: 2000,
+                snapshot_max_chunk_size: 1024 * 1024,
+                ..default
+            },
+            Profile::Testing => Config {
+                heartbeat_interval: 20,
+                election_timeout_min: 100,
+                election_timeout_max: 200,
+                install_snapshot_timeout: 200,
+                snapshot_policy: SnapshotPolicy::LogsSinceLast(100),
+                snapshot_max_chunk_size: 64 * 1024,
+                max_applied_log_to_keep: 100,
+                ..default
+            },
+        };
+
+        config.validate().expect("a preset config is valid")
+    }
+
     pub fn build(args: &[&str]) -> Result<Config, ConfigError> {
         let config = <Self as StructOpt>::from_iter(args);
         config.validate()
@@ -155,6 +614,38 @@ impl Config {
             return Err(ConfigError::MaxPayloadEntriesTooSmall);
         }
 
+        if self.max_payload_bytes == Some(0) {
+            return Err(ConfigError::MaxPayloadBytesTooSmall);
+        }
+
+        if self.max_entry_size_bytes == Some(0) {
+            return Err(ConfigError::MaxEntrySizeTooSmall);
+        }
+
+        if self.replication_streams_per_follower == 0 {
+            return Err(ConfigError::ReplicationStreamsTooSmall);
+        }
+
+        if self.snapshot_transfer_bytes_per_sec == Some(0) {
+            return Err(ConfigError::SnapshotTransferRateTooSmall);
+        }
+
+        if self.max_uncommitted_entries == Some(0) {
+            return Err(ConfigError::MaxUncommittedEntriesTooSmall);
+        }
+
+        if self.learner_eviction_timeout == Some(0) {
+            return Err(ConfigError::LearnerEvictionTimeoutTooSmall);
+        }
+
+        if self.dedup_window == Some(0) {
+            return Err(ConfigError::DedupWindowTooSmall);
+        }
+
+        if self.max_apply_batch == Some(0) {
+            return Err(ConfigError::MaxApplyBatchTooSmall);
+        }
+
         Ok(self)
     }
 }
@@ -175,10 +666,31 @@ mod tests {
 
         assert_eq!(50, cfg.heartbeat_interval);
         assert_eq!(300, cfg.max_payload_entries);
+        assert_eq!(None, cfg.max_payload_bytes);
+        assert_eq!(None, cfg.max_entry_size_bytes);
+        assert_eq!(1, cfg.replication_streams_per_follower);
         assert_eq!(1000, cfg.replication_lag_threshold);
 
         assert_eq!(3 * 1024 * 1024, cfg.snapshot_max_chunk_size);
         assert_eq!(SnapshotPolicy::LogsSinceLast(5000), cfg.snapshot_policy);
+        assert_eq!(None, cfg.election_rng_seed);
+        assert!(cfg.on_fatal_storage_error.is_none());
+        assert!(cfg.on_state_change.is_none());
+        assert!(cfg.clock.is_none());
+        assert_eq!(None, cfg.snapshot_transfer_bytes_per_sec);
+        assert_eq!(AckOn::Apply, cfg.client_write_ack);
+        assert_eq!(None, cfg.max_uncommitted_entries);
+        assert!(!cfg.allow_stale_reads_on_quorum_loss);
+        assert!(!cfg.enable_tick_metrics);
+        assert_eq!(None, cfg.learner_eviction_timeout);
+        assert_eq!(CodecType::Json, cfg.codec);
+        assert_eq!(ReportMembershipOn::Append, cfg.report_membership_on);
+        assert_eq!(CommitAdvance::Eager, cfg.commit_advance);
+        assert_eq!(ApplyRetry::Never, cfg.apply_retry);
+        assert!(!cfg.apply_on_blocking_pool);
+        assert!(!cfg.slow_idle_learner_heartbeat);
+        assert_eq!(None, cfg.dedup_window);
+        assert_eq!(None, cfg.max_apply_batch);
     }
 
     #[test]
@@ -194,6 +706,123 @@ mod tests {
         assert_eq!(err, ConfigError::InvalidElectionTimeoutMinMax);
     }
 
+    #[test]
+    fn test_zero_max_payload_bytes_produces_expected_error() {
+        let config = Config {
+            max_payload_bytes: Some(0),
+            ..Default::default()
+        };
+
+        let res = config.validate();
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::MaxPayloadBytesTooSmall);
+    }
+
+    #[test]
+    fn test_zero_max_entry_size_produces_expected_error() {
+        let config = Config {
+            max_entry_size_bytes: Some(0),
+            ..Default::default()
+        };
+
+        let res = config.validate();
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::MaxEntrySizeTooSmall);
+    }
+
+    #[test]
+    fn test_zero_snapshot_transfer_rate_produces_expected_error() {
+        let config = Config {
+            snapshot_transfer_bytes_per_sec: Some(0),
+            ..Default::default()
+        };
+
+        let res = config.validate();
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::SnapshotTransferRateTooSmall);
+    }
+
+    #[test]
+    fn test_zero_max_uncommitted_entries_produces_expected_error() {
+        let config = Config {
+            max_uncommitted_entries: Some(0),
+            ..Default::default()
+        };
+
+        let res = config.validate();
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::MaxUncommittedEntriesTooSmall);
+    }
+
+    #[test]
+    fn test_zero_replication_streams_produces_expected_error() {
+        let config = Config {
+            replication_streams_per_follower: 0,
+            ..Default::default()
+        };
+
+        let res = config.validate();
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::ReplicationStreamsTooSmall);
+    }
+
+    #[test]
+    fn test_zero_learner_eviction_timeout_produces_expected_error() {
+        let config = Config {
+            learner_eviction_timeout: Some(0),
+            ..Default::default()
+        };
+
+        let res = config.validate();
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::LearnerEvictionTimeoutTooSmall);
+    }
+
+    #[test]
+    fn test_zero_dedup_window_produces_expected_error() {
+        let config = Config {
+            dedup_window: Some(0),
+            ..Default::default()
+        };
+
+        let res = config.validate();
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::DedupWindowTooSmall);
+    }
+
+    #[test]
+    fn test_zero_max_apply_batch_produces_expected_error() {
+        let config = Config {
+            max_apply_batch: Some(0),
+            ..Default::default()
+        };
+
+        let res = config.validate();
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::MaxApplyBatchTooSmall);
+    }
+
+    #[test]
+    fn test_preset_is_valid() -> anyhow::Result<()> {
+        for profile in [Profile::LAN, Profile::WAN, Profile::Testing] {
+            let config = Config::preset(profile);
+            config.validate()?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_preset_lan_is_tighter_than_wan() {
+        let lan = Config::preset(Profile::LAN);
+        let wan = Config::preset(Profile::WAN);
+
+        assert!(lan.heartbeat_interval < wan.heartbeat_interval);
+        assert!(lan.election_timeout_min < wan.election_timeout_min);
+        assert!(lan.election_timeout_max < wan.election_timeout_max);
+        assert!(lan.install_snapshot_timeout < wan.install_snapshot_timeout);
+    }
+
     #[test]
     fn test_build() -> anyhow::Result<()> {
         let config = Config::build(&[
@@ -208,6 +837,23 @@ mod tests {
             "--snapshot-policy=since_last:203",
             "--snapshot-max-chunk-size=204",
             "--max-applied-log-to-keep=205",
+            "--election-rng-seed=206",
+            "--snapshot-transfer-bytes-per-sec=207",
+            "--client-write-ack=commit",
+            "--max-uncommitted-entries=208",
+            "--allow-stale-reads-on-quorum-loss=true",
+            "--max-payload-bytes=1KiB",
+            "--max-entry-size-bytes=2KiB",
+            "--enable-tick-metrics=true",
+            "--learner-eviction-timeout=209",
+            "--codec=bincode",
+            "--report-membership-on=commit",
+            "--commit-advance=batched",
+            "--apply-retry=backoff:3:210",
+            "--apply-on-blocking-pool=true",
+            "--slow-idle-learner-heartbeat=true",
+            "--dedup-window=211",
+            "--max-apply-batch=212",
         ])?;
 
         assert_eq!("bar", config.cluster_name);
@@ -220,6 +866,37 @@ mod tests {
         assert_eq!(SnapshotPolicy::LogsSinceLast(203), config.snapshot_policy);
         assert_eq!(204, config.snapshot_max_chunk_size);
         assert_eq!(205, config.max_applied_log_to_keep);
+        assert_eq!(Some(206), config.election_rng_seed);
+        assert_eq!(Some(207), config.snapshot_transfer_bytes_per_sec);
+        assert_eq!(AckOn::Commit, config.client_write_ack);
+        assert_eq!(Some(208), config.max_uncommitted_entries);
+        assert!(config.allow_stale_reads_on_quorum_loss);
+        assert_eq!(Some(1024), config.max_payload_bytes);
+        assert_eq!(Some(2048), config.max_entry_size_bytes);
+        assert!(config.enable_tick_metrics);
+        assert_eq!(Some(209), config.learner_eviction_timeout);
+        assert_eq!(CodecType::Bincode, config.codec);
+        assert_eq!(ReportMembershipOn::Commit, config.report_membership_on);
+        assert_eq!(CommitAdvance::Batched, config.commit_advance);
+        assert_eq!(
+            ApplyRetry::Backoff {
+                max_retries: 3,
+                backoff_ms: 210
+            },
+            config.apply_retry
+        );
+        assert!(config.apply_on_blocking_pool);
+        assert!(config.slow_idle_learner_heartbeat);
+        assert_eq!(Some(211), config.dedup_window);
+        assert_eq!(Some(212), config.max_apply_batch);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_policy_never() -> anyhow::Result<()> {
+        let config = Config::build(&["foo", "--snapshot-policy=never"])?;
+        assert_eq!(SnapshotPolicy::Never, config.snapshot_policy);
 
         Ok(())
     }
@@ -1,5 +1,11 @@
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::join_all;
+use tokio::io::AsyncWriteExt;
+use tokio::time::timeout;
 
 use crate::core::client::ClientRequestEntry;
 use crate::core::EffectiveMembership;
@@ -11,11 +17,17 @@ use crate::error::AddLearnerError;
 use crate::error::ChangeMembershipError;
 use crate::error::ClientWriteError;
 use crate::error::InitializeError;
+use crate::error::PauseReplicationError;
+use crate::error::SetRelayError;
 use crate::raft::AddLearnerResponse;
 use crate::raft::ClientWriteRequest;
 use crate::raft::ClientWriteResponse;
 use crate::raft::Membership;
+use crate::raft::MembershipPlan;
 use crate::raft::RaftRespTx;
+use crate::raft::VoteRequest;
+use crate::replication::RaftEvent;
+use crate::storage::SnapshotMeta;
 use crate::AppData;
 use crate::AppDataResponse;
 use crate::LogId;
@@ -27,15 +39,118 @@ use crate::RaftStorage;
 impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LearnerState<'a, D, R, N, S> {
     /// Handle the admin `init_with_config` command.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub(super) async fn handle_init_with_config(
-        &mut self,
-        mut members: BTreeSet<NodeId>,
-    ) -> Result<(), InitializeError> {
+    pub(super) async fn handle_init_with_config(&mut self, members: BTreeSet<NodeId>) -> Result<(), InitializeError> {
         if self.core.last_log_id.index != 0 || self.core.current_term != 0 {
             tracing::error!({self.core.last_log_id.index, self.core.current_term}, "rejecting init_with_config request as last_log_index or current_term is 0");
-            return Err(InitializeError::NotAllowed);
+            return Err(InitializeError::NotPristine {
+                last_log_id: self.core.last_log_id,
+            });
+        }
+
+        self.check_members(&members).await?;
+
+        self.init_membership(members, LogId { term: 1, index: 1 }).await
+    }
+
+    /// Handle the admin `init_from_snapshot` command.
+    ///
+    /// The snapshot is installed as the base of the state machine, then the cluster is initialized with `members`,
+    /// in a term not less than the term of the last log in the snapshot.
+    #[tracing::instrument(level = "debug", skip(self, data))]
+    pub(super) async fn handle_init_from_snapshot(
+        &mut self,
+        members: BTreeSet<NodeId>,
+        meta: SnapshotMeta,
+        data: Vec<u8>,
+    ) -> Result<(), InitializeError> {
+        if !self.core.last_log_id.is_sentinel() || !self.core.last_applied.is_sentinel() || self.core.current_term != 0
+        {
+            tracing::error!({%self.core.last_log_id, %self.core.last_applied, self.core.current_term}, "rejecting init_from_snapshot request on a non-pristine node");
+            return Err(InitializeError::NotPristine {
+                last_log_id: std::cmp::max(self.core.last_log_id, self.core.last_applied),
+            });
         }
 
+        let last = meta.last_log_id;
+        if last.is_sentinel() {
+            tracing::error!(%last, "rejecting init_from_snapshot request with an empty snapshot");
+            return Err(InitializeError::EmptySnapshot);
+        }
+
+        self.check_members(&members).await?;
+
+        let mut snapshot =
+            self.core.storage.begin_receiving_snapshot().await.map_err(|e| self.core.map_storage_error(e))?;
+        snapshot.as_mut().write_all(&data).await.map_err(|e| self.core.map_fatal_storage_error(e.into()))?;
+        snapshot.as_mut().shutdown().await.map_err(|e| self.core.map_fatal_storage_error(e.into()))?;
+
+        self.core
+            .storage
+            .finalize_snapshot_installation(&meta, snapshot)
+            .await
+            .map_err(|e| self.core.map_storage_error(e))?;
+
+        // Every log in the snapshot is committed and applied.
+        self.core.last_applied = last;
+        self.core.committed = last;
+        self.core.last_log_id = last;
+        self.core.snapshot_last_log_id = last;
+
+        let applied_membership =
+            self.core.storage.get_committed_membership().await.map_err(|e| self.core.map_storage_error(e))?;
+        self.core.applied_membership = applied_membership;
+        self.core.update_log_usage().await?;
+
+        // A leader can not append a log with a term less than its last log.
+        self.core.current_term = last.term;
+        self.core.save_hard_state().await?;
+
+        self.init_membership(members, LogId::new(last.term + 1, last.index + 1)).await
+    }
+
+    /// Check the initial members are not empty, and every member other than this node responds.
+    ///
+    /// A member is probed with a vote request of term 0, which never grants a vote, and is unreachable if it does not
+    /// respond within `Config::election_timeout_max`.
+    async fn check_members(&self, members: &BTreeSet<NodeId>) -> Result<(), InitializeError> {
+        if members.is_empty() {
+            return Err(InitializeError::EmptyMembers);
+        }
+
+        let probe_timeout = Duration::from_millis(self.core.config.election_timeout_max);
+        let others = members.iter().filter(|id| **id != self.core.id).cloned().collect::<Vec<_>>();
+
+        let probes = others.iter().map(|target| {
+            let rpc = VoteRequest::new(0, self.core.id, LogId::none());
+            timeout(probe_timeout, self.core.network.send_vote(*target, rpc))
+        });
+        let results = join_all(probes).await;
+
+        for (target, res) in others.into_iter().zip(results.into_iter()) {
+            match res {
+                Ok(Ok(_)) => {}
+                Ok(Err(err)) => {
+                    tracing::error!(target, error=%err, "rejecting init request: member is unreachable");
+                    return Err(InitializeError::MemberUnreachable { node_id: target });
+                }
+                Err(_elapsed) => {
+                    tracing::error!(
+                        target,
+                        ?probe_timeout,
+                        "rejecting init request: member does not respond"
+                    );
+                    return Err(InitializeError::MemberUnreachable { node_id: target });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Use `members` as the membership of the cluster and start an election.
+    ///
+    /// The membership is in memory only, until the leader commits it as the first log of its term, at `log_id`.
+    async fn init_membership(&mut self, mut members: BTreeSet<NodeId>, log_id: LogId) -> Result<(), InitializeError> {
         // Ensure given config contains this nodes ID as well.
         if !members.contains(&self.core.id) {
             members.insert(self.core.id);
@@ -44,7 +159,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         // Build a new membership config from given init data & assign it as the new cluster
         // membership config in memory only.
         self.core.effective_membership = EffectiveMembership {
-            log_id: LogId { term: 1, index: 1 },
+            log_id,
             membership: Membership::new_single(members),
         };
 
@@ -75,6 +190,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         target: NodeId,
         tx: RaftRespTx<AddLearnerResponse, AddLearnerError>,
         blocking: bool,
+        standby: bool,
     ) {
         // Ensure the node doesn't already exist in the current
         // config, in the set of new nodes already being synced, or in the nodes being removed.
@@ -92,17 +208,22 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             return;
         }
 
+        if standby {
+            self.leader_metrics.replication.entry(target).or_default().standby = true;
+            self.leader_report_metrics();
+        }
+
         if blocking {
-            let state = self.spawn_replication_stream(target, Some(tx));
+            let mut state = self.spawn_replication_stream(target, Some(tx));
+            state.standby = standby;
             self.nodes.insert(target, state);
         } else {
-            let state = self.spawn_replication_stream(target, None);
+            let mut state = self.spawn_replication_stream(target, None);
+            state.standby = standby;
             self.nodes.insert(target, state);
 
             // non-blocking mode, do not know about the replication stat.
-            let _ = tx.send(Ok(AddLearnerResponse {
-                matched: LogId::new(0, 0),
-            }));
+            let _ = tx.send(Ok(AddLearnerResponse { matched: LogId::none() }));
         }
     }
 
@@ -110,51 +231,24 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     pub(super) async fn change_membership(
         &mut self,
         members: BTreeSet<NodeId>,
+        demote: BTreeSet<NodeId>,
         blocking: bool,
         tx: RaftRespTx<ClientWriteResponse<R>, ClientWriteError>,
     ) {
-        // Ensure cluster will have at least one node.
-        if members.is_empty() {
-            let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(
-                ChangeMembershipError::EmptyMembership,
-            )));
-            return;
-        }
-
-        // The last membership config is not committed yet.
-        // Can not process the next one.
-        if self.core.committed < self.core.effective_membership.log_id {
-            let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(
-                ChangeMembershipError::InProgress {
-                    membership_log_id: self.core.effective_membership.log_id,
-                },
-            )));
-            return;
-        }
-
-        let new_config;
-
-        let curr = &self.core.effective_membership.membership;
-
-        if let Some(next_membership) = curr.get_ith_config(1) {
-            // When it is in joint state, it is only allowed to change to the `members_after_consensus`
-            if &members != next_membership {
-                let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(
-                    ChangeMembershipError::Incompatible {
-                        curr: curr.clone(),
-                        to: members,
-                    },
-                )));
+        let new_config = match self.next_membership_config(&members) {
+            Ok(x) => x,
+            Err(e) => {
+                let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(e)));
                 return;
-            } else {
-                new_config = Membership::new_single(next_membership.clone());
             }
-        } else {
-            // currently it is uniform config, enter joint state
-            new_config = Membership::new_multi(vec![curr.get_ith_config(0).unwrap().clone(), members.clone()]);
+        };
+
+        if let Err(e) = self.check_demote(&members, &demote) {
+            let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(e)));
+            return;
         }
 
-        tracing::debug!(?new_config, "new_config");
+        tracing::debug!(%new_config, "new_config");
 
         // Check the proposed config for any new nodes. If ALL new nodes already have replication
         // streams AND are ready to join, then we can immediately proceed with entering joint
@@ -198,6 +292,26 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             }
         }
 
+        // A standby being made a voter is promoted: it is no longer a standby.
+        for id in members.iter() {
+            if let Some(node) = self.nodes.get_mut(id) {
+                if node.standby {
+                    tracing::info!(target = id, "promote standby to voter");
+                    node.standby = false;
+                    self.leader_metrics.replication.entry(*id).or_default().standby = false;
+                }
+                // A demoted node being made a voter again is removed as usual if it is removed later.
+                node.demoted = false;
+            }
+        }
+
+        for id in demote.iter() {
+            if let Some(node) = self.nodes.get_mut(id) {
+                tracing::info!(target = id, "demote voter to learner");
+                node.demoted = true;
+            }
+        }
+
         // TODO(xp): 111 report metrics?
         let res = self.append_membership_log(new_config, Some(tx)).await;
 
@@ -206,12 +320,162 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         }
     }
 
+    /// Build the plan of changing membership to `members`, without changing anything.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(super) fn change_membership_dry_run(
+        &self,
+        members: BTreeSet<NodeId>,
+    ) -> Result<MembershipPlan, ChangeMembershipError> {
+        let new_config = self.next_membership_config(&members)?;
+
+        let mut steps = vec![];
+        if new_config.is_joint() {
+            let final_config = new_config.to_final_config();
+            steps.push(new_config);
+            steps.push(final_config);
+        } else {
+            steps.push(new_config);
+        }
+
+        let mut plan = MembershipPlan {
+            current: self.core.effective_membership.membership.clone(),
+            steps,
+            unreachable: BTreeSet::new(),
+            lagging: BTreeMap::new(),
+        };
+
+        for node_id in members.iter() {
+            if node_id == &self.core.id {
+                continue;
+            }
+
+            match self.nodes.get(node_id) {
+                Some(node) => {
+                    if !node.is_line_rate(&self.core.last_log_id, &self.core.config) {
+                        plan.lagging.insert(*node_id, node.matched);
+                    }
+                }
+                None => {
+                    plan.unreachable.insert(*node_id);
+                }
+            }
+        }
+
+        Ok(plan)
+    }
+
+    /// Check that every node in `demote` is a voter other than the leader, which is removed by changing membership to
+    /// `members`.
+    fn check_demote(&self, members: &BTreeSet<NodeId>, demote: &BTreeSet<NodeId>) -> Result<(), ChangeMembershipError> {
+        let voters = self.core.effective_membership.membership.all_nodes();
+
+        for id in demote.iter() {
+            if *id == self.core.id || members.contains(id) || !voters.contains(id) || !self.nodes.contains_key(id) {
+                return Err(ChangeMembershipError::CanNotDemote { node_id: *id });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the membership config to propose next in order to change membership to `members`.
+    ///
+    /// If the current config is uniform, it is a joint config of the current and the target one.
+    /// If the current config is a joint config, it is the uniform config of `members`, which has to be the second
+    /// config in the joint config.
+    ///
+    /// If the current config is uniform and the voter set is not changed, the quorum is not affected: the uniform
+    /// config is proposed at once, without a joint step. Learners are not part of a config, thus adding learners never
+    /// requires a joint step.
+    fn next_membership_config(&self, members: &BTreeSet<NodeId>) -> Result<Membership, ChangeMembershipError> {
+        // Ensure cluster will have at least one node.
+        if members.is_empty() {
+            return Err(ChangeMembershipError::EmptyMembership);
+        }
+
+        // The last membership config is not committed yet.
+        // Can not process the next one.
+        if self.core.committed < self.core.effective_membership.log_id {
+            return Err(ChangeMembershipError::InProgress {
+                membership_log_id: self.core.effective_membership.log_id,
+            });
+        }
+
+        let curr = &self.core.effective_membership.membership;
+
+        let next = if let Some(next_membership) = curr.get_ith_config(1) {
+            // When it is in joint state, it is only allowed to change to the `members_after_consensus`
+            if members != next_membership {
+                return Err(ChangeMembershipError::Incompatible {
+                    curr: curr.clone(),
+                    to: members.clone(),
+                });
+            }
+
+            Membership::try_new_single(next_membership.clone())
+        } else if curr.get_ith_config(0) == Some(members) {
+            // The voters are not changed, there is no need to enter joint state.
+            Membership::try_new_single(members.clone())
+        } else {
+            // currently it is uniform config, enter joint state
+            Membership::try_new_multi(vec![curr.get_ith_config(0).unwrap().clone(), members.clone()])
+        };
+
+        // Priorities are kept until they are replaced by `set_priorities()`.
+        Ok(next?.with_priorities(curr.priorities().clone()))
+    }
+
+    /// Handle the admin `set_priorities` command: append a uniform config with the same voters and the new
+    /// priorities.
+    #[tracing::instrument(level = "debug", skip(self, tx))]
+    pub(super) async fn set_priorities(
+        &mut self,
+        priorities: BTreeMap<NodeId, u64>,
+        tx: RaftRespTx<ClientWriteResponse<R>, ClientWriteError>,
+    ) {
+        let curr = &self.core.effective_membership;
+
+        if curr.membership.is_joint() {
+            let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(
+                ChangeMembershipError::InProgress {
+                    membership_log_id: curr.log_id,
+                },
+            )));
+            return;
+        }
+
+        let voters = curr.membership.get_ith_config(0).cloned().unwrap_or_default();
+        let new_config = match self.next_membership_config(&voters) {
+            Ok(x) => x.with_priorities(priorities),
+            Err(e) => {
+                let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(e)));
+                return;
+            }
+        };
+
+        let res = self.append_membership_log(new_config, Some(tx)).await;
+
+        if let Err(e) = res {
+            tracing::error!("append membership log error: {:?}", e);
+        }
+    }
+
     #[tracing::instrument(level = "debug", skip(self, resp_tx), fields(id=self.core.id))]
     pub async fn append_membership_log(
         &mut self,
         mem: Membership,
         resp_tx: Option<RaftRespTx<ClientWriteResponse<R>, ClientWriteError>>,
     ) -> Result<(), RaftError> {
+        // A config without voter would halt the cluster forever. It must never be appended, no matter where it comes
+        // from. The current config is kept.
+        if let Err(e) = mem.validate() {
+            tracing::error!(%mem, error=%e, "refuse to append an invalid membership log");
+            if let Some(tx) = resp_tx {
+                let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(e)));
+            }
+            return Ok(());
+        }
+
         let payload = ClientWriteRequest::<D>::new_config(mem.clone());
         let res = self.append_payload_to_log(payload.entry).await;
 
@@ -221,6 +485,8 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             membership: mem,
         };
 
+        self.update_idle_heartbeat();
+        self.update_relays();
         self.leader_report_metrics();
 
         let entry = match res {
@@ -240,6 +506,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         let cr_entry = ClientRequestEntry {
             entry: Arc::new(entry),
             tx: resp_tx,
+            request_id: None,
         };
 
         self.replicate_client_request(cr_entry).await;
@@ -268,7 +535,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
         let all = membership.all_nodes();
         for (id, state) in self.nodes.iter_mut() {
-            if all.contains(id) {
+            if all.contains(id) || state.demoted {
                 continue;
             }
 
@@ -290,6 +557,131 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         self.leader_report_metrics();
     }
 
+    /// Handle the admin `step_down` command: give up leadership and become a follower.
+    ///
+    /// No successor is chosen: heartbeats stop and a new leader is elected once a node times out, which could be this
+    /// node again.
+    #[tracing::instrument(level = "debug", skip(self, tx))]
+    pub(super) fn step_down(&mut self, tx: RaftRespTx<(), ClientWriteError>) {
+        tracing::info!(
+            id = self.core.id,
+            term = self.core.current_term,
+            "raft node is stepping down on demand"
+        );
+
+        self.core.set_target_state(State::Follower);
+        self.core.update_current_leader(UpdateCurrentLeader::Unknown);
+        // The election timeout of a leader is stale, do not start an election at once.
+        self.core.update_next_election_timeout(false);
+
+        let _ = tx.send(Ok(()));
+    }
+
+    /// Handle the admin `pause_replication` and `resume_replication` commands: tell the replication stream to the
+    /// target to stop or to resume sending logs.
+    #[tracing::instrument(level = "debug", skip(self, tx))]
+    pub(super) fn pause_replication(
+        &mut self,
+        target: NodeId,
+        paused: bool,
+        tx: RaftRespTx<(), PauseReplicationError>,
+    ) {
+        let node = match self.nodes.get(&target) {
+            Some(x) => x,
+            None => {
+                let _ = tx.send(Err(PauseReplicationError::NotFound(target)));
+                return;
+            }
+        };
+
+        tracing::info!(target, paused, "set replication paused");
+
+        let _ = node.repl_stream.repl_tx.send((RaftEvent::SetPaused { paused }, tracing::debug_span!("CH")));
+        let _ = tx.send(Ok(()));
+    }
+
+    /// Handle the admin `set_relay` command: let the learner receive the logs from the relay, or from the leader if
+    /// `relay` is `None`.
+    #[tracing::instrument(level = "debug", skip(self, tx))]
+    pub(super) fn set_relay(&mut self, learner: NodeId, relay: Option<NodeId>, tx: RaftRespTx<(), SetRelayError>) {
+        if let Err(err) = self.check_relay(learner, relay) {
+            let _ = tx.send(Err(err));
+            return;
+        }
+
+        tracing::info!(learner, ?relay, "set relay");
+
+        match relay {
+            Some(relay) => self.relays.insert(learner, relay),
+            None => self.relays.remove(&learner),
+        };
+
+        // A relayed learner receives only heartbeats from the leader.
+        if let Some(node) = self.nodes.get(&learner) {
+            let relayed = relay.is_some();
+            let _ = node.repl_stream.repl_tx.send((RaftEvent::SetRelayed { relayed }, tracing::debug_span!("CH")));
+        }
+
+        self.update_relays();
+        let _ = tx.send(Ok(()));
+    }
+
+    fn check_relay(&self, learner: NodeId, relay: Option<NodeId>) -> Result<(), SetRelayError> {
+        for id in std::iter::once(learner).chain(relay) {
+            if self.core.effective_membership.membership.contains(&id) {
+                return Err(SetRelayError::NotLearner(id));
+            }
+            if !self.nodes.contains_key(&id) {
+                return Err(SetRelayError::NotFound(id));
+            }
+        }
+
+        if let Some(relay) = relay {
+            // Only one level of relaying: a relay is replicated to by the leader, and a relayed learner relays to no
+            // one.
+            let relay_is_relayed = self.relays.contains_key(&relay);
+            let learner_is_relay = self.relays.values().any(|r| *r == learner);
+            if relay == learner || relay_is_relayed || learner_is_relay {
+                return Err(SetRelayError::InvalidRelay { learner, relay });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drop the relays that are no longer valid, i.e., the learner or the relay becomes a voter or is removed, and
+    /// tell every replication target the learners it relays the logs to.
+    pub(super) fn update_relays(&mut self) {
+        let invalid = self
+            .relays
+            .iter()
+            .filter(|(learner, relay)| {
+                [*learner, *relay]
+                    .iter()
+                    .any(|id| !self.nodes.contains_key(id) || self.core.effective_membership.membership.contains(id))
+            })
+            .map(|(learner, _)| *learner)
+            .collect::<Vec<_>>();
+
+        for learner in invalid {
+            tracing::info!(learner, "drop relay of learner");
+            self.relays.remove(&learner);
+
+            if let Some(node) = self.nodes.get(&learner) {
+                let _ = node
+                    .repl_stream
+                    .repl_tx
+                    .send((RaftEvent::SetRelayed { relayed: false }, tracing::debug_span!("CH")));
+            }
+        }
+
+        for (target, node) in self.nodes.iter() {
+            let relay_to =
+                self.relays.iter().filter(|(_, relay)| *relay == target).map(|(learner, _)| *learner).collect();
+            let _ = node.repl_stream.repl_tx.send((RaftEvent::SetRelayTo { relay_to }, tracing::debug_span!("CH")));
+        }
+    }
+
     /// Remove a replication if the membership that does not include it has committed.
     ///
     /// Return true if removed.
@@ -314,9 +706,16 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             }
         }
 
+        self.remove_replication(target);
+        true
+    }
+
+    /// Drop the replication stream to the target and forget about it.
+    pub(super) fn remove_replication(&mut self, target: NodeId) {
         tracing::info!("removed replication to: {}", target);
         self.nodes.remove(&target);
         self.leader_metrics.replication.remove(&target);
-        true
+        self.core.perf_metrics.replication_rpc.remove(&target);
+        self.update_relays();
     }
 }
@@ -1,3 +1,5 @@
+use tokio::time::Instant;
+
 use crate::core::apply_to_state_machine;
 use crate::core::RaftCore;
 use crate::core::State;
@@ -15,6 +17,8 @@ use crate::MessageSummary;
 use crate::RaftError;
 use crate::RaftNetwork;
 use crate::RaftStorage;
+use crate::ReportMembershipOn;
+use crate::StorageError;
 use crate::Update;
 
 impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> RaftCore<D, R, N, S> {
@@ -37,6 +41,8 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 term: self.current_term,
                 matched: None,
                 conflict: None,
+                last_log_id: None,
+                instance_uuid: Some(self.instance_uuid),
             });
         }
 
@@ -66,8 +72,9 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             let mut report_metrics = false;
 
             if msg.term > self.current_term {
+                // The new term is saved along with the entries to append, or before responding if there is none.
                 self.update_current_term(msg.term, None);
-                self.save_hard_state().await?;
+                self.hard_state_dirty = true;
                 report_metrics = true;
             }
 
@@ -94,7 +101,17 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         //              +----------------+------------------------+
         //              ` 0              ` last_applied           ` last_log_id
 
-        return self.append_apply_log_entries(&msg.prev_log_id, msg_entries, valid_committed).await;
+        let resp = self.append_apply_log_entries(&msg.prev_log_id, msg_entries, valid_committed).await?;
+
+        if self.hard_state_dirty {
+            self.save_hard_state().await?;
+        }
+
+        if resp.matched.is_some() && (!msg.relay_to.is_empty() || !self.relay_streams.is_empty()) {
+            self.update_relay(msg.leader_id, msg.term, &msg.relay_to);
+        }
+
+        Ok(resp)
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
@@ -109,9 +126,13 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
         self.last_log_id = self.get_log_id(start - 1).await?;
 
+        // Deleted configs are not pending any more. The effective one is reloaded below, if it is deleted.
+        self.pending_membership.retain(|m| m.log_id.index < start);
+        self.forget_committed_membership_if_no_pending();
+
         // TODO(xp): get_membership() should have a defensive check to ensure it always returns Some() if node is
         //           initialized. Because a node always commit a membership log as the first log entry.
-        let membership = self.storage.get_membership().await.map_err(|err| self.map_storage_error(err))?;
+        let membership = self.get_membership().await.map_err(|err| self.map_storage_error(err))?;
 
         // TODO(xp): This is a dirty patch:
         //           When a node starts in a single-node mode, it does not append an initial log
@@ -132,11 +153,16 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             return Ok(self.last_applied);
         }
 
-        let entries = self.storage.get_log_entries(index..=index).await.map_err(|err| self.map_storage_error(err))?;
+        let entries = self.get_log_entries_exact(index..=index).await?;
 
-        let entry = entries
-            .first()
-            .ok_or_else(|| self.map_fatal_storage_error(anyhow::anyhow!("log entry not found at: {}", index)))?;
+        let entry = entries.first().ok_or_else(|| {
+            self.map_storage_error(StorageError::ShortRead {
+                start: index,
+                end: index + 1,
+                expected: Some(index),
+                got: None,
+            })
+        })?;
 
         Ok(entry.log_id)
     }
@@ -220,10 +246,10 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
         if start == 0 {
             // A simple way is to sync from the beginning.
-            return Ok(LogId { term: 0, index: 0 });
+            return Ok(LogId::none());
         }
 
-        let entries = self.storage.get_log_entries(start..=start).await.map_err(|err| self.map_storage_error(err))?;
+        let entries = self.get_log_entries_exact(start..=start).await?;
 
         let log_id = entries.first().unwrap().log_id;
 
@@ -258,6 +284,8 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 term: self.current_term,
                 matched: None,
                 conflict: Some(*prev_log_id),
+                last_log_id: Some(self.last_log_id),
+                instance_uuid: Some(self.instance_uuid),
             });
         }
 
@@ -288,7 +316,13 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
         // commit index must not > last_log_id.index
         // This is guaranteed by caller.
-        self.committed = committed;
+        // It never goes back, e.g., a relayed learner receives heartbeats from the leader that prove less logs to be
+        // consistent than the relay does.
+        if committed > self.committed {
+            self.committed = committed;
+        }
+
+        self.apply_committed_membership();
 
         self.replicate_to_state_machine_if_needed().await?;
 
@@ -298,6 +332,8 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             term: self.current_term,
             matched,
             conflict: None,
+            last_log_id: None,
+            instance_uuid: Some(self.instance_uuid),
         })
     }
 
@@ -305,7 +341,9 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// and the the unmatched entries.
     ///
     /// The entries in request that are matches local ones does not need to be append again.
-    /// Filter them out.
+    /// Filter them out, thus a retransmitted append-entries request does not write to storage again.
+    ///
+    /// Only log ids are read from storage, and none is read for an entry beyond the last local log.
     pub async fn skip_matching_entries<'s, 'e>(
         &'s self,
         entries: &'e [Entry<D>],
@@ -320,13 +358,15 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 continue;
             }
 
-            // TODO(xp): this is a naive impl. Batch loading entries from storage.
-            let log = self.storage.try_get_log_entry(index).await.map_err(|err| RaftError::RaftStorage(err.into()))?;
+            // There is no local log at this index, neither after it.
+            if index > self.last_log_id.index {
+                return Ok((i, &entries[i..]));
+            }
+
+            let local = self.storage.get_log_id(index).await.map_err(|err| RaftError::RaftStorage(err.into()))?;
 
-            if let Some(local) = log {
-                if local.log_id == log_id {
-                    continue;
-                }
+            if local == Some(log_id) {
+                continue;
             }
 
             return Ok((i, &entries[i..]));
@@ -340,6 +380,11 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// This way to check if the entries in append-entries request is consecutive with local logs.
     /// Raft only accept consecutive logs to be appended.
     pub async fn does_log_id_match(&self, remote_log_id: &LogId) -> RaftResult<bool> {
+        // The sentinel is before any log, thus it matches an empty log as well as any other.
+        if remote_log_id.is_sentinel() {
+            return Ok(true);
+        }
+
         let index = remote_log_id.index;
 
         // Committed entries are always safe and are consistent to a valid leader.
@@ -347,20 +392,14 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             return Ok(true);
         }
 
-        let log = self.storage.try_get_log_entry(index).await.map_err(|err| RaftError::RaftStorage(err.into()))?;
-        tracing::debug!(
-            "check log id matching: local: {:?} remote: {}",
-            log.as_ref().map(|x| x.log_id),
-            remote_log_id
-        );
-
-        if let Some(local) = log {
-            if local.log_id == *remote_log_id {
-                return Ok(true);
-            }
+        if index > self.last_log_id.index {
+            return Ok(false);
         }
 
-        Ok(false)
+        let local = self.storage.get_log_id(index).await.map_err(|err| RaftError::RaftStorage(err.into()))?;
+        tracing::debug!("check log id matching: local: {:?} remote: {}", local, remote_log_id);
+
+        Ok(local == Some(*remote_log_id))
     }
 
     /// Append the given entries to the log.
@@ -373,8 +412,8 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             return Ok(());
         }
 
-        // Check the given entries for any config changes and take the most recent.
-        let last_conf_change = entries
+        // Check the given entries for any config changes.
+        let mut conf_changes = entries
             .iter()
             .filter_map(|ent| match &ent.payload {
                 EntryPayload::Membership(conf) => Some(EffectiveMembership {
@@ -383,20 +422,35 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 }),
                 _ => None,
             })
-            .last();
-
-        // TODO(xp): only when last_conf_change is newer than current one.
-        //           For now it is guaranteed by `delete_logs()`, for it updates membership config when delete logs.
-        //           and `skip_matching_entries()`, for it does not re-append existent log entries.
-        //           This task should be done by StorageAdaptor.
-        if let Some(conf) = last_conf_change {
-            tracing::debug!({membership=?conf}, "applying new membership config received from leader");
+            .collect::<Vec<_>>();
+
+        if self.config.report_membership_on == ReportMembershipOn::Commit {
+            // They are reported when committed, by `apply_committed_membership()`.
+            self.append_pending_membership(&conf_changes);
+        }
+
+        if let Some(conf) = conf_changes.pop() {
+            // Take the most recent one.
+            // TODO(xp): only when last_conf_change is newer than current one.
+            //           For now it is guaranteed by `delete_logs()`, for it updates membership config when delete logs.
+            //           and `skip_matching_entries()`, for it does not re-append existent log entries.
+            //           This task should be done by StorageAdaptor.
+            tracing::debug!({membership=%conf.summary()}, "applying new membership config received from leader");
             self.update_membership(conf)?;
-        };
+        }
 
         // Replicate entries to log (same as append, but in follower mode).
         let entry_refs = entries.iter().collect::<Vec<_>>();
-        self.storage.append_to_log(&entry_refs).await.map_err(|err| self.map_storage_error(err))?;
+        if self.hard_state_dirty {
+            let hs = self.hard_state_to_save()?;
+            self.storage
+                .append_and_save_hard_state(&entry_refs, &hs)
+                .await
+                .map_err(|err| self.map_storage_error(err))?;
+            self.hard_state_saved(&hs);
+        } else {
+            self.storage.append_to_log(&entry_refs).await.map_err(|err| self.map_storage_error(err))?;
+        }
         if let Some(entry) = entries.last() {
             self.last_log_id = entry.log_id;
         }
@@ -419,6 +473,11 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             return Ok(());
         }
 
+        // With `Config::apply_on_blocking_pool`, logs are applied by the apply task, which this does not wait for.
+        if self.tx_apply.is_some() {
+            return self.apply_committed_in_background().await;
+        }
+
         // If we don't have any new entries to replicate, then do nothing.
         if self.committed <= self.last_applied {
             tracing::debug!(
@@ -429,28 +488,45 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             return Ok(());
         }
 
-        // Drain entries from the beginning of the cache up to commit index.
+        // Drain entries from the beginning of the cache up to commit index, in batches if
+        // `Config::max_apply_batch` is set.
 
-        let entries = self
-            .storage
-            .get_log_entries(self.last_applied.index + 1..=self.committed.index)
-            .await
-            .map_err(|e| self.map_storage_error(e))?;
+        while self.last_applied.index < self.committed.index {
+            let n = self.apply_batch_size(self.committed.index - self.last_applied.index);
+            let batch_start = self.last_applied.next_index();
+
+            let entries = self.get_log_entries_exact(batch_start..batch_start + n).await?;
 
-        let last_log_id = entries.last().map(|x| x.log_id).unwrap();
+            let last_log_id = entries.last().map(|x| x.log_id).unwrap();
 
-        tracing::debug!("entries: {}", entries.as_slice().summary());
-        tracing::debug!(?last_log_id);
+            tracing::debug!("entries: {}", entries.as_slice().summary());
+            tracing::debug!(?last_log_id);
 
-        let entries_refs: Vec<_> = entries.iter().collect();
+            let entries_refs: Vec<_> = entries.iter().collect();
 
-        apply_to_state_machine(self.storage.clone(), &entries_refs, self.config.max_applied_log_to_keep)
+            let start = self.perf_start();
+            let batch_started_at = Instant::now();
+            apply_to_state_machine(
+                self.storage.clone(),
+                &entries_refs,
+                self.config.max_applied_log_to_keep,
+                self.purge_upto(),
+                self.config.apply_retry,
+                self.config.apply_on_blocking_pool,
+            )
             .await
             .map_err(|e| self.map_storage_error(e))?;
+            self.record_apply(start, entries_refs.len());
+            self.record_apply_batch(entries_refs.len(), batch_started_at);
 
-        self.last_applied = last_log_id;
+            self.update_applied_membership(&entries_refs);
+
+            self.last_applied = last_log_id;
+            self.update_log_usage().await?;
+
+            self.report_metrics(Update::Ignore);
+        }
 
-        self.report_metrics(Update::Ignore);
         self.trigger_log_compaction_if_needed(false);
 
         Ok(())
@@ -462,9 +538,11 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// from the AppendEntries RPC handler.
     #[tracing::instrument(level = "debug", skip(self))]
     async fn initial_replicate_to_state_machine(&mut self) -> Result<(), RaftError> {
+        // Logs sent to the apply task while this node was a leader are applied before the ones to replay.
+        self.wait_for_applied().await;
+
         let stop = std::cmp::min(self.committed.index, self.last_log_id.index) + 1;
-        let start = self.last_applied.index + 1;
-        let storage = self.storage.clone();
+        let start = self.last_applied.next_index();
 
         tracing::debug!(start, stop, %self.committed, %self.last_log_id, "start stop");
 
@@ -475,18 +553,9 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         }
 
         // Fetch the series of entries which must be applied to the state machine, then apply them.
+        self.replay_logs(start, stop).await?;
 
-        let entries = storage.get_log_entries(start..stop).await.map_err(|e| self.map_storage_error(e))?;
-
-        let new_last_applied = entries.last().unwrap();
-
-        let data_entries: Vec<_> = entries.iter().collect();
-
-        apply_to_state_machine(storage, &data_entries, self.config.max_applied_log_to_keep)
-            .await
-            .map_err(|e| self.map_storage_error(e))?;
-
-        self.last_applied = new_last_applied.log_id;
+        self.update_log_usage().await?;
         self.report_metrics(Update::Ignore);
         self.trigger_log_compaction_if_needed(false);
 
@@ -0,0 +1,43 @@
+use tokio::time::Duration;
+
+/// Sizes the batches of logs applied to the state machine at a time, when `Config::max_apply_batch` is set.
+///
+/// A batch never holds more logs than are committed and not applied yet, thus under a trickle of writes a log is
+/// applied as soon as it is committed. The size doubles, upto `max`, every time a full batch is applied within
+/// `target_latency`, i.e., logs are committed faster than they are applied. It is halved every time a batch takes
+/// longer than that.
+#[derive(Debug, Clone)]
+pub(crate) struct ApplyBatch {
+    max: u64,
+    target_latency: Duration,
+    size: u64,
+}
+
+impl ApplyBatch {
+    pub(crate) fn new(max: u64, target_latency: Duration) -> Self {
+        Self {
+            max,
+            target_latency,
+            size: 1,
+        }
+    }
+
+    /// The current max size of a batch.
+    pub(crate) fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the number of logs to apply next, out of `pending` ones waiting to be applied.
+    pub(crate) fn next_batch(&self, pending: u64) -> u64 {
+        std::cmp::min(self.size, pending)
+    }
+
+    /// Adapt the batch size to the time it took to apply a batch of `n` logs.
+    pub(crate) fn record(&mut self, n: u64, latency: Duration) {
+        if latency > self.target_latency {
+            self.size = std::cmp::max(self.size / 2, 1);
+        } else if n >= self.size {
+            self.size = std::cmp::min(self.size * 2, self.max);
+        }
+    }
+}
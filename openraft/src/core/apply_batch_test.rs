@@ -0,0 +1,58 @@
+use tokio::time::Duration;
+
+use crate::core::apply_batch::ApplyBatch;
+
+#[test]
+fn test_apply_batch_trickle() -> anyhow::Result<()> {
+    let mut b = ApplyBatch::new(64, Duration::from_millis(50));
+
+    // A single pending log is applied at once, and a batch that is not full does not grow the size.
+    for _ in 0..10 {
+        assert_eq!(1, b.next_batch(1));
+        b.record(1, Duration::from_millis(1));
+    }
+    assert_eq!(1, b.next_batch(1));
+    assert_eq!(0, b.next_batch(0));
+
+    Ok(())
+}
+
+#[test]
+fn test_apply_batch_burst() -> anyhow::Result<()> {
+    let mut b = ApplyBatch::new(64, Duration::from_millis(50));
+
+    let mut sizes = vec![];
+    let mut pending = 200;
+    while pending > 0 {
+        let n = b.next_batch(pending);
+        sizes.push(n);
+        b.record(n, Duration::from_millis(1));
+        pending -= n;
+    }
+
+    assert_eq!(vec![1, 2, 4, 8, 16, 32, 64, 64, 9], sizes);
+    assert_eq!(64, b.size(), "bounded by max");
+
+    Ok(())
+}
+
+#[test]
+fn test_apply_batch_slow_apply() -> anyhow::Result<()> {
+    let mut b = ApplyBatch::new(64, Duration::from_millis(50));
+
+    for _ in 0..6 {
+        let n = b.next_batch(1000);
+        b.record(n, Duration::from_millis(1));
+    }
+    assert_eq!(64, b.size());
+
+    b.record(64, Duration::from_millis(100));
+    assert_eq!(32, b.size(), "a slow batch halves the size");
+
+    for _ in 0..10 {
+        b.record(b.size(), Duration::from_millis(100));
+    }
+    assert_eq!(1, b.size(), "at least 1");
+
+    Ok(())
+}
@@ -0,0 +1,244 @@
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use futures::future::FutureExt;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use crate::config::ApplyRetry;
+use crate::core::apply_with_retry;
+use crate::core::client::entry_membership;
+use crate::core::client::write_response;
+use crate::core::client::ClientRequestEntry;
+use crate::core::delete_applied_logs;
+use crate::core::RaftCore;
+use crate::core::State;
+use crate::core::Update;
+use crate::error::ClientWriteError;
+use crate::error::RaftError;
+use crate::error::RaftResult;
+use crate::raft::ClientWriteResponse;
+use crate::raft::Entry;
+use crate::raft::RaftRespTx;
+use crate::AppData;
+use crate::AppDataResponse;
+use crate::LogId;
+use crate::MessageSummary;
+use crate::RaftNetwork;
+use crate::RaftStorage;
+use crate::StorageError;
+
+/// Consecutive committed logs for the apply task to apply, see `Config::apply_on_blocking_pool`.
+pub(super) struct ApplyJob<D: AppData, R: AppDataResponse> {
+    pub entries: Vec<Entry<D>>,
+
+    /// The client requests of some of the `entries`, to answer once they are applied.
+    pub reqs: Vec<ClientRequestEntry<D, R>>,
+}
+
+/// The result of an `ApplyJob`, reported back to RaftCore by the apply task.
+pub(super) struct Applied<D: AppData, R: AppDataResponse> {
+    pub job: ApplyJob<D, R>,
+
+    /// The responses of the state machine, or the panic raised by it.
+    pub result: std::thread::Result<Result<Vec<R>, StorageError>>,
+
+    /// When the apply task starts applying the job.
+    pub started_at: Instant,
+}
+
+/// Apply the jobs sent by RaftCore one after another, in the order they are sent, and report every result back.
+///
+/// It stops after the first failed job, since RaftCore shuts down on it, or once RaftCore is gone.
+pub(super) async fn run_apply_task<D, R, S>(
+    sto: Arc<S>,
+    apply_retry: ApplyRetry,
+    mut rx_apply: mpsc::UnboundedReceiver<ApplyJob<D, R>>,
+    tx_applied: mpsc::UnboundedSender<Applied<D, R>>,
+) where
+    D: AppData,
+    R: AppDataResponse,
+    S: RaftStorage<D, R>,
+{
+    while let Some(job) = rx_apply.recv().await {
+        tracing::debug!(entries=%job.entries.as_slice().summary(), "apply task recv job");
+
+        let started_at = Instant::now();
+        let entry_refs = job.entries.iter().collect::<Vec<_>>();
+        let result = AssertUnwindSafe(apply_with_retry(&sto, &entry_refs, apply_retry, true)).catch_unwind().await;
+
+        let failed = !matches!(result, Ok(Ok(_)));
+        let res = tx_applied.send(Applied {
+            job,
+            result,
+            started_at,
+        });
+
+        if failed || res.is_err() {
+            return;
+        }
+    }
+}
+
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> RaftCore<D, R, N, S> {
+    /// The last log that is applied, or is sent to the apply task to apply.
+    pub(super) fn apply_cursor(&self) -> LogId {
+        std::cmp::max(self.last_applied, self.apply_requested)
+    }
+
+    /// Send consecutive logs, following the `apply_cursor()`, to the apply task, along with the client requests to
+    /// answer once they are applied.
+    pub(super) fn send_apply_job(&mut self, entries: Vec<Entry<D>>, reqs: Vec<ClientRequestEntry<D, R>>) {
+        let (last, tx) = match (entries.last(), &self.tx_apply) {
+            (Some(last), Some(tx)) => (last.log_id, tx),
+            _ => return,
+        };
+
+        tracing::debug!(entries=%entries.as_slice().summary(), "send logs to apply task");
+
+        // The task is gone only after a failed job, whose result shuts RaftCore down.
+        if tx.send(ApplyJob { entries, reqs }).is_err() {
+            tracing::info!(%last, "apply task is stopped, logs are not applied");
+            return;
+        }
+        self.apply_requested = last;
+    }
+
+    /// Send the committed logs that are not sent yet to the apply task, in batches of `apply_batch_size()`.
+    ///
+    /// `last_applied` is updated when the task reports them applied, see `finish_applied()`.
+    pub(super) async fn apply_committed_in_background(&mut self) -> RaftResult<()> {
+        while self.apply_cursor().index < self.committed.index {
+            let cursor = self.apply_cursor();
+            let n = self.apply_batch_size(self.committed.index - cursor.index);
+            let start = cursor.next_index();
+
+            let entries = self.get_log_entries_exact(start..start + n).await?;
+            self.send_apply_job(entries, vec![]);
+        }
+        Ok(())
+    }
+
+    /// Update the state of RaftCore with a job the apply task has finished, and return the result of every client
+    /// request of it.
+    ///
+    /// A panic in the state machine is propagated, as if it is applied on this task. Metrics are not reported, the
+    /// caller does it.
+    pub(super) async fn finish_applied(
+        &mut self,
+        done: Applied<D, R>,
+    ) -> Vec<(ClientRequestEntry<D, R>, RaftResult<R>)> {
+        let Applied {
+            job: ApplyJob { entries, reqs },
+            result,
+            started_at,
+        } = done;
+
+        let result = match result {
+            Ok(x) => x,
+            Err(panic) => std::panic::resume_unwind(panic),
+        };
+
+        self.record_apply(self.perf_start().map(|_| started_at), entries.len());
+        self.record_apply_batch(entries.len(), started_at);
+
+        let resps = match result {
+            Ok(x) => x,
+            Err(err) => {
+                // Every request of the job fails with the same error.
+                let msg = err.to_string();
+                self.map_storage_error(err);
+                return reqs.into_iter().map(|req| (req, Err(RaftError::RaftStorage(anyhow!("{}", msg))))).collect();
+            }
+        };
+
+        let entry_refs = entries.iter().collect::<Vec<_>>();
+        self.update_applied_membership(&entry_refs);
+
+        let first = entries[0].log_id.index;
+        let last = entries[entries.len() - 1].log_id;
+        self.last_applied = last;
+
+        // Logs are purged here rather than by the apply task, thus RaftCore never misses a log upto `last_applied`.
+        let upto = match self.purge_upto() {
+            Some(upto) => std::cmp::min(upto, last),
+            None => last,
+        };
+        let res = delete_applied_logs(self.storage.clone(), &upto, self.config.max_applied_log_to_keep).await;
+        if let Err(err) = res {
+            self.map_storage_error(err);
+        }
+
+        // A storage error here shuts raft down, but it must not replace the result of the apply.
+        if let Err(err) = self.update_log_usage().await {
+            tracing::error!(error=%err, "failed to refresh log usage after apply");
+        }
+        self.trigger_log_compaction_if_needed(false);
+
+        let mut resps = resps.into_iter().map(Some).collect::<Vec<_>>();
+        reqs.into_iter()
+            .map(|req| {
+                let log_id = req.entry.log_id;
+                let resp = resps.get_mut((log_id.index - first) as usize).and_then(Option::take);
+                let res = resp.ok_or_else(|| RaftError::RaftStorage(anyhow!("no response of log {}", log_id)));
+                (req, res)
+            })
+            .collect()
+    }
+
+    /// Handle a job the apply task has finished while this node is not a leader: the client requests of it are from
+    /// a leadership this node has lost.
+    pub(super) async fn handle_applied(&mut self, done: Applied<D, R>) {
+        for (req, res) in self.finish_applied(done).await {
+            self.send_client_write_response(&req.entry, res, req.tx);
+        }
+        self.report_metrics(Update::Ignore);
+    }
+
+    /// Wait for the apply task to finish every job sent to it.
+    ///
+    /// It is called before logs are applied or a snapshot is installed on RaftCore, as changes to the state machine
+    /// must be serialized.
+    pub(super) async fn wait_for_applied(&mut self) {
+        while self.apply_requested > self.last_applied && self.target_state != State::Shutdown {
+            match self.rx_applied.recv().await {
+                Some(done) => self.handle_applied(done).await,
+                None => return,
+            }
+        }
+    }
+
+    /// Answer a client write with the response of the state machine.
+    pub(super) fn send_client_write_response(
+        &self,
+        entry: &Entry<D>,
+        resp: RaftResult<R>,
+        tx: Option<RaftRespTx<ClientWriteResponse<R>, ClientWriteError>>,
+    ) {
+        let tx = match tx {
+            None => return,
+            Some(x) => x,
+        };
+
+        let res = match resp {
+            Ok(data) => {
+                let res = write_response(entry.log_id, self.current_term, data, entry_membership(entry));
+                if let Err(app_err) = &res {
+                    tracing::info!(err=%app_err, entry=%entry.summary(), "state machine rejected client entry");
+                }
+                res
+            }
+            Err(raft_err) => {
+                tracing::error!(err=?raft_err, entry=%entry.summary(), "apply client entry");
+                Err(ClientWriteError::RaftError(raft_err))
+            }
+        };
+
+        let send_res = tx.send(res);
+        tracing::debug!(
+            "send client response through tx, send_res is error: {}",
+            send_res.is_err()
+        );
+    }
+}
@@ -1,27 +1,40 @@
 use std::sync::Arc;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 
 use anyhow::anyhow;
 use futures::future::TryFutureExt;
 use futures::stream::FuturesUnordered;
 use futures::stream::StreamExt;
+use tokio::sync::oneshot;
 use tokio::time::timeout;
 use tokio::time::Duration;
+use tokio::time::Instant;
 use tracing::Instrument;
 
+use crate::config::AckOn;
+use crate::core::apply_task::Applied;
 use crate::core::apply_to_state_machine;
 use crate::core::LeaderState;
 use crate::core::State;
 use crate::error::ClientReadError;
 use crate::error::ClientWriteError;
+use crate::error::EntryTooLarge;
+use crate::error::LeaderStepped;
+use crate::error::QuorumLost;
 use crate::error::RaftError;
 use crate::error::RaftResult;
+use crate::error::Throttled;
 use crate::quorum;
 use crate::raft::AppendEntriesRequest;
+use crate::raft::ClientReadResponse;
 use crate::raft::ClientWriteRequest;
 use crate::raft::ClientWriteResponse;
 use crate::raft::Entry;
 use crate::raft::EntryPayload;
+use crate::raft::Membership;
 use crate::raft::RaftRespTx;
+use crate::raft::StaleReadResponse;
 use crate::replication::RaftEvent;
 use crate::AppData;
 use crate::AppDataResponse;
@@ -41,6 +54,24 @@ pub(super) struct ClientRequestEntry<D: AppData, R: AppDataResponse> {
 
     /// The response channel for the request.
     pub tx: Option<RaftRespTx<ClientWriteResponse<R>, ClientWriteError>>,
+
+    /// The request id the client assigned to the request, see `Config::dedup_window`.
+    pub request_id: Option<String>,
+}
+
+/// A client write the leader remembers by its request id, to answer a retry of it, see `Config::dedup_window`.
+pub(super) struct RecentWrite<R: AppDataResponse> {
+    /// The log id assigned to the write.
+    pub log_id: LogId,
+
+    /// When the write is appended.
+    pub appended_at: Instant,
+
+    /// The response of the state machine, once the write is applied.
+    pub applied: Option<R>,
+
+    /// The response channels of the retries received before the write is applied.
+    pub waiting: Vec<RaftRespTx<ClientWriteResponse<R>, ClientWriteError>>,
 }
 
 impl<D: AppData, R: AppDataResponse> MessageSummary for ClientRequestEntry<D, R> {
@@ -53,15 +84,14 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     /// Commit the initial entry which new leaders are obligated to create when first coming to power, per §8.
     #[tracing::instrument(level = "trace", skip(self))]
     pub(super) async fn commit_initial_leader_entry(&mut self) -> RaftResult<()> {
-        // If the cluster has just formed, and the current index is 0, then commit the current
-        // config, else a blank payload.
-        let last_index = self.core.last_log_id.index;
-
-        let req: ClientWriteRequest<D> = if last_index == 0 {
-            ClientWriteRequest::new_config(self.core.effective_membership.membership.clone())
-        } else {
-            ClientWriteRequest::new_blank_payload()
-        };
+        // If the cluster has just formed, i.e., the membership set by `initialize()` or
+        // `initialize_from_snapshot()` is not in the log yet, then commit the current config, else a blank payload.
+        let req: ClientWriteRequest<D> =
+            if self.core.last_log_id.is_sentinel() || self.core.effective_membership.log_id > self.core.last_log_id {
+                ClientWriteRequest::new_config(self.core.effective_membership.membership.clone())
+            } else {
+                ClientWriteRequest::new_blank_payload()
+            };
 
         // Commit the initial payload to the cluster.
         let entry = self.append_payload_to_log(req.entry).await?;
@@ -72,6 +102,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         let cr_entry = ClientRequestEntry {
             entry: Arc::new(entry),
             tx: None,
+            request_id: None,
         };
         // TODO(xp): it should update the lost_log_id
         self.replicate_client_request(cr_entry).await;
@@ -92,7 +123,83 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     /// handles this by having the leader exchange heartbeat messages with a majority of the
     /// cluster before responding to read-only requests.
     #[tracing::instrument(level = "trace", skip(self, tx))]
-    pub(super) async fn handle_client_read_request(&mut self, tx: RaftRespTx<(), ClientReadError>) {
+    pub(super) async fn handle_client_read_request(&mut self, tx: RaftRespTx<ClientReadResponse, ClientReadError>) {
+        // The term in which the leadership is confirmed, and the committed log a read has to see.
+        let term = self.core.current_term;
+        let read_index = self.core.committed;
+
+        let res = self.confirm_leadership().await;
+        let _ = tx.send(res.map(|_| ClientReadResponse { term, read_index }));
+    }
+
+    /// Handle stale read requests, see `Raft::stale_read()`.
+    ///
+    /// It confirms the leadership just like a client read. If the confirmation fails and the leader is in the
+    /// read-only mode, the read is served anyway and flagged as stale.
+    #[tracing::instrument(level = "trace", skip(self, tx))]
+    pub(super) async fn handle_stale_read_request(&mut self, tx: RaftRespTx<StaleReadResponse, ClientReadError>) {
+        let term = self.core.current_term;
+
+        let res = self.confirm_leadership().await;
+
+        let res = match res {
+            Ok(()) => Ok(false),
+            Err(_) if self.core.is_stale => Ok(true),
+            Err(err) => Err(err),
+        };
+
+        let _ = tx.send(res.map(|is_stale| StaleReadResponse {
+            term,
+            last_applied: self.core.last_applied,
+            is_stale,
+        }));
+    }
+
+    /// Reply `LeaderStepped` to every write that is not committed yet, when this node is no longer the leader.
+    ///
+    /// Such a write may still be committed by the next leader, but this node can not tell.
+    pub(super) fn reject_awaiting_committed(&mut self) {
+        for req in self.awaiting_committed.drain(..) {
+            if let Some(tx) = req.tx {
+                let _ = tx.send(Err(ClientWriteError::LeaderStepped(LeaderStepped {
+                    node_id: self.core.id,
+                    term: req.entry.log_id.term,
+                })));
+            }
+        }
+
+        for recent in self.recent_writes.values_mut() {
+            for tx in recent.waiting.drain(..) {
+                let _ = tx.send(Err(ClientWriteError::LeaderStepped(LeaderStepped {
+                    node_id: self.core.id,
+                    term: recent.log_id.term,
+                })));
+            }
+        }
+    }
+
+    /// Confirm this node is still the leader by exchanging heartbeats with a quorum.
+    ///
+    /// If `Config::allow_stale_reads_on_quorum_loss` is set, a failure to reach a quorum puts the leader into the
+    /// read-only mode, and a success brings it back. The mode is also left when replication acks from a quorum come
+    /// back, see `LeaderState::try_leave_stale()`.
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn confirm_leadership(&mut self) -> Result<(), ClientReadError> {
+        let res = self.confirm_leadership_with_quorum().await;
+
+        let is_stale =
+            res.is_err() && self.core.config.allow_stale_reads_on_quorum_loss && self.core.target_state.is_leader();
+        if self.core.is_stale != is_stale {
+            tracing::info!(is_stale, "quorum-loss read-only mode changed");
+            self.core.is_stale = is_stale;
+            self.stale_since = if is_stale { Some(Instant::now()) } else { None };
+            self.leader_report_metrics();
+        }
+
+        res
+    }
+
+    async fn confirm_leadership_with_quorum(&mut self) -> Result<(), ClientReadError> {
         // Setup sentinel values to track when we've received majority confirmation of leadership.
         let mut c0_confirmed = 0usize;
 
@@ -123,8 +230,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         // If we already have all needed confirmations — which would be the case for single node
         // clusters — then respond.
         if c0_confirmed >= c0_needed && c1_confirmed >= c1_needed {
-            let _ = tx.send(Ok(()));
-            return;
+            return Ok(());
         }
 
         // Spawn parallel requests, all with the standard timeout for heartbeats.
@@ -140,6 +246,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 prev_log_id: node.matched,
                 entries: vec![],
                 leader_commit: self.core.committed,
+                relay_to: vec![],
             };
             let target = *id;
             let network = self.core.network.clone();
@@ -174,9 +281,14 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             };
 
             // If we receive a response with a greater term, then revert to follower and abort this request.
-            if data.term != self.core.current_term {
+            if data.term > self.core.current_term {
+                let term = self.core.current_term;
                 self.core.update_current_term(data.term, None);
                 self.core.set_target_state(State::Follower);
+                return Err(ClientReadError::LeaderStepped(LeaderStepped {
+                    node_id: self.core.id,
+                    term,
+                }));
             }
 
             // If the term is the same, then it means we are still the leader.
@@ -193,29 +305,84 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             }
 
             if c0_confirmed >= c0_needed && c1_confirmed >= c1_needed {
-                let _ = tx.send(Ok(()));
-                return;
+                return Ok(());
             }
         }
 
         // If we've hit this location, then we've failed to gather needed confirmations due to
         // request failures.
-        let _ = tx.send(Err(ClientReadError::RaftError(RaftError::RaftNetwork(anyhow!(
+        Err(ClientReadError::RaftError(RaftError::RaftNetwork(anyhow!(
             "too many requests failed, could not confirm leadership"
-        )))));
+        ))))
     }
 
     /// Handle client write requests.
-    #[tracing::instrument(level = "trace", skip(self, tx), fields(rpc=%rpc.summary()))]
+    #[tracing::instrument(level = "trace", skip(self, ticket_tx, tx), fields(rpc=%rpc.summary()))]
     pub(super) async fn handle_client_write_request(
         &mut self,
         rpc: ClientWriteRequest<D>,
+        ticket_tx: Option<oneshot::Sender<LogId>>,
         tx: RaftRespTx<ClientWriteResponse<R>, ClientWriteError>,
     ) {
+        // A retry of a recent write is answered with the response of the first one, instead of being appended again.
+        let term = self.core.current_term;
+        if let Some(recent) = self.recent_write(rpc.request_id.as_ref()) {
+            tracing::info!(request_id=?rpc.request_id, log_id=%recent.log_id, "client write is a retry, not appended");
+
+            if let Some(ticket_tx) = ticket_tx {
+                let _ = ticket_tx.send(recent.log_id);
+            }
+
+            match &recent.applied {
+                Some(data) => {
+                    let _ = tx.send(write_response(recent.log_id, term, data.clone(), None));
+                }
+                None => {
+                    recent.waiting.push(tx);
+                }
+            }
+            return;
+        }
+
+        if self.core.is_stale {
+            tracing::debug!("refuse client write in quorum-loss read-only mode");
+            let _ = tx.send(Err(ClientWriteError::QuorumLost(QuorumLost { node_id: self.core.id })));
+            return;
+        }
+
+        if let Some(max) = self.core.config.max_uncommitted_entries {
+            let uncommitted = self.core.last_log_id.index.saturating_sub(self.core.committed.index);
+            if uncommitted >= max {
+                tracing::debug!(uncommitted, max, "throttle client write");
+                let _ = tx.send(Err(ClientWriteError::Throttled(Throttled { uncommitted, max })));
+                return;
+            }
+        }
+
+        if let Some(limit) = self.core.config.max_entry_size_bytes {
+            let size = rpc.entry.size_hint(&self.core.config.codec);
+            if size > limit {
+                tracing::debug!(size, limit, "client write entry is too large");
+                let _ = tx.send(Err(ClientWriteError::EntryTooLarge(EntryTooLarge { size, limit })));
+                return;
+            }
+        }
+
+        // Keep invalid data out of the log: a rejected entry is never appended, thus never replicated.
+        if let EntryPayload::Normal(data) = &rpc.entry {
+            if let Err(err) = self.core.storage.validate_entry(data).await {
+                tracing::debug!(error=%err, "client write entry is rejected");
+                let _ = tx.send(Err(ClientWriteError::InvalidEntry(err)));
+                return;
+            }
+        }
+
+        let request_id = rpc.request_id;
         let entry = match self.append_payload_to_log(rpc.entry).await {
             Ok(entry) => ClientRequestEntry {
                 entry: Arc::new(entry),
                 tx: Some(tx),
+                request_id,
             },
 
             Err(err) => {
@@ -224,19 +391,88 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             }
         };
 
+        if let Some(ticket_tx) = ticket_tx {
+            let _ = ticket_tx.send(entry.entry.log_id);
+        }
+
+        if let (Some(request_id), Some(_)) = (&entry.request_id, self.core.config.dedup_window) {
+            let now = Instant::now();
+            self.recent_writes.insert(request_id.clone(), RecentWrite {
+                log_id: entry.entry.log_id,
+                appended_at: now,
+                applied: None,
+                waiting: vec![],
+            });
+            self.recent_write_order.push_back((now, request_id.clone()));
+        }
+
         self.leader_report_metrics();
 
         self.replicate_client_request(entry).await;
     }
 
-    /// Transform the given payload into an entry, assign an index and term, and append the entry to the log.
+    /// Returns the write with `request_id` this leader appended within `Config::dedup_window`, if there is one.
+    fn recent_write(&mut self, request_id: Option<&String>) -> Option<&mut RecentWrite<R>> {
+        let window = Duration::from_millis(self.core.config.dedup_window?);
+
+        // Forget the writes out of the window, oldest first. A write a retry is still waiting for is kept, and so are
+        // the ones after it, until it is applied: logs are applied in order, thus they are not applied either.
+        while let Some((appended_at, request_id)) = self.recent_write_order.front() {
+            if appended_at.elapsed() < window {
+                break;
+            }
+
+            // It may be already forgotten, or replaced by a later write with the same request id.
+            if let Some(w) = self.recent_writes.get(request_id) {
+                if w.appended_at == *appended_at {
+                    if !w.waiting.is_empty() {
+                        break;
+                    }
+                    self.recent_writes.remove(request_id);
+                }
+            }
+            self.recent_write_order.pop_front();
+        }
+
+        self.recent_writes.get_mut(request_id?)
+    }
+
+    /// Answer the retries waiting for a client write with the response of the state machine, and remember it for the
+    /// retries to come.
+    fn complete_recent_write(&mut self, request_id: &str, log_id: LogId, apply_res: &RaftResult<R>) {
+        let term = self.core.current_term;
+        let recent = match self.recent_writes.get_mut(request_id) {
+            Some(x) if x.log_id == log_id => x,
+            _ => return,
+        };
+
+        match apply_res {
+            Ok(data) => {
+                for tx in recent.waiting.drain(..) {
+                    let _ = tx.send(write_response(log_id, term, data.clone(), None));
+                }
+                recent.applied = Some(data.clone());
+            }
+            Err(_) => {
+                // The node is shutting down on a storage error, the retries are dropped with it.
+                self.recent_writes.remove(request_id);
+            }
+        }
+    }
+
+    /// Transform the given payload into an entry, assign an index, term and timestamp, and append the entry to the
+    /// log.
     #[tracing::instrument(level = "debug", skip(self, payload))]
     pub(super) async fn append_payload_to_log(&mut self, payload: EntryPayload<D>) -> RaftResult<Entry<D>> {
         let entry = Entry {
             log_id: LogId {
-                index: self.core.last_log_id.index + 1,
+                index: self.core.last_log_id.next_index(),
                 term: self.core.current_term,
             },
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
             payload,
         };
         self.core.storage.append_to_log(&[&entry]).await.map_err(|err| self.core.map_storage_error(err))?;
@@ -266,13 +502,15 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         let nodes = self.nodes.keys().collect::<Vec<_>>();
         tracing::debug!(?nodes, ?all_members, "replicate_client_request");
 
-        // Except the leader itself, there are other nodes that need to replicate log to.
-        let await_quorum = all_members.len() > 1;
+        // Except the leader itself, there are other voters that need to replicate log to.
+        // Checking the number of voters is not enough: a leader being removed is not a voter of the config it commits.
+        let await_quorum = !self.core.effective_membership.is_sole_voter(self.core.id);
 
         if await_quorum {
             self.awaiting_committed.push(req);
         } else {
-            // Else, there are no voting nodes for replication, so the payload is now committed.
+            // Else, the leader is the only voter, so the payload is committed once it is appended, without any RPC.
+            // Replication to learners below does not delay the commit.
             self.core.committed = entry_arc.log_id;
             tracing::debug!(%self.core.committed, "update committed, no need to replicate");
 
@@ -294,16 +532,159 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     /// Handle the post-commit logic for a client request.
     #[tracing::instrument(level = "debug", skip(self, req))]
     pub(super) async fn client_request_post_commit(&mut self, req: ClientRequestEntry<D, R>) {
+        if self.core.tx_apply.is_some() {
+            self.apply_in_background(vec![req]).await;
+            return;
+        }
+
         let entry = &req.entry;
+        let mut tx = req.tx;
+
+        // Respond before applying, if the client does not need the response of the state machine.
+        if self.core.config.client_write_ack == AckOn::Commit {
+            if let Some(tx) = tx.take() {
+                let _ = tx.send(Ok(ClientWriteResponse {
+                    log_id: entry.log_id,
+                    term: self.core.current_term,
+                    data: None,
+                    membership: entry_membership(entry),
+                }));
+            }
+        }
 
         let apply_res = self.apply_entry_to_state_machine(entry).await;
 
-        self.send_response(entry, apply_res, req.tx).await;
+        if let Some(request_id) = &req.request_id {
+            self.complete_recent_write(request_id, entry.log_id, &apply_res);
+        }
+
+        self.send_response(entry, apply_res, tx).await;
+
+        // Trigger log compaction if needed.
+        self.core.trigger_log_compaction_if_needed(false);
+    }
+
+    /// Handle the post-commit logic for client requests committed at once, applying them in batches of consecutive
+    /// logs, see `Config::max_apply_batch`.
+    #[tracing::instrument(level = "debug", skip(self, reqs), fields(n=reqs.len()))]
+    pub(super) async fn client_requests_post_commit(&mut self, mut reqs: Vec<ClientRequestEntry<D, R>>) {
+        while !reqs.is_empty() {
+            let limit = self.core.apply_batch_size(reqs.len() as u64) as usize;
+
+            let mut n = 1;
+            while n < limit && reqs[n].entry.log_id.index == reqs[n - 1].entry.log_id.index + 1 {
+                n += 1;
+            }
+
+            let rest = reqs.split_off(n);
+            let batch = std::mem::replace(&mut reqs, rest);
+            self.apply_batch_post_commit(batch).await;
+        }
 
         // Trigger log compaction if needed.
         self.core.trigger_log_compaction_if_needed(false);
     }
 
+    /// Apply a batch of client requests of consecutive logs and respond to them.
+    async fn apply_batch_post_commit(&mut self, mut batch: Vec<ClientRequestEntry<D, R>>) {
+        if self.core.tx_apply.is_some() {
+            self.apply_in_background(batch).await;
+            return;
+        }
+
+        self.respond_on_commit(&mut batch);
+
+        let entry_refs = batch.iter().map(|req| &*req.entry).collect::<Vec<_>>();
+
+        let apply_res = self.apply_entries_to_state_machine(&entry_refs).await;
+
+        let results = match apply_res {
+            Ok(resps) => resps.into_iter().map(Ok).collect::<Vec<_>>(),
+            Err(err) => {
+                // Every request in the batch fails with the same error.
+                let msg = err.to_string();
+                batch.iter().map(|_| Err(RaftError::RaftStorage(anyhow!("{}", msg)))).collect::<Vec<_>>()
+            }
+        };
+
+        for (req, res) in batch.into_iter().zip(results.into_iter()) {
+            if let Some(request_id) = &req.request_id {
+                self.complete_recent_write(request_id, req.entry.log_id, &res);
+            }
+
+            self.send_response(&req.entry, res, req.tx).await;
+        }
+    }
+
+    /// Respond to the client requests before applying them, if the clients do not need the response of the state
+    /// machine.
+    fn respond_on_commit(&mut self, batch: &mut [ClientRequestEntry<D, R>]) {
+        if self.core.config.client_write_ack != AckOn::Commit {
+            return;
+        }
+
+        for req in batch.iter_mut() {
+            if let Some(tx) = req.tx.take() {
+                let _ = tx.send(Ok(ClientWriteResponse {
+                    log_id: req.entry.log_id,
+                    term: self.core.current_term,
+                    data: None,
+                    membership: entry_membership(&req.entry),
+                }));
+            }
+        }
+    }
+
+    /// Send a batch of committed client requests to the apply task, see `Config::apply_on_blocking_pool`.
+    ///
+    /// The committed logs not applied yet before the batch, i.e., the ones from before this node became leader, are
+    /// sent along. The clients are answered when the task reports the batch applied, see `handle_applied()`.
+    async fn apply_in_background(&mut self, mut batch: Vec<ClientRequestEntry<D, R>>) {
+        self.respond_on_commit(&mut batch);
+
+        for req in batch.iter() {
+            self.handle_special_log(&req.entry);
+        }
+
+        let indexes = batch.iter().map(|req| req.entry.log_id.index).collect::<Vec<_>>();
+        let mut entries: Vec<Entry<D>> = Vec::with_capacity(batch.len());
+        let mut next = self.core.apply_cursor().next_index();
+
+        for (i, index) in indexes.into_iter().enumerate() {
+            if next < index {
+                match self.core.get_log_entries_exact(next..index).await {
+                    Ok(x) => entries.extend(x),
+                    Err(err) => {
+                        // The node is shutting down on a storage error.
+                        let msg = err.to_string();
+                        for req in batch {
+                            let res = Err(RaftError::RaftStorage(anyhow!("{}", msg)));
+                            self.send_response(&req.entry, res, req.tx).await;
+                        }
+                        return;
+                    }
+                }
+            }
+            entries.push((*batch[i].entry).clone());
+            next = index + 1;
+        }
+
+        self.core.send_apply_job(entries, batch);
+    }
+
+    /// Answer the client requests of a job the apply task has finished, see `Config::apply_on_blocking_pool`.
+    pub(super) async fn handle_applied(&mut self, done: Applied<D, R>) {
+        for (req, res) in self.core.finish_applied(done).await {
+            if let Some(request_id) = &req.request_id {
+                self.complete_recent_write(request_id, req.entry.log_id, &res);
+            }
+
+            self.send_response(&req.entry, res, req.tx).await;
+        }
+
+        self.leader_report_metrics();
+    }
+
     #[tracing::instrument(level = "debug", skip(self, entry, resp, tx), fields(entry=%entry.summary()))]
     pub(super) async fn send_response(
         &mut self,
@@ -311,42 +692,13 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         resp: RaftResult<R>,
         tx: Option<RaftRespTx<ClientWriteResponse<R>, ClientWriteError>>,
     ) {
-        let tx = match tx {
-            None => return,
-            Some(x) => x,
-        };
-
-        let res = match resp {
-            Ok(data) => {
-                let membership = if let EntryPayload::Membership(ref c) = entry.payload {
-                    Some(c.clone())
-                } else {
-                    None
-                };
-
-                Ok(ClientWriteResponse {
-                    log_id: entry.log_id,
-                    data,
-                    membership,
-                })
-            }
-            Err(raft_err) => {
-                tracing::error!(err=?raft_err, entry=%entry.summary(), "apply client entry");
-                Err(ClientWriteError::RaftError(raft_err))
-            }
-        };
-
-        let send_res = tx.send(res);
-        tracing::debug!(
-            "send client response through tx, send_res is error: {}",
-            send_res.is_err()
-        );
+        self.core.send_client_write_response(entry, resp, tx);
     }
 
     pub fn handle_special_log(&mut self, entry: &Entry<D>) {
         match &entry.payload {
             EntryPayload::Membership(ref m) => {
-                if m.is_in_joint_consensus() {
+                if m.is_joint() {
                     // nothing to do
                 } else {
                     self.handle_uniform_consensus_committed(&entry.log_id);
@@ -360,49 +712,49 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     /// Apply the given log entry to the state machine.
     #[tracing::instrument(level = "debug", skip(self, entry))]
     pub(super) async fn apply_entry_to_state_machine(&mut self, entry: &Entry<D>) -> RaftResult<R> {
-        self.handle_special_log(entry);
+        let res = self.apply_entries_to_state_machine(&[entry]).await?;
+        Ok(res.into_iter().next().unwrap())
+    }
+
+    /// Apply the given consecutive log entries to the state machine in one call.
+    #[tracing::instrument(level = "debug", skip(self, entries), fields(n=entries.len()))]
+    async fn apply_entries_to_state_machine(&mut self, entries: &[&Entry<D>]) -> RaftResult<Vec<R>> {
+        for entry in entries {
+            self.handle_special_log(entry);
+        }
 
         // First, we just ensure that we apply any outstanding up to, but not including, the index
-        // of the given entry. We need to be able to return the data response from applying this
-        // entry to the state machine.
+        // of the first given entry. We need to be able to return the data response from applying these
+        // entries to the state machine.
         //
         // Note that this would only ever happen if a node had unapplied logs from before becoming leader.
 
-        let log_id = &entry.log_id;
-        let index = log_id.index;
+        let log_id = &entries.last().unwrap().log_id;
+        let index = entries[0].log_id.index;
 
-        let expected_next_index = self.core.last_applied.index + 1;
+        let expected_next_index = self.core.last_applied.next_index();
         if index != expected_next_index {
-            let entries = self
-                .core
-                .storage
-                .get_log_entries(expected_next_index..index)
-                .await
-                .map_err(|err| self.core.map_storage_error(err))?;
-
-            if let Some(entry) = entries.last() {
-                self.core.last_applied = entry.log_id;
-            }
-
-            let data_entries: Vec<_> = entries.iter().collect();
-            if !data_entries.is_empty() {
-                apply_to_state_machine(
-                    self.core.storage.clone(),
-                    &data_entries,
-                    self.core.config.max_applied_log_to_keep,
-                )
-                .await
-                .map_err(|err| self.core.map_storage_error(err))?;
-            }
+            self.core.replay_logs(expected_next_index, index).await?;
         }
 
-        // Apply this entry to the state machine and return its data response.
+        // Apply these entries to the state machine and return their data responses.
+        let start = self.core.perf_start();
+        let batch_started_at = Instant::now();
         let apply_res = apply_to_state_machine(
             self.core.storage.clone(),
-            &[entry],
+            entries,
             self.core.config.max_applied_log_to_keep,
+            self.core.purge_upto(),
+            self.core.config.apply_retry,
+            self.core.config.apply_on_blocking_pool,
         )
         .await;
+        self.core.record_apply(start, entries.len());
+        self.core.record_apply_batch(entries.len(), batch_started_at);
+
+        if apply_res.is_ok() {
+            self.core.update_applied_membership(entries);
+        }
 
         let res = apply_res.map_err(|err| {
             if let StorageError::IO { .. } = err {
@@ -416,11 +768,42 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         });
 
         self.core.last_applied = *log_id;
-        self.leader_report_metrics();
-        let res = res?;
 
+        // A storage error here shuts raft down, but it must not replace the result of the apply.
+        if let Err(err) = self.core.update_log_usage().await {
+            tracing::error!(error=%err, "failed to refresh log usage after apply");
+        }
+        self.leader_report_metrics();
         // TODO(xp) merge this function to replication_to_state_machine?
 
-        Ok(res.into_iter().next().unwrap())
+        res
+    }
+}
+
+/// Build the response to a client write from the response of the state machine.
+pub(super) fn write_response<R: AppDataResponse>(
+    log_id: LogId,
+    term: u64,
+    data: R,
+    membership: Option<Membership>,
+) -> Result<ClientWriteResponse<R>, ClientWriteError> {
+    if let Some(app_err) = data.application_error() {
+        return Err(ClientWriteError::ApplicationError(app_err));
+    }
+
+    Ok(ClientWriteResponse {
+        log_id,
+        term,
+        data: Some(data),
+        membership,
+    })
+}
+
+/// Returns the membership config if the entry is a change-membership entry.
+pub(super) fn entry_membership<D: AppData>(entry: &Entry<D>) -> Option<Membership> {
+    if let EntryPayload::Membership(ref c) = entry.payload {
+        Some(c.clone())
+    } else {
+        None
     }
 }
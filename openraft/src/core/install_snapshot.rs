@@ -9,6 +9,7 @@ use crate::core::SnapshotState;
 use crate::core::State;
 use crate::core::UpdateCurrentLeader;
 use crate::error::RaftResult;
+use crate::metrics::SnapshotProgress;
 use crate::raft::InstallSnapshotRequest;
 use crate::raft::InstallSnapshotResponse;
 use crate::AppData;
@@ -35,6 +36,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         if req.term < self.current_term {
             return Ok(InstallSnapshotResponse {
                 term: self.current_term,
+                need_full_snapshot: false,
             });
         }
 
@@ -64,6 +66,52 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             self.report_metrics(Update::Ignore);
         }
 
+        // A snapshot that does not advance the state machine is useless: installing it would roll back the already
+        // applied logs. Every chunk of it is acknowledged without being written, so that the leader finishes the
+        // transfer and moves on.
+        if req.meta.last_log_id <= self.last_applied {
+            tracing::info!(
+                snapshot_last_log_id = %req.meta.last_log_id,
+                %self.last_applied,
+                "skip installing snapshot: already applied"
+            );
+
+            let is_streaming_it = match &self.snapshot_state {
+                Some(SnapshotState::Streaming { id, .. }) => id == &req.meta.snapshot_id,
+                _ => false,
+            };
+            if is_streaming_it {
+                self.snapshot_state = None;
+            }
+
+            return Ok(InstallSnapshotResponse {
+                term: self.current_term,
+                need_full_snapshot: false,
+            });
+        }
+
+        // A corrupted chunk is rejected before touching the snapshot state. The receiving stream stays at the current
+        // offset, thus when the leader resends the chunk the transfer carries on.
+        if !req.verify_checksum() {
+            let got = InstallSnapshotRequest::checksum_of(&req.data);
+            tracing::warn!(
+                snapshot_id = %req.meta.snapshot_id,
+                req.offset,
+                expect = req.checksum,
+                got,
+                "snapshot chunk checksum mismatch"
+            );
+
+            return Err(RaftError::SnapshotChecksumMismatch {
+                segment: SnapshotSegmentId {
+                    id: req.meta.snapshot_id.clone(),
+                    offset: req.offset,
+                },
+                expect: req.checksum,
+                got,
+            });
+        }
+
         // Compare current snapshot state with received RPC and handle as needed.
         // - Init a new state if it is empty or building a snapshot locally.
         // - Mismatched id with offset=0 indicates a new stream has been sent, the old one should be dropped and start
@@ -111,15 +159,38 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             });
         }
 
+        // A delta snapshot can only be installed upon its base. Otherwise ask the leader for a full snapshot.
+        if let Some(base) = &req.meta.base_snapshot_id {
+            let current = self.storage.get_current_snapshot().await.map_err(|err| self.map_storage_error(err))?;
+            let current_id = current.map(|x| x.meta.snapshot_id);
+
+            if current_id.as_ref() != Some(base) {
+                tracing::info!(
+                    %base,
+                    ?current_id,
+                    "base snapshot not found, can not install delta snapshot: {}",
+                    req.meta.snapshot_id
+                );
+
+                return Ok(InstallSnapshotResponse {
+                    term: self.current_term,
+                    need_full_snapshot: true,
+                });
+            }
+        }
+
         // Create a new snapshot and begin writing its contents.
         let mut snapshot = self.storage.begin_receiving_snapshot().await.map_err(|err| self.map_storage_error(err))?;
         snapshot.as_mut().write_all(&req.data).await?;
 
+        self.update_snapshot_receiving(&req, req.data.len() as u64);
+
         // If this was a small snapshot, and it is already done, then finish up.
         if req.done {
             self.finalize_snapshot_installation(req, snapshot).await?;
             return Ok(InstallSnapshotResponse {
                 term: self.current_term,
+                need_full_snapshot: false,
             });
         }
 
@@ -131,6 +202,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         });
         Ok(InstallSnapshotResponse {
             term: self.current_term,
+            need_full_snapshot: false,
         })
     }
 
@@ -159,6 +231,8 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         }
         offset += req.data.len() as u64;
 
+        self.update_snapshot_receiving(&req, offset);
+
         // If the snapshot stream is done, then finalize.
         if req.done {
             self.finalize_snapshot_installation(req, snapshot).await?;
@@ -167,9 +241,22 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         }
         Ok(InstallSnapshotResponse {
             term: self.current_term,
+            need_full_snapshot: false,
         })
     }
 
+    /// Report the number of bytes of a snapshot received so far.
+    ///
+    /// The total size is known only when the last chunk is received.
+    fn update_snapshot_receiving(&mut self, req: &InstallSnapshotRequest, transferred: u64) {
+        self.snapshot_receiving = Some(SnapshotProgress {
+            meta: req.meta.clone(),
+            transferred,
+            total: if req.done { Some(transferred) } else { None },
+        });
+        self.report_metrics(Update::Ignore);
+    }
+
     /// Finalize the installation of a new snapshot.
     ///
     /// Any errors which come up from this routine will cause the Raft node to go into shutdown.
@@ -195,7 +282,18 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         // --------------------------------------------------------------------> time
         // ```
 
-        // TODO(xp): do not install if self.last_applied >= snapshot.meta.last_applied
+        // A snapshot that is not newer than `last_applied` has already been rejected in
+        // `handle_install_snapshot_request()`. But logs being applied by the apply task, see
+        // `Config::apply_on_blocking_pool`, may have caught up with it since.
+        self.wait_for_applied().await;
+        if req.meta.last_log_id <= self.last_applied {
+            tracing::info!(
+                snapshot_last_log_id = %req.meta.last_log_id,
+                %self.last_applied,
+                "skip installing snapshot: already applied"
+            );
+            return Ok(());
+        }
 
         let changes = self
             .storage
@@ -210,10 +308,21 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         // If you have any question about this, let me know: drdr.xp at gmail.com
 
         if let Some(last_applied) = changes.last_applied {
-            // Applied logs are not needed.
-            delete_applied_logs(self.storage.clone(), &last_applied, self.config.max_applied_log_to_keep)
-                .await
-                .map_err(|e| self.map_storage_error(e))?;
+            match changes.purge_upto {
+                Some(purge_upto) => {
+                    // Logs subsumed by the snapshot are not needed.
+                    let upto = std::cmp::min(purge_upto, last_applied);
+                    tracing::debug!(%upto, "purge logs included in the installed snapshot");
+
+                    self.storage.delete_logs_from(..upto.next_index()).await.map_err(|e| self.map_storage_error(e))?;
+                }
+                None => {
+                    // Applied logs are not needed.
+                    delete_applied_logs(self.storage.clone(), &last_applied, self.config.max_applied_log_to_keep)
+                        .await
+                        .map_err(|e| self.map_storage_error(e))?;
+                }
+            }
 
             // snapshot is installed
             self.last_applied = last_applied;
@@ -225,17 +334,27 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 self.last_log_id = self.last_applied;
             }
 
-            // There could be unknown membership in the snapshot.
-            let membership = self.storage.get_membership().await.map_err(|err| self.map_storage_error(err))?;
+            // There could be unknown membership in the snapshot: reload the applied state from storage.
+            let applied_membership =
+                self.storage.get_committed_membership().await.map_err(|err| self.map_storage_error(err))?;
+            self.applied_membership = applied_membership;
+
+            let membership = self.get_membership().await.map_err(|err| self.map_storage_error(err))?;
             tracing::debug!("storage membership: {:?}", membership);
 
             assert!(membership.is_some());
 
             let membership = membership.unwrap();
 
+            // Configs included in the snapshot are committed.
+            let last_applied = self.last_applied.index;
+            self.pending_membership.retain(|m| m.log_id.index > last_applied);
+            self.forget_committed_membership_if_no_pending();
+
             self.update_membership(membership)?;
 
             self.snapshot_last_log_id = self.last_applied;
+            self.update_log_usage().await?;
             self.report_metrics(Update::Ignore);
         } else {
             // snapshot not installed
@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use crate::codec::CodecType;
+use crate::core::LeaderState;
+use crate::core::RaftCore;
+use crate::error::AuditFollowerError;
+use crate::error::CodecError;
+use crate::error::RaftError;
+use crate::raft::LogAuditReport;
+use crate::raft::LogAuditRequest;
+use crate::raft::LogAuditResponse;
+use crate::raft::RaftRespTx;
+use crate::AppData;
+use crate::AppDataResponse;
+use crate::MessageSummary;
+use crate::NodeId;
+use crate::RaftNetwork;
+use crate::RaftStorage;
+use crate::StorageError;
+
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> RaftCore<D, R, N, S> {
+    /// Compute the checksums of the local logs in the requested range, for a leader that audits this node.
+    ///
+    /// Logs that are purged on this node are left out, by starting from the first log in the log.
+    #[tracing::instrument(level = "debug", skip(self, rpc), fields(rpc=%rpc.summary()))]
+    pub(super) async fn handle_log_audit_request(
+        &mut self,
+        rpc: LogAuditRequest,
+    ) -> Result<LogAuditResponse, RaftError> {
+        let first = self.storage.first_id_in_log().await.map_err(|err| self.map_storage_error(err))?;
+
+        let start = match first {
+            Some(first) => std::cmp::max(rpc.start, first.index),
+            None => rpc.end,
+        };
+        let end = std::cmp::min(rpc.end, self.last_log_id.index + 1);
+
+        let checksums = match log_checksums(&self.storage, self.config.codec, start, end, rpc.chunk_size).await {
+            Ok(x) => x,
+            Err(ChecksumError::Storage(err)) => return Err(self.map_storage_error(err)),
+            Err(ChecksumError::Codec(err)) => return Err(err.into()),
+        };
+
+        Ok(LogAuditResponse { start, checksums })
+    }
+}
+
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LeaderState<'a, D, R, N, S> {
+    /// Handle the admin `audit_follower` command: compare the logs the target has matched with those of the leader.
+    ///
+    /// The RPCs are sent in a separate task, thus the leader is not blocked.
+    #[tracing::instrument(level = "debug", skip(self, tx))]
+    pub(super) fn audit_follower(&mut self, target: NodeId, tx: RaftRespTx<LogAuditReport, AuditFollowerError>) {
+        let matched = match self.nodes.get(&target) {
+            Some(x) => x.matched,
+            None => {
+                let _ = tx.send(Err(AuditFollowerError::NotFound(target)));
+                return;
+            }
+        };
+
+        let leader_id = self.core.id;
+        let network = self.core.network.clone();
+        let storage = self.core.storage.clone();
+        let codec = self.core.config.codec;
+        let chunk_size = self.core.config.max_payload_entries;
+
+        // Only the logs known to be replicated to the target have to be identical.
+        let end = matched.index + 1;
+
+        tokio::spawn(async move {
+            let res = audit(leader_id, target, end, chunk_size, codec, network, storage).await;
+            let _ = tx.send(res);
+        });
+    }
+}
+
+/// Compare the logs in `[first log on the leader, end)` on the target with those of the leader.
+///
+/// The checksums of chunks of logs are compared first, then the checksum of every log in the first differing chunk,
+/// to find the first divergent log.
+async fn audit<D, R, N, S>(
+    leader_id: NodeId,
+    target: NodeId,
+    end: u64,
+    chunk_size: u64,
+    codec: CodecType,
+    network: Arc<N>,
+    storage: Arc<S>,
+) -> Result<LogAuditReport, AuditFollowerError>
+where
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D>,
+    S: RaftStorage<D, R>,
+{
+    let first = storage.first_id_in_log().await?;
+    let start = first.map(|x| x.index).unwrap_or(end);
+
+    let rpc = LogAuditRequest {
+        leader_id,
+        start,
+        end,
+        chunk_size,
+    };
+    let (start, chunk) = match first_diff(target, rpc, codec, &network, &storage).await? {
+        (start, None) => {
+            return Ok(LogAuditReport {
+                target,
+                start,
+                end,
+                first_divergent: None,
+            })
+        }
+        (start, Some(chunk)) => (start, chunk),
+    };
+
+    let chunk_end = std::cmp::min(chunk + chunk_size, end);
+    let rpc = LogAuditRequest {
+        leader_id,
+        start: chunk,
+        end: chunk_end,
+        chunk_size: 1,
+    };
+    let (_, first_divergent) = first_diff(target, rpc, codec, &network, &storage).await?;
+
+    tracing::warn!(target, ?first_divergent, "log divergence found on follower");
+
+    Ok(LogAuditReport {
+        target,
+        start,
+        end,
+        first_divergent: Some(first_divergent.unwrap_or(chunk)),
+    })
+}
+
+/// Send the target `rpc` for the checksums of its logs, and return where the comparison starts and the first index of
+/// the first chunk that differs from the leader's.
+async fn first_diff<D, R, N, S>(
+    target: NodeId,
+    rpc: LogAuditRequest,
+    codec: CodecType,
+    network: &Arc<N>,
+    storage: &Arc<S>,
+) -> Result<(u64, Option<u64>), AuditFollowerError>
+where
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D>,
+    S: RaftStorage<D, R>,
+{
+    let (start, end, chunk_size) = (rpc.start, rpc.end, rpc.chunk_size);
+
+    let resp = network
+        .send_log_audit(target, rpc)
+        .await
+        .map_err(|source| AuditFollowerError::Network { target, source })?;
+
+    // The target may have purged more logs than the leader, those are applied and need not to be compared.
+    let start = std::cmp::max(start, resp.start);
+    let mine = log_checksums(storage, codec, start, end, chunk_size).await?;
+
+    for (i, checksum) in mine.iter().enumerate() {
+        if resp.checksums.get(i) != Some(checksum) {
+            return Ok((start, Some(start + i as u64 * chunk_size)));
+        }
+    }
+
+    Ok((start, None))
+}
+
+/// An error computing the checksums of logs.
+enum ChecksumError {
+    Storage(StorageError),
+    Codec(CodecError),
+}
+
+impl From<ChecksumError> for AuditFollowerError {
+    fn from(err: ChecksumError) -> Self {
+        match err {
+            ChecksumError::Storage(err) => err.into(),
+            ChecksumError::Codec(err) => err.into(),
+        }
+    }
+}
+
+/// Returns the checksum of every `chunk_size` logs in `[start, end)`, with the payloads encoded with `codec`.
+async fn log_checksums<D, R, S>(
+    sto: &Arc<S>,
+    codec: CodecType,
+    start: u64,
+    end: u64,
+    chunk_size: u64,
+) -> Result<Vec<u32>, ChecksumError>
+where
+    D: AppData,
+    R: AppDataResponse,
+    S: RaftStorage<D, R>,
+{
+    let chunk_size = std::cmp::max(chunk_size, 1);
+    let mut checksums = vec![];
+
+    let mut chunk_start = start;
+    while chunk_start < end {
+        let chunk_end = std::cmp::min(chunk_start + chunk_size, end);
+        let entries = sto.try_get_log_entries(chunk_start..chunk_end).await.map_err(ChecksumError::Storage)?;
+        let checksum = LogAuditResponse::checksum_of_logs(&entries, &codec).map_err(ChecksumError::Codec)?;
+        checksums.push(checksum);
+        chunk_start = chunk_end;
+    }
+
+    Ok(checksums)
+}
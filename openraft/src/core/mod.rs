@@ -2,8 +2,14 @@
 
 mod admin;
 mod append_entries;
+mod apply_batch;
+#[cfg(test)]
+mod apply_batch_test;
+mod apply_task;
 mod client;
 mod install_snapshot;
+mod log_audit;
+mod relay;
 pub(crate) mod replication;
 #[cfg(test)]
 mod replication_state_test;
@@ -14,13 +20,20 @@ mod startup_test;
 
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
+use std::collections::VecDeque;
+use std::ops::RangeBounds;
+use std::panic::AssertUnwindSafe;
 use std::sync::Arc;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 
 use futures::future::AbortHandle;
 use futures::future::Abortable;
+use futures::future::FutureExt;
 use maplit::btreeset;
-use rand::thread_rng;
+use rand::rngs::StdRng;
 use rand::Rng;
+use rand::SeedableRng;
 use serde::Deserialize;
 use serde::Serialize;
 use tokio::sync::broadcast;
@@ -28,6 +41,8 @@ use tokio::sync::mpsc;
 use tokio::sync::oneshot;
 use tokio::sync::watch;
 use tokio::task::JoinHandle;
+use tokio::time::interval;
+use tokio::time::sleep;
 use tokio::time::sleep_until;
 use tokio::time::Duration;
 use tokio::time::Instant;
@@ -35,38 +50,67 @@ use tracing::trace_span;
 use tracing::Instrument;
 use tracing::Span;
 
+use crate::config::ApplyRetry;
+use crate::config::CommitAdvance;
 use crate::config::Config;
 use crate::config::SnapshotPolicy;
+use crate::core::apply_batch::ApplyBatch;
+use crate::core::apply_task::run_apply_task;
+use crate::core::apply_task::Applied;
+use crate::core::apply_task::ApplyJob;
 use crate::core::client::ClientRequestEntry;
+use crate::core::client::RecentWrite;
 use crate::error::AddLearnerError;
 use crate::error::ClientReadError;
 use crate::error::ClientWriteError;
 use crate::error::ForwardToLeader;
 use crate::error::InitializeError;
+use crate::error::NoLeader;
+use crate::error::NotInitialized;
 use crate::error::RaftError;
 use crate::error::RaftResult;
+use crate::error::RewriteLogError;
+use crate::log_range::normalize_range;
 use crate::metrics::LeaderMetrics;
+use crate::metrics::PerfMetrics;
 use crate::metrics::RaftMetrics;
+use crate::metrics::RaftStateDump;
+use crate::metrics::RunningState;
+use crate::metrics::ShutdownReason;
+use crate::metrics::SnapshotProgress;
+use crate::metrics::StateChecksum;
+use crate::metrics::StateTransition;
+use crate::metrics::StorageFault;
+use crate::quorum;
 use crate::raft::AddLearnerResponse;
+use crate::raft::ClientReadResponse;
 use crate::raft::ClientWriteRequest;
 use crate::raft::ClientWriteResponse;
 use crate::raft::Entry;
 use crate::raft::EntryPayload;
+use crate::raft::InitialRole;
 use crate::raft::Membership;
 use crate::raft::RaftMsg;
 use crate::raft::RaftRespTx;
+use crate::replication::FollowerProgress;
 use crate::replication::ReplicaEvent;
 use crate::replication::ReplicationStream;
 use crate::storage::HardState;
 use crate::AppData;
 use crate::AppDataResponse;
+use crate::DefensiveError;
+use crate::ErrorSubject;
+use crate::ErrorVerb;
 use crate::LogId;
 use crate::MessageSummary;
 use crate::NodeId;
 use crate::RaftNetwork;
 use crate::RaftStorage;
+use crate::ReplicationMetrics;
 use crate::StorageError;
+use crate::StorageIOError;
 use crate::Update;
+use crate::Violation;
 
 /// The currently active membership config.
 ///
@@ -86,10 +130,42 @@ pub struct EffectiveMembership {
 impl EffectiveMembership {
     pub fn new_initial(node_id: u64) -> Self {
         EffectiveMembership {
-            log_id: LogId::new(0, 0),
+            log_id: LogId::none(),
             membership: Membership::new_initial(node_id),
         }
     }
+
+    /// Returns the number of voters, i.e., the nodes in any of the configs.
+    pub fn voter_count(&self) -> usize {
+        self.membership.all_nodes().len()
+    }
+
+    /// Returns true if `id` is the only voter, i.e., a log is committed as soon as `id` appends it.
+    ///
+    /// Learners do not count: they never vote and never take part in a commit.
+    pub fn is_sole_voter(&self, id: NodeId) -> bool {
+        self.voter_count() == 1 && self.membership.contains(&id)
+    }
+
+    /// Returns the minimal number of voters whose agreement constitutes a quorum.
+    ///
+    /// For a uniform config it is the majority of it, e.g., 2 for `{1,2,3}`.
+    /// For a joint config the voters have to be a majority of both configs, and the voters present in both configs
+    /// count toward both majorities, e.g., 4 for `joint({1,2,3},{4,5,6})` and 2 for `joint({1,2,3},{2,3,4})`.
+    pub fn quorum_size(&self) -> usize {
+        let configs = self.membership.get_configs();
+
+        match configs {
+            [c] => quorum::majority_of(c.len()),
+            [a, b] => {
+                let (ma, mb) = (quorum::majority_of(a.len()), quorum::majority_of(b.len()));
+                let shared = a.intersection(b).count();
+                ma + mb - std::cmp::min(shared, std::cmp::min(ma, mb))
+            }
+            // A membership has at most 2 configs. Otherwise the sum of majorities is an upper bound.
+            _ => configs.iter().map(|c| quorum::majority_of(c.len())).sum(),
+        }
+    }
 }
 
 impl MessageSummary for EffectiveMembership {
@@ -98,6 +174,9 @@ impl MessageSummary for EffectiveMembership {
     }
 }
 
+/// The number of recent state transitions a node keeps for `Raft::dump_state()`.
+const MAX_STATE_TRANSITIONS: usize = 32;
+
 /// The core type implementing the Raft protocol.
 pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
     /// This node's ID.
@@ -128,6 +207,12 @@ pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftSt
     /// The log id of the highest log entry which has been applied to the local state machine.
     last_applied: LogId,
 
+    /// The last membership applied to the state machine.
+    ///
+    /// It caches the membership returned by `RaftStorage::get_committed_membership()`: it is read from storage on
+    /// startup and after installing a snapshot, and is updated in memory when logs are applied.
+    applied_membership: Option<EffectiveMembership>,
+
     /// The current term.
     ///
     /// Is initialized to 0 on first boot, and increases monotonically. This is normally based on
@@ -144,9 +229,45 @@ pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftSt
     /// first-come-first-served basis. See §5.4.1 for additional restriction on votes.
     voted_for: Option<NodeId>,
 
+    /// Whether `current_term` or `voted_for` is updated but not saved yet.
+    ///
+    /// It is saved along with the next entries appended with `RaftStorage::append_and_save_hard_state()`, or by
+    /// `save_hard_state()` before a response is sent.
+    hard_state_dirty: bool,
+
+    /// The term of the hard state last loaded from or saved to storage.
+    ///
+    /// A hard state to save is checked against it, instead of reading the persisted one back on every save.
+    saved_term: u64,
+
     /// The last entry to be appended to the log.
     last_log_id: LogId,
 
+    /// The index of the first log in storage, or `None` if the log is empty.
+    ///
+    /// It is read from storage on startup and whenever applied logs are purged. See `RaftMetrics::log_entry_count`.
+    first_log_index: Option<u64>,
+
+    /// The size of logs in storage returned by `RaftStorage::log_size_bytes()`, updated along with `first_log_index`.
+    log_bytes: Option<u64>,
+
+    /// The state machine checksum returned by `RaftStorage::state_checksum_at()`, computed on startup and on
+    /// `Raft::state_checksum()`.
+    state_checksum: Option<StateChecksum>,
+
+    /// Membership configs received from the leader that are not committed yet, in log order.
+    ///
+    /// They are tracked only for reporting, when `Config::report_membership_on` is `Commit`. Otherwise it is always
+    /// empty. The latest of them is already the `effective_membership`.
+    pending_membership: Vec<EffectiveMembership>,
+
+    /// The last committed membership config, reported in metrics in place of `effective_membership` while
+    /// `pending_membership` is not empty.
+    committed_membership: Option<EffectiveMembership>,
+
+    /// The most recent state transitions, at most `MAX_STATE_TRANSITIONS` of them, for `Raft::dump_state()`.
+    state_transitions: VecDeque<StateTransition>,
+
     /// The node's current snapshot state.
     snapshot_state: Option<SnapshotState<S::SnapshotData>>,
 
@@ -155,6 +276,33 @@ pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftSt
     /// This is primarily used in making a determination on when a compaction job needs to be triggered.
     snapshot_last_log_id: LogId,
 
+    /// Progress of the last snapshot received from the leader.
+    snapshot_receiving: Option<SnapshotProgress>,
+
+    /// Whether this node is a leader in the quorum-loss read-only mode.
+    is_stale: bool,
+
+    /// Whether `Config::on_fatal_storage_error` has been called.
+    fatal_storage_error_reported: bool,
+
+    /// Why this node is shutting down, the first cause only. See `RaftMetrics::running_state`.
+    shutdown_reason: Option<ShutdownReason>,
+
+    /// The adaptive size of the batches of logs to apply, if `Config::max_apply_batch` is set.
+    apply_batch: Option<ApplyBatch>,
+
+    /// A random id of this process, reported in `AppendEntriesResponse::instance_uuid`.
+    ///
+    /// It is not generated with `rng`, which is deterministic if `Config::election_rng_seed` is set.
+    instance_uuid: u128,
+
+    /// The last node id found to be used by more than one process, see `RaftMetrics::duplicate_node_id`.
+    duplicate_node_id: Option<NodeId>,
+
+    /// Progress of replaying committed logs into the state machine, see `RaftMetrics::startup_replay_target`.
+    startup_replay_target: u64,
+    startup_replay_applied: u64,
+
     /// A bool indicating if this system has performed its initial replication of
     /// outstanding entries to the state machine.
     has_completed_initial_replication_to_sm: bool,
@@ -165,13 +313,42 @@ pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftSt
     /// The duration until the next election timeout.
     next_election_timeout: Option<Instant>,
 
+    /// The last time a follower read `Config::clock` and the clock of the tokio runtime, to detect a clock jump.
+    last_clock_reading: Option<(Instant, Instant)>,
+
+    /// The random number generator for election timeouts, seeded with `Config::election_rng_seed` if it is set.
+    rng: StdRng,
+
+    /// The role to start in instead of the one derived from storage, see `Raft::new_with_initial_role()`.
+    initial_role: Option<InitialRole>,
+
     tx_compaction: mpsc::Sender<SnapshotUpdate>,
     rx_compaction: mpsc::Receiver<SnapshotUpdate>,
 
+    /// Sends logs to the apply task, if `Config::apply_on_blocking_pool` is set.
+    tx_apply: Option<mpsc::UnboundedSender<ApplyJob<D, R>>>,
+    rx_applied: mpsc::UnboundedReceiver<Applied<D, R>>,
+
+    /// The last log sent to the apply task. It is applied once `last_applied` catches up with it.
+    apply_requested: LogId,
+
+    /// The replication streams to the learners this learner relays the logs to, see `Raft::set_relay()`.
+    relay_streams: BTreeMap<NodeId, ReplicationStream>,
+
+    /// The leader id and the term the relay streams replicate in.
+    relay_leader: Option<(NodeId, u64)>,
+
+    tx_relay: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData>, Span)>,
+    rx_relay: mpsc::UnboundedReceiver<(ReplicaEvent<S::SnapshotData>, Span)>,
+
     rx_api: mpsc::UnboundedReceiver<(RaftMsg<D, R>, Span)>,
 
     tx_metrics: watch::Sender<RaftMetrics>,
 
+    /// The timing stats collected if `Config::enable_tick_metrics` is set.
+    perf_metrics: PerfMetrics,
+    tx_perf_metrics: watch::Sender<PerfMetrics>,
+
     rx_shutdown: oneshot::Receiver<()>,
 }
 
@@ -181,58 +358,143 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         config: Arc<Config>,
         network: Arc<N>,
         storage: Arc<S>,
+        initial_role: Option<InitialRole>,
         rx_api: mpsc::UnboundedReceiver<(RaftMsg<D, R>, Span)>,
         tx_metrics: watch::Sender<RaftMetrics>,
+        tx_perf_metrics: watch::Sender<PerfMetrics>,
         rx_shutdown: oneshot::Receiver<()>,
     ) -> JoinHandle<RaftResult<()>> {
         let membership = Membership::new_initial(id); // This is updated from storage in the main loop.
         let (tx_compaction, rx_compaction) = mpsc::channel(1);
+        let (tx_relay, rx_relay) = mpsc::unbounded_channel();
+        let (tx_applied, rx_applied) = mpsc::unbounded_channel();
+        let tx_apply = if config.apply_on_blocking_pool {
+            let (tx_apply, rx_apply) = mpsc::unbounded_channel();
+            let task = run_apply_task(storage.clone(), config.apply_retry, rx_apply, tx_applied);
+            tokio::spawn(task.instrument(trace_span!("apply", id).or_current()));
+            Some(tx_apply)
+        } else {
+            None
+        };
+        let rng = match config.election_rng_seed {
+            Some(seed) => StdRng::seed_from_u64(seed.wrapping_add(id)),
+            None => StdRng::from_entropy(),
+        };
+        let apply_batch = config
+            .max_apply_batch
+            .map(|max| ApplyBatch::new(max, Duration::from_millis(config.heartbeat_interval)));
         let this = Self {
             id,
             config,
             effective_membership: EffectiveMembership {
-                log_id: LogId::default(),
+                log_id: LogId::none(),
                 membership,
             },
             network,
             storage,
             target_state: State::Follower,
-            committed: LogId::new(0, 0),
-            last_applied: LogId::new(0, 0),
+            committed: LogId::none(),
+            last_applied: LogId::none(),
+            applied_membership: None,
             current_term: 0,
             current_leader: None,
             voted_for: None,
-            last_log_id: LogId::new(0, 0),
+            last_log_id: LogId::none(),
             snapshot_state: None,
-            snapshot_last_log_id: LogId::new(0, 0),
+            first_log_index: None,
+            log_bytes: None,
+            state_checksum: None,
+            pending_membership: Vec::new(),
+            committed_membership: None,
+            state_transitions: VecDeque::new(),
+            snapshot_last_log_id: LogId::none(),
+            snapshot_receiving: None,
+            is_stale: false,
+            fatal_storage_error_reported: false,
+            shutdown_reason: None,
+            apply_batch,
+            hard_state_dirty: false,
+            saved_term: 0,
+            instance_uuid: rand::random(),
+            duplicate_node_id: None,
+            startup_replay_target: 0,
+            startup_replay_applied: 0,
             has_completed_initial_replication_to_sm: false,
             last_heartbeat: None,
             next_election_timeout: None,
+            last_clock_reading: None,
+            rng,
+            initial_role,
             tx_compaction,
             rx_compaction,
+            tx_apply,
+            rx_applied,
+            apply_requested: LogId::none(),
+            relay_streams: BTreeMap::new(),
+            relay_leader: None,
+            tx_relay,
+            rx_relay,
             rx_api,
             tx_metrics,
+            perf_metrics: PerfMetrics {
+                id,
+                ..Default::default()
+            },
+            tx_perf_metrics,
             rx_shutdown,
         };
         tokio::spawn(this.main().instrument(trace_span!("spawn").or_current()))
     }
 
-    /// The main loop of the Raft protocol.
+    /// Run the main loop, and report why it stops in `RaftMetrics::running_state`.
+    ///
+    /// A panic is reported too, before it is propagated to the task that awaits the core.
     #[tracing::instrument(level="trace", skip(self), fields(id=self.id, cluster=%self.config.cluster_name))]
     async fn main(mut self) -> RaftResult<()> {
+        let res = AssertUnwindSafe(self.run_main()).catch_unwind().await;
+
+        let res = match res {
+            Ok(res) => res,
+            Err(panic) => {
+                let msg = panic_message(&*panic);
+                tracing::error!(id = self.id, panic = %msg, "raft core panicked, shutting down");
+                self.shutdown_with(ShutdownReason::Panic(msg));
+                self.report_metrics(Update::Ignore);
+                std::panic::resume_unwind(panic);
+            }
+        };
+
+        if let Err(err) = &res {
+            self.shutdown_with(ShutdownReason::Error(err.to_string()));
+        }
+        self.report_metrics(Update::Ignore);
+
+        res
+    }
+
+    /// The main loop of the Raft protocol.
+    async fn run_main(&mut self) -> RaftResult<()> {
         tracing::debug!("raft node is initializing");
 
         let state = self.storage.get_initial_state().await.map_err(|err| self.map_storage_error(err))?;
         self.last_log_id = state.last_log_id;
         self.current_term = state.hard_state.current_term;
+        self.saved_term = state.hard_state.current_term;
         self.voted_for = state.hard_state.voted_for;
         self.effective_membership = state.last_membership.clone();
         self.last_applied = state.last_applied;
 
+        let applied_membership =
+            self.storage.get_committed_membership().await.map_err(|err| self.map_storage_error(err))?;
+        self.applied_membership = applied_membership;
+
+        self.update_log_usage().await?;
+        self.update_state_checksum().await?;
+
         // NOTE: this is repeated here for clarity. It is unsafe to initialize the node's commit
         // index to any other value. The commit index must be determined by a leader after
         // successfully committing a new log to the cluster.
-        self.committed = LogId::new(0, 0);
+        self.committed = LogId::none();
 
         // Fetch the most recent snapshot in the system.
         if let Some(snapshot) = self.storage.get_current_snapshot().await.map_err(|err| self.map_storage_error(err))? {
@@ -240,7 +502,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             self.report_metrics(Update::Ignore);
         }
 
-        let has_log = self.last_log_id.index != u64::MIN;
+        let has_log = !self.last_log_id.is_sentinel();
         let single = self.effective_membership.membership.all_nodes().len() == 1;
         let is_voter = self.effective_membership.membership.contains(&self.id);
 
@@ -270,11 +532,14 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             // Here we use a 30 second overhead on the initial next_election_timeout. This is because we need
             // to ensure that restarted nodes don't disrupt a stable cluster by timing out and driving up their
             // term before network communication is established.
-            let inst =
-                Instant::now() + Duration::from_millis(thread_rng().gen_range(1..3) * self.config.heartbeat_interval);
+            let inst = self.now() + Duration::from_millis(self.rng.gen_range(1..3) * self.config.heartbeat_interval);
             self.next_election_timeout = Some(inst);
         }
 
+        if let Some(role) = self.initial_role.take() {
+            self.apply_initial_role(role, has_log, is_voter);
+        }
+
         tracing::debug!("id={} target_state: {:?}", self.id, self.target_state);
 
         // This is central loop of the system. The Raft core assumes a few different roles based
@@ -283,10 +548,10 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         // if some error has been encountered, or if a state change is required.
         loop {
             match &self.target_state {
-                State::Leader => LeaderState::new(&mut self).run().await?,
-                State::Candidate => CandidateState::new(&mut self).run().await?,
-                State::Follower => FollowerState::new(&mut self).run().await?,
-                State::Learner => LearnerState::new(&mut self).run().await?,
+                State::Leader => LeaderState::new(self).run().await?,
+                State::Candidate => CandidateState::new(self).run().await?,
+                State::Follower => FollowerState::new(self).run().await?,
+                State::Learner => LearnerState::new(self).run().await?,
                 State::Shutdown => {
                     tracing::info!("node has shutdown");
                     return Ok(());
@@ -295,6 +560,35 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         }
     }
 
+    /// Start in the given role instead of the one derived from storage, if it is consistent with storage.
+    ///
+    /// Only the state and the known leader are seeded. The term, vote and logs are left as they are in storage.
+    fn apply_initial_role(&mut self, role: InitialRole, has_log: bool, is_voter: bool) {
+        match role {
+            InitialRole::Follower { leader } if has_log && is_voter && leader != self.id => {
+                self.target_state = State::Follower;
+                self.current_leader = Some(leader);
+                // Wait for the leader for a whole election timeout.
+                self.next_election_timeout = None;
+            }
+            InitialRole::Learner { leader } if leader != Some(self.id) => {
+                self.target_state = State::Learner;
+                self.current_leader = leader;
+            }
+            _ => {
+                tracing::warn!(
+                    ?role,
+                    has_log,
+                    is_voter,
+                    "initial role is inconsistent with storage, ignored"
+                );
+                return;
+            }
+        }
+
+        tracing::info!(?role, "start in the initial role");
+    }
+
     /// Report a metrics payload on the current state of the Raft node.
     #[tracing::instrument(level = "trace", skip(self))]
     fn report_metrics(&mut self, leader_metrics: Update<Option<&LeaderMetrics>>) {
@@ -307,12 +601,30 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             id: self.id,
             state: self.target_state,
             current_term: self.current_term,
+            voted_for: self.voted_for,
             last_log_index: self.last_log_id.index,
             last_applied: self.last_applied.index,
+            initialized: self.is_initialized(),
+            committed: self.committed_for_metrics(),
             current_leader: self.current_leader,
-            membership_config: self.effective_membership.clone(),
+            membership_config: self.committed_membership.as_ref().unwrap_or(&self.effective_membership).clone(),
+            voter_count: self.effective_membership.voter_count(),
+            quorum_size: self.effective_membership.quorum_size(),
             snapshot: self.snapshot_last_log_id,
+            log_entry_count: self.log_entry_count(),
+            log_bytes: self.log_bytes,
             leader_metrics,
+            snapshot_receiving: self.snapshot_receiving.clone(),
+            is_stale: self.is_stale,
+            duplicate_node_id: self.duplicate_node_id,
+            startup_replay_target: self.startup_replay_target,
+            startup_replay_applied: self.startup_replay_applied,
+            state_checksum: self.state_checksum,
+            pending_membership: self.pending_membership.last().cloned(),
+            running_state: match &self.shutdown_reason {
+                Some(reason) => RunningState::Shutdown(reason.clone()),
+                None => RunningState::Running,
+            },
         };
 
         tracing::debug!("report_metrics: {}", m.summary());
@@ -323,14 +635,285 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         }
     }
 
+    /// Replay logs in `[start, stop)` into the state machine, i.e., logs committed before this node restarted or became
+    /// a leader.
+    ///
+    /// Logs are applied in batches of at most `Config::max_payload_entries`, and the progress is reported in
+    /// `RaftMetrics::startup_replay_applied` after every batch.
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn replay_logs(&mut self, start: u64, stop: u64) -> RaftResult<()> {
+        if start >= stop {
+            return Ok(());
+        }
+
+        tracing::info!(start, stop, "replay committed logs into state machine");
+
+        self.startup_replay_target = stop - 1;
+        self.startup_replay_applied = self.last_applied.index;
+        self.report_metrics(Update::Ignore);
+
+        let mut batch_start = start;
+        while batch_start < stop {
+            let batch_end = std::cmp::min(batch_start + self.config.max_payload_entries, stop);
+
+            let entries = self.get_log_entries_exact(batch_start..batch_end).await?;
+            let entry_refs: Vec<_> = entries.iter().collect();
+
+            let perf = self.perf_start();
+            apply_to_state_machine(
+                self.storage.clone(),
+                &entry_refs,
+                self.config.max_applied_log_to_keep,
+                self.purge_upto(),
+                self.config.apply_retry,
+                self.config.apply_on_blocking_pool,
+            )
+            .await
+            .map_err(|err| self.map_storage_error(err))?;
+            self.record_apply(perf, entry_refs.len());
+
+            self.update_applied_membership(&entry_refs);
+
+            if let Some(last) = entries.last() {
+                self.last_applied = last.log_id;
+            }
+            self.startup_replay_applied = self.last_applied.index;
+            self.report_metrics(Update::Ignore);
+
+            batch_start = batch_end;
+        }
+
+        tracing::info!(%self.last_applied, "replay committed logs done");
+
+        Ok(())
+    }
+
+    /// Reload the first log index and the log size from storage, after logs are applied and purged.
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn update_log_usage(&mut self) -> RaftResult<()> {
+        let first = self.storage.first_id_in_log().await.map_err(|err| self.map_storage_error(err))?;
+        self.first_log_index = first.map(|x| x.index);
+
+        self.log_bytes = self.storage.log_size_bytes().await.map_err(|err| self.map_storage_error(err))?;
+
+        Ok(())
+    }
+
+    /// Compute the state machine checksum at the last applied log, see `Raft::state_checksum()`.
+    ///
+    /// It is not computed when logs are applied, because a checksum may have to read the whole state machine.
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn update_state_checksum(&mut self) -> RaftResult<Option<StateChecksum>> {
+        let log_id = self.last_applied;
+        let checksum = self.storage.state_checksum_at(log_id).await.map_err(|err| self.map_storage_error(err))?;
+        self.state_checksum = checksum.map(|checksum| StateChecksum { log_id, checksum });
+
+        self.report_metrics(Update::Ignore);
+        Ok(self.state_checksum)
+    }
+
+    /// The last known committed log id to report in metrics.
+    ///
+    /// An applied log is committed, even if `committed` is not yet learned from the leader, e.g., after a restart.
+    fn committed_for_metrics(&self) -> Option<LogId> {
+        std::cmp::max(self.committed, self.last_applied).to_option()
+    }
+
+    /// Returns whether the given log id is committed, as far as this node knows.
+    ///
+    /// A log id beyond the known committed index is not committed. Otherwise, the log id is committed only if the log
+    /// at its index has the same term, since an uncommitted log with the same index might have been overridden by a
+    /// newer leader. If the log is already purged and is not the last one in the snapshot, its term can not be
+    /// verified and it returns false.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) async fn is_committed(&mut self, log_id: LogId) -> Result<bool, RaftError> {
+        // The sentinel is not a log.
+        if log_id.is_sentinel() {
+            return Ok(false);
+        }
+
+        let committed = match self.committed_for_metrics() {
+            None => return Ok(false),
+            Some(x) => x,
+        };
+
+        if log_id.index > committed.index {
+            return Ok(false);
+        }
+
+        if log_id.index == committed.index
+            || log_id.index == self.last_applied.index
+            || log_id.index == self.snapshot_last_log_id.index
+        {
+            return Ok(log_id == committed || log_id == self.last_applied || log_id == self.snapshot_last_log_id);
+        }
+
+        let entry = self.storage.try_get_log_entry(log_id.index).await.map_err(|err| self.map_storage_error(err))?;
+
+        Ok(entry.map(|ent| ent.log_id == log_id).unwrap_or(false))
+    }
+
+    /// Returns the log id at `index`, without reading the log payload. See `Raft::get_log_id()`.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) async fn log_id_at(&mut self, index: u64) -> Result<Option<LogId>, RaftError> {
+        // Logs upto the snapshot may be purged, but the last one included in the snapshot is still known.
+        if index == self.snapshot_last_log_id.index && !self.snapshot_last_log_id.is_sentinel() {
+            return Ok(Some(self.snapshot_last_log_id));
+        }
+
+        if index == self.last_applied.index && !self.last_applied.is_sentinel() {
+            return Ok(Some(self.last_applied));
+        }
+
+        let log_id = self.storage.get_log_id(index).await.map_err(|err| self.map_storage_error(err))?;
+
+        Ok(log_id)
+    }
+
+    /// Replace the payload of an applied log that is included in the current snapshot.
+    ///
+    /// The log id of the log is not changed. See `Raft::rewrite_log_entry()`.
+    #[tracing::instrument(level = "debug", skip(self, payload))]
+    pub(crate) async fn rewrite_log_entry(&mut self, log_id: LogId, payload: D) -> Result<(), RewriteLogError> {
+        if log_id.is_sentinel() || log_id.index > self.last_applied.index {
+            return Err(RewriteLogError::NotApplied {
+                log_id,
+                last_applied: self.last_applied,
+            });
+        }
+
+        if log_id.index > self.snapshot_last_log_id.index {
+            return Err(RewriteLogError::NotInSnapshot {
+                log_id,
+                snapshot_last_log_id: self.snapshot_last_log_id,
+            });
+        }
+
+        let entry = self.storage.try_get_log_entry(log_id.index).await.map_err(|err| self.map_storage_error(err))?;
+        let entry = match entry {
+            Some(ent) if ent.log_id == log_id => ent,
+            _ => return Err(RewriteLogError::NotFound(log_id)),
+        };
+
+        if !matches!(entry.payload, EntryPayload::Normal(_)) {
+            return Err(RewriteLogError::NotNormal(log_id));
+        }
+
+        let rewritten =
+            self.storage.rewrite_log_entry(log_id, payload).await.map_err(|err| self.map_storage_error(err))?;
+        if !rewritten {
+            // The log may be purged after it is read.
+            let entry =
+                self.storage.try_get_log_entry(log_id.index).await.map_err(|err| self.map_storage_error(err))?;
+            if entry.map(|ent| ent.log_id) != Some(log_id) {
+                return Err(RewriteLogError::NotFound(log_id));
+            }
+            return Err(RewriteLogError::Unsupported);
+        }
+
+        tracing::info!(%log_id, "log payload is rewritten");
+        Ok(())
+    }
+
+    /// The number of logs in storage, counted from the cached first log index upto the last log id.
+    fn log_entry_count(&self) -> u64 {
+        match self.first_log_index {
+            Some(first) if self.last_log_id.index >= first => self.last_log_id.index - first + 1,
+            _ => 0,
+        }
+    }
+
+    /// Returns the instant to measure a duration from, or None if `Config::enable_tick_metrics` is not set.
+    fn perf_start(&self) -> Option<Instant> {
+        if self.config.enable_tick_metrics {
+            Some(Instant::now())
+        } else {
+            None
+        }
+    }
+
+    /// Record the time an iteration of the core loop took, since `start` returned by `perf_start()`.
+    fn record_tick(&mut self, start: Option<Instant>) {
+        if let Some(start) = start {
+            let elapsed = start.elapsed();
+            self.perf_metrics.ticks += 1;
+            self.perf_metrics.last_tick = elapsed;
+            self.perf_metrics.max_tick = std::cmp::max(self.perf_metrics.max_tick, elapsed);
+            self.report_perf_metrics();
+        }
+    }
+
+    /// Record the time applying `n` logs took, since `start` returned by `perf_start()`.
+    fn record_apply(&mut self, start: Option<Instant>, n: usize) {
+        if let Some(start) = start {
+            self.perf_metrics.last_apply_entries = n as u64;
+            self.perf_metrics.last_apply = start.elapsed();
+            self.report_perf_metrics();
+        }
+    }
+
+    /// Returns the number of logs to apply in the next batch, out of `pending` committed ones. See
+    /// `Config::max_apply_batch`.
+    fn apply_batch_size(&self, pending: u64) -> u64 {
+        match &self.apply_batch {
+            Some(b) => b.next_batch(pending),
+            None => pending,
+        }
+    }
+
+    /// Adapt the size of the batches to apply to the time it took to apply `n` logs, since `start`.
+    fn record_apply_batch(&mut self, n: usize, start: Instant) {
+        if let Some(b) = &mut self.apply_batch {
+            b.record(n as u64, start.elapsed());
+            tracing::debug!(n, size = b.size(), "apply batch size");
+        }
+    }
+
+    fn report_perf_metrics(&mut self) {
+        let res = self.tx_perf_metrics.send(self.perf_metrics.clone());
+        if let Err(err) = res {
+            tracing::debug!(error=%err, id=self.id, "error reporting perf metrics");
+        }
+    }
+
     /// Save the Raft node's current hard state to disk.
+    ///
+    /// The term in storage must never go backward. Saving a term lower than the one last saved is a fatal error and
+    /// shuts down the node.
     #[tracing::instrument(level = "trace", skip(self))]
     async fn save_hard_state(&mut self) -> RaftResult<()> {
+        let hs = self.hard_state_to_save()?;
+
+        self.storage.save_hard_state(&hs).await.map_err(|err| self.map_storage_error(err))?;
+        self.hard_state_saved(&hs);
+        Ok(())
+    }
+
+    /// Build the hard state to save, and check that it does not go backward.
+    ///
+    /// It is checked against the term last loaded or saved by this node. A term written to the store behind the back
+    /// of raft is caught by a defensive store, see `DefensiveCheck`.
+    fn hard_state_to_save(&mut self) -> RaftResult<HardState> {
         let hs = HardState {
             current_term: self.current_term,
             voted_for: self.voted_for,
         };
-        self.storage.save_hard_state(&hs).await.map_err(|err| self.map_storage_error(err))
+
+        if hs.current_term < self.saved_term {
+            let err = DefensiveError::new(ErrorSubject::HardState, Violation::TermNotAscending {
+                curr: self.saved_term,
+                to: hs.current_term,
+            });
+            return Err(self.map_storage_error(err.into()));
+        }
+
+        Ok(hs)
+    }
+
+    /// Record that `hs` is durably saved.
+    fn hard_state_saved(&mut self, hs: &HardState) {
+        self.hard_state_dirty = false;
+        self.saved_term = hs.current_term;
     }
 
     /// Update core's target state, ensuring all invariants are upheld.
@@ -338,11 +921,73 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     fn set_target_state(&mut self, target_state: State) {
         tracing::debug!(id = self.id, ?target_state, "set_target_state");
 
-        if target_state == State::Follower && !self.effective_membership.membership.contains(&self.id) {
-            self.target_state = State::Learner;
-        } else {
-            self.target_state = target_state;
+        // Only a leader can be in the read-only mode.
+        if target_state != State::Leader {
+            self.is_stale = false;
         }
+
+        let target_state =
+            if target_state == State::Follower && !self.effective_membership.membership.contains(&self.id) {
+                State::Learner
+            } else {
+                target_state
+            };
+
+        if target_state != self.target_state {
+            if self.state_transitions.len() == MAX_STATE_TRANSITIONS {
+                self.state_transitions.pop_front();
+            }
+            self.state_transitions.push_back(StateTransition {
+                from: self.target_state,
+                to: target_state,
+                term: self.current_term,
+                unix_time_ms: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or_default(),
+            });
+
+            if let Some(handler) = &self.config.on_state_change {
+                (handler.0)(self.target_state, target_state);
+            }
+        }
+
+        self.target_state = target_state;
+    }
+
+    /// Build a dump of the state of this node, with the replication progress provided by a leader.
+    fn dump_state(&self, replication: BTreeMap<NodeId, ReplicationMetrics>) -> RaftStateDump {
+        RaftStateDump {
+            id: self.id,
+            state: self.target_state,
+            current_term: self.current_term,
+            voted_for: self.voted_for,
+            current_leader: self.current_leader,
+            committed: self.committed,
+            last_applied: self.last_applied,
+            last_log_id: self.last_log_id,
+            snapshot_last_log_id: self.snapshot_last_log_id,
+            effective_membership: self.effective_membership.clone(),
+            pending_membership: self.pending_membership.last().cloned(),
+            replication,
+            state_transitions: self.state_transitions.iter().cloned().collect(),
+        }
+    }
+
+    /// Generate a new random election timeout within the configured min & max.
+    fn new_rand_election_timeout(&mut self) -> u64 {
+        let t = self.rng.gen_range(self.config.election_timeout_min..self.config.election_timeout_max);
+        t + self.election_priority_delay()
+    }
+
+    /// The extra time to wait before starting an election, to let voters with a higher priority campaign first.
+    ///
+    /// Every distinct priority higher than that of this node, see `Membership::priority_rank()`, adds the width of
+    /// the election timeout range. Thus a preferred voter almost always times out first, while the others still
+    /// start an election if it is down.
+    fn election_priority_delay(&self) -> u64 {
+        let rank = self.effective_membership.membership.priority_rank(&self.id) as u64;
+        rank * (self.config.election_timeout_max - self.config.election_timeout_min)
     }
 
     /// Get the next election timeout, generating a new value if not set.
@@ -351,9 +996,9 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         match self.next_election_timeout {
             Some(inst) => inst,
             None => {
-                let t = Duration::from_millis(self.config.new_rand_election_timeout());
+                let t = Duration::from_millis(self.new_rand_election_timeout());
                 tracing::debug!("create election timeout after: {:?}", t);
-                let inst = Instant::now() + t;
+                let inst = self.now() + t;
                 self.next_election_timeout = Some(inst);
                 inst
             }
@@ -365,9 +1010,9 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// If `heartbeat=true`, then also update the value of `last_heartbeat`.
     #[tracing::instrument(level = "trace", skip(self))]
     fn update_next_election_timeout(&mut self, heartbeat: bool) {
-        let now = Instant::now();
+        let now = self.now();
 
-        let t = Duration::from_millis(self.config.new_rand_election_timeout());
+        let t = Duration::from_millis(self.new_rand_election_timeout());
         tracing::debug!("update election timeout after: {:?}", t);
 
         self.next_election_timeout = Some(now + t);
@@ -376,6 +1021,50 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         }
     }
 
+    /// Returns the current instant of `Config::clock`, or of the tokio runtime if it is not set.
+    fn now(&self) -> Instant {
+        match &self.config.clock {
+            Some(clock) => clock.0.now(),
+            None => Instant::now(),
+        }
+    }
+
+    /// Convert an instant of `Config::clock` to an instant of the tokio runtime, to sleep until it.
+    fn to_runtime_instant(&self, inst: Instant) -> Instant {
+        Instant::now() + inst.saturating_duration_since(self.now())
+    }
+
+    /// Re-base the election timer if `Config::clock` moved further than the clock of the tokio runtime since the last
+    /// reading.
+    ///
+    /// The time the runtime does not see, e.g., when the clock jumps after a VM migration, should not count toward the
+    /// election timeout: the leader has had no chance to send a heartbeat that this node could see. Time spent by this
+    /// node, e.g., in a slow storage write, is seen by both clocks and is never a jump.
+    fn rebase_on_clock_jump(&mut self) {
+        if self.config.clock.is_none() {
+            return;
+        }
+
+        let now = self.now();
+        let runtime_now = Instant::now();
+
+        if let Some((prev, runtime_prev)) = self.last_clock_reading {
+            let elapsed = now.saturating_duration_since(prev);
+            let runtime_elapsed = runtime_now.saturating_duration_since(runtime_prev);
+
+            // The two clocks are not read at the same instant: a difference within 1 ms is not a jump.
+            let jump = elapsed.saturating_sub(runtime_elapsed);
+            if jump > Duration::from_millis(1) {
+                tracing::warn!(?jump, id = self.id, "clock jump detected, re-base election timeout");
+                if let Some(t) = self.next_election_timeout {
+                    self.next_election_timeout = Some(t + jump);
+                }
+            }
+        }
+
+        self.last_clock_reading = Some((now, runtime_now));
+    }
+
     /// Update the value of the `current_leader` property.
     #[tracing::instrument(level = "trace", skip(self))]
     fn update_current_leader(&mut self, update: UpdateCurrentLeader) {
@@ -406,19 +1095,133 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// This method assumes that a storage error observed here is non-recoverable. As such, the
     /// Raft node will be instructed to stop. If such behavior is not needed, then don't use this
     /// interface.
+    ///
+    /// `err` is an io error writing to the store, e.g. to a snapshot being received. It is reported just like a
+    /// `StorageError`, see `map_storage_error()`.
     #[tracing::instrument(level = "trace", skip(self))]
     fn map_fatal_storage_error(&mut self, err: anyhow::Error) -> RaftError {
-        tracing::error!({error=?err, id=self.id}, "fatal storage error, shutting down");
-        self.set_target_state(State::Shutdown);
-        RaftError::RaftStorage(err)
+        let err = match err.downcast::<StorageError>() {
+            Ok(storage_err) => storage_err,
+            Err(err) => StorageIOError::new(ErrorSubject::Store, ErrorVerb::Write, err).into(),
+        };
+        self.map_storage_error(err)
     }
 
     fn map_storage_error(&mut self, err: StorageError) -> RaftError {
         tracing::error!({error=?err, id=self.id}, "fatal storage error, shutting down");
-        self.set_target_state(State::Shutdown);
+        self.report_fatal_storage_error(&err);
+        self.shutdown_with(ShutdownReason::StorageFault(StorageFault {
+            error: err.to_string(),
+            transient: err.is_transient(),
+        }));
         RaftError::RaftStorage(err.into())
     }
 
+    /// Go into shutdown for `reason`, unless it is already shutting down for another one: the first cause is kept.
+    pub(self) fn shutdown_with(&mut self, reason: ShutdownReason) {
+        if self.shutdown_reason.is_none() {
+            tracing::info!(id = self.id, ?reason, "shutting down");
+            self.shutdown_reason = Some(reason);
+        }
+        self.set_target_state(State::Shutdown);
+    }
+
+    /// Call the application's `Config::on_fatal_storage_error` with the first fatal storage error.
+    fn report_fatal_storage_error(&mut self, err: &StorageError) {
+        if self.fatal_storage_error_reported {
+            return;
+        }
+        self.fatal_storage_error_reported = true;
+
+        if let Some(handler) = &self.config.on_fatal_storage_error {
+            (handler.0)(err);
+        }
+    }
+
+    /// Read logs in `range` from storage and check that they exactly cover the range.
+    ///
+    /// The range is not clamped to the known logs: every index in it is expected, thus a caller always gets as many
+    /// entries as it asks for. A store that returns a gap or a short read is buggy: it is a fatal error and Raft goes
+    /// into shutdown.
+    async fn get_log_entries_exact<RB: RangeBounds<u64>>(&mut self, range: RB) -> RaftResult<Vec<Entry<D>>> {
+        // Only turn the bounds into a half-open range.
+        let range = normalize_range(&range, 0, u64::MAX - 1);
+        let (start, end) = (range.start, range.end);
+
+        let entries = self.storage.get_log_entries(range).await.map_err(|err| self.map_storage_error(err))?;
+        check_log_entries_cover(start, end, &entries).map_err(|err| self.map_storage_error(err))?;
+        Ok(entries)
+    }
+
+    /// Update the cached applied membership with the entries that have just been applied to the state machine.
+    fn update_applied_membership(&mut self, entries: &[&Entry<D>]) {
+        let last_membership = entries.iter().rev().find_map(|ent| match &ent.payload {
+            EntryPayload::Membership(mem) => Some(EffectiveMembership {
+                log_id: ent.log_id,
+                membership: mem.clone(),
+            }),
+            _ => None,
+        });
+
+        if last_membership.is_some() {
+            self.applied_membership = last_membership;
+        }
+    }
+
+    /// Returns the last membership config found in log or state machine.
+    ///
+    /// The same as `RaftStorage::get_membership()`, except that the membership in state machine is read from the
+    /// cached `applied_membership`.
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn get_membership(&self) -> Result<Option<EffectiveMembership>, StorageError> {
+        let sm_mem_index = match &self.applied_membership {
+            None => 0,
+            Some(mem) => mem.log_id.index,
+        };
+
+        let log_mem = self.storage.last_membership_in_log(sm_mem_index + 1).await?;
+
+        if log_mem.is_some() {
+            return Ok(log_mem);
+        }
+
+        Ok(self.applied_membership.clone())
+    }
+
+    /// Report the pending membership configs that are committed, with `ReportMembershipOn::Commit`.
+    ///
+    /// They are already in use: only the config reported in metrics changes.
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn apply_committed_membership(&mut self) {
+        let committed = self.committed.index;
+        let n = self.pending_membership.iter().take_while(|m| m.log_id.index <= committed).count();
+
+        if let Some(conf) = self.pending_membership.drain(..n).last() {
+            tracing::debug!({membership=%conf.summary()}, "pending membership config is committed");
+            self.committed_membership = Some(conf);
+        }
+        self.forget_committed_membership_if_no_pending();
+    }
+
+    /// Track configs appended to the log as pending, with `ReportMembershipOn::Commit`, and remember the committed
+    /// one to report until they are committed.
+    fn append_pending_membership(&mut self, confs: &[EffectiveMembership]) {
+        if confs.is_empty() {
+            return;
+        }
+        if self.pending_membership.is_empty() {
+            self.committed_membership = Some(self.effective_membership.clone());
+        }
+        self.pending_membership.extend_from_slice(confs);
+    }
+
+    /// Report `effective_membership` again once no config is pending.
+    fn forget_committed_membership_if_no_pending(&mut self) {
+        if self.pending_membership.is_empty() {
+            self.committed_membership = None;
+        }
+    }
+
     /// Update the node's current membership config & save hard state.
     #[tracing::instrument(level = "trace", skip(self))]
     fn update_membership(&mut self, cfg: EffectiveMembership) -> RaftResult<()> {
@@ -455,23 +1258,38 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         }
     }
 
+    /// Returns the greatest log id that may be purged after applying logs, or `None` if there is no bound.
+    ///
+    /// With `SnapshotPolicy::Never`, a log is kept until an explicit snapshot includes it.
+    pub(self) fn purge_upto(&self) -> Option<LogId> {
+        match self.config.snapshot_policy {
+            SnapshotPolicy::LogsSinceLast(_) => None,
+            SnapshotPolicy::Never => Some(self.snapshot_last_log_id),
+        }
+    }
+
     /// Trigger a log compaction (snapshot) job if needed.
     /// If force is True, it will skip the threshold check and start creating snapshot as demanded.
+    /// With `SnapshotPolicy::Never`, a job is started only if force is True.
     #[tracing::instrument(level = "trace", skip(self))]
     pub(self) fn trigger_log_compaction_if_needed(&mut self, force: bool) {
         if self.snapshot_state.is_some() {
             return;
         }
-        let SnapshotPolicy::LogsSinceLast(threshold) = &self.config.snapshot_policy;
         // Check to ensure we have actual entries for compaction.
-        if self.last_applied.index == 0 || self.last_applied.index < self.snapshot_last_log_id.index {
+        if self.last_applied.is_sentinel() || self.last_applied.index < self.snapshot_last_log_id.index {
             return;
         }
 
         if !force {
-            // If we are below the threshold, then there is nothing to do.
-            if self.last_applied.index < self.snapshot_last_log_id.index + *threshold {
-                return;
+            match &self.config.snapshot_policy {
+                SnapshotPolicy::LogsSinceLast(threshold) => {
+                    // If we are below the threshold, then there is nothing to do.
+                    if self.last_applied.index < self.snapshot_last_log_id.index + *threshold {
+                        return;
+                    }
+                }
+                SnapshotPolicy::Never => return,
             }
         }
 
@@ -487,7 +1305,12 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
         tokio::spawn(
             async move {
-                let f = storage.do_log_compaction();
+                // Build from a checkpoint, so that logs are applied to the live state machine meanwhile.
+                let f = async move {
+                    let checkpoint = storage.begin_checkpoint().await?;
+                    tracing::debug!(?checkpoint, "begin log compaction from checkpoint");
+                    storage.do_log_compaction_from(checkpoint).await
+                };
                 let res = Abortable::new(f, reg).await;
                 match res {
                     Ok(res) => match res {
@@ -512,7 +1335,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// Reject an init config request due to the Raft node being in a state which prohibits the request.
     #[tracing::instrument(level = "trace", skip(self, tx))]
     fn reject_init_with_config(&self, tx: oneshot::Sender<Result<(), InitializeError>>) {
-        let _ = tx.send(Err(InitializeError::NotAllowed));
+        let _ = tx.send(Err(InitializeError::AlreadyInitialized));
     }
 
     /// Reject a proposed config change request due to the Raft node being in a state which prohibits the request.
@@ -526,6 +1349,14 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         let _ = tx.send(Err(err.into()));
     }
 
+    /// Returns true if this node has ever received any log or snapshot.
+    ///
+    /// A node without any log has never been initialized, nor been added to a cluster.
+    /// It is reported in `RaftMetrics::initialized`, which `Raft::is_initialized()` reads.
+    pub(crate) fn is_initialized(&self) -> bool {
+        !self.last_log_id.is_sentinel() || !self.last_applied.is_sentinel()
+    }
+
     /// Forward the given client write request to the leader.
     #[tracing::instrument(level = "trace", skip(self, req, tx))]
     fn forward_client_write_request(
@@ -535,9 +1366,14 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     ) {
         match req.entry {
             EntryPayload::Normal(_entry) => {
-                let _ = tx.send(Err(ClientWriteError::ForwardToLeader(ForwardToLeader {
-                    leader_id: self.current_leader,
-                })));
+                if !self.is_initialized() {
+                    let _ = tx.send(Err(ClientWriteError::NotInitialized(NotInitialized {
+                        node_id: self.id,
+                    })));
+                    return;
+                }
+
+                let _ = tx.send(Err(self.not_leader_error()));
             }
             _ => {
                 // This is unreachable, and well controlled by the type system, but let's log an
@@ -549,38 +1385,162 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
     /// Forward the given client read request to the leader.
     #[tracing::instrument(level = "trace", skip(self, tx))]
-    fn forward_client_read_request(&self, tx: RaftRespTx<(), ClientReadError>) {
-        let _ = tx.send(Err(ClientReadError::ForwardToLeader(ForwardToLeader {
-            leader_id: self.current_leader,
-        })));
+    fn forward_client_read_request<T>(&self, tx: RaftRespTx<T, ClientReadError>) {
+        let _ = tx.send(Err(self.not_leader_error()));
+    }
+
+    /// Build the error for a client request received by a non-leader: `ForwardToLeader` if a leader is known,
+    /// otherwise `NoLeader`.
+    fn not_leader_error<E>(&self) -> E
+    where E: From<ForwardToLeader> + From<NoLeader> {
+        match self.current_leader {
+            Some(leader_id) => ForwardToLeader {
+                leader_id: Some(leader_id),
+            }
+            .into(),
+            None => NoLeader { node_id: self.id }.into(),
+        }
     }
 }
 
+/// Render the payload of a panic, which is a `&str` or a `String` if it is raised by `panic!()`.
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = panic.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = panic.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Check that `entries` are exactly the logs in `[start, end)`, in order and without a gap.
+fn check_log_entries_cover<D: AppData>(start: u64, end: u64, entries: &[Entry<D>]) -> Result<(), StorageError> {
+    let want = end.saturating_sub(start) as usize;
+
+    for i in 0..std::cmp::max(want, entries.len()) {
+        let expected = if i < want { Some(start + i as u64) } else { None };
+        let got = entries.get(i).map(|x| x.log_id.index);
+
+        if expected != got {
+            return Err(StorageError::ShortRead {
+                start,
+                end,
+                expected,
+                got,
+            });
+        }
+    }
+
+    Ok(())
+}
+
 #[tracing::instrument(level = "trace", skip(sto), fields(entries=%entries.summary()))]
 async fn apply_to_state_machine<D, R, S>(
     sto: Arc<S>,
     entries: &[&Entry<D>],
     max_keep: u64,
+    purge_upto: Option<LogId>,
+    apply_retry: ApplyRetry,
+    on_blocking_pool: bool,
 ) -> Result<Vec<R>, StorageError>
 where
     D: AppData,
     R: AppDataResponse,
     S: RaftStorage<D, R>,
 {
-    tracing::debug!(entries=%entries.summary(), max_keep, "apply_to_state_machine");
+    tracing::debug!(entries=%entries.summary(), max_keep, ?purge_upto, "apply_to_state_machine");
 
     let last = entries.last().map(|x| x.log_id);
 
     if let Some(last_applied) = last {
         // TODO(xp): apply_to_state_machine should return the last applied
-        let res = sto.apply_to_state_machine(entries).await?;
-        delete_applied_logs(sto, &last_applied, max_keep).await?;
+        let res = apply_with_retry(&sto, entries, apply_retry, on_blocking_pool).await?;
+        let upto = match purge_upto {
+            Some(upto) => std::cmp::min(upto, last_applied),
+            None => last_applied,
+        };
+        delete_applied_logs(sto, &upto, max_keep).await?;
         Ok(res)
     } else {
         Ok(vec![])
     }
 }
 
+/// Apply logs to the state machine, and apply them again on a transient storage error as `apply_retry` allows.
+async fn apply_with_retry<D, R, S>(
+    sto: &Arc<S>,
+    entries: &[&Entry<D>],
+    apply_retry: ApplyRetry,
+    on_blocking_pool: bool,
+) -> Result<Vec<R>, StorageError>
+where
+    D: AppData,
+    R: AppDataResponse,
+    S: RaftStorage<D, R>,
+{
+    let (max_retries, mut backoff) = match apply_retry {
+        ApplyRetry::Never => return apply_once(sto, entries, on_blocking_pool).await,
+        ApplyRetry::Backoff {
+            max_retries,
+            backoff_ms,
+        } => (max_retries, Duration::from_millis(backoff_ms)),
+    };
+
+    let mut retries = 0;
+    loop {
+        match apply_once(sto, entries, on_blocking_pool).await {
+            Err(err) if err.is_transient() && retries < max_retries => {
+                retries += 1;
+                tracing::warn!(error=%err, retries, ?backoff, "transient error applying logs, retry");
+
+                sleep(backoff).await;
+                backoff = backoff.saturating_mul(2);
+            }
+            res => return res,
+        }
+    }
+}
+
+/// Apply logs to the state machine, on the blocking thread pool if `on_blocking_pool` is set.
+///
+/// With `Config::apply_on_blocking_pool` set, committed logs are applied by the apply task, which RaftCore does not
+/// wait for. RaftCore waits only for replaying the logs committed before it starts, see `replay_logs()`.
+async fn apply_once<D, R, S>(
+    sto: &Arc<S>,
+    entries: &[&Entry<D>],
+    on_blocking_pool: bool,
+) -> Result<Vec<R>, StorageError>
+where
+    D: AppData,
+    R: AppDataResponse,
+    S: RaftStorage<D, R>,
+{
+    if !on_blocking_pool {
+        return sto.apply_to_state_machine(entries).await;
+    }
+
+    let sto = sto.clone();
+    let entries = entries.iter().map(|x| (*x).clone()).collect::<Vec<_>>();
+    let rt = tokio::runtime::Handle::current();
+
+    let res = tokio::task::spawn_blocking(move || {
+        let entry_refs = entries.iter().collect::<Vec<_>>();
+        rt.block_on(sto.apply_to_state_machine(&entry_refs))
+    })
+    .await;
+
+    match res {
+        Ok(x) => x,
+        // A panic in the state machine is propagated, as if it is called on this task.
+        Err(join_err) if join_err.is_panic() => std::panic::resume_unwind(join_err.into_panic()),
+        Err(join_err) => {
+            let err = anyhow::anyhow!("apply task is cancelled: {}", join_err);
+            Err(StorageIOError::new(ErrorSubject::StateMachine, ErrorVerb::Write, err).into())
+        }
+    }
+}
+
 #[tracing::instrument(level = "trace", skip(sto))]
 async fn delete_applied_logs<D, R, S>(sto: Arc<S>, last_applied: &LogId, max_keep: u64) -> Result<(), StorageError>
 where
@@ -589,7 +1549,7 @@ where
     S: RaftStorage<D, R>,
 {
     // TODO(xp): periodically batch delete
-    let x = last_applied.index + 1;
+    let x = last_applied.next_index();
     let x = x.saturating_sub(max_keep);
 
     tracing::debug!(%last_applied, max_keep, delete_lt = x, "delete_applied_logs");
@@ -644,6 +1604,9 @@ pub(self) enum SnapshotUpdate {
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum State {
     /// The node is completely passive; replicating entries, but neither voting nor timing out.
+    ///
+    /// It never becomes a candidate by itself: it leaves this state only when it receives a membership config that
+    /// contains it.
     Learner,
     /// The node is replicating logs from the leader.
     Follower,
@@ -697,6 +1660,19 @@ struct LeaderState<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: Raf
 
     /// A buffer of client requests which have been appended locally and are awaiting to be committed to the cluster.
     pub(super) awaiting_committed: Vec<ClientRequestEntry<D, R>>,
+
+    /// The client writes appended by this leader within `Config::dedup_window`, by request id.
+    pub(super) recent_writes: BTreeMap<String, RecentWrite<R>>,
+
+    /// The request ids in `recent_writes` in the order they are appended, to forget them when they are out of the
+    /// window without scanning all of them.
+    pub(super) recent_write_order: VecDeque<(Instant, String)>,
+
+    /// The relay of every learner that receives the logs from another learner, see `Raft::set_relay()`.
+    pub(super) relays: BTreeMap<NodeId, NodeId>,
+
+    /// When the leader entered the quorum-loss read-only mode, see `Config::allow_stale_reads_on_quorum_loss`.
+    pub(super) stale_since: Option<Instant>,
 }
 
 impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LeaderState<'a, D, R, N, S> {
@@ -710,12 +1686,20 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             replication_tx,
             replication_rx,
             awaiting_committed: Vec::new(),
+            recent_writes: BTreeMap::new(),
+            recent_write_order: VecDeque::new(),
+            relays: BTreeMap::new(),
+            stale_since: None,
         }
     }
 
     /// Transition to the Raft leader state.
     #[tracing::instrument(level="debug", skip(self), fields(id=self.core.id, raft_state="leader"))]
     pub(self) async fn run(mut self) -> RaftResult<()> {
+        // A leader reports the latest config in its log, which it already uses.
+        self.core.pending_membership.clear();
+        self.core.forget_committed_membership_if_no_pending();
+
         // Spawn replication streams.
         let targets = self
             .core
@@ -744,10 +1728,17 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
     #[tracing::instrument(level="debug", skip(self), fields(id=self.core.id))]
     pub(self) async fn leader_loop(mut self) -> RaftResult<()> {
+        // It is only used with `CommitAdvance::Batched`.
+        let mut commit_tick = interval(Duration::from_millis(self.core.config.heartbeat_interval));
+
         loop {
             if !self.core.target_state.is_leader() {
                 tracing::info!("id={} state becomes: {:?}", self.core.id, self.core.target_state);
 
+                if self.core.target_state != State::Shutdown {
+                    self.reject_awaiting_committed();
+                }
+
                 // implicit drop replication_rx
                 // notify to all nodes DO NOT send replication event any more.
                 return Ok(());
@@ -758,20 +1749,33 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
             tokio::select! {
                 Some((msg,span)) = self.core.rx_api.recv() => {
+                    let start = self.core.perf_start();
                     self.handle_msg(msg).instrument(span).await;
+                    self.core.record_tick(start);
                 },
                 Some(update) = self.core.rx_compaction.recv() => {
                     tracing::info!("leader recv from rx_compaction: {:?}", update);
                     self.core.update_snapshot_state(update);
                 }
+                Some(done) = self.core.rx_applied.recv() => {
+                    let start = self.core.perf_start();
+                    self.handle_applied(done).await;
+                    self.core.record_tick(start);
+                }
                 Some((event, span)) = self.replication_rx.recv() => {
                     tracing::info!("leader recv from replication_rx: {:?}", event.summary());
                     let _ent = span.enter();
+                    let start = self.core.perf_start();
                     self.handle_replica_event(event).await;
+                    self.core.record_tick(start);
+                }
+                _ = commit_tick.tick(), if self.core.config.commit_advance == CommitAdvance::Batched => {
+                    self.advance_commit().await;
+                    self.leader_report_metrics();
                 }
                 Ok(_) = &mut self.core.rx_shutdown => {
                     tracing::info!("leader recv from rx_shudown");
-                    self.core.set_target_state(State::Shutdown);
+                    self.core.shutdown_with(ShutdownReason::Requested);
                 }
             }
         }
@@ -791,29 +1795,125 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             RaftMsg::InstallSnapshot { rpc, tx } => {
                 let _ = tx.send(self.core.handle_install_snapshot_request(rpc).await);
             }
+            RaftMsg::LogAudit { rpc, tx } => {
+                let _ = tx.send(self.core.handle_log_audit_request(rpc).await);
+            }
             RaftMsg::ClientReadRequest { tx } => {
                 self.handle_client_read_request(tx).await;
             }
-            RaftMsg::ClientWriteRequest { rpc, tx } => {
-                self.handle_client_write_request(rpc, tx).await;
+            RaftMsg::StaleReadRequest { tx } => {
+                self.handle_stale_read_request(tx).await;
+            }
+            RaftMsg::ClientWriteRequest { rpc, ticket_tx, tx } => {
+                self.handle_client_write_request(rpc, ticket_tx, tx).await;
             }
             RaftMsg::Initialize { tx, .. } => {
                 self.core.reject_init_with_config(tx);
             }
-            RaftMsg::AddLearner { id, tx, blocking } => {
-                self.add_learner(id, tx, blocking);
+            RaftMsg::InitializeFromSnapshot { tx, .. } => {
+                self.core.reject_init_with_config(tx);
+            }
+            RaftMsg::AddLearner {
+                id,
+                tx,
+                blocking,
+                standby,
+            } => {
+                self.add_learner(id, tx, blocking, standby);
+            }
+            RaftMsg::ChangeMembership {
+                members,
+                blocking,
+                demote,
+                tx,
+            } => {
+                self.change_membership(members, demote, blocking, tx).await;
+            }
+            RaftMsg::ChangeMembershipDryRun { members, tx } => {
+                let _ = tx.send(self.change_membership_dry_run(members).map_err(|e| e.into()));
+            }
+            RaftMsg::SetPriorities { priorities, tx } => {
+                self.set_priorities(priorities, tx).await;
+            }
+            RaftMsg::StepDown { tx } => {
+                self.step_down(tx);
+            }
+            RaftMsg::PauseReplication { target, paused, tx } => {
+                self.pause_replication(target, paused, tx);
+            }
+            RaftMsg::SetRelay { learner, relay, tx } => {
+                self.set_relay(learner, relay, tx);
+            }
+            RaftMsg::AuditFollower { target, tx } => {
+                self.audit_follower(target, tx);
+            }
+            RaftMsg::IsCommitted { log_id, tx } => {
+                let _ = tx.send(self.core.is_committed(log_id).await);
             }
-            RaftMsg::ChangeMembership { members, blocking, tx } => {
-                self.change_membership(members, blocking, tx).await;
+            RaftMsg::GetLogId { index, tx } => {
+                let _ = tx.send(self.core.log_id_at(index).await);
+            }
+            RaftMsg::RewriteLogEntry { log_id, payload, tx } => {
+                let res = match self.check_replicated_to_all(log_id) {
+                    Ok(()) => self.core.rewrite_log_entry(log_id, payload).await,
+                    Err(err) => Err(err),
+                };
+                let _ = tx.send(res);
+            }
+            RaftMsg::TriggerSnapshot { tx } => {
+                self.core.trigger_log_compaction_if_needed(true);
+                let _ = tx.send(Ok(()));
+            }
+            RaftMsg::GetStateChecksum { tx } => {
+                let _ = tx.send(self.core.update_state_checksum().await);
+            }
+            RaftMsg::DumpState { tx } => {
+                let replication = self.leader_metrics.replication.iter().map(|(id, m)| (*id, m.clone())).collect();
+                let _ = tx.send(Ok(self.core.dump_state(replication)));
+            }
+            RaftMsg::GetReplicationTable { tx } => {
+                let _ = tx.send(Ok(Some(self.replication_table())));
             }
         }
     }
 
+    /// Check that every replication target has replicated `log_id`, before it is rewritten on this leader.
+    fn check_replicated_to_all(&self, log_id: LogId) -> Result<(), RewriteLogError> {
+        for (target, state) in self.nodes.iter() {
+            if state.matched < log_id {
+                return Err(RewriteLogError::NotReplicated {
+                    log_id,
+                    target: *target,
+                    matched: state.matched,
+                });
+            }
+        }
+        Ok(())
+    }
+
     /// Report metrics with leader specific states.
     #[tracing::instrument(level = "trace", skip(self))]
     pub fn leader_report_metrics(&mut self) {
         self.core.report_metrics(Update::Update(Some(&self.leader_metrics)));
     }
+
+    /// Build the replication progress of every target. See `Raft::replication_table()`.
+    fn replication_table(&self) -> BTreeMap<NodeId, FollowerProgress> {
+        self.nodes
+            .iter()
+            .map(|(id, node)| {
+                let state = self.leader_metrics.replication.get(id).map(|m| m.state).unwrap_or_default();
+                let progress = FollowerProgress {
+                    matched: node.matched,
+                    next_index: node.next_index,
+                    in_flight: node.in_flight,
+                    last_contact: node.last_ack.map(|t| t.elapsed()),
+                    state,
+                };
+                (*id, progress)
+            })
+            .collect()
+    }
 }
 
 /// A struct tracking the state of a replication stream from the perspective of the Raft actor.
@@ -822,6 +1922,22 @@ struct ReplicationState {
     pub remove_since: Option<u64>,
     pub repl_stream: ReplicationStream,
 
+    /// Whether the target is a standby that is not promoted to a voter yet, see `Raft::add_standby()`.
+    pub standby: bool,
+
+    /// Whether the target is a voter demoted to a learner, see `Raft::demote_voter()`. The replication to it is kept
+    /// after it is removed from the membership config.
+    pub demoted: bool,
+
+    /// The index of the first log to send next, as reported by the replication stream.
+    pub next_index: u64,
+
+    /// The number of logs sent and not acknowledged yet, as reported by the replication stream.
+    pub in_flight: u64,
+
+    /// When the target responded last time, as reported by the replication stream.
+    pub last_ack: Option<Instant>,
+
     /// The response channel to use for when this node has successfully synced with the cluster.
     pub tx: Option<RaftRespTx<AddLearnerResponse, AddLearnerError>>,
 }
@@ -893,7 +2009,8 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 if !self.core.target_state.is_candidate() {
                     return Ok(());
                 }
-                let timeout_fut = sleep_until(self.core.get_next_election_timeout());
+                let timeout = self.core.get_next_election_timeout();
+                let timeout_fut = sleep_until(self.core.to_runtime_instant(timeout));
 
                 let span = tracing::debug_span!("CHrx:CandidateState");
                 let _ent = span.enter();
@@ -902,10 +2019,13 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                     _ = timeout_fut => break, // This election has timed-out. Break to outer loop, which starts a new term.
                     Some((res, peer)) = pending_votes.recv() => self.handle_vote_response(res, peer).await?,
                     Some((msg,span)) = self.core.rx_api.recv() => {
+                        let start = self.core.perf_start();
                         self.handle_msg(msg).instrument(span).await;
+                        self.core.record_tick(start);
                     },
                     Some(update) = self.core.rx_compaction.recv() => self.core.update_snapshot_state(update),
-                    Ok(_) = &mut self.core.rx_shutdown => self.core.set_target_state(State::Shutdown),
+                    Some(done) = self.core.rx_applied.recv() => self.core.handle_applied(done).await,
+                    Ok(_) = &mut self.core.rx_shutdown => self.core.shutdown_with(ShutdownReason::Requested),
                 }
             }
         }
@@ -924,21 +2044,70 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             RaftMsg::InstallSnapshot { rpc, tx } => {
                 let _ = tx.send(self.core.handle_install_snapshot_request(rpc).await);
             }
+            RaftMsg::LogAudit { rpc, tx } => {
+                let _ = tx.send(self.core.handle_log_audit_request(rpc).await);
+            }
             RaftMsg::ClientReadRequest { tx } => {
                 self.core.forward_client_read_request(tx);
             }
-            RaftMsg::ClientWriteRequest { rpc, tx } => {
+            RaftMsg::StaleReadRequest { tx } => {
+                self.core.forward_client_read_request(tx);
+            }
+            RaftMsg::ClientWriteRequest { rpc, tx, .. } => {
                 self.core.forward_client_write_request(rpc, tx);
             }
             RaftMsg::Initialize { tx, .. } => {
                 self.core.reject_init_with_config(tx);
             }
+            RaftMsg::InitializeFromSnapshot { tx, .. } => {
+                self.core.reject_init_with_config(tx);
+            }
             RaftMsg::AddLearner { tx, .. } => {
                 self.core.reject_config_change_not_leader(tx);
             }
             RaftMsg::ChangeMembership { tx, .. } => {
                 self.core.reject_config_change_not_leader(tx);
             }
+            RaftMsg::ChangeMembershipDryRun { tx, .. } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::SetPriorities { tx, .. } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::StepDown { tx } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::PauseReplication { tx, .. } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::SetRelay { tx, .. } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::AuditFollower { tx, .. } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::IsCommitted { log_id, tx } => {
+                let _ = tx.send(self.core.is_committed(log_id).await);
+            }
+            RaftMsg::GetLogId { index, tx } => {
+                let _ = tx.send(self.core.log_id_at(index).await);
+            }
+            RaftMsg::RewriteLogEntry { log_id, payload, tx } => {
+                let _ = tx.send(self.core.rewrite_log_entry(log_id, payload).await);
+            }
+            RaftMsg::TriggerSnapshot { tx } => {
+                self.core.trigger_log_compaction_if_needed(true);
+                let _ = tx.send(Ok(()));
+            }
+            RaftMsg::GetStateChecksum { tx } => {
+                let _ = tx.send(self.core.update_state_checksum().await);
+            }
+            RaftMsg::DumpState { tx } => {
+                let _ = tx.send(Ok(self.core.dump_state(BTreeMap::new())));
+            }
+            RaftMsg::GetReplicationTable { tx } => {
+                let _ = tx.send(Ok(None));
+            }
         }
     }
 }
@@ -959,24 +2128,36 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     #[tracing::instrument(level="debug", skip(self), fields(id=self.core.id, raft_state="follower"))]
     pub(self) async fn run(mut self) -> RaftResult<()> {
         self.core.report_metrics(Update::Update(None));
+        self.core.last_clock_reading = None;
+
         loop {
             if !self.core.target_state.is_follower() {
                 return Ok(());
             }
 
-            let election_timeout = sleep_until(self.core.get_next_election_timeout()); // Value is updated as heartbeats are received.
+            self.core.rebase_on_clock_jump();
+            // Value is updated as heartbeats are received.
+            let timeout = self.core.get_next_election_timeout();
+            let election_timeout = sleep_until(self.core.to_runtime_instant(timeout));
 
             tokio::select! {
                 // If an election timeout is hit, then we need to transition to candidate.
                 _ = election_timeout => {
-                    tracing::debug!("timeout to recv a event, change to CandidateState");
-                    self.core.set_target_state(State::Candidate)
+                    // The clock may have jumped during the sleep, which is then not a timeout.
+                    self.core.rebase_on_clock_jump();
+                    if self.core.now() >= self.core.get_next_election_timeout() {
+                        tracing::debug!("timeout to recv a event, change to CandidateState");
+                        self.core.set_target_state(State::Candidate)
+                    }
                 },
                 Some((msg,span)) = self.core.rx_api.recv() => {
+                    let start = self.core.perf_start();
                     self.handle_msg(msg).instrument(span).await;
+                    self.core.record_tick(start);
                 },
                 Some(update) = self.core.rx_compaction.recv() => self.core.update_snapshot_state(update),
-                Ok(_) = &mut self.core.rx_shutdown => self.core.set_target_state(State::Shutdown),
+                Some(done) = self.core.rx_applied.recv() => self.core.handle_applied(done).await,
+                Ok(_) = &mut self.core.rx_shutdown => self.core.shutdown_with(ShutdownReason::Requested),
             }
         }
     }
@@ -995,21 +2176,70 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             RaftMsg::InstallSnapshot { rpc, tx } => {
                 let _ = tx.send(self.core.handle_install_snapshot_request(rpc).await);
             }
+            RaftMsg::LogAudit { rpc, tx } => {
+                let _ = tx.send(self.core.handle_log_audit_request(rpc).await);
+            }
             RaftMsg::ClientReadRequest { tx } => {
                 self.core.forward_client_read_request(tx);
             }
-            RaftMsg::ClientWriteRequest { rpc, tx } => {
+            RaftMsg::StaleReadRequest { tx } => {
+                self.core.forward_client_read_request(tx);
+            }
+            RaftMsg::ClientWriteRequest { rpc, tx, .. } => {
                 self.core.forward_client_write_request(rpc, tx);
             }
             RaftMsg::Initialize { tx, .. } => {
                 self.core.reject_init_with_config(tx);
             }
+            RaftMsg::InitializeFromSnapshot { tx, .. } => {
+                self.core.reject_init_with_config(tx);
+            }
             RaftMsg::AddLearner { tx, .. } => {
                 self.core.reject_config_change_not_leader(tx);
             }
             RaftMsg::ChangeMembership { tx, .. } => {
                 self.core.reject_config_change_not_leader(tx);
             }
+            RaftMsg::ChangeMembershipDryRun { tx, .. } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::SetPriorities { tx, .. } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::StepDown { tx } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::PauseReplication { tx, .. } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::SetRelay { tx, .. } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::AuditFollower { tx, .. } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::IsCommitted { log_id, tx } => {
+                let _ = tx.send(self.core.is_committed(log_id).await);
+            }
+            RaftMsg::GetLogId { index, tx } => {
+                let _ = tx.send(self.core.log_id_at(index).await);
+            }
+            RaftMsg::RewriteLogEntry { log_id, payload, tx } => {
+                let _ = tx.send(self.core.rewrite_log_entry(log_id, payload).await);
+            }
+            RaftMsg::TriggerSnapshot { tx } => {
+                self.core.trigger_log_compaction_if_needed(true);
+                let _ = tx.send(Ok(()));
+            }
+            RaftMsg::GetStateChecksum { tx } => {
+                let _ = tx.send(self.core.update_state_checksum().await);
+            }
+            RaftMsg::DumpState { tx } => {
+                let _ = tx.send(Ok(self.core.dump_state(BTreeMap::new())));
+            }
+            RaftMsg::GetReplicationTable { tx } => {
+                let _ = tx.send(Ok(None));
+            }
         }
     }
 }
@@ -1040,10 +2270,16 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
             tokio::select! {
                 Some((msg,span)) = self.core.rx_api.recv() => {
+                    let start = self.core.perf_start();
                     self.handle_msg(msg).instrument(span).await;
+                    self.core.record_tick(start);
                 },
                 Some(update) = self.core.rx_compaction.recv() => self.core.update_snapshot_state(update),
-                Ok(_) = &mut self.core.rx_shutdown => self.core.set_target_state(State::Shutdown),
+                Some(done) = self.core.rx_applied.recv() => self.core.handle_applied(done).await,
+                Some((event, span)) = self.core.rx_relay.recv() => {
+                    self.core.handle_relay_event(event).instrument(span).await;
+                },
+                Ok(_) = &mut self.core.rx_shutdown => self.core.shutdown_with(ShutdownReason::Requested),
             }
         }
     }
@@ -1062,21 +2298,75 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             RaftMsg::InstallSnapshot { rpc, tx } => {
                 let _ = tx.send(self.core.handle_install_snapshot_request(rpc).await);
             }
+            RaftMsg::LogAudit { rpc, tx } => {
+                let _ = tx.send(self.core.handle_log_audit_request(rpc).await);
+            }
             RaftMsg::ClientReadRequest { tx } => {
                 self.core.forward_client_read_request(tx);
             }
-            RaftMsg::ClientWriteRequest { rpc, tx } => {
+            RaftMsg::StaleReadRequest { tx } => {
+                self.core.forward_client_read_request(tx);
+            }
+            RaftMsg::ClientWriteRequest { rpc, tx, .. } => {
                 self.core.forward_client_write_request(rpc, tx);
             }
             RaftMsg::Initialize { members, tx } => {
                 let _ = tx.send(self.handle_init_with_config(members).await);
             }
+            RaftMsg::InitializeFromSnapshot {
+                members,
+                meta,
+                data,
+                tx,
+            } => {
+                let _ = tx.send(self.handle_init_from_snapshot(members, meta, data).await);
+            }
             RaftMsg::AddLearner { tx, .. } => {
                 self.core.reject_config_change_not_leader(tx);
             }
             RaftMsg::ChangeMembership { tx, .. } => {
                 self.core.reject_config_change_not_leader(tx);
             }
+            RaftMsg::ChangeMembershipDryRun { tx, .. } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::SetPriorities { tx, .. } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::StepDown { tx } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::PauseReplication { tx, .. } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::SetRelay { tx, .. } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::AuditFollower { tx, .. } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::IsCommitted { log_id, tx } => {
+                let _ = tx.send(self.core.is_committed(log_id).await);
+            }
+            RaftMsg::GetLogId { index, tx } => {
+                let _ = tx.send(self.core.log_id_at(index).await);
+            }
+            RaftMsg::RewriteLogEntry { log_id, payload, tx } => {
+                let _ = tx.send(self.core.rewrite_log_entry(log_id, payload).await);
+            }
+            RaftMsg::TriggerSnapshot { tx } => {
+                self.core.trigger_log_compaction_if_needed(true);
+                let _ = tx.send(Ok(()));
+            }
+            RaftMsg::GetStateChecksum { tx } => {
+                let _ = tx.send(self.core.update_state_checksum().await);
+            }
+            RaftMsg::DumpState { tx } => {
+                let _ = tx.send(Ok(self.core.dump_state(BTreeMap::new())));
+            }
+            RaftMsg::GetReplicationTable { tx } => {
+                let _ = tx.send(Ok(None));
+            }
         }
     }
 }
@@ -0,0 +1,105 @@
+use crate::core::RaftCore;
+use crate::replication::RaftEvent;
+use crate::replication::ReplicaEvent;
+use crate::replication::ReplicationStream;
+use crate::AppData;
+use crate::AppDataResponse;
+use crate::NodeId;
+use crate::RaftNetwork;
+use crate::RaftStorage;
+
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> RaftCore<D, R, N, S> {
+    /// Update the replication streams to the learners this node relays the logs to, once an AppendEntries RPC from
+    /// the leader is accepted, and let them replicate the logs this node has now.
+    ///
+    /// Only a learner relays. The streams replicate in the term and with the id of the leader, thus a relayed learner
+    /// treats them as the leader.
+    pub(super) fn update_relay(&mut self, leader_id: NodeId, term: u64, relay_to: &[NodeId]) {
+        let relay_to = if self.target_state.is_learner() { relay_to } else { &[] };
+
+        // Streams of another leader or term would replicate with a stale term.
+        if self.relay_leader != Some((leader_id, term)) {
+            self.relay_streams.clear();
+            self.relay_leader = Some((leader_id, term));
+        }
+
+        self.relay_streams.retain(|target, _| {
+            let keep = relay_to.contains(target);
+            if !keep {
+                tracing::info!(target, "stop relaying logs");
+            }
+            keep
+        });
+
+        for target in relay_to {
+            if *target == self.id || self.relay_streams.contains_key(target) {
+                continue;
+            }
+
+            tracing::info!(target, leader_id, term, "start relaying logs");
+
+            let stream = ReplicationStream::new(
+                leader_id,
+                *target,
+                term,
+                self.config.clone(),
+                self.last_log_id,
+                self.committed,
+                self.network.clone(),
+                self.storage.clone(),
+                self.tx_relay.clone(),
+                false,
+            );
+            self.relay_streams.insert(*target, stream);
+        }
+
+        for stream in self.relay_streams.values() {
+            let _ = stream.repl_tx.send((
+                RaftEvent::Replicate {
+                    appended: self.last_log_id,
+                    committed: self.committed,
+                },
+                tracing::debug_span!("CH"),
+            ));
+        }
+    }
+
+    /// Handle an event coming from one of the replication streams this node relays the logs with.
+    ///
+    /// The progress of a relayed learner is tracked by the streams only, the leader does not need it.
+    #[tracing::instrument(level = "trace", skip(self, event))]
+    pub(super) async fn handle_relay_event(&mut self, event: ReplicaEvent<S::SnapshotData>) {
+        match event {
+            ReplicaEvent::NeedsSnapshot { target, tx } => {
+                let current = match self.storage.get_current_snapshot().await {
+                    Ok(x) => x,
+                    Err(err) => {
+                        let _ = self.map_storage_error(err);
+                        return;
+                    }
+                };
+
+                match current {
+                    Some(snapshot) => {
+                        let _ = tx.send(snapshot);
+                    }
+                    None => {
+                        // The stream asks again once `tx` is dropped.
+                        tracing::info!(target, "build a snapshot for a relayed learner");
+                        self.trigger_log_compaction_if_needed(true);
+                    }
+                }
+            }
+            ReplicaEvent::RevertToFollower { target, term } => {
+                // The leader learns about the greater term by itself.
+                tracing::info!(target, term, "relayed learner has a greater term, stop relaying logs");
+                self.relay_streams.remove(&target);
+            }
+            ReplicaEvent::Shutdown => {
+                tracing::error!("relaying logs is stopped by a storage error");
+                self.relay_streams.clear();
+            }
+            _ => {}
+        }
+    }
+}
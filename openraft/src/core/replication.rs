@@ -1,8 +1,11 @@
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::time::Duration;
 
 use tokio::sync::oneshot;
 use tracing_futures::Instrument;
 
+use crate::config::CommitAdvance;
 use crate::config::SnapshotPolicy;
 use crate::core::LeaderState;
 use crate::core::ReplicationState;
@@ -11,8 +14,12 @@ use crate::core::State;
 use crate::core::UpdateCurrentLeader;
 use crate::error::AddLearnerError;
 use crate::error::RaftResult;
+use crate::metrics::ShutdownReason;
+use crate::metrics::SnapshotProgress;
+use crate::metrics::StorageFault;
 use crate::raft::AddLearnerResponse;
 use crate::raft::RaftRespTx;
+use crate::replication::AddLearnerState;
 use crate::replication::RaftEvent;
 use crate::replication::ReplicaEvent;
 use crate::replication::ReplicationStream;
@@ -24,7 +31,6 @@ use crate::LogId;
 use crate::NodeId;
 use crate::RaftNetwork;
 use crate::RaftStorage;
-use crate::ReplicationMetrics;
 
 impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LeaderState<'a, D, R, N, S> {
     /// Spawn a new replication stream returning its replication state handle.
@@ -44,15 +50,43 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             self.core.network.clone(),
             self.core.storage.clone(),
             self.replication_tx.clone(),
+            self.allows_idle_heartbeat(target),
         );
         ReplicationState {
-            matched: LogId { term: 0, index: 0 },
+            matched: LogId::none(),
             repl_stream,
             remove_since: None,
+            standby: false,
+            demoted: false,
+            next_index: self.core.last_log_id.index + 1,
+            in_flight: 0,
+            last_ack: None,
             tx: caller_tx,
         }
     }
 
+    /// Whether the heartbeat to a target may slow down when it is idle: only a learner, which never starts an
+    /// election, may go without heartbeats for longer than `heartbeat_interval`. See
+    /// `Config::slow_idle_learner_heartbeat`.
+    pub(super) fn allows_idle_heartbeat(&self, target: NodeId) -> bool {
+        self.core.config.slow_idle_learner_heartbeat && !self.core.effective_membership.membership.contains(&target)
+    }
+
+    /// Tell every replication stream whether its heartbeat may slow down, after the membership changes.
+    pub(super) fn update_idle_heartbeat(&self) {
+        if !self.core.config.slow_idle_learner_heartbeat {
+            return;
+        }
+
+        for (target, node) in self.nodes.iter() {
+            let idle_heartbeat = self.allows_idle_heartbeat(*target);
+            let _ = node.repl_stream.repl_tx.send((
+                RaftEvent::SetIdleHeartbeat { idle_heartbeat },
+                tracing::debug_span!("CH"),
+            ));
+        }
+    }
+
     /// Handle a replication event coming from one of the replication streams.
     #[tracing::instrument(level = "trace", skip(self, event), fields(event=%event.summary()))]
     pub(super) async fn handle_replica_event(&mut self, event: ReplicaEvent<S::SnapshotData>) {
@@ -60,8 +94,65 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             ReplicaEvent::RevertToFollower { target, term } => self.handle_revert_to_follower(target, term).await,
             ReplicaEvent::UpdateMatched { target, matched } => self.handle_update_matched(target, matched).await,
             ReplicaEvent::NeedsSnapshot { target, tx } => self.handle_needs_snapshot(target, tx).await,
+            ReplicaEvent::UpdateReplicationState { target, state } => {
+                if self.nodes.contains_key(&target) {
+                    self.leader_metrics.replication.entry(target).or_default().state = state;
+                    self.leader_report_metrics();
+                }
+                Ok(())
+            }
+            ReplicaEvent::UpdateProgress {
+                target,
+                next_index,
+                in_flight,
+                last_ack,
+            } => {
+                if let Some(state) = self.nodes.get_mut(&target) {
+                    state.next_index = next_index;
+                    state.in_flight = in_flight;
+                    state.last_ack = last_ack;
+                }
+                self.try_leave_stale();
+                Ok(())
+            }
+            ReplicaEvent::Responded { target } => {
+                if self.nodes.contains_key(&target) {
+                    self.leader_metrics.replication.entry(target).or_default().add_learner_state =
+                        AddLearnerState::Ready;
+                    self.leader_report_metrics();
+                }
+                Ok(())
+            }
+            ReplicaEvent::UpdateSnapshotProgress { target, progress } => {
+                self.handle_update_snapshot_progress(target, progress)
+            }
+            ReplicaEvent::UpdateRpcLatency { target, latency } => {
+                if self.nodes.contains_key(&target) {
+                    self.core.perf_metrics.replication_rpc.insert(target, latency);
+                    self.core.report_perf_metrics();
+                }
+                Ok(())
+            }
+            ReplicaEvent::Unreachable { target, elapsed } => {
+                self.handle_unreachable(target, elapsed);
+                Ok(())
+            }
+            ReplicaEvent::DuplicateNodeId { target } => {
+                tracing::error!(
+                    id = self.core.id,
+                    target,
+                    "duplicate node id: more than one node runs with id {}",
+                    target
+                );
+                self.core.duplicate_node_id = Some(target);
+                self.leader_report_metrics();
+                Ok(())
+            }
             ReplicaEvent::Shutdown => {
-                self.core.set_target_state(State::Shutdown);
+                self.core.shutdown_with(ShutdownReason::StorageFault(StorageFault {
+                    error: "a replication stream failed to read storage".to_string(),
+                    transient: false,
+                }));
                 return;
             }
         };
@@ -71,6 +162,61 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         }
     }
 
+    /// Leave the quorum-loss read-only mode once a quorum has acknowledged a replication RPC since it is entered.
+    ///
+    /// Without it, a leader that regained its quorum would refuse writes until the next read confirms the leadership.
+    fn try_leave_stale(&mut self) {
+        let since = match self.stale_since {
+            Some(x) => x,
+            None => return,
+        };
+
+        let mut acked = BTreeSet::new();
+        acked.insert(self.core.id);
+        for (id, state) in self.nodes.iter() {
+            if state.last_ack.map(|t| t > since).unwrap_or(false) {
+                acked.insert(*id);
+            }
+        }
+
+        if !self.core.effective_membership.membership.is_majority(&acked) {
+            return;
+        }
+
+        tracing::info!(
+            is_stale = false,
+            "quorum-loss read-only mode changed: a quorum acked replication"
+        );
+        self.core.is_stale = false;
+        self.stale_since = None;
+        self.leader_report_metrics();
+    }
+
+    /// Evict a learner that has not responded for `Config::learner_eviction_timeout`.
+    ///
+    /// A voter is never evicted: removing it is a membership change and is left to the application.
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn handle_unreachable(&mut self, target: NodeId, elapsed: Duration) {
+        if self.core.effective_membership.membership.contains(&target) {
+            tracing::debug!(target, ?elapsed, "voter is unreachable, it is not evicted");
+            return;
+        }
+
+        if !self.nodes.contains_key(&target) {
+            return;
+        }
+
+        tracing::warn!(
+            id = self.core.id,
+            target,
+            ?elapsed,
+            "evict learner that has not responded since learner_eviction_timeout"
+        );
+
+        self.remove_replication(target);
+        self.leader_report_metrics();
+    }
+
     /// Handle events from replication streams for when this node needs to revert to follower state.
     #[tracing::instrument(level = "trace", skip(self, term))]
     async fn handle_revert_to_follower(&mut self, _: NodeId, term: u64) -> RaftResult<()> {
@@ -116,11 +262,23 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             self.update_leader_metrics(target, matched);
         }
 
-        if matched <= self.core.committed {
+        // With `CommitAdvance::Batched`, the commit index is advanced by the leader loop once every heartbeat interval.
+        if matched <= self.core.committed || self.core.config.commit_advance == CommitAdvance::Batched {
             self.leader_report_metrics();
             return Ok(());
         }
 
+        self.advance_commit().await;
+
+        // TODO(xp): does this update too frequently?
+        self.leader_report_metrics();
+        Ok(())
+    }
+
+    /// Advance the commit index to the greatest log acknowledged by a quorum, send it to every replication stream and
+    /// handle the client requests that become committed.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(super) async fn advance_commit(&mut self) {
         let commit_index = self.calc_commit_log_id();
 
         // Determine if we have a new commit index, accounting for joint consensus.
@@ -151,21 +309,35 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             if let Some(offset) = filter {
                 // Build a new ApplyLogsTask from each of the given client requests.
 
-                for request in self.awaiting_committed.drain(..=offset).collect::<Vec<_>>() {
-                    self.client_request_post_commit(request).await;
+                let requests = self.awaiting_committed.drain(..=offset).collect::<Vec<_>>();
+
+                if self.core.apply_batch.is_some() {
+                    self.client_requests_post_commit(requests).await;
+                } else {
+                    for request in requests {
+                        self.client_request_post_commit(request).await;
+                    }
                 }
             }
         }
-
-        // TODO(xp): does this update too frequently?
-        self.leader_report_metrics();
-        Ok(())
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
     fn update_leader_metrics(&mut self, target: NodeId, matched: LogId) {
         tracing::debug!(%target, %matched, "update_leader_metrics");
-        self.leader_metrics.replication.insert(target, ReplicationMetrics { matched });
+        self.leader_metrics.replication.entry(target).or_default().matched = matched;
+    }
+
+    /// Handle events from a replication stream which is sending a snapshot to its target.
+    #[tracing::instrument(level = "trace", skip(self, progress), fields(progress=%progress.summary()))]
+    fn handle_update_snapshot_progress(&mut self, target: NodeId, progress: SnapshotProgress) -> RaftResult<()> {
+        if !self.nodes.contains_key(&target) {
+            return Ok(());
+        }
+
+        self.leader_metrics.replication.entry(target).or_default().snapshot_sending = Some(progress);
+        self.leader_report_metrics();
+        Ok(())
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
@@ -209,9 +381,10 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         _: NodeId,
         tx: oneshot::Sender<Snapshot<S::SnapshotData>>,
     ) -> RaftResult<()> {
-        // Ensure snapshotting is configured, else do nothing.
+        // Without a threshold, the current snapshot is never considered too old.
         let threshold = match &self.core.config.snapshot_policy {
-            SnapshotPolicy::LogsSinceLast(threshold) => *threshold,
+            SnapshotPolicy::LogsSinceLast(threshold) => Some(*threshold),
+            SnapshotPolicy::Never => None,
         };
 
         // Check for existence of current snapshot.
@@ -221,11 +394,15 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         if let Some(snapshot) = current_snapshot_opt {
             // If snapshot exists, ensure its distance from the leader's last log index is <= half
             // of the configured snapshot threshold, else create a new snapshot.
-            if snapshot_is_within_half_of_threshold(
-                &snapshot.meta.last_log_id.index,
-                &self.core.last_log_id.index,
-                &threshold,
-            ) {
+            let fresh = match threshold {
+                Some(threshold) => snapshot_is_within_half_of_threshold(
+                    &snapshot.meta.last_log_id.index,
+                    &self.core.last_log_id.index,
+                    &threshold,
+                ),
+                None => true,
+            };
+            if fresh {
                 let _ = tx.send(snapshot);
                 return Ok(());
             }
@@ -1,5 +1,4 @@
 use tokio::sync::mpsc;
-use tokio::time::Instant;
 use tracing_futures::Instrument;
 
 use crate::core::CandidateState;
@@ -7,6 +6,7 @@ use crate::core::RaftCore;
 use crate::core::State;
 use crate::core::UpdateCurrentLeader;
 use crate::error::RaftResult;
+use crate::raft::VoteRejection;
 use crate::raft::VoteRequest;
 use crate::raft::VoteResponse;
 use crate::summary::MessageSummary;
@@ -15,6 +15,7 @@ use crate::AppDataResponse;
 use crate::NodeId;
 use crate::RaftNetwork;
 use crate::RaftStorage;
+use crate::Update;
 
 impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> RaftCore<D, R, N, S> {
     /// An RPC invoked by candidates to gather votes (§5.2).
@@ -24,6 +25,17 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     pub(super) async fn handle_vote_request(&mut self, msg: VoteRequest) -> RaftResult<VoteResponse> {
         tracing::debug!({candidate=msg.candidate_id, self.current_term, rpc_term=msg.term}, "start handle_vote_request");
 
+        // A vote request of term 0 is a probe sent by a node being initialized, to check this node is reachable. No
+        // leader is ever elected in term 0.
+        if msg.term == 0 {
+            return Ok(VoteResponse {
+                term: self.current_term,
+                vote_granted: false,
+                last_log_id: self.last_log_id,
+                reject_reason: Some(VoteRejection::Probe),
+            });
+        }
+
         // If candidate's current term is less than this nodes current term, reject.
         if msg.term < self.current_term {
             tracing::debug!({candidate=msg.candidate_id, self.current_term, rpc_term=msg.term}, "RequestVote RPC term is less than current term");
@@ -31,13 +43,14 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 term: self.current_term,
                 vote_granted: false,
                 last_log_id: self.last_log_id,
+                reject_reason: Some(VoteRejection::StaleTerm),
             });
         }
 
         // Do not respond to the request if we've received a heartbeat within the election timeout minimum.
         if let Some(inst) = &self.last_heartbeat {
-            let now = Instant::now();
-            let delta = now.duration_since(*inst);
+            let now = self.now();
+            let delta = now.saturating_duration_since(*inst);
             if self.config.election_timeout_min >= (delta.as_millis() as u64) {
                 tracing::debug!(
                     { candidate = msg.candidate_id },
@@ -47,6 +60,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                     term: self.current_term,
                     vote_granted: false,
                     last_log_id: self.last_log_id,
+                    reject_reason: Some(VoteRejection::LeaderAlive),
                 });
             }
         }
@@ -59,19 +73,24 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             self.update_next_election_timeout(false);
             self.set_target_state(State::Follower);
             self.save_hard_state().await?;
+            self.report_metrics(Update::Ignore);
         }
 
         // Check if candidate's log is at least as up-to-date as this node's.
         // If candidate's log is not at least as up-to-date as this node, then reject.
+        // `LogId` is compared by term first, then by index (§5.4.1).
         if msg.last_log_id < self.last_log_id {
             tracing::debug!(
-                { candidate = msg.candidate_id },
+                candidate = msg.candidate_id,
+                candidate_last_log_id = %msg.last_log_id,
+                last_log_id = %self.last_log_id,
                 "rejecting vote request as candidate's log is not up-to-date"
             );
             return Ok(VoteResponse {
                 term: self.current_term,
                 vote_granted: false,
                 last_log_id: self.last_log_id,
+                reject_reason: Some(VoteRejection::StaleLog),
             });
         }
 
@@ -85,12 +104,14 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 term: self.current_term,
                 vote_granted: true,
                 last_log_id: self.last_log_id,
+                reject_reason: None,
             }),
             // This node has already voted for a different candidate.
             Some(_) => Ok(VoteResponse {
                 term: self.current_term,
                 vote_granted: false,
                 last_log_id: self.last_log_id,
+                reject_reason: Some(VoteRejection::AlreadyVoted),
             }),
             // This node has not yet voted for the current term, so vote for the candidate.
             None => {
@@ -98,11 +119,13 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 self.set_target_state(State::Follower);
                 self.update_next_election_timeout(false);
                 self.save_hard_state().await?;
+                self.report_metrics(Update::Ignore);
                 tracing::debug!({candidate=msg.candidate_id, msg.term}, "voted for candidate");
                 Ok(VoteResponse {
                     term: self.current_term,
                     vote_granted: true,
                     last_log_id: self.last_log_id,
+                    reject_reason: None,
                 })
             }
         }
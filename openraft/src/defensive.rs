@@ -88,6 +88,9 @@ where
     }
 
     /// The log entries fed into a store must be consecutive otherwise it is a bug.
+    ///
+    /// The indexes must increase by one, and the terms must not decrease: a log with a smaller term never follows
+    /// one with a greater term in a valid log.
     async fn defensive_consecutive_input(&self, entries: &[&Entry<D>]) -> Result<(), StorageError> {
         if !self.is_defensive() {
             return Ok(());
@@ -100,7 +103,7 @@ where
         let mut prev_log_id = entries[0].log_id;
 
         for e in entries.iter().skip(1) {
-            if e.log_id.index != prev_log_id.index + 1 {
+            if e.log_id.index != prev_log_id.index + 1 || e.log_id.term < prev_log_id.term {
                 return Err(DefensiveError::new(ErrorSubject::Logs, Violation::LogsNonConsecutive {
                     prev: prev_log_id,
                     next: e.log_id,
@@ -138,7 +141,7 @@ where
         let last_id = self.last_log_id().await?;
 
         let first_id = entries[0].log_id;
-        if last_id.index + 1 != first_id.index {
+        if last_id.next_index() != first_id.index {
             return Err(
                 DefensiveError::new(ErrorSubject::Log(first_id), Violation::LogsNonConsecutive {
                     prev: last_id,
@@ -191,7 +194,7 @@ where
         let (last_id, _) = self.inner().last_applied_state().await?;
 
         let first_id = entries[0].log_id;
-        if last_id.index + 1 != first_id.index {
+        if last_id.next_index() != first_id.index {
             return Err(
                 DefensiveError::new(ErrorSubject::Apply(first_id), Violation::ApplyNonConsecutive {
                     prev: last_id,
@@ -4,7 +4,9 @@ use std::collections::BTreeSet;
 use std::fmt::Debug;
 use std::time::Duration;
 
+use crate::codec::CodecType;
 use crate::raft::Membership;
+use crate::raft_types::SnapshotId;
 use crate::raft_types::SnapshotSegmentId;
 use crate::LogId;
 use crate::NodeId;
@@ -24,6 +26,15 @@ pub enum RaftError {
         got: SnapshotSegmentId,
     },
 
+    /// A snapshot chunk is corrupted: its data does not match the checksum.
+    /// The chunk is not written and the leader should resend it.
+    #[error("snapshot chunk {segment} checksum mismatch, expect: {expect:#010x}, got: {got:#010x}")]
+    SnapshotChecksumMismatch {
+        segment: SnapshotSegmentId,
+        expect: u32,
+        got: u32,
+    },
+
     /// An error which has come from the `RaftStorage` layer.
     #[error("{0}")]
     RaftStorage(anyhow::Error),
@@ -35,6 +46,10 @@ pub enum RaftError {
     /// An internal Raft error indicating that Raft is shutting down.
     #[error("Raft is shutting down")]
     ShuttingDown,
+
+    /// A value fails to encode or decode with `Config::codec`.
+    #[error(transparent)]
+    Codec(#[from] CodecError),
 }
 
 /// Error variants related to the Replication.
@@ -78,6 +93,10 @@ pub enum ReplicationError {
         #[backtrace]
         source: anyhow::Error,
     },
+
+    /// The target does not have the base of a delta snapshot, and the store has no full snapshot to send instead.
+    #[error("target {target} lacks the base of delta snapshot {delta}, and there is no full snapshot")]
+    NoFullSnapshot { target: NodeId, delta: SnapshotId },
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -92,6 +111,91 @@ pub struct ForwardToLeader {
     pub leader_id: Option<NodeId>,
 }
 
+/// The node is not a leader and does not know one, e.g., an election is in progress. The client should retry later.
+#[derive(Debug, thiserror::Error)]
+#[error("node {node_id} knows no leader, an election may be in progress")]
+pub struct NoLeader {
+    pub node_id: NodeId,
+}
+
+/// The node was the leader of `term` when it received the request, but lost the leadership before the request is
+/// finished.
+///
+/// A write may or may not be committed by the next leader. The client should find the new leader and retry the
+/// request, if it is idempotent.
+#[derive(Debug, thiserror::Error)]
+#[error("node {node_id} stepped down from leader of term {term} before the request is finished")]
+pub struct LeaderStepped {
+    pub node_id: NodeId,
+    pub term: u64,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("node {node_id} is not initialized")]
+pub struct NotInitialized {
+    pub node_id: NodeId,
+}
+
+/// The leader has too many uncommitted entries to accept a new write, see `Config::max_uncommitted_entries`.
+#[derive(Debug, thiserror::Error)]
+#[error("too many uncommitted entries: {uncommitted}, max: {max}, retry later")]
+pub struct Throttled {
+    pub uncommitted: u64,
+    pub max: u64,
+}
+
+/// The entry of a client write is larger than `Config::max_entry_size_bytes`.
+#[derive(Debug, thiserror::Error)]
+#[error("entry too large: {size} bytes, limit: {limit} bytes")]
+pub struct EntryTooLarge {
+    pub size: u64,
+    pub limit: u64,
+}
+
+/// The leader lost the quorum and is in the read-only mode, see `Config::allow_stale_reads_on_quorum_loss`.
+#[derive(Debug, thiserror::Error)]
+#[error("node {node_id} lost the quorum, it is read-only")]
+pub struct QuorumLost {
+    pub node_id: NodeId,
+}
+
+/// A client write is canceled by the caller before a response is received.
+///
+/// The entry is not removed if it has been appended, thus if `maybe_committed` is true, the write may still be
+/// committed and applied. The fate of the write is unknown and a retry should be made idempotent. If it is false, the
+/// write is never sent to raft.
+#[derive(Debug, thiserror::Error)]
+#[error("client write is canceled, maybe committed: {maybe_committed}")]
+pub struct Cancelled {
+    pub maybe_committed: bool,
+}
+
+/// An error that occurs when a `Codec` encodes or decodes a value.
+#[derive(Debug, thiserror::Error)]
+#[error("{codec:?} codec error: {source}")]
+pub struct CodecError {
+    /// The codec that fails.
+    pub codec: CodecType,
+
+    pub source: Box<dyn std::error::Error + Send + Sync + 'static>,
+}
+
+impl CodecError {
+    pub fn new(codec: CodecType, source: impl Into<Box<dyn std::error::Error + Send + Sync + 'static>>) -> Self {
+        Self {
+            codec,
+            source: source.into(),
+        }
+    }
+}
+
+/// A string that can not be parsed as a `SnapshotId`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid snapshot id: {id:?}, expect: <term>-<index>-<seq>-<node_id>")]
+pub struct InvalidSnapshotId {
+    pub id: String,
+}
+
 impl From<tokio::io::Error> for RaftError {
     fn from(src: tokio::io::Error) -> Self {
         RaftError::RaftStorage(src.into())
@@ -104,8 +208,17 @@ pub enum ClientReadError {
     #[error(transparent)]
     RaftError(#[from] RaftError),
 
+    /// The node is not the leader, the client should redirect the request to `leader_id`.
     #[error(transparent)]
     ForwardToLeader(#[from] ForwardToLeader),
+
+    /// No leader is known, the client should wait and retry.
+    #[error(transparent)]
+    NoLeader(#[from] NoLeader),
+
+    /// The node lost its leadership while confirming it.
+    #[error(transparent)]
+    LeaderStepped(#[from] LeaderStepped),
 }
 
 /// An error related to a client write request.
@@ -114,12 +227,50 @@ pub enum ClientWriteError {
     #[error("{0}")]
     RaftError(#[from] RaftError),
 
+    /// The node is not the leader, the client should redirect the request to `leader_id`.
     #[error(transparent)]
     ForwardToLeader(#[from] ForwardToLeader),
 
+    /// No leader is known, the client should wait and retry.
+    #[error(transparent)]
+    NoLeader(#[from] NoLeader),
+
+    /// The node lost its leadership before the write is committed.
+    #[error(transparent)]
+    LeaderStepped(#[from] LeaderStepped),
+
+    /// The node has never been initialized, nor joined a cluster.
+    #[error(transparent)]
+    NotInitialized(#[from] NotInitialized),
+
     /// When writing a change-membership entry.
     #[error(transparent)]
     ChangeMembershipError(#[from] ChangeMembershipError),
+
+    /// The leader applies back-pressure: the write is not appended and should be retried later.
+    #[error(transparent)]
+    Throttled(#[from] Throttled),
+
+    /// The entry is larger than `Config::max_entry_size_bytes` and is not appended.
+    #[error(transparent)]
+    EntryTooLarge(#[from] EntryTooLarge),
+
+    /// The leader is in the quorum-loss read-only mode and refuses writes.
+    #[error(transparent)]
+    QuorumLost(#[from] QuorumLost),
+
+    /// The caller canceled the write before a response is received, see `Raft::client_write_cancelable()`.
+    #[error(transparent)]
+    Cancelled(#[from] Cancelled),
+
+    /// The entry is rejected by `RaftStorage::validate_entry()` and is not appended.
+    #[error("invalid entry: {0}")]
+    InvalidEntry(StorageError),
+
+    /// The state machine applied the entry but rejected the request, see `AppDataResponse::application_error()`.
+    #[error("application error: {0}")]
+    #[try_into(ignore)]
+    ApplicationError(Box<dyn std::error::Error + Send + Sync>),
 }
 
 /// Error variants related to configuration.
@@ -135,6 +286,38 @@ pub enum ConfigError {
     #[error("the given value for max_payload_entries is too small, must be > 0")]
     MaxPayloadEntriesTooSmall,
 
+    /// The given value for max_payload_bytes is too small, must be > 0.
+    #[error("the given value for max_payload_bytes is too small, must be > 0")]
+    MaxPayloadBytesTooSmall,
+
+    /// The given value for max_entry_size_bytes is too small, must be > 0.
+    #[error("the given value for max_entry_size_bytes is too small, must be > 0")]
+    MaxEntrySizeTooSmall,
+
+    /// The given value for replication_streams_per_follower is too small, must be > 0.
+    #[error("the given value for replication_streams_per_follower is too small, must be > 0")]
+    ReplicationStreamsTooSmall,
+
+    /// The given value for snapshot_transfer_bytes_per_sec is too small, must be > 0.
+    #[error("the given value for snapshot_transfer_bytes_per_sec is too small, must be > 0")]
+    SnapshotTransferRateTooSmall,
+
+    /// The given value for max_uncommitted_entries is too small, must be > 0.
+    #[error("the given value for max_uncommitted_entries is too small, must be > 0")]
+    MaxUncommittedEntriesTooSmall,
+
+    /// The given value for learner_eviction_timeout is too small, must be > 0.
+    #[error("the given value for learner_eviction_timeout is too small, must be > 0")]
+    LearnerEvictionTimeoutTooSmall,
+
+    /// The given value for dedup_window is too small, must be > 0.
+    #[error("the given value for dedup_window is too small, must be > 0")]
+    DedupWindowTooSmall,
+
+    /// The given value for max_apply_batch is too small, must be > 0.
+    #[error("the given value for max_apply_batch is too small, must be > 0")]
+    MaxApplyBatchTooSmall,
+
     /// election_timeout_min smaller than heartbeat_interval would cause endless election.
     /// A recommended election_timeout_min value is about 3 times heartbeat_interval.
     #[error("election_timeout_min value must be > heartbeat_interval")]
@@ -149,9 +332,26 @@ pub enum InitializeError {
     #[error("{0}")]
     RaftError(#[from] RaftError),
 
-    /// The requested action is not allowed due to the Raft node's current state.
-    #[error("the requested action is not allowed due to the Raft node's current state")]
-    NotAllowed,
+    /// The node is already initialized, i.e., it is a member of a running cluster.
+    #[error("the node is already initialized")]
+    AlreadyInitialized,
+
+    /// The node is not pristine: it has logs, a snapshot or a term greater than 0, e.g., it has been added to a
+    /// cluster as a learner.
+    #[error("the node is not pristine, last log id: {last_log_id}")]
+    NotPristine { last_log_id: LogId },
+
+    /// The initial membership is empty.
+    #[error("the initial members can not be empty")]
+    EmptyMembers,
+
+    /// A member of the initial membership does not respond.
+    #[error("member {node_id} is unreachable")]
+    MemberUnreachable { node_id: NodeId },
+
+    /// The snapshot to initialize the node from contains no log.
+    #[error("the snapshot to initialize from is empty")]
+    EmptySnapshot,
 }
 
 /// The set of errors which may take place when requesting to propose a config change.
@@ -180,6 +380,44 @@ pub enum ChangeMembershipError {
     // TODO(xp): 111 test it
     #[error("now allowed to change from {curr:?} to {to:?}")]
     Incompatible { curr: Membership, to: BTreeSet<NodeId> },
+
+    /// Only a voter other than the leader, which is removed by the change, can be demoted to a learner.
+    #[error("node {node_id} can not be demoted to learner")]
+    CanNotDemote { node_id: NodeId },
+}
+
+/// An error related to `Raft::rewrite_log_entry()`.
+#[derive(Debug, thiserror::Error)]
+pub enum RewriteLogError {
+    #[error("{0}")]
+    RaftError(#[from] RaftError),
+
+    #[error("log {log_id} is not applied yet, last applied: {last_applied}")]
+    NotApplied { log_id: LogId, last_applied: LogId },
+
+    #[error("log {log_id} is not included in a snapshot, snapshot last log: {snapshot_last_log_id}")]
+    NotInSnapshot { log_id: LogId, snapshot_last_log_id: LogId },
+
+    /// The log is purged, or the log at the same index has a different term.
+    #[error("log {0} is not found")]
+    NotFound(LogId),
+
+    /// Only the payload of a normal log can be rewritten: a blank or membership log has no application data.
+    #[error("log {0} is not a normal log")]
+    NotNormal(LogId),
+
+    /// A leader rewrites a log only when every replication target has it, otherwise it would replicate the rewritten
+    /// payload to the target, which then applies it.
+    #[error("log {log_id} is not replicated to target {target}, matched: {matched}")]
+    NotReplicated {
+        log_id: LogId,
+        target: NodeId,
+        matched: LogId,
+    },
+
+    /// `RaftStorage::rewrite_log_entry()` is not implemented by the store.
+    #[error("the store does not support rewriting a log")]
+    Unsupported,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -193,3 +431,81 @@ pub enum AddLearnerError {
     #[error("node {0} is already a learner")]
     Exists(NodeId),
 }
+
+/// Error of `Raft::pause_replication()` and `Raft::resume_replication()`.
+#[derive(Debug, thiserror::Error)]
+pub enum PauseReplicationError {
+    #[error("{0}")]
+    RaftError(#[from] RaftError),
+
+    #[error(transparent)]
+    ForwardToLeader(#[from] ForwardToLeader),
+
+    #[error("node {0} is not a replication target of the leader")]
+    NotFound(NodeId),
+}
+
+/// Error of `Raft::set_relay()`.
+#[derive(Debug, thiserror::Error)]
+pub enum SetRelayError {
+    #[error("{0}")]
+    RaftError(#[from] RaftError),
+
+    #[error(transparent)]
+    ForwardToLeader(#[from] ForwardToLeader),
+
+    #[error("node {0} is not a replication target of the leader")]
+    NotFound(NodeId),
+
+    #[error("node {0} is a voter, only a learner can relay or be relayed")]
+    NotLearner(NodeId),
+
+    #[error("learner {learner} can not relay through {relay}: a learner can not relay to itself, and a relay can not be relayed")]
+    InvalidRelay { learner: NodeId, relay: NodeId },
+}
+
+/// Error of `Raft::audit_follower()`.
+#[derive(Debug, thiserror::Error)]
+pub enum AuditFollowerError {
+    #[error("{0}")]
+    RaftError(#[from] RaftError),
+
+    #[error(transparent)]
+    ForwardToLeader(#[from] ForwardToLeader),
+
+    #[error("node {0} is not a replication target of the leader")]
+    NotFound(NodeId),
+
+    /// Failed to read logs from the local storage.
+    #[error(transparent)]
+    StorageError(#[from] StorageError),
+
+    /// Failed to encode the local logs to compute their checksums.
+    #[error(transparent)]
+    Codec(#[from] CodecError),
+
+    /// Failed to send the `LogAuditRequest`, e.g., the target is unreachable or the network does not implement
+    /// `RaftNetwork::send_log_audit()`.
+    #[error("failed to send log audit request to {target}: {source}")]
+    Network { target: NodeId, source: anyhow::Error },
+}
+
+/// Error of `Raft::add_voter()`.
+#[derive(Debug, thiserror::Error)]
+pub enum AddVoterError {
+    #[error(transparent)]
+    AddLearner(#[from] AddLearnerError),
+
+    #[error(transparent)]
+    CatchUpTimeout(#[from] CatchUpTimeout),
+
+    #[error(transparent)]
+    ChangeMembership(#[from] ClientWriteError),
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("node {node_id} did not catch up with the leader within {timeout:?}")]
+pub struct CatchUpTimeout {
+    pub node_id: NodeId,
+    pub timeout: Duration,
+}
@@ -1,9 +1,18 @@
 #![doc = include_str!("../README.md")]
 #![feature(backtrace)]
 
+#[cfg(feature = "test-utils")]
+mod arbitrary_impl;
+mod clock;
+pub mod codec;
+#[cfg(test)]
+mod codec_test;
 pub mod config;
 mod core;
 pub mod error;
+mod log_range;
+#[cfg(test)]
+mod log_range_test;
 pub mod metrics;
 #[cfg(test)]
 mod metrics_wait_test;
@@ -11,10 +20,14 @@ pub mod network;
 mod quorum;
 pub mod raft;
 mod raft_types;
+#[cfg(test)]
+mod raft_types_test;
 mod replication;
 pub mod storage;
 mod storage_error;
 mod summary;
+#[cfg(feature = "test-utils")]
+pub mod testing;
 
 mod defensive;
 #[cfg(test)]
@@ -28,8 +41,22 @@ use serde::Serialize;
 pub use store_ext::StoreExt;
 pub use store_wrapper::Wrapper;
 
+pub use crate::clock::Clock;
+pub use crate::clock::TokioClock;
+pub use crate::codec::BincodeCodec;
+pub use crate::codec::Codec;
+pub use crate::codec::CodecType;
+pub use crate::codec::JsonCodec;
+pub use crate::config::AckOn;
+pub use crate::config::ApplyRetry;
+pub use crate::config::ClockSource;
+pub use crate::config::CommitAdvance;
 pub use crate::config::Config;
+pub use crate::config::FatalStorageErrorHandler;
+pub use crate::config::Profile;
+pub use crate::config::ReportMembershipOn;
 pub use crate::config::SnapshotPolicy;
+pub use crate::config::StateChangeHandler;
 pub use crate::core::EffectiveMembership;
 pub use crate::core::State;
 pub use crate::defensive::DefensiveCheck;
@@ -39,7 +66,17 @@ pub use crate::error::ConfigError;
 pub use crate::error::InitializeError;
 pub use crate::error::RaftError;
 pub use crate::error::ReplicationError;
+pub use crate::error::RewriteLogError;
+pub use crate::log_range::normalize_range;
+pub use crate::metrics::PerfMetrics;
 pub use crate::metrics::RaftMetrics;
+pub use crate::metrics::RaftStateDump;
+pub use crate::metrics::RunningState;
+pub use crate::metrics::ShutdownReason;
+pub use crate::metrics::StateChecksum;
+pub use crate::metrics::StateTransition;
+pub use crate::metrics::StorageFault;
+pub use crate::network::NetworkError;
 pub use crate::network::RaftNetwork;
 pub use crate::raft::Raft;
 pub use crate::raft_types::LogId;
@@ -47,7 +84,11 @@ pub use crate::raft_types::SnapshotId;
 pub use crate::raft_types::SnapshotSegmentId;
 pub use crate::raft_types::StateMachineChanges;
 pub use crate::raft_types::Update;
+pub use crate::replication::AddLearnerState;
+pub use crate::replication::FollowerProgress;
 pub use crate::replication::ReplicationMetrics;
+pub use crate::replication::ReplicationState;
+pub use crate::storage::CheckpointHandle;
 pub use crate::storage::RaftStorage;
 pub use crate::storage::RaftStorageDebug;
 pub use crate::storage::SnapshotMeta;
@@ -56,6 +97,7 @@ pub use crate::storage_error::ErrorSubject;
 pub use crate::storage_error::ErrorVerb;
 pub use crate::storage_error::StorageError;
 pub use crate::storage_error::StorageIOError;
+pub use crate::storage_error::TransientStorageError;
 pub use crate::storage_error::Violation;
 pub use crate::summary::MessageSummary;
 
@@ -85,4 +127,17 @@ pub trait AppData: Clone + Send + Sync + Serialize + DeserializeOwned + 'static
 /// related to the success or failure of a client request — application specific validation logic,
 /// enforcing of data constraints, and anything of that nature — are expressly out of the realm of
 /// the Raft consensus protocol.
-pub trait AppDataResponse: Clone + Send + Sync + Serialize + DeserializeOwned + 'static {}
+///
+/// An application error encapsulated in a response can be surfaced to the client by implementing
+/// `application_error()`, in which case `Raft::client_write` returns `ClientWriteError::ApplicationError`.
+pub trait AppDataResponse: Clone + Send + Sync + Serialize + DeserializeOwned + 'static {
+    /// Returns the error if the state machine rejected the request that produced this response.
+    ///
+    /// A rejected request is still a committed and applied log entry: it is just not a successful one for the client.
+    /// Thus the state machine must reject a request deterministically, on every node.
+    ///
+    /// By default a response is never an error.
+    fn application_error(&self) -> Option<Box<dyn std::error::Error + Send + Sync>> {
+        None
+    }
+}
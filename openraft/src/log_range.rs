@@ -0,0 +1,41 @@
+use std::ops::Bound;
+use std::ops::Range;
+use std::ops::RangeBounds;
+
+/// Normalize a range of log indexes into a half-open `start..end` range that lies within the log bounds
+/// `[first, last]`.
+///
+/// Every form of `RangeBounds<u64>` is accepted: `a..b`, `a..=b`, `a..`, `..b`, `..=b`, `..`, and bounds built from
+/// `Bound::Excluded`. The returned range contains exactly the indexes that are both in `range` and in `[first, last]`.
+/// If there is no such index, an empty range `s..s` is returned, where `s` is the clamped start of `range`.
+///
+/// `first > last` stands for an empty log, in which case the returned range is always empty.
+///
+/// `last` must be less than `u64::MAX`, so that the exclusive end `last + 1` is representable.
+///
+/// A `RaftStorage` implementation can use this to turn the range passed to `get_log_entries()` or
+/// `delete_logs_from()` into plain start and end indexes, without handling every bound type by hand.
+pub fn normalize_range<RB: RangeBounds<u64>>(range: &RB, first: u64, last: u64) -> Range<u64> {
+    debug_assert!(last < u64::MAX, "last log index must be less than u64::MAX");
+
+    // Computed in u128 so that `Excluded(u64::MAX)` as a start and `Included(u64::MAX)` as an end do not overflow.
+    let start = match range.start_bound() {
+        Bound::Included(x) => *x as u128,
+        Bound::Excluded(x) => *x as u128 + 1,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(x) => *x as u128 + 1,
+        Bound::Excluded(x) => *x as u128,
+        Bound::Unbounded => u128::MAX,
+    };
+
+    let start = std::cmp::max(start, first as u128);
+    let end = std::cmp::min(end, last as u128 + 1);
+
+    // `start` is at most `u64::MAX + 1` only when the range starts after `u64::MAX`, which is beyond any log.
+    let start = std::cmp::min(start, u64::MAX as u128) as u64;
+    let end = std::cmp::max(end, start as u128) as u64;
+
+    start..end
+}
@@ -0,0 +1,105 @@
+use std::ops::Bound;
+use std::ops::Range;
+use std::ops::RangeBounds;
+
+use proptest::prelude::*;
+
+use crate::normalize_range;
+
+/// Width of the window of indexes the bounds are drawn from.
+const WIDTH: u64 = 40;
+
+#[test]
+fn test_normalize_range() -> anyhow::Result<()> {
+    // Every form of range, against logs in [3, 10].
+    assert_eq!(5..8, normalize_range(&(5..8), 3, 10));
+    assert_eq!(5..9, normalize_range(&(5..=8), 3, 10));
+    assert_eq!(5..11, normalize_range(&(5..), 3, 10));
+    assert_eq!(3..8, normalize_range(&(..8), 3, 10));
+    assert_eq!(3..9, normalize_range(&(..=8), 3, 10));
+    assert_eq!(3..11, normalize_range(&(..), 3, 10));
+    assert_eq!(6..8, normalize_range(&(Bound::Excluded(5), Bound::Excluded(8)), 3, 10));
+
+    // Clamped to the log bounds.
+    assert_eq!(3..11, normalize_range(&(0..100), 3, 10));
+
+    // Empty ranges.
+    assert_eq!(5..5, normalize_range(&(5..5), 3, 10));
+    assert_eq!(5..5, normalize_range(&(5..2), 3, 10));
+    assert_eq!(20..20, normalize_range(&(20..30), 3, 10));
+    assert_eq!(3..3, normalize_range(&(0..2), 3, 10));
+
+    // Empty log.
+    assert_eq!(5..5, normalize_range(&(..), 5, 4));
+
+    // No overflow at the edge of u64.
+    assert_eq!(
+        u64::MAX - 1..u64::MAX,
+        normalize_range(&(u64::MAX - 1..=u64::MAX), 0, u64::MAX - 1)
+    );
+    assert_eq!(
+        u64::MAX..u64::MAX,
+        normalize_range(&(Bound::Excluded(u64::MAX), Bound::Unbounded), 0, u64::MAX - 1)
+    );
+
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(4096))]
+
+    #[test]
+    fn prop_normalize_range_near_zero(
+        (start, end, first, last) in bounds_in_window(0),
+    ) {
+        let range = (start, end);
+        prop_assert_eq!(reference(&range, first, last, 0), normalize_range(&range, first, last));
+    }
+
+    #[test]
+    fn prop_normalize_range_near_u64_max(
+        (start, end, first, last) in bounds_in_window(u64::MAX - WIDTH),
+    ) {
+        let range = (start, end);
+        prop_assert_eq!(reference(&range, first, last, u64::MAX - WIDTH), normalize_range(&range, first, last));
+    }
+}
+
+fn bound(base: u64) -> impl Strategy<Value = Bound<u64>> {
+    prop_oneof![
+        (base..=base + WIDTH).prop_map(Bound::Included),
+        (base..=base + WIDTH).prop_map(Bound::Excluded),
+        Just(Bound::Unbounded),
+    ]
+}
+
+/// Random `(start_bound, end_bound, first, last)` with every index in `[base, base + WIDTH]` and `last < u64::MAX`.
+fn bounds_in_window(base: u64) -> impl Strategy<Value = (Bound<u64>, Bound<u64>, u64, u64)> {
+    (bound(base), bound(base), base..=base + WIDTH, base..base + WIDTH)
+}
+
+/// Normalize a range by testing every index in the window one by one.
+fn reference(range: &(Bound<u64>, Bound<u64>), first: u64, last: u64, base: u64) -> Range<u64> {
+    // One index past the window, so that a start bound excluding the last index of the window is found.
+    let window = base as u128..=base as u128 + WIDTH as u128 + 1;
+
+    let in_log = |i: u128| i >= first as u128 && i <= last as u128;
+    let in_range = |i: u128| i <= u64::MAX as u128 && range.contains(&(i as u64));
+
+    let indexes: Vec<u128> = window.clone().filter(|i| in_log(*i) && in_range(*i)).collect();
+
+    if let (Some(s), Some(e)) = (indexes.first(), indexes.last()) {
+        assert_eq!((e - s + 1) as usize, indexes.len(), "indexes in range are consecutive");
+        return *s as u64..*e as u64 + 1;
+    }
+
+    // Empty: start at the first index allowed by the start bound and the log start.
+    let start_ok = |i: u128| match range.0 {
+        Bound::Included(x) => i >= x as u128,
+        Bound::Excluded(x) => i > x as u128,
+        Bound::Unbounded => true,
+    };
+    let s = window.into_iter().find(|i| *i >= first as u128 && start_ok(*i)).unwrap();
+    let s = std::cmp::min(s, u64::MAX as u128) as u64;
+    s..s
+}
@@ -1,9 +1,14 @@
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 
 use maplit::btreemap;
 use maplit::btreeset;
 
+use crate::core::EffectiveMembership;
+use crate::error::ChangeMembershipError;
 use crate::raft::Membership;
+use crate::LogId;
+use crate::MessageSummary;
 use crate::NodeId;
 
 #[test]
@@ -132,3 +137,129 @@ fn test_membership_greatest_majority_value() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_membership_is_joint() -> anyhow::Result<()> {
+    let m123 = Membership::new_single(btreeset! {1,2,3});
+    let m123_345 = Membership::new_multi(vec![btreeset! {1,2,3}, btreeset! {3,4,5}]);
+
+    assert!(!m123.is_joint());
+    assert!(m123_345.is_joint());
+
+    assert_eq!(&[btreeset! {1,2,3}], m123.get_configs());
+    assert_eq!(&[btreeset! {1,2,3}, btreeset! {3,4,5}], m123_345.get_configs());
+
+    Ok(())
+}
+
+#[test]
+fn test_membership_display() -> anyhow::Result<()> {
+    let m012 = Membership::new_single(btreeset! {0,1,2});
+    let m012_234 = Membership::new_multi(vec![btreeset! {0,1,2}, btreeset! {2,3,4}]);
+
+    assert_eq!("{0,1,2}", m012.to_string());
+    assert_eq!("joint({0,1,2},{2,3,4})", m012_234.to_string());
+    assert_eq!("{}", Membership::new_single(btreeset! {}).to_string());
+
+    // summary() is the same as Display
+    assert_eq!("joint({0,1,2},{2,3,4})", m012_234.summary());
+
+    Ok(())
+}
+
+#[test]
+fn test_effective_membership_quorum_size() -> anyhow::Result<()> {
+    let em = |configs: Vec<BTreeSet<NodeId>>| EffectiveMembership {
+        log_id: LogId::new(1, 1),
+        membership: Membership::new_multi(configs),
+    };
+
+    let m1 = em(vec![btreeset! {1}]);
+    assert_eq!(1, m1.voter_count());
+    assert_eq!(1, m1.quorum_size());
+
+    let m123 = em(vec![btreeset! {1,2,3}]);
+    assert_eq!(3, m123.voter_count());
+    assert_eq!(2, m123.quorum_size());
+
+    let m1234 = em(vec![btreeset! {1,2,3,4}]);
+    assert_eq!(4, m1234.voter_count());
+    assert_eq!(3, m1234.quorum_size());
+
+    // Joint config without shared voters: a majority of each config.
+    let m123_456 = em(vec![btreeset! {1,2,3}, btreeset! {4,5,6}]);
+    assert_eq!(6, m123_456.voter_count());
+    assert_eq!(4, m123_456.quorum_size());
+
+    // Shared voters count toward both majorities: {3} + one of {1,2} + one of {4,5}.
+    let m123_345 = em(vec![btreeset! {1,2,3}, btreeset! {3,4,5}]);
+    assert_eq!(5, m123_345.voter_count());
+    assert_eq!(3, m123_345.quorum_size());
+
+    // {2,3} is a majority of both.
+    let m123_234 = em(vec![btreeset! {1,2,3}, btreeset! {2,3,4}]);
+    assert_eq!(4, m123_234.voter_count());
+    assert_eq!(2, m123_234.quorum_size());
+
+    // Growing from 1 to 3 voters: {1} and 2 of {1,2,3}.
+    let m1_123 = em(vec![btreeset! {1}, btreeset! {1,2,3}]);
+    assert_eq!(3, m1_123.voter_count());
+    assert_eq!(2, m1_123.quorum_size());
+
+    Ok(())
+}
+
+#[test]
+fn test_membership_reject_empty() -> anyhow::Result<()> {
+    let res = Membership::try_new_single(btreeset! {});
+    assert!(matches!(res, Err(ChangeMembershipError::EmptyMembership)));
+
+    let res = Membership::try_new_multi(vec![]);
+    assert!(matches!(res, Err(ChangeMembershipError::EmptyMembership)));
+
+    let res = Membership::try_new_multi(vec![btreeset! {1,2,3}, btreeset! {}]);
+    assert!(matches!(res, Err(ChangeMembershipError::EmptyMembership)));
+
+    assert_eq!(
+        Membership::new_single(btreeset! {1,2,3}),
+        Membership::try_new_single(btreeset! {1,2,3})?
+    );
+    assert_eq!(
+        Membership::new_multi(vec![btreeset! {1,2,3}, btreeset! {3,4,5}]),
+        Membership::try_new_multi(vec![btreeset! {1,2,3}, btreeset! {3,4,5}])?
+    );
+
+    assert!(Membership::new_single(btreeset! {}).validate().is_err());
+    assert!(Membership::new_single(btreeset! {1}).validate().is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn test_membership_priorities() -> anyhow::Result<()> {
+    let m = Membership::new_multi(vec![btreeset! {1,2,3}, btreeset! {3,4}]);
+
+    assert_eq!(0, m.priority(&1));
+    assert_eq!(0, m.priority_rank(&1));
+
+    let m = m.with_priorities(btreemap! {1=>10, 2=>10, 3=>5});
+
+    assert_eq!(10, m.priority(&1));
+    assert_eq!(0, m.priority(&4));
+
+    assert_eq!(0, m.priority_rank(&1));
+    assert_eq!(0, m.priority_rank(&2));
+    assert_eq!(1, m.priority_rank(&3));
+    assert_eq!(2, m.priority_rank(&4));
+
+    // Priorities are kept when leaving a joint config.
+    let last = m.to_final_config();
+    assert_eq!(m.priorities(), last.priorities());
+    assert_eq!(1, last.priority_rank(&4), "node 1 and 2 are not in the config");
+
+    // A membership serialized without priorities has none.
+    let m: Membership = serde_json::from_str(r#"{"configs":[[1,2,3]],"all_nodes":[1,2,3]}"#)?;
+    assert_eq!(Membership::new_single(btreeset! {1,2,3}), m);
+
+    Ok(())
+}
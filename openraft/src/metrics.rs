@@ -7,6 +7,7 @@
 //! Metrics are observed on a running Raft node via the `Raft::metrics()` method, which will
 //! return a stream of metrics.
 
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::collections::HashMap;
 
@@ -25,6 +26,7 @@ use crate::MessageSummary;
 use crate::NodeId;
 use crate::RaftError;
 use crate::ReplicationMetrics;
+use crate::SnapshotMeta;
 
 /// A set of metrics describing the current state of a Raft node.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -35,33 +37,198 @@ pub struct RaftMetrics {
     pub state: State,
     /// The current term of the Raft node.
     pub current_term: u64,
+    /// The candidate this node voted for in `current_term`, or None if it has not voted yet.
+    ///
+    /// During an election that does not converge, nodes voting for different candidates in the same term reveal a
+    /// split vote.
+    pub voted_for: Option<NodeId>,
     /// The last log index to be appended to this Raft node's log.
     pub last_log_index: u64,
     /// The last log index to be applied to this Raft node's state machine.
     pub last_applied: u64,
+    /// Whether this node has ever received any log or snapshot, i.e., it has been initialized or added to a cluster.
+    pub initialized: bool,
+    /// The last log id known to be committed by this Raft node, or None if no log is known to be committed.
+    ///
+    /// A log is applied only after it is committed, thus `last_applied <= committed <= last_log_index` always holds:
+    /// the gap between `committed` and `last_applied` is the apply backlog, and the gap between `last_log_index` and
+    /// `committed` is the replication backlog.
+    pub committed: Option<LogId>,
     /// The current cluster leader.
     pub current_leader: Option<NodeId>,
     /// The current membership config of the cluster.
     pub membership_config: EffectiveMembership,
+    /// The number of voters in the current membership config, including both configs of a joint config.
+    pub voter_count: usize,
+    /// The minimal number of voters that constitutes a quorum in the current membership config.
+    /// See `EffectiveMembership::quorum_size()`.
+    pub quorum_size: usize,
 
     /// The id of the last log included in snapshot.
     /// If there is no snapshot, it is (0,0).
     pub snapshot: LogId,
 
+    /// The number of logs retained in storage, including applied ones that are not purged yet.
+    /// See `Config::max_applied_log_to_keep`.
+    pub log_entry_count: u64,
+
+    /// An estimate of the size in bytes of logs in storage, returned by `RaftStorage::log_size_bytes()`.
+    /// It is refreshed when applied logs are purged, and is None if the storage does not report it.
+    pub log_bytes: Option<u64>,
+
     /// The metrics about the leader. It is Some() only when this node is leader.
     pub leader_metrics: Option<LeaderMetrics>,
+
+    /// Progress of the last snapshot this node received from the leader, updated on every chunk.
+    /// It is None if this node has never received a snapshot.
+    pub snapshot_receiving: Option<SnapshotProgress>,
+
+    /// Whether this node is a leader that lost the quorum and serves only stale reads.
+    /// See `Config::allow_stale_reads_on_quorum_loss`.
+    pub is_stale: bool,
+
+    /// The last node id a leader found to be used by more than one process, i.e., two nodes are mistakenly started
+    /// with the same id. It is detected with `AppendEntriesResponse::instance_uuid`.
+    pub duplicate_node_id: Option<NodeId>,
+
+    /// The last log index to replay into the state machine, of the logs committed before this node restarted or
+    /// became a leader. It is 0 if no replay has started.
+    ///
+    /// Replaying a large log may take a long time before the node serves. The progress is
+    /// `startup_replay_applied / startup_replay_target`.
+    pub startup_replay_target: u64,
+
+    /// The last log index replayed so far, see `startup_replay_target`.
+    pub startup_replay_applied: u64,
+
+    /// The checksum of the state machine returned by `RaftStorage::state_checksum_at()`, and the last applied log id
+    /// it is computed at. It is computed on startup and by `Raft::state_checksum()`, and is None if the storage does
+    /// not report it.
+    ///
+    /// Replicas at the same applied log id should have the same checksum, otherwise their states have diverged.
+    pub state_checksum: Option<StateChecksum>,
+
+    /// The latest membership config received from the leader that is not yet committed, thus not yet effective.
+    /// It is always None unless `Config::report_membership_on` is `Commit`.
+    pub pending_membership: Option<EffectiveMembership>,
+
+    /// Whether the Raft core task is running, or why it has shut down.
+    ///
+    /// The last metrics a node reports before it stops carry the reason, thus a supervisor watching the metrics can
+    /// decide whether to restart it.
+    pub running_state: RunningState,
+}
+
+/// Whether the Raft core task is running, see `RaftMetrics::running_state`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RunningState {
+    Running,
+    Shutdown(ShutdownReason),
+}
+
+/// Why the Raft core task has shut down.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShutdownReason {
+    /// `Raft::shutdown()` is called.
+    Requested,
+
+    /// A fatal storage error.
+    StorageFault(StorageFault),
+
+    /// The core task panicked, with the panic message.
+    Panic(String),
+
+    /// Any other error the core task stops with.
+    Error(String),
+}
+
+/// The fatal storage error a Raft node shuts down with.
+///
+/// `StorageError` can not be cloned or serialized, thus it is kept as the rendered message.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageFault {
+    /// The rendered storage error.
+    pub error: String,
+
+    /// Whether the error is a `TransientStorageError`, i.e., a restarted node may succeed.
+    pub transient: bool,
+}
+
+/// A checksum of the state machine at a specific applied log id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateChecksum {
+    /// The last applied log id of the state machine when the checksum is computed.
+    pub log_id: LogId,
+    /// The checksum returned by `RaftStorage::state_checksum_at()`.
+    pub checksum: u64,
+}
+
+/// A snapshot of the full state of a Raft node, returned by `Raft::dump_state()`.
+///
+/// It is meant to be attached to a bug report: `to_json()` renders it as a single JSON blob.
+/// All fields are read by the core task at the same time, thus they are consistent with each other.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RaftStateDump {
+    /// The ID of the Raft node.
+    pub id: NodeId,
+    /// The state of the Raft node.
+    pub state: State,
+    /// The current term of the Raft node.
+    pub current_term: u64,
+    /// The candidate this node voted for in the current term.
+    pub voted_for: Option<NodeId>,
+    /// The current cluster leader.
+    pub current_leader: Option<NodeId>,
+    /// The last log id this node knows to be committed.
+    pub committed: LogId,
+    /// The last log id applied to the state machine.
+    pub last_applied: LogId,
+    /// The last log id appended to the log.
+    pub last_log_id: LogId,
+    /// The last log id included in the current snapshot.
+    pub snapshot_last_log_id: LogId,
+    /// The membership config in use.
+    pub effective_membership: EffectiveMembership,
+    /// The membership config received but not yet effective. See `RaftMetrics::pending_membership`.
+    pub pending_membership: Option<EffectiveMembership>,
+    /// Replication progress of every target. It is empty unless this node is leader.
+    pub replication: BTreeMap<NodeId, ReplicationMetrics>,
+    /// The most recent state transitions of this node, the oldest first.
+    pub state_transitions: Vec<StateTransition>,
+}
+
+impl RaftStateDump {
+    /// Render the dump as pretty printed JSON.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+/// A change of the state of a Raft node, e.g., from `Follower` to `Candidate`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateTransition {
+    pub from: State,
+    pub to: State,
+    /// The term of the node when the transition took place.
+    pub term: u64,
+    /// The wall clock time of the transition, in milliseconds since the UNIX epoch.
+    pub unix_time_ms: u64,
 }
 
 impl MessageSummary for RaftMetrics {
     fn summary(&self) -> String {
-        format!("Metrics{{id:{},{:?}, term:{}, last_log:{}, last_applied:{}, leader:{:?}, membership:{}, snapshot:{}, replication:{}",
+        format!("Metrics{{id:{},{:?}, term:{}, voted_for:{:?}, last_log:{}, last_applied:{}, committed:{:?}, leader:{:?}, membership:{}, voters:{}, quorum:{}, snapshot:{}, replication:{}",
             self.id,
             self.state,
             self.current_term,
+            self.voted_for,
             self.last_log_index,
             self.last_applied,
+            self.committed,
             self.current_leader,
             self.membership_config.summary(),
+            self.voter_count,
+            self.quorum_size,
             self.snapshot,
             self.leader_metrics.as_ref().map(|x| x.summary()).unwrap_or_default(),
         )
@@ -90,22 +257,126 @@ impl MessageSummary for LeaderMetrics {
     }
 }
 
+/// Progress of transferring a snapshot between a leader and a follower.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotProgress {
+    /// The snapshot being transferred.
+    pub meta: SnapshotMeta,
+
+    /// Number of bytes transferred so far.
+    pub transferred: u64,
+
+    /// Total size of the snapshot in bytes.
+    ///
+    /// The sending end knows it from the beginning.
+    /// The receiving end does not learn it until the last chunk arrives, thus it is None until then.
+    pub total: Option<u64>,
+}
+
+impl SnapshotProgress {
+    /// Returns true if every byte of the snapshot has been transferred.
+    pub fn is_done(&self) -> bool {
+        self.total == Some(self.transferred)
+    }
+
+    /// Returns the transferred percentage, in `[0, 100]`, or None if the total size is not yet known.
+    pub fn percent(&self) -> Option<u64> {
+        let total = self.total?;
+        if total == 0 {
+            return Some(100);
+        }
+        Some(std::cmp::min(self.transferred, total) * 100 / total)
+    }
+}
+
+impl MessageSummary for SnapshotProgress {
+    fn summary(&self) -> String {
+        format!(
+            "{}:{}/{}",
+            self.meta.snapshot_id,
+            self.transferred,
+            self.total.map(|x| x.to_string()).unwrap_or_else(|| "?".to_string())
+        )
+    }
+}
+
+/// Timing stats of the internals of a Raft node, for performance debugging.
+///
+/// They are collected only when `Config::enable_tick_metrics` is set, and are observed via `Raft::perf_metrics()`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PerfMetrics {
+    /// The ID of the Raft node.
+    pub id: NodeId,
+
+    /// The number of iterations of the core loop measured so far.
+    ///
+    /// An iteration handles one API message or one event from a replication stream.
+    pub ticks: u64,
+
+    /// The time the last iteration of the core loop took.
+    pub last_tick: Duration,
+
+    /// The longest time an iteration of the core loop took.
+    ///
+    /// The core handles nothing else meanwhile, thus a large value indicates a blocking call, e.g., a slow storage.
+    pub max_tick: Duration,
+
+    /// The latency of the last AppendEntries RPC to every replication target. It is collected only by a leader.
+    pub replication_rpc: BTreeMap<NodeId, Duration>,
+
+    /// The number of logs in the last batch applied to the state machine.
+    pub last_apply_entries: u64,
+
+    /// The time it took to apply the last batch of logs to the state machine.
+    pub last_apply: Duration,
+}
+
+impl MessageSummary for PerfMetrics {
+    fn summary(&self) -> String {
+        format!(
+            "PerfMetrics{{id:{}, ticks:{}, last_tick:{:?}, max_tick:{:?}, replication_rpc:{:?}, last_apply:{}:{:?}}}",
+            self.id,
+            self.ticks,
+            self.last_tick,
+            self.max_tick,
+            self.replication_rpc,
+            self.last_apply_entries,
+            self.last_apply
+        )
+    }
+}
+
 impl RaftMetrics {
     pub(crate) fn new_initial(id: NodeId) -> Self {
-        let membership_config = Membership::new_initial(id);
+        let membership_config = EffectiveMembership {
+            log_id: LogId::none(),
+            membership: Membership::new_initial(id),
+        };
         Self {
             id,
             state: State::Follower,
             current_term: 0,
+            voted_for: None,
             last_log_index: 0,
             last_applied: 0,
+            initialized: false,
+            committed: None,
             current_leader: None,
-            membership_config: EffectiveMembership {
-                log_id: LogId::default(),
-                membership: membership_config,
-            },
-            snapshot: LogId { term: 0, index: 0 },
+            voter_count: membership_config.voter_count(),
+            quorum_size: membership_config.quorum_size(),
+            membership_config,
+            snapshot: LogId::none(),
+            log_entry_count: 0,
+            log_bytes: None,
             leader_metrics: None,
+            snapshot_receiving: None,
+            is_stale: false,
+            duplicate_node_id: None,
+            startup_replay_target: 0,
+            startup_replay_applied: 0,
+            state_checksum: None,
+            pending_membership: None,
+            running_state: RunningState::Running,
         }
     }
 }
@@ -5,6 +5,7 @@ use tokio::sync::watch;
 use tokio::time::sleep;
 
 use crate::core::EffectiveMembership;
+use crate::metrics::RunningState;
 use crate::metrics::Wait;
 use crate::metrics::WaitError;
 use crate::raft::Membership;
@@ -179,16 +180,31 @@ fn init_wait_test() -> (RaftMetrics, Wait, watch::Sender<RaftMetrics>) {
         id: 0,
         state: State::Learner,
         current_term: 0,
+        voted_for: None,
         last_log_index: 0,
         last_applied: 0,
+        initialized: false,
+        committed: None,
         current_leader: None,
         membership_config: EffectiveMembership {
             log_id: LogId::default(),
             membership: Membership::new_single(btreeset! {}),
         },
+        voter_count: 0,
+        quorum_size: 1,
 
         snapshot: LogId { term: 0, index: 0 },
+        log_entry_count: 0,
+        log_bytes: None,
         leader_metrics: None,
+        snapshot_receiving: None,
+        is_stale: false,
+        duplicate_node_id: None,
+        startup_replay_target: 0,
+        startup_replay_applied: 0,
+        state_checksum: None,
+        pending_membership: None,
+        running_state: RunningState::Running,
     };
     let (tx, rx) = watch::channel(init.clone());
     let w = Wait {
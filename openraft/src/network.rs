@@ -7,6 +7,8 @@ use crate::raft::AppendEntriesRequest;
 use crate::raft::AppendEntriesResponse;
 use crate::raft::InstallSnapshotRequest;
 use crate::raft::InstallSnapshotResponse;
+use crate::raft::LogAuditRequest;
+use crate::raft::LogAuditResponse;
 use crate::raft::VoteRequest;
 use crate::raft::VoteResponse;
 use crate::AppData;
@@ -32,4 +34,36 @@ where D: AppData
 
     /// Send a RequestVote RPC to the target Raft node (§5).
     async fn send_vote(&self, target: NodeId, rpc: VoteRequest) -> Result<VoteResponse>;
+
+    /// Send a LogAudit RPC to the target Raft node, which is handled by `Raft::log_audit()`.
+    ///
+    /// It is not part of the Raft protocol and is only sent by `Raft::audit_follower()`.
+    /// The default implementation returns an error.
+    async fn send_log_audit(&self, target: NodeId, rpc: LogAuditRequest) -> Result<LogAuditResponse> {
+        let _ = rpc;
+        Err(anyhow::anyhow!("send_log_audit to {} is not supported", target))
+    }
+}
+
+/// A classified error a `RaftNetwork` impl can return, to tell raft how to retry a failed AppendEntries RPC.
+///
+/// It is returned wrapped in an `anyhow::Error`, e.g., `Err(NetworkError::Transient(e).into())`, and raft finds it
+/// with `downcast_ref()`. An error that is not classified is retried as soon as there are logs to replicate, or at the
+/// next heartbeat.
+#[derive(Debug, thiserror::Error)]
+pub enum NetworkError {
+    /// A failure that may go away after a while, e.g., the connection is refused or reset.
+    /// It is retried with an exponential backoff.
+    #[error("transient network error: {0}")]
+    Transient(anyhow::Error),
+
+    /// The target is not expected to become reachable, e.g., its address can not be resolved.
+    /// It is retried with the max backoff.
+    #[error("unrecoverable network error: {0}")]
+    Unrecoverable(anyhow::Error),
+
+    /// The target received the request but failed to handle it, e.g., it is busy.
+    /// It is retried quickly with the min backoff.
+    #[error("remote error: {0}")]
+    RemoteError(anyhow::Error),
 }
@@ -3,30 +3,52 @@
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::future::Future;
+use std::io::SeekFrom;
 use std::sync::Arc;
 use std::time::Duration;
 
 use maplit::btreeset;
 use serde::Deserialize;
 use serde::Serialize;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncSeekExt;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
 use tokio::sync::watch;
 use tokio::sync::Mutex;
 use tokio::task::JoinHandle;
+use tokio::time::error::Elapsed;
 use tracing::Span;
 
+use crate::codec::Codec;
 use crate::config::Config;
 use crate::core::RaftCore;
 use crate::error::AddLearnerError;
+use crate::error::AddVoterError;
+use crate::error::AuditFollowerError;
+use crate::error::Cancelled;
+use crate::error::CatchUpTimeout;
+use crate::error::ChangeMembershipError;
 use crate::error::ClientReadError;
 use crate::error::ClientWriteError;
+use crate::error::CodecError;
 use crate::error::InitializeError;
+use crate::error::PauseReplicationError;
 use crate::error::RaftError;
 use crate::error::RaftResult;
+use crate::error::RewriteLogError;
+use crate::error::SetRelayError;
+use crate::metrics::PerfMetrics;
 use crate::metrics::RaftMetrics;
+use crate::metrics::RaftStateDump;
+use crate::metrics::StateChecksum;
 use crate::metrics::Wait;
 use crate::quorum;
+use crate::replication::FollowerProgress;
+use crate::storage::Snapshot;
 use crate::AppData;
 use crate::AppDataResponse;
 use crate::LogId;
@@ -38,7 +60,9 @@ use crate::SnapshotMeta;
 
 struct RaftInner<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
     tx_api: mpsc::UnboundedSender<(RaftMsg<D, R>, Span)>,
+    config: Arc<Config>,
     rx_metrics: watch::Receiver<RaftMetrics>,
+    rx_perf_metrics: watch::Receiver<PerfMetrics>,
     raft_handle: Mutex<Option<JoinHandle<RaftResult<()>>>>,
     tx_shutdown: Mutex<Option<oneshot::Sender<()>>>,
     marker_n: std::marker::PhantomData<N>,
@@ -91,13 +115,59 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// See the docs on the `RaftStorage` trait for more details.
     #[tracing::instrument(level="debug", skip(config, network, storage), fields(cluster=%config.cluster_name))]
     pub fn new(id: NodeId, config: Arc<Config>, network: Arc<N>, storage: Arc<S>) -> Self {
+        Self::spawn(id, config, network, storage, None)
+    }
+
+    /// Create and spawn a new Raft task that starts in the given role, without going through an election.
+    ///
+    /// It is meant for tests that do not care about the election phase. The role only replaces the one Raft would
+    /// derive from storage when it starts: the term, vote and logs are still loaded from storage, and the leader is
+    /// only a hint. Thus it can not fabricate a leader: a node can not be seeded as a leader, and a follower that
+    /// does not hear from the hinted leader times out and starts an election as usual.
+    ///
+    /// A role that is inconsistent with storage, e.g., a follower that is not a voter in the membership in storage,
+    /// is ignored.
+    #[tracing::instrument(level="debug", skip(config, network, storage), fields(cluster=%config.cluster_name))]
+    pub fn new_with_initial_role(
+        id: NodeId,
+        config: Arc<Config>,
+        network: Arc<N>,
+        storage: Arc<S>,
+        role: InitialRole,
+    ) -> Self {
+        Self::spawn(id, config, network, storage, Some(role))
+    }
+
+    fn spawn(
+        id: NodeId,
+        config: Arc<Config>,
+        network: Arc<N>,
+        storage: Arc<S>,
+        initial_role: Option<InitialRole>,
+    ) -> Self {
         let (tx_api, rx_api) = mpsc::unbounded_channel();
         let (tx_metrics, rx_metrics) = watch::channel(RaftMetrics::new_initial(id));
+        let (tx_perf_metrics, rx_perf_metrics) = watch::channel(PerfMetrics {
+            id,
+            ..Default::default()
+        });
         let (tx_shutdown, rx_shutdown) = oneshot::channel();
-        let raft_handle = RaftCore::spawn(id, config, network, storage, rx_api, tx_metrics, rx_shutdown);
+        let raft_handle = RaftCore::spawn(
+            id,
+            config.clone(),
+            network,
+            storage,
+            initial_role,
+            rx_api,
+            tx_metrics,
+            tx_perf_metrics,
+            rx_shutdown,
+        );
         let inner = RaftInner {
             tx_api,
+            config,
             rx_metrics,
+            rx_perf_metrics,
             raft_handle: Mutex::new(Some(raft_handle)),
             tx_shutdown: Mutex::new(Some(tx_shutdown)),
             marker_n: std::marker::PhantomData,
@@ -135,6 +205,16 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         self.call_core(RaftMsg::InstallSnapshot { rpc, tx }, rx).await
     }
 
+    /// Submit a LogAudit RPC to this Raft node.
+    ///
+    /// It is sent by a leader that audits this node with `audit_follower()`, and returns the checksums of the
+    /// requested logs on this node. It does not change anything.
+    #[tracing::instrument(level = "debug", skip(self, rpc), fields(rpc=%rpc.summary()))]
+    pub async fn log_audit(&self, rpc: LogAuditRequest) -> Result<LogAuditResponse, RaftError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::LogAudit { rpc, tx }, rx).await
+    }
+
     /// Get the ID of the current leader from this Raft node.
     ///
     /// This method is based on the Raft metrics system which does a good job at staying
@@ -145,22 +225,106 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         self.metrics().borrow().current_leader
     }
 
+    /// Get the current term of this Raft node.
+    ///
+    /// The term never decreases, and a leader is elected in a term no other leader is elected in. Thus it can be
+    /// attached to an external operation as a fencing token, e.g., to a lock held in a lock service, so that an
+    /// operation from a deposed leader can be rejected.
+    ///
+    /// It is read from the metrics, which are updated as soon as the term is changed and saved.
+    pub fn current_term(&self) -> u64 {
+        self.inner.rx_metrics.borrow().current_term
+    }
+
     /// Check to ensure this node is still the cluster leader, in order to guard against stale reads (§8).
     ///
     /// The actual read operation itself is up to the application, this method just ensures that
     /// the read will not be stale.
+    ///
+    /// The response contains the term in which the leadership is confirmed. A client can compare it across retries to
+    /// detect that it is served by a stale leader.
+    ///
+    /// A non-leader returns `ClientReadError::ForwardToLeader` or `ClientReadError::NoLeader`, like `client_write()`
+    /// does. If a greater term is seen while confirming the leadership, `ClientReadError::LeaderStepped` is returned.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn client_read(&self) -> Result<(), ClientReadError> {
+    pub async fn client_read(&self) -> Result<ClientReadResponse, ClientReadError> {
         let (tx, rx) = oneshot::channel();
         self.call_core(RaftMsg::ClientReadRequest { tx }, rx).await
     }
 
+    /// Serve a read that may be stale when the leader lost the quorum.
+    ///
+    /// It confirms the leadership like `client_read()` does. If the confirmation fails and
+    /// `Config::allow_stale_reads_on_quorum_loss` is set, the leader enters the read-only mode: the read is still
+    /// served with the local state machine, and the response is flagged with `is_stale`. Otherwise the error is
+    /// returned.
+    ///
+    /// The application must never treat such a read as linearizable.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn stale_read(&self) -> Result<StaleReadResponse, ClientReadError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::StaleReadRequest { tx }, rx).await
+    }
+
+    /// Start a linearizable read-only transaction that spans several state machine queries.
+    ///
+    /// It confirms the leadership the same way `client_read()` does, waits until the state machine applies the
+    /// `read_index`, then returns a view pinned at the applied index. Every query the application runs with the view
+    /// should read the state machine at `AppliedView::last_applied`, thus they all observe the same point in time,
+    /// no matter how many logs are applied meanwhile.
+    ///
+    /// The state machine has to support reading at a given applied index, e.g., an MVCC store that keeps old versions
+    /// for as long as a view is in use. Keeping these versions is up to the application.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn read_snapshot(&self) -> Result<AppliedView, ClientReadError> {
+        let resp = self.client_read().await?;
+
+        let mut rx = self.inner.rx_metrics.clone();
+        let last_applied = loop {
+            let last_applied = rx.borrow().last_applied;
+            if last_applied >= resp.read_index.index {
+                break last_applied;
+            }
+
+            if rx.changed().await.is_err() {
+                return Err(ClientReadError::RaftError(RaftError::ShuttingDown));
+            }
+        };
+
+        Ok(AppliedView {
+            term: resp.term,
+            read_index: resp.read_index,
+            last_applied,
+        })
+    }
+
     /// Submit a mutating client request to Raft to update the state of the system (§5.1).
     ///
     /// It will be appended to the log, committed to the cluster, and then applied to the
     /// application state machine. The result of applying the request to the state machine will
     /// be returned as the response from this method.
     ///
+    /// If the state machine rejects the request, i.e., `AppDataResponse::application_error()` returns an error,
+    /// `ClientWriteError::ApplicationError` is returned instead. The entry is committed and applied anyway.
+    ///
+    /// If `Config::client_write_ack` is `AckOn::Commit`, it returns once the request is committed, without waiting for
+    /// it to be applied. The response then carries no application data and a rejection by the state machine is not
+    /// reported. See `AckOn`.
+    ///
+    /// If `Config::max_uncommitted_entries` is set and the leader already has that many uncommitted entries,
+    /// `ClientWriteError::Throttled` is returned without appending the request. The client should retry later.
+    ///
+    /// If `Config::max_entry_size_bytes` is set and the serialized request is larger, `ClientWriteError::EntryTooLarge`
+    /// is returned without appending the request.
+    ///
+    /// If `RaftStorage::validate_entry()` rejects the request, `ClientWriteError::InvalidEntry` is returned and the
+    /// request is not appended.
+    ///
+    /// If this node is not the leader, `ClientWriteError::ForwardToLeader` is returned when a leader is known, and
+    /// `ClientWriteError::NoLeader` when there is none, e.g., during an election. If this node loses its leadership
+    /// before the request is committed, `ClientWriteError::LeaderStepped` is returned: the request may or may not be
+    /// committed by the next leader.
+    ///
     /// Our goal for Raft is to implement linearizable semantics. If the leader crashes after committing
     /// a log entry but before responding to the client, the client may retry the command with a new
     /// leader, causing it to be executed a second time. As such, clients should assign unique serial
@@ -175,16 +339,117 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     #[tracing::instrument(level = "debug", skip(self, rpc))]
     pub async fn client_write(&self, rpc: ClientWriteRequest<D>) -> Result<ClientWriteResponse<R>, ClientWriteError> {
         let (tx, rx) = oneshot::channel();
-        self.call_core(RaftMsg::ClientWriteRequest { rpc, tx }, rx).await
+        self.call_core(
+            RaftMsg::ClientWriteRequest {
+                rpc,
+                ticket_tx: None,
+                tx,
+            },
+            rx,
+        )
+        .await
+    }
+
+    /// Submit a mutating client request to Raft like `client_write()`, but return as soon as the leader appends it,
+    /// with the log id assigned to it and a future that resolves to the response once it is committed and applied.
+    ///
+    /// The log id is a ticket to track the write: the client may persist it, and if it stops waiting for the future,
+    /// e.g., it reconnects to another node, check with `is_committed()` whether the write is committed. An error is
+    /// returned without a ticket if the request is refused before it is appended, e.g., this node is not the leader.
+    #[tracing::instrument(level = "debug", skip(self, rpc))]
+    pub async fn client_write_ticket(
+        &self,
+        rpc: ClientWriteRequest<D>,
+    ) -> Result<
+        (
+            LogId,
+            impl Future<Output = Result<ClientWriteResponse<R>, ClientWriteError>>,
+        ),
+        ClientWriteError,
+    > {
+        let (ticket_tx, ticket_rx) = oneshot::channel();
+        let (tx, rx) = oneshot::channel();
+
+        let mes = RaftMsg::ClientWriteRequest {
+            rpc,
+            ticket_tx: Some(ticket_tx),
+            tx,
+        };
+        let sum = mes.summary();
+
+        let send_res = self.inner.tx_api.send((mes, tracing::Span::current()));
+        if let Err(send_err) = send_res {
+            tracing::error!(%send_err, mes=%sum, "error send tx to RaftCore");
+            return Err(RaftError::ShuttingDown.into());
+        }
+
+        let done = async move {
+            match rx.await {
+                Ok(res) => res,
+                Err(e) => {
+                    tracing::error!(%e, "error recv rx from RaftCore");
+                    Err(RaftError::ShuttingDown.into())
+                }
+            }
+        };
+
+        match ticket_rx.await {
+            Ok(log_id) => Ok((log_id, done)),
+            Err(_) => {
+                // No log id is assigned: the request is refused, and the reason is sent with `tx`.
+                let err = match done.await {
+                    Ok(_) => RaftError::ShuttingDown.into(),
+                    Err(err) => err,
+                };
+                Err(err)
+            }
+        }
+    }
+
+    /// Submit a mutating client request to Raft like `client_write()`, but stop waiting for the response once `cancel`
+    /// resolves.
+    ///
+    /// On cancellation `ClientWriteError::Cancelled` is returned. An entry that is already appended can not be removed
+    /// safely, thus it is not aborted and may still be committed and applied: `Cancelled::maybe_committed` is true. If
+    /// `cancel` has already resolved when this method is called, the request is not sent and `maybe_committed` is
+    /// false.
+    #[tracing::instrument(level = "debug", skip(self, rpc, cancel))]
+    pub async fn client_write_cancelable<C>(
+        &self,
+        rpc: ClientWriteRequest<D>,
+        cancel: C,
+    ) -> Result<ClientWriteResponse<R>, ClientWriteError>
+    where
+        C: Future<Output = ()>,
+    {
+        tokio::pin!(cancel);
+
+        if futures::poll!(&mut cancel).is_ready() {
+            return Err(Cancelled { maybe_committed: false }.into());
+        }
+
+        tokio::select! {
+            biased;
+            res = self.client_write(rpc) => res,
+            _ = &mut cancel => {
+                tracing::info!("client write is canceled");
+                Err(Cancelled { maybe_committed: true }.into())
+            }
+        }
     }
 
     /// Initialize a pristine Raft node with the given config.
     ///
     /// This command should be called on pristine nodes — where the log index is 0 and the node is
     /// in Learner state — as if either of those constraints are false, it indicates that the
-    /// cluster is already formed and in motion. If `InitializeError::NotAllowed` is returned
-    /// from this function, it is safe to ignore, as it simply indicates that the cluster is
-    /// already up and running, which is ultimately the goal of this function.
+    /// cluster is already formed and in motion. If `InitializeError::AlreadyInitialized` or
+    /// `InitializeError::NotPristine` is returned from this function, it is safe to ignore, as it
+    /// simply indicates that the cluster is already up and running, which is ultimately the goal of
+    /// this function.
+    ///
+    /// `members` must not be empty, and every member other than this node must be reachable: it is
+    /// probed before initializing, and `InitializeError::MemberUnreachable` is returned if it does
+    /// not respond. Nothing is changed then, and it is safe to retry once the member is started.
     ///
     /// This command will work for single-node or multi-node cluster formation. This command
     /// should be called with all discovered nodes which need to be part of cluster, and as such
@@ -212,6 +477,52 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         self.call_core(RaftMsg::Initialize { members, tx }, rx).await
     }
 
+    /// Initialize a pristine Raft node with a snapshot as the base of its state, to restore a cluster from a backup.
+    ///
+    /// The snapshot is installed into the state machine, `last_applied` is set to its last log id, and then the
+    /// cluster is initialized with `members` like `initialize()` does, in a term greater than the term of the
+    /// last log in the snapshot. The leader commits the membership as the log after the snapshot, and the other
+    /// members receive the snapshot with the normal snapshot replication.
+    ///
+    /// It is only for a disaster recovery of a whole cluster, and the caller has to make sure that:
+    /// - every member is pristine, i.e., has no log, no snapshot and a term of 0, and `initialize_from_snapshot()` is
+    ///   called on only one of them. Otherwise `InitializeError::NotPristine` is returned if this node is not pristine,
+    ///   and the other nodes could diverge from the restored state.
+    /// - the snapshot is trusted: it is installed without any check and replaces the state on every member.
+    /// - if this node restarts before the membership is committed, its storage is wiped and the restore is retried.
+    ///
+    /// The snapshot data is read into memory before being installed.
+    #[tracing::instrument(level = "debug", skip(self, snapshot), fields(snapshot=%snapshot.meta.last_log_id))]
+    pub async fn initialize_from_snapshot(
+        &self,
+        members: BTreeSet<NodeId>,
+        mut snapshot: Snapshot<S::SnapshotData>,
+    ) -> Result<(), InitializeError> {
+        let mut data = Vec::new();
+        snapshot
+            .snapshot
+            .seek(SeekFrom::Start(0))
+            .await
+            .map_err(|e| InitializeError::RaftError(RaftError::RaftStorage(e.into())))?;
+        snapshot
+            .snapshot
+            .read_to_end(&mut data)
+            .await
+            .map_err(|e| InitializeError::RaftError(RaftError::RaftStorage(e.into())))?;
+
+        let (tx, rx) = oneshot::channel();
+        self.call_core(
+            RaftMsg::InitializeFromSnapshot {
+                members,
+                meta: snapshot.meta,
+                data,
+                tx,
+            },
+            rx,
+        )
+        .await
+    }
+
     /// Synchronize a new Raft node, optionally, blocking until up-to-speed (§6).
     ///
     /// - Add a node as learner into the cluster.
@@ -224,10 +535,71 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// If blocking is false, this function returns at once as successfully setting up the replication.
     ///
     /// If the node to add is already a voter or learner, it returns `RaftResponse::NoChange` at once.
+    ///
+    /// A learner is never promoted automatically: it stays in `State::Learner` and never starts an election, even if
+    /// it loses contact with the leader, until it is made a voter by `change_membership()`. Thus a new node can catch
+    /// up entirely, e.g., by installing a snapshot, before it affects the quorum.
     #[tracing::instrument(level = "debug", skip(self, id), fields(target=id))]
     pub async fn add_learner(&self, id: NodeId, blocking: bool) -> Result<AddLearnerResponse, AddLearnerError> {
         let (tx, rx) = oneshot::channel();
-        self.call_core(RaftMsg::AddLearner { id, blocking, tx }, rx).await
+        self.call_core(
+            RaftMsg::AddLearner {
+                id,
+                blocking,
+                standby: false,
+                tx,
+            },
+            rx,
+        )
+        .await
+    }
+
+    /// Add a node as a standby, e.g., a warm replica in another region for disaster recovery.
+    ///
+    /// A standby is a learner with an operator intent marker: it receives and applies every log so that it can be
+    /// promoted quickly with `change_membership()` in a failover, but it is not part of the membership config. Thus
+    /// like any learner it never counts toward a quorum, it is never asked to confirm a leadership for a read, and it
+    /// forwards reads to the leader. The leader reports it with `ReplicationMetrics::standby`, until it is made a
+    /// voter.
+    ///
+    /// Like a learner, the mark is kept only by the leader that adds it. After a leader change the node has to be
+    /// added again.
+    ///
+    /// See `add_learner()` for `blocking` and the response.
+    #[tracing::instrument(level = "debug", skip(self, id), fields(target=id))]
+    pub async fn add_standby(&self, id: NodeId, blocking: bool) -> Result<AddLearnerResponse, AddLearnerError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(
+            RaftMsg::AddLearner {
+                id,
+                blocking,
+                standby: true,
+                tx,
+            },
+            rx,
+        )
+        .await
+    }
+
+    /// Add several nodes as learners in one call, see `add_learner()`.
+    ///
+    /// Learners are not part of the membership config, thus adding them does not change the quorum and no membership
+    /// log is appended. The nodes are added concurrently. If `blocking` is true, it returns when every node is up to
+    /// date. It returns the response for each node, or the first error.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn add_learners(
+        &self,
+        ids: BTreeSet<NodeId>,
+        blocking: bool,
+    ) -> Result<BTreeMap<NodeId, AddLearnerResponse>, AddLearnerError> {
+        let results = futures::future::join_all(ids.iter().map(|id| self.add_learner(*id, blocking))).await;
+
+        let mut res = BTreeMap::new();
+        for (id, r) in ids.into_iter().zip(results.into_iter()) {
+            res.insert(id, r?);
+        }
+
+        Ok(res)
     }
 
     /// Propose a cluster configuration change.
@@ -249,6 +621,16 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         &self,
         members: BTreeSet<NodeId>,
         blocking: bool,
+    ) -> Result<ClientWriteResponse<R>, ClientWriteError> {
+        self.change_membership_demote(members, btreeset! {}, blocking).await
+    }
+
+    /// Change membership to `members`, and keep the voters in `demote`, which are removed by the change, as learners.
+    async fn change_membership_demote(
+        &self,
+        members: BTreeSet<NodeId>,
+        demote: BTreeSet<NodeId>,
+        blocking: bool,
     ) -> Result<ClientWriteResponse<R>, ClientWriteError> {
         tracing::info!(?members, "change_membership: add every member as learner");
 
@@ -288,6 +670,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 RaftMsg::ChangeMembership {
                     members: members.clone(),
                     blocking,
+                    demote: demote.clone(),
                     tx,
                 },
                 rx,
@@ -299,21 +682,328 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         let (log_id, joint) = (res.log_id, res.membership.clone().unwrap());
 
         // There is a previously in progress joint state and it becomes the membership config we want.
-        if !joint.is_in_joint_consensus() {
+        if !joint.is_joint() {
             return Ok(res);
         }
 
-        tracing::debug!("committed a joint config: {} {:?}", log_id, joint);
+        tracing::debug!("committed a joint config: {} {}", log_id, joint);
         tracing::debug!("the second step is to change to uniform config: {:?}", members);
 
         let (tx, rx) = oneshot::channel();
-        let res = self.call_core(RaftMsg::ChangeMembership { members, blocking, tx }, rx).await?;
+        let res = self
+            .call_core(
+                RaftMsg::ChangeMembership {
+                    members,
+                    blocking,
+                    demote,
+                    tx,
+                },
+                rx,
+            )
+            .await?;
 
         tracing::info!("res of second change_membership: {}", res.summary());
 
         Ok(res)
     }
 
+    /// Set the priorities of voters to become the leader, e.g., to prefer the voters in the primary datacenter.
+    ///
+    /// A voter not in `priorities` has a priority of 0. A voter waits longer before starting an election for every
+    /// distinct priority higher than its own, by the width of the election timeout range. Thus a voter with the
+    /// highest priority usually wins an election, while a voter with a lower priority still becomes the leader if the
+    /// preferred ones are down. A leader is never deposed because a voter with a higher priority is available.
+    ///
+    /// The priorities are part of the membership config: a uniform config with the same voters and the new
+    /// priorities is committed, and they are kept by the following membership changes. It returns
+    /// `ChangeMembershipError::InProgress` if a membership change is in progress.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn set_priorities(
+        &self,
+        priorities: BTreeMap<NodeId, u64>,
+    ) -> Result<ClientWriteResponse<R>, ClientWriteError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::SetPriorities { priorities, tx }, rx).await
+    }
+
+    /// Validate a cluster configuration change without changing anything.
+    ///
+    /// It returns the membership configs `change_membership()` would propose, and the nodes in the target config that
+    /// the leader does not replicate to yet or that are lagging. No log is appended and no learner is added.
+    ///
+    /// Errors such as `ChangeMembershipError::InProgress` or `ChangeMembershipError::Incompatible` are returned just
+    /// like `change_membership()` does.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn change_membership_dry_run(
+        &self,
+        members: BTreeSet<NodeId>,
+    ) -> Result<MembershipPlan, ClientWriteError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::ChangeMembershipDryRun { members, tx }, rx).await
+    }
+
+    /// Add a node as a voter: add it as a learner, wait for it to catch up, then promote it.
+    ///
+    /// It returns when the membership config with the node as a voter is committed, i.e., the response of the last
+    /// `change_membership()` call. The voters are the ones in the last config of the current membership plus `id`.
+    ///
+    /// If the node does not catch up within `timeout`, it returns `AddVoterError::CatchUpTimeout` and the membership
+    /// is left unchanged. The node is not removed: it stays a learner and the leader keeps replicating to it, thus
+    /// calling this method again resumes the catch-up. There is no API to remove a learner, but a learner that stops
+    /// responding is evicted by the leader if `Config::learner_eviction_timeout` is set.
+    ///
+    /// If the node is already a learner or a voter, it is promoted without waiting, and the promotion fails with
+    /// `ChangeMembershipError::LearnerIsLagging` if the node is not up to date.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn add_voter(&self, id: NodeId, timeout: Duration) -> Result<ClientWriteResponse<R>, AddVoterError> {
+        let res = tokio::time::timeout(timeout, self.add_learner(id, true)).await;
+
+        match res {
+            Ok(Ok(resp)) => {
+                tracing::info!(target = id, "add_voter: learner caught up: {:?}", resp);
+            }
+            Ok(Err(AddLearnerError::Exists(node_id))) => {
+                tracing::info!(%node_id, "add_voter: already a learner or voter");
+            }
+            Ok(Err(e)) => return Err(e.into()),
+            Err(_elapsed) => {
+                return Err(CatchUpTimeout { node_id: id, timeout }.into());
+            }
+        }
+
+        let mut members = {
+            let m = self.metrics().borrow();
+            m.membership_config.membership.get_configs().last().cloned().unwrap_or_default()
+        };
+        members.insert(id);
+
+        tracing::info!(?members, "add_voter: promote to voter");
+
+        let resp = self.change_membership(members, false).await?;
+        Ok(resp)
+    }
+
+    /// Demote a voter to a learner, e.g., before decommissioning it: it no longer counts toward a quorum, but the
+    /// leader keeps replicating to it.
+    ///
+    /// It returns when the membership config without the node is committed, i.e., the response of the last
+    /// `change_membership()` call. The voters are the ones in the last config of the current membership without `id`.
+    /// Like `change_membership()`, the node is removed in two steps: once the joint config is committed, the node still
+    /// counts toward the quorum of the old config; once the uniform config is committed, the remaining voters form the
+    /// quorum on their own.
+    ///
+    /// The leader can not demote itself: it returns `ChangeMembershipError::CanNotDemote`, as does a node that is not a
+    /// voter. Like any learner, the demoted node is known only to the leader: after a leader change it has to be added
+    /// again with `add_learner()`.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn demote_voter(&self, id: NodeId) -> Result<ClientWriteResponse<R>, ClientWriteError> {
+        let mut members = {
+            let m = self.metrics().borrow();
+            m.membership_config.membership.get_configs().last().cloned().unwrap_or_default()
+        };
+        members.remove(&id);
+
+        tracing::info!(?members, "demote_voter: demote {} to learner", id);
+
+        self.change_membership_demote(members, btreeset! {id}, false).await
+    }
+
+    /// Make the leader give up its leadership and revert to a follower, without choosing a successor.
+    ///
+    /// The node stops sending heartbeats, thus a new leader is elected in a normal election once a node times out.
+    /// The new leader could be any voter, including this node.
+    ///
+    /// If this node is not a leader, it returns `ClientWriteError::ForwardToLeader`.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn step_down(&self) -> Result<(), ClientWriteError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::StepDown { tx }, rx).await
+    }
+
+    /// Stop replicating logs to a target temporarily, e.g., for maintenance on it such as a disk replacement.
+    ///
+    /// The target stays in the membership config, and the leader keeps sending it heartbeats without any log, thus a
+    /// running target does not start an election. Until `resume_replication()` is called:
+    /// - The target does not receive any log or snapshot, and it is not evicted by `Config::learner_eviction_timeout`.
+    ///   A snapshot being sent is stopped before its next chunk, and is sent again from the start after resuming.
+    /// - A paused voter still counts toward the quorum, but it can not acknowledge any new log.
+    ///
+    /// **Never pause a majority of the voters**: no log can be committed then, and the cluster stops serving writes
+    /// until enough of them are resumed.
+    ///
+    /// Like a learner, the pause is kept only by the current leader. After a leader change, replication to the target
+    /// is resumed.
+    ///
+    /// If this node is not a leader, it returns `PauseReplicationError::ForwardToLeader`. If the leader does not
+    /// replicate to `target`, it returns `PauseReplicationError::NotFound`.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn pause_replication(&self, target: NodeId) -> Result<(), PauseReplicationError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(
+            RaftMsg::PauseReplication {
+                target,
+                paused: true,
+                tx,
+            },
+            rx,
+        )
+        .await
+    }
+
+    /// Resume replication to a target paused by `pause_replication()`.
+    ///
+    /// The target catches up from where it stopped, by logs, or by a snapshot if the logs it lacks are purged
+    /// meanwhile. Resuming a target that is not paused does nothing.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn resume_replication(&self, target: NodeId) -> Result<(), PauseReplicationError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(
+            RaftMsg::PauseReplication {
+                target,
+                paused: false,
+                tx,
+            },
+            rx,
+        )
+        .await
+    }
+
+    /// Let a learner receive the logs from another learner, the relay, instead of from the leader, to save the
+    /// bandwidth of the leader when there are many learners, e.g., read replicas. With `relay` being `None` the
+    /// leader replicates to the learner again.
+    ///
+    /// The leader tells the relay to replicate to the learner in the AppendEntries RPC it sends to the relay, see
+    /// `AppendEntriesRequest::relay_to`, and the relay replicates its own logs to the learner in the term of the
+    /// leader. Learners do not vote, thus it does not affect the safety. The leader still sends the learner heartbeats
+    /// without any log, like a paused target, but the replication metrics of the learner are not updated by the
+    /// leader. It is independent of `pause_replication()`: a paused learner stays paused when its relay is unset, and
+    /// resuming a relayed learner does not make the leader send it logs.
+    ///
+    /// Only one level of relaying is supported: the relay must be replicated to by the leader. The relay is kept only
+    /// by the current leader, and it is dropped if the learner or the relay becomes a voter or is removed.
+    ///
+    /// If this node is not a leader, it returns `SetRelayError::ForwardToLeader`.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn set_relay(&self, learner: NodeId, relay: Option<NodeId>) -> Result<(), SetRelayError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::SetRelay { learner, relay, tx }, rx).await
+    }
+
+    /// Check that the logs on a follower or learner are identical to those on the leader.
+    ///
+    /// The leader asks the target for the checksums of the logs it is known to have, i.e., upto the matched log,
+    /// with `RaftNetwork::send_log_audit()`, and compares them with its own. The payloads are compared as well as the
+    /// log ids, thus it catches a log that is corrupted or overwritten on the target, which replication does not
+    /// notice. Logs purged on either node are not compared.
+    ///
+    /// It returns a `LogAuditReport` with the first divergent log, if there is one. It does not repair anything.
+    /// A log purged on the leader while it is audited, e.g., by a snapshot, is reported as divergent; audit again to
+    /// rule it out.
+    ///
+    /// It must be called on the leader, and the `RaftNetwork` must implement `send_log_audit()`.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn audit_follower(&self, target: NodeId) -> Result<LogAuditReport, AuditFollowerError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::AuditFollower { target, tx }, rx).await
+    }
+
+    /// Returns whether a log id is known to be committed by this node.
+    ///
+    /// It returns false if `log_id` is beyond the commit index this node knows of, or if the log at that index on this
+    /// node has a different term, e.g., an uncommitted log that was overridden by a new leader.
+    ///
+    /// It can be called on any node. A follower or learner may learn of a commit later than the leader does, thus it
+    /// may return false for a log that is already committed by the cluster.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn is_committed(&self, log_id: LogId) -> Result<bool, RaftError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::IsCommitted { log_id, tx }, rx).await
+    }
+
+    /// Returns the log id, i.e., the term, of the log at `index` on this node, without reading the log payload.
+    ///
+    /// It returns `None` if there is no log at `index` on this node, e.g., `index` is beyond the last log, or the log
+    /// is purged. For the last log included in the snapshot, the log id in the snapshot is returned even if the log
+    /// itself is purged.
+    ///
+    /// It can be called on any node. The log at `index` on a follower or learner may be uncommitted and be replaced
+    /// later, use `is_committed()` to check it.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn get_log_id(&self, index: u64) -> Result<Option<LogId>, RaftError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::GetLogId { index, tx }, rx).await
+    }
+
+    /// Replace the payload of a historical log on this node with a redacted one, e.g., for a data deletion request.
+    ///
+    /// Raft logs are immutable, and this is an advanced operation that bypasses it. It only touches the log of this
+    /// node: to remove data from the cluster it has to be called on every node, and the state machine has to be
+    /// overwritten separately, e.g., with a normal client write.
+    ///
+    /// It is allowed only when all of these hold, otherwise a `RewriteLogError` is returned and nothing changes:
+    /// - the log is applied to the state machine of this node;
+    /// - the log is included in the current snapshot of this node, i.e., it is never applied again on this node;
+    /// - the log with exactly this log id is still in the log, and it is a normal log, not a blank or membership log;
+    /// - if this node is the leader, every replication target, including learners, has replicated the log, so that the
+    ///   redacted payload is never replicated and applied on another node;
+    /// - the store implements `RaftStorage::rewrite_log_entry()`.
+    ///
+    /// The log id, i.e., the term and the index, is preserved, thus log matching and replication are not affected.
+    #[tracing::instrument(level = "debug", skip(self, payload))]
+    pub async fn rewrite_log_entry(&self, log_id: LogId, payload: D) -> Result<(), RewriteLogError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::RewriteLogEntry { log_id, payload, tx }, rx).await
+    }
+
+    /// Start building a snapshot on this node, regardless of `Config::snapshot_policy`.
+    ///
+    /// It returns once the job is started, or if a snapshot is already being built or installed; it does not wait
+    /// for the snapshot to complete. Use `Wait::snapshot()` to wait for it. Logs included in the snapshot are purged
+    /// when logs are applied next time, according to `Config::max_applied_log_to_keep`.
+    ///
+    /// It can be called on any node. Nothing is built if no log is applied yet.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn trigger_snapshot(&self) -> Result<(), RaftError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::TriggerSnapshot { tx }, rx).await
+    }
+
+    /// Returns the replication progress of every follower and learner, if this node is the leader.
+    ///
+    /// For each target it includes the matched log, the next log to send, the number of logs in flight, the time since
+    /// the target responded last time and the replication state. Unlike `RaftMetrics::leader_metrics`, which is
+    /// updated only when the matched log or the state changes, it reflects what the replication streams are doing at
+    /// the time of the call. It is meant for diagnosing a follower that does not catch up.
+    ///
+    /// It returns `None` if this node is not the leader, or is shutting down.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn replication_table(&self) -> Option<BTreeMap<NodeId, FollowerProgress>> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::GetReplicationTable { tx }, rx).await.unwrap_or(None)
+    }
+
+    /// Compute the checksum of the state machine at the last applied log, with `RaftStorage::state_checksum_at()`.
+    ///
+    /// Replicas at the same applied log id should have the same checksum, otherwise their states have diverged.
+    /// It is computed only on demand, since it may have to read the whole state machine. The result is reported in
+    /// `RaftMetrics::state_checksum` too. It returns `None` if the storage does not support it.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn state_checksum(&self) -> Result<Option<StateChecksum>, RaftError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::GetStateChecksum { tx }, rx).await
+    }
+
+    /// Capture the full state of this node for a bug report.
+    ///
+    /// The dump includes the term, vote, commit, applied and last log ids, the membership configs, replication
+    /// progress of every target if this node is leader, and the recent state transitions.
+    /// Use `RaftStateDump::to_json()` to render it as a single JSON blob.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn dump_state(&self) -> Result<RaftStateDump, RaftError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::DumpState { tx }, rx).await
+    }
+
     /// Invoke RaftCore by sending a RaftMsg and blocks waiting for response.
     #[tracing::instrument(level = "debug", skip(self, mes, rx))]
     pub(crate) async fn call_core<T, E>(&self, mes: RaftMsg<D, R>, rx: RaftRespRx<T, E>) -> Result<T, E>
@@ -340,11 +1030,27 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         res
     }
 
+    /// Returns true if this node has ever received any log or snapshot, i.e., it has been initialized or added to a
+    /// cluster.
+    ///
+    /// It is the same as `RaftMetrics::initialized`, and a client write to a node that is not initialized is rejected
+    /// with `ClientWriteError::NotInitialized`.
+    pub fn is_initialized(&self) -> bool {
+        self.inner.rx_metrics.borrow().initialized
+    }
+
     /// Get a handle to the metrics channel.
     pub fn metrics(&self) -> watch::Receiver<RaftMetrics> {
         self.inner.rx_metrics.clone()
     }
 
+    /// Get a handle to the performance metrics channel.
+    ///
+    /// It is updated only when `Config::enable_tick_metrics` is set, otherwise it never changes.
+    pub fn perf_metrics(&self) -> watch::Receiver<PerfMetrics> {
+        self.inner.rx_perf_metrics.clone()
+    }
+
     /// Get a handle to wait for the metrics to satisfy some condition.
     ///
     /// ```ignore
@@ -373,6 +1079,74 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         }
     }
 
+    /// Wait until the log `target` is applied to the state machine, i.e., until `last_applied >= target`.
+    ///
+    /// It resolves at once if `target` is already applied, and returns an `Elapsed` error if it is not applied
+    /// within `timeout`. Only the index of `target` is compared, since the metrics tracks only the applied index.
+    #[tracing::instrument(level = "debug", skip(self), fields(target=%target))]
+    pub async fn await_applied(&self, target: LogId, timeout: Duration) -> Result<(), Elapsed> {
+        let mut rx = self.inner.rx_metrics.clone();
+
+        tokio::time::timeout(timeout, async move {
+            loop {
+                if rx.borrow().last_applied >= target.index {
+                    return;
+                }
+
+                if rx.changed().await.is_err() {
+                    // RaftCore is gone, the target will never be applied: leave it to the timeout.
+                    futures::future::pending::<()>().await;
+                }
+            }
+        })
+        .await
+    }
+
+    /// Wait until this node observes a stable leader, and return the id of the leader.
+    ///
+    /// A leader is stable if this node sees the same leader in the same term for at least one
+    /// `Config::heartbeat_interval`, i.e., it is not a leader that is being deposed by an ongoing election.
+    /// It returns an `Elapsed` error if no stable leader is observed within `timeout`, e.g., when a quorum is not
+    /// reachable and no leader can be elected.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn await_leader(&self, timeout: Duration) -> Result<NodeId, Elapsed> {
+        let mut rx = self.inner.rx_metrics.clone();
+        let stable_for = Duration::from_millis(self.inner.config.heartbeat_interval);
+
+        tokio::time::timeout(timeout, async move {
+            loop {
+                let observed = {
+                    let m = rx.borrow();
+                    m.current_leader.map(|leader| (leader, m.current_term))
+                };
+
+                if let Some((leader, term)) = observed {
+                    let deadline = tokio::time::Instant::now() + stable_for;
+
+                    // Other metrics, e.g., the applied log, may change meanwhile; only the leader and the term matter.
+                    loop {
+                        match tokio::time::timeout_at(deadline, rx.changed()).await {
+                            Err(_stable) => return leader,
+                            Ok(Ok(())) => {
+                                let m = rx.borrow();
+                                if m.current_leader != Some(leader) || m.current_term != term {
+                                    break;
+                                }
+                            }
+                            Ok(Err(_closed)) => {
+                                // RaftCore is gone, leave it to the timeout.
+                                futures::future::pending::<()>().await;
+                            }
+                        }
+                    }
+                } else if rx.changed().await.is_err() {
+                    futures::future::pending::<()>().await;
+                }
+            }
+        })
+        .await
+    }
+
     /// Shutdown this Raft node.
     pub async fn shutdown(&self) -> anyhow::Result<()> {
         if let Some(tx) = self.inner.tx_shutdown.lock().await.take() {
@@ -393,6 +1167,16 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Cl
     }
 }
 
+/// The role a Raft node starts in, see `Raft::new_with_initial_role()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitialRole {
+    /// Start as a follower of `leader`. The node has to be a voter in the membership in storage and have some logs.
+    Follower { leader: NodeId },
+
+    /// Start as a learner, optionally knowing the leader.
+    Learner { leader: Option<NodeId> },
+}
+
 pub(crate) type RaftRespTx<T, E> = oneshot::Sender<Result<T, E>>;
 pub(crate) type RaftRespRx<T, E> = oneshot::Receiver<Result<T, E>>;
 
@@ -401,6 +1185,29 @@ pub struct AddLearnerResponse {
     pub matched: LogId,
 }
 
+/// The result of a membership change dry-run, see `Raft::change_membership_dry_run()`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MembershipPlan {
+    /// The membership config in use when the plan is made.
+    pub current: Membership,
+
+    /// The membership configs that would be proposed, in order: usually a joint config followed by a uniform config.
+    pub steps: Vec<Membership>,
+
+    /// Nodes in the target config that the leader does not replicate to, i.e., they are not added as learner yet.
+    pub unreachable: BTreeSet<NodeId>,
+
+    /// Nodes in the target config that are not caught up with the leader, and the log id replicated to each of them.
+    pub lagging: BTreeMap<NodeId, LogId>,
+}
+
+impl MembershipPlan {
+    /// Returns true if every node in the target config is reachable and caught up.
+    pub fn is_ready(&self) -> bool {
+        self.unreachable.is_empty() && self.lagging.is_empty()
+    }
+}
+
 /// A message coming from the Raft API.
 pub(crate) enum RaftMsg<D: AppData, R: AppDataResponse> {
     AppendEntries {
@@ -417,15 +1224,26 @@ pub(crate) enum RaftMsg<D: AppData, R: AppDataResponse> {
     },
     ClientWriteRequest {
         rpc: ClientWriteRequest<D>,
+        /// Send the log id assigned to the request once it is appended, see `Raft::client_write_ticket()`.
+        ticket_tx: Option<oneshot::Sender<LogId>>,
         tx: RaftRespTx<ClientWriteResponse<R>, ClientWriteError>,
     },
     ClientReadRequest {
-        tx: RaftRespTx<(), ClientReadError>,
+        tx: RaftRespTx<ClientReadResponse, ClientReadError>,
+    },
+    StaleReadRequest {
+        tx: RaftRespTx<StaleReadResponse, ClientReadError>,
     },
     Initialize {
         members: BTreeSet<NodeId>,
         tx: RaftRespTx<(), InitializeError>,
     },
+    InitializeFromSnapshot {
+        members: BTreeSet<NodeId>,
+        meta: SnapshotMeta,
+        data: Vec<u8>,
+        tx: RaftRespTx<(), InitializeError>,
+    },
     // TODO(xp): make tx a field of a struct
     /// Request raft core to setup a new replication to a learner.
     AddLearner {
@@ -434,6 +1252,9 @@ pub(crate) enum RaftMsg<D: AppData, R: AppDataResponse> {
         /// If block until the newly added learner becomes line-rate.
         blocking: bool,
 
+        /// Whether to mark the learner as a standby, see `Raft::add_standby()`.
+        standby: bool,
+
         /// Send the log id when the replication becomes line-rate.
         tx: RaftRespTx<AddLearnerResponse, AddLearnerError>,
     },
@@ -444,8 +1265,73 @@ pub(crate) enum RaftMsg<D: AppData, R: AppDataResponse> {
         ///
         /// Otherwise, wait for commit of the member change log.
         blocking: bool,
+        /// Voters that are removed by this change but are kept as learners.
+        demote: BTreeSet<NodeId>,
+        tx: RaftRespTx<ClientWriteResponse<R>, ClientWriteError>,
+    },
+    /// Request the leader to commit a membership config with new priorities of voters.
+    SetPriorities {
+        priorities: BTreeMap<NodeId, u64>,
         tx: RaftRespTx<ClientWriteResponse<R>, ClientWriteError>,
     },
+    /// Request raft core to build the plan of a membership change without changing anything.
+    ChangeMembershipDryRun {
+        members: BTreeSet<NodeId>,
+        tx: RaftRespTx<MembershipPlan, ClientWriteError>,
+    },
+    /// Request the leader to give up leadership.
+    StepDown {
+        tx: RaftRespTx<(), ClientWriteError>,
+    },
+    /// Pause or resume replication to a target.
+    PauseReplication {
+        target: NodeId,
+        paused: bool,
+        tx: RaftRespTx<(), PauseReplicationError>,
+    },
+    /// Let a learner receive the logs from another learner, or from the leader again.
+    SetRelay {
+        learner: NodeId,
+        relay: Option<NodeId>,
+        tx: RaftRespTx<(), SetRelayError>,
+    },
+    LogAudit {
+        rpc: LogAuditRequest,
+        tx: RaftRespTx<LogAuditResponse, RaftError>,
+    },
+    /// Compare the logs on a target with those on the leader.
+    AuditFollower {
+        target: NodeId,
+        tx: RaftRespTx<LogAuditReport, AuditFollowerError>,
+    },
+    /// Query whether a log id is committed.
+    IsCommitted {
+        log_id: LogId,
+        tx: RaftRespTx<bool, RaftError>,
+    },
+    /// Query the log id at a log index.
+    GetLogId {
+        index: u64,
+        tx: RaftRespTx<Option<LogId>, RaftError>,
+    },
+    /// Query the replication progress of every target, on the leader.
+    GetReplicationTable {
+        tx: RaftRespTx<Option<BTreeMap<NodeId, FollowerProgress>>, RaftError>,
+    },
+    GetStateChecksum {
+        tx: RaftRespTx<Option<StateChecksum>, RaftError>,
+    },
+    DumpState {
+        tx: RaftRespTx<RaftStateDump, RaftError>,
+    },
+    TriggerSnapshot {
+        tx: RaftRespTx<(), RaftError>,
+    },
+    RewriteLogEntry {
+        log_id: LogId,
+        payload: D,
+        tx: RaftRespTx<(), RewriteLogError>,
+    },
 }
 
 impl<D, R> MessageSummary for RaftMsg<D, R>
@@ -468,14 +1354,60 @@ where
                 format!("ClientWriteRequest: {}", rpc.summary())
             }
             RaftMsg::ClientReadRequest { .. } => "ClientReadRequest".to_string(),
+            RaftMsg::StaleReadRequest { .. } => "StaleReadRequest".to_string(),
             RaftMsg::Initialize { members, .. } => {
                 format!("Initialize: {:?}", members)
             }
-            RaftMsg::AddLearner { id, blocking, .. } => {
-                format!("AddLearner: id: {}, blocking: {}", id, blocking)
+            RaftMsg::InitializeFromSnapshot { members, meta, .. } => {
+                format!("InitializeFromSnapshot: {:?}, snapshot: {:?}", members, meta)
+            }
+            RaftMsg::AddLearner {
+                id, blocking, standby, ..
+            } => {
+                format!("AddLearner: id: {}, blocking: {}, standby: {}", id, blocking, standby)
+            }
+            RaftMsg::ChangeMembership {
+                members,
+                blocking,
+                demote,
+                ..
+            } => {
+                format!(
+                    "ChangeMembership: members: {:?}, blocking: {}, demote: {:?}",
+                    members, blocking, demote
+                )
+            }
+            RaftMsg::ChangeMembershipDryRun { members, .. } => {
+                format!("ChangeMembershipDryRun: members: {:?}", members)
+            }
+            RaftMsg::SetPriorities { priorities, .. } => {
+                format!("SetPriorities: {:?}", priorities)
+            }
+            RaftMsg::StepDown { .. } => "StepDown".to_string(),
+            RaftMsg::PauseReplication { target, paused, .. } => {
+                format!("PauseReplication: target: {}, paused: {}", target, paused)
+            }
+            RaftMsg::SetRelay { learner, relay, .. } => {
+                format!("SetRelay: learner: {}, relay: {:?}", learner, relay)
             }
-            RaftMsg::ChangeMembership { members, blocking, .. } => {
-                format!("ChangeMembership: members: {:?}, blocking: {}", members, blocking)
+            RaftMsg::LogAudit { rpc, .. } => {
+                format!("LogAudit: {}", rpc.summary())
+            }
+            RaftMsg::AuditFollower { target, .. } => {
+                format!("AuditFollower: target: {}", target)
+            }
+            RaftMsg::IsCommitted { log_id, .. } => {
+                format!("IsCommitted: {}", log_id)
+            }
+            RaftMsg::GetLogId { index, .. } => {
+                format!("GetLogId: {}", index)
+            }
+            RaftMsg::GetStateChecksum { .. } => "GetStateChecksum".to_string(),
+            RaftMsg::DumpState { .. } => "DumpState".to_string(),
+            RaftMsg::GetReplicationTable { .. } => "GetReplicationTable".to_string(),
+            RaftMsg::TriggerSnapshot { .. } => "TriggerSnapshot".to_string(),
+            RaftMsg::RewriteLogEntry { log_id, .. } => {
+                format!("RewriteLogEntry: {}", log_id)
             }
         }
     }
@@ -502,7 +1434,16 @@ pub struct AppendEntriesRequest<D: AppData> {
     pub entries: Vec<Entry<D>>,
 
     /// The leader's committed log id.
+    ///
+    /// A heartbeat carries it too, so that a follower applies committed logs without any new log being replicated
+    /// to it. A follower commits up to the last log this request proves to be consistent with the leader.
     pub leader_commit: LogId,
+
+    /// The learners the target learner replicates the logs to on behalf of the leader, see `Raft::set_relay()`.
+    ///
+    /// It is empty for a target that is not a relay, and the target stops relaying once it receives an empty one.
+    #[serde(default)]
+    pub relay_to: Vec<NodeId>,
 }
 
 impl<D: AppData> MessageSummary for AppendEntriesRequest<D> {
@@ -534,6 +1475,21 @@ pub struct AppendEntriesResponse {
     /// `conflict` is None if `matched` is `Some()`, because if there is a matching entry, all following inconsistent
     /// entries will be deleted.
     pub conflict: Option<LogId>,
+
+    /// The last log id on the responding node, sent along with a `conflict`.
+    ///
+    /// It is a hint for the leader to find the matching log without bisecting the whole log, e.g., when a learner
+    /// that has received a part of the logs is taken over by a new leader.
+    /// It is `None` if the append-entry succeeds or if the responding node does not report it.
+    #[serde(default)]
+    pub last_log_id: Option<LogId>,
+
+    /// A random id of the responding node process, generated when it starts.
+    ///
+    /// A leader tracks it for every target to detect two processes running with the same node id.
+    /// It is `None` if the responding node does not report it.
+    #[serde(default)]
+    pub instance_uuid: Option<u128>,
 }
 
 impl AppendEntriesResponse {
@@ -547,11 +1503,34 @@ impl AppendEntriesResponse {
 pub struct Entry<D: AppData> {
     pub log_id: LogId,
 
+    /// The wall-clock time of the leader when it appended this entry, in milliseconds since the unix epoch.
+    ///
+    /// It is assigned by the leader and replicated as is, thus it is the same on every node and is available to
+    /// `RaftStorage::apply_to_state_machine()`, e.g., to expire data in a state machine with a TTL.
+    /// It is not monotonic: a leader's clock may go backward, and the next leader's clock may be behind. Raft never
+    /// relies on it for safety.
+    ///
+    /// It is 0 if unknown, e.g., for an entry built by the application, or stored before this field was added: it is
+    /// deserialized as 0 if absent, with a self-describing format such as JSON.
+    #[serde(default)]
+    pub timestamp_ms: u64,
+
     /// This entry's payload.
     #[serde(bound = "D: AppData")]
     pub payload: EntryPayload<D>,
 }
 
+impl<D: AppData> Entry<D> {
+    /// An estimate of the size in bytes of this entry's payload.
+    ///
+    /// It is the length of the payload encoded with `codec`, which should be `Config::codec`, the format a
+    /// `RaftNetwork` is expected to send. It is used to limit the size of a replication batch by
+    /// `Config::max_payload_bytes`. A payload that fails to encode counts as 0 bytes.
+    pub fn payload_size_hint<C: Codec>(&self, codec: &C) -> u64 {
+        self.payload.size_hint(codec)
+    }
+}
+
 impl<D: AppData> MessageSummary for Entry<D> {
     fn summary(&self) -> String {
         format!("{}:{}", self.log_id, self.payload.summary())
@@ -606,6 +1585,13 @@ pub enum EntryPayload<D: AppData> {
     Membership(Membership),
 }
 
+impl<D: AppData> EntryPayload<D> {
+    /// An estimate of the size in bytes of this payload, see `Entry::payload_size_hint()`.
+    pub fn size_hint<C: Codec>(&self, codec: &C) -> u64 {
+        codec.encoded_size(self).unwrap_or_default()
+    }
+}
+
 impl<D: AppData> MessageSummary for EntryPayload<D> {
     fn summary(&self) -> String {
         match self {
@@ -683,32 +1669,106 @@ pub struct Membership {
 
     /// Cache of all node ids.
     all_nodes: BTreeSet<NodeId>,
+
+    /// The priority of a voter to become the leader. A voter not in it has a priority of 0.
+    ///
+    /// A membership serialized before priorities are introduced has none.
+    #[serde(default)]
+    priorities: BTreeMap<NodeId, u64>,
 }
 
 impl MessageSummary for Membership {
     fn summary(&self) -> String {
-        let mut res = vec!["[".to_string()];
-        for (i, c) in self.configs.iter().enumerate() {
-            if i > 0 {
-                res.push(",".to_string());
-            }
-            res.push(format!("{:?}", c));
+        self.to_string()
+    }
+}
+
+/// Renders a uniform config as `{0,1,2}` and a joint config as `joint({0,1,2},{2,3,4})`.
+impl Display for Membership {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let configs = self
+            .configs
+            .iter()
+            .map(|c| {
+                format!(
+                    "{{{}}}",
+                    c.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",")
+                )
+            })
+            .collect::<Vec<_>>();
+
+        if self.is_joint() {
+            write!(f, "joint({})", configs.join(","))
+        } else {
+            write!(f, "{}", configs.join(","))
         }
-        res.push("]".to_string());
-        res.join("")
     }
 }
 
 impl Membership {
     pub fn new_single(members: BTreeSet<NodeId>) -> Self {
         let configs = vec![members];
-        let all_nodes = Self::build_all_nodes(&configs);
-        Membership { configs, all_nodes }
+        Self::new_multi(configs)
     }
 
     pub fn new_multi(configs: Vec<BTreeSet<NodeId>>) -> Self {
         let all_nodes = Self::build_all_nodes(&configs);
-        Membership { configs, all_nodes }
+        Membership {
+            configs,
+            all_nodes,
+            priorities: BTreeMap::new(),
+        }
+    }
+
+    /// Replace the priorities of voters to become the leader, see `Raft::set_priorities()`.
+    #[must_use]
+    pub fn with_priorities(mut self, priorities: BTreeMap<NodeId, u64>) -> Self {
+        self.priorities = priorities;
+        self
+    }
+
+    pub fn priorities(&self) -> &BTreeMap<NodeId, u64> {
+        &self.priorities
+    }
+
+    /// Returns the priority of a node to become the leader, 0 if it is not set.
+    pub fn priority(&self, id: &NodeId) -> u64 {
+        self.priorities.get(id).cloned().unwrap_or_default()
+    }
+
+    /// Returns the number of distinct priorities of the nodes in this config that are higher than that of `id`.
+    ///
+    /// It is 0 for a node with the highest priority, or if no priority is set.
+    pub fn priority_rank(&self, id: &NodeId) -> usize {
+        let mine = self.priority(id);
+        let higher = self.all_nodes.iter().map(|x| self.priority(x)).filter(|p| *p > mine).collect::<BTreeSet<_>>();
+        higher.len()
+    }
+
+    /// Build a uniform config like `new_single()`, but returns `ChangeMembershipError::EmptyMembership` if there is
+    /// no voter.
+    pub fn try_new_single(members: BTreeSet<NodeId>) -> Result<Self, ChangeMembershipError> {
+        let m = Self::new_single(members);
+        m.validate()?;
+        Ok(m)
+    }
+
+    /// Build a config like `new_multi()`, but returns `ChangeMembershipError::EmptyMembership` if there is no config
+    /// or any of the configs has no voter.
+    pub fn try_new_multi(configs: Vec<BTreeSet<NodeId>>) -> Result<Self, ChangeMembershipError> {
+        let m = Self::new_multi(configs);
+        m.validate()?;
+        Ok(m)
+    }
+
+    /// Check that there is at least one config and every config has at least one voter.
+    ///
+    /// A config without voter can never form a quorum: a cluster that adopts it halts forever.
+    pub fn validate(&self) -> Result<(), ChangeMembershipError> {
+        if self.configs.is_empty() || self.configs.iter().any(|c| c.is_empty()) {
+            return Err(ChangeMembershipError::EmptyMembership);
+        }
+        Ok(())
     }
 
     pub fn all_nodes(&self) -> &BTreeSet<NodeId> {
@@ -725,7 +1785,7 @@ impl Membership {
         self.all_nodes = Self::build_all_nodes(&self.configs);
     }
 
-    pub fn get_configs(&self) -> &Vec<BTreeSet<NodeId>> {
+    pub fn get_configs(&self) -> &[BTreeSet<NodeId>] {
         &self.configs
     }
 
@@ -748,9 +1808,14 @@ impl Membership {
         false
     }
 
+    /// Returns true if it is a joint config, i.e., it consists of more than one member set.
+    pub fn is_joint(&self) -> bool {
+        self.configs.len() > 1
+    }
+
     /// Check to see if the config is currently in joint consensus.
     pub fn is_in_joint_consensus(&self) -> bool {
-        self.configs.len() > 1
+        self.is_joint()
     }
 
     // TODO(xp): rename this
@@ -764,7 +1829,7 @@ impl Membership {
         assert!(!self.configs.is_empty());
 
         let last = self.configs.last().cloned().unwrap();
-        Membership::new_single(last)
+        Membership::new_single(last).with_priorities(self.priorities.clone())
     }
 
     /// Return true if the given set of ids constitutes a majority.
@@ -873,6 +1938,25 @@ pub struct VoteResponse {
 
     /// The last log id stored on the remote voter.
     pub last_log_id: LogId,
+
+    /// Why the vote is not granted. It is `None` if the vote is granted, or if the responding node does not report it.
+    #[serde(default)]
+    pub reject_reason: Option<VoteRejection>,
+}
+
+/// The reason a voter does not grant its vote to a candidate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VoteRejection {
+    /// The request is a probe of term 0, sent by a node being initialized.
+    Probe,
+    /// The candidate's term is less than the voter's.
+    StaleTerm,
+    /// The voter received a heartbeat from the leader within the election timeout minimum.
+    LeaderAlive,
+    /// The candidate's last log is not at least as up-to-date as the voter's (§5.4.1).
+    StaleLog,
+    /// The voter already voted for another candidate in this term.
+    AlreadyVoted,
 }
 
 //////////////////////////////////////////////////////////////////////////////////////////////////
@@ -892,11 +1976,35 @@ pub struct InstallSnapshotRequest {
     pub offset: u64,
     /// The raw bytes of the snapshot chunk, starting at `offset`.
     pub data: Vec<u8>,
+    /// The CRC32 checksum of `data`, built with [`InstallSnapshotRequest::checksum_of`].
+    ///
+    /// The receiver verifies it before writing the chunk, to detect a chunk corrupted in transfer.
+    pub checksum: u32,
 
     /// Will be `true` if this is the last chunk in the snapshot.
     pub done: bool,
 }
 
+impl InstallSnapshotRequest {
+    /// Calculates the CRC32(IEEE) checksum of a snapshot chunk.
+    pub fn checksum_of(data: &[u8]) -> u32 {
+        let mut crc = 0xFFFF_FFFFu32;
+        for b in data {
+            crc ^= *b as u32;
+            for _ in 0..8 {
+                let mask = (crc & 1).wrapping_neg();
+                crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+            }
+        }
+        !crc
+    }
+
+    /// Returns true if `checksum` matches the checksum of `data`.
+    pub fn verify_checksum(&self) -> bool {
+        Self::checksum_of(&self.data) == self.checksum
+    }
+}
+
 impl MessageSummary for InstallSnapshotRequest {
     fn summary(&self) -> String {
         format!(
@@ -916,6 +2024,93 @@ impl MessageSummary for InstallSnapshotRequest {
 pub struct InstallSnapshotResponse {
     /// The receiving node's current term, for leader to update itself.
     pub term: u64,
+
+    /// Set if the request is a delta snapshot but the receiving node does not have the base snapshot.
+    /// The leader should send a full snapshot instead.
+    #[serde(default)]
+    pub need_full_snapshot: bool,
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A request for the checksums of the logs in a range on the receiving node, sent by `Raft::audit_follower()`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LogAuditRequest {
+    /// The id of the leader that audits the receiving node.
+    pub leader_id: NodeId,
+
+    /// The first log to audit, inclusive.
+    pub start: u64,
+
+    /// The last log to audit, exclusive.
+    pub end: u64,
+
+    /// The number of logs each checksum covers.
+    pub chunk_size: u64,
+}
+
+impl MessageSummary for LogAuditRequest {
+    fn summary(&self) -> String {
+        format!(
+            "leader_id: {}, range: [{}, {}), chunk_size: {}",
+            self.leader_id, self.start, self.end, self.chunk_size
+        )
+    }
+}
+
+/// The response to a `LogAuditRequest`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LogAuditResponse {
+    /// The first log the checksums cover.
+    ///
+    /// It is greater than the requested `start` if the logs before it are purged on the receiving node.
+    pub start: u64,
+
+    /// The checksum of every `chunk_size` logs since `start`, upto the requested `end` or the last log on the
+    /// receiving node. The last one may cover fewer logs.
+    pub checksums: Vec<u32>,
+}
+
+impl LogAuditResponse {
+    /// Returns the checksum of a series of logs, which covers the log ids, the timestamps and the payloads.
+    ///
+    /// The log ids and the timestamps are hashed as fixed size integers, and every payload is encoded with `codec`,
+    /// which should be `Config::codec`. Identical logs have identical checksums on the leader and the audited node
+    /// only if `D` serializes deterministically, e.g., it does not serialize a `HashMap`, whose order differs between
+    /// processes.
+    pub fn checksum_of_logs<D: AppData, C: Codec>(entries: &[Entry<D>], codec: &C) -> Result<u32, CodecError> {
+        let mut data = vec![];
+
+        for ent in entries {
+            let payload = codec.encode(&ent.payload)?;
+
+            data.extend_from_slice(&ent.log_id.term.to_le_bytes());
+            data.extend_from_slice(&ent.log_id.index.to_le_bytes());
+            data.extend_from_slice(&ent.timestamp_ms.to_le_bytes());
+            // The length tells where a payload ends and the next log starts.
+            data.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+            data.extend_from_slice(&payload);
+        }
+
+        Ok(InstallSnapshotRequest::checksum_of(&data))
+    }
+}
+
+/// The result of `Raft::audit_follower()`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LogAuditReport {
+    /// The audited node.
+    pub target: NodeId,
+
+    /// The first log compared, inclusive.
+    pub start: u64,
+
+    /// The last log compared, exclusive.
+    pub end: u64,
+
+    /// The index of the first log that differs on the audited node from the one on the leader, or `None` if all
+    /// logs in `[start, end)` are identical.
+    pub first_divergent: Option<u64>,
 }
 
 //////////////////////////////////////////////////////////////////////////////////////////////////
@@ -929,6 +2124,10 @@ pub struct ClientWriteRequest<D: AppData> {
     /// The application specific contents of this client request.
     #[serde(bound = "D: AppData")]
     pub(crate) entry: EntryPayload<D>,
+
+    /// The id the client assigns to this request, to deduplicate retries, see `Config::dedup_window`.
+    #[serde(default)]
+    pub(crate) request_id: Option<String>,
 }
 
 impl<D: AppData> MessageSummary for ClientWriteRequest<D> {
@@ -943,9 +2142,21 @@ impl<D: AppData> ClientWriteRequest<D> {
         Self::new_base(EntryPayload::Normal(entry))
     }
 
+    /// Set the id of this request, which must be unique to the client write.
+    ///
+    /// A client that retries a write should send it with the same request id, so that the leader does not append it
+    /// twice, see `Config::dedup_window`.
+    pub fn with_request_id(mut self, request_id: impl ToString) -> Self {
+        self.request_id = Some(request_id.to_string());
+        self
+    }
+
     /// Create a new instance.
     pub(crate) fn new_base(entry: EntryPayload<D>) -> Self {
-        Self { entry }
+        Self {
+            entry,
+            request_id: None,
+        }
     }
 
     /// Generate a new payload holding a config change.
@@ -961,14 +2172,63 @@ impl<D: AppData> ClientWriteRequest<D> {
     }
 }
 
+/// The response to a client read request, see `Raft::client_read()`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientReadResponse {
+    /// The term in which the leader confirmed its leadership with a quorum.
+    pub term: u64,
+
+    /// The last committed log when the read is received. A read is linearizable once it is applied.
+    pub read_index: LogId,
+}
+
+/// The response to a stale read request, see `Raft::stale_read()`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StaleReadResponse {
+    /// The term of the leader when the read is received.
+    pub term: u64,
+
+    /// The last log applied to the state machine when the read is served.
+    pub last_applied: LogId,
+
+    /// Whether the leader failed to confirm its leadership, i.e., the state machine may be stale.
+    ///
+    /// A read is never linearizable, even when it is `false`: use `Raft::client_read()` for linearizable reads.
+    pub is_stale: bool,
+}
+
+/// A point-in-time view of the state machine for a linearizable read-only transaction, see `Raft::read_snapshot()`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppliedView {
+    /// The term in which the leader confirmed its leadership with a quorum.
+    pub term: u64,
+
+    /// The last committed log when the read is received.
+    pub read_index: LogId,
+
+    /// The index of the last applied log this view is pinned at. It is always `>= read_index.index`.
+    ///
+    /// Every query in the transaction should read the state machine at this version.
+    pub last_applied: u64,
+}
+
 /// The response to a `ClientRequest`.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ClientWriteResponse<R: AppDataResponse> {
     pub log_id: LogId,
 
+    /// The term of the leader that committed the log.
+    ///
+    /// It never decreases across the responses of a cluster. A client can compare it across retries to detect that it
+    /// is served by a stale leader.
+    pub term: u64,
+
     /// Application specific response data.
+    ///
+    /// It is `None` if the response is sent before the log is applied, i.e., `Config::client_write_ack` is
+    /// `AckOn::Commit`.
     #[serde(bound = "R: AppDataResponse")]
-    pub data: R,
+    pub data: Option<R>,
 
     /// If the log entry is a change-membership entry.
     pub membership: Option<Membership>,
@@ -976,6 +2236,9 @@ pub struct ClientWriteResponse<R: AppDataResponse> {
 
 impl<R: AppDataResponse> MessageSummary for ClientWriteResponse<R> {
     fn summary(&self) -> String {
-        format!("log_id: {}, membership: {:?}", self.log_id, self.membership)
+        format!(
+            "log_id: {}, term: {}, membership: {:?}",
+            self.log_id, self.term, self.membership
+        )
     }
 }
@@ -1,11 +1,26 @@
+use std::cmp::Ordering;
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::str::FromStr;
 
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::error::InvalidSnapshotId;
+use crate::NodeId;
+
 /// The identity of a raft log.
 /// A term and an index identifies an log globally.
+///
+/// Log ids are ordered by `(term, index)`: `term` dominates, e.g., `1-5 < 2-3`.
+/// This is the order raft uses to decide which log is more up-to-date, e.g., when granting a vote, or when deciding if
+/// a log is committed. When only the position in the log matters, e.g., when looking up a log entry in a store, use
+/// [`LogId::cmp_index`] or compare the `index` fields explicitly.
+///
+/// `0-0` is a sentinel, not the id of a real log: a real log always has a term and an index greater than 0. It stands
+/// for "no log", e.g., the last log id of an empty log, or the `prev_log_id` to replicate from the very beginning.
+/// Use [`LogId::none`] to build it, [`LogId::is_sentinel`] to check for it, and [`LogId::to_option`] to convert it to
+/// `None`, instead of comparing against `0-0`.
 #[derive(Debug, Default, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LogId {
     pub term: u64,
@@ -32,10 +47,116 @@ impl LogId {
         }
         LogId { term, index }
     }
+
+    /// The sentinel log id `0-0`, that is before any real log.
+    pub fn none() -> Self {
+        LogId { term: 0, index: 0 }
+    }
+
+    /// Returns true if this is the sentinel `0-0`, i.e., there is no log.
+    pub fn is_sentinel(&self) -> bool {
+        self.index == 0
+    }
+
+    /// Returns `None` for the sentinel, otherwise `Some(self)`.
+    pub fn to_option(self) -> Option<LogId> {
+        if self.is_sentinel() {
+            None
+        } else {
+            Some(self)
+        }
+    }
+
+    /// Returns the index of the log entry right after this one.
+    pub fn next_index(&self) -> u64 {
+        self.index + 1
+    }
+
+    /// Compares only the index of two log ids, ignoring the term.
+    ///
+    /// Unlike `Ord`, this does not tell which log is more up-to-date. E.g., `2-3` is less than `1-5` by index.
+    pub fn cmp_index(&self, other: &LogId) -> Ordering {
+        self.index.cmp(&other.index)
+    }
+}
+
+/// The identity of a snapshot.
+///
+/// Everytime a snapshot is created, it is assigned with a globally unique id: `seq` is increased by the node every
+/// time it builds a snapshot, thus no two snapshots built by the same node share an id, even if they are built at the
+/// same log.
+///
+/// It is displayed as and parsed from `<term>-<index>-<seq>-<node_id>`, e.g., `1-100-3-2` is the 3rd snapshot built by
+/// node 2, which includes logs upto `1-100`. A store should use this form to name snapshot files.
+/// It is serialized in the same form.
+///
+/// The legacy form `<term>-<index>-<seq>`, which ids of snapshots built before had, is parsed too, with `node_id` 0.
+#[derive(Debug, Default, Clone, PartialOrd, Ord, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SnapshotId {
+    /// The last log id included in the snapshot.
+    pub last_log_id: LogId,
+
+    /// The sequence number of the snapshot built by the node.
+    pub seq: u64,
+
+    /// The id of the node that builds the snapshot.
+    pub node_id: NodeId,
 }
 
-// Everytime a snapshot is created, it is assigned with a globally unique id.
-pub type SnapshotId = String;
+impl SnapshotId {
+    pub fn new(last_log_id: LogId, seq: u64, node_id: NodeId) -> Self {
+        SnapshotId {
+            last_log_id,
+            seq,
+            node_id,
+        }
+    }
+}
+
+impl Display for SnapshotId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}-{}", self.last_log_id, self.seq, self.node_id)
+    }
+}
+
+impl FromStr for SnapshotId {
+    type Err = InvalidSnapshotId;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidSnapshotId { id: s.to_string() };
+
+        let parts = s.split('-').map(|x| x.parse::<u64>()).collect::<Result<Vec<_>, _>>().map_err(|_| invalid())?;
+
+        let (term, index, seq, node_id) = match parts.as_slice() {
+            [term, index, seq, node_id] => (*term, *index, *seq, *node_id),
+            // The legacy form does not have a node id.
+            [term, index, seq] => (*term, *index, *seq, 0),
+            _ => return Err(invalid()),
+        };
+
+        // The zero-th log id must be the sentinel (0,0), see `LogId::new()`.
+        if (term == 0) != (index == 0) {
+            return Err(invalid());
+        }
+
+        Ok(SnapshotId::new(LogId { term, index }, seq, node_id))
+    }
+}
+
+impl TryFrom<String> for SnapshotId {
+    type Error = InvalidSnapshotId;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<SnapshotId> for String {
+    fn from(id: SnapshotId) -> Self {
+        id.to_string()
+    }
+}
 
 /// The identity of a segment of a snapshot.
 #[derive(Debug, Default, Clone, PartialOrd, PartialEq, Eq, Serialize, Deserialize)]
@@ -44,12 +165,9 @@ pub struct SnapshotSegmentId {
     pub offset: u64,
 }
 
-impl<D: ToString> From<(D, u64)> for SnapshotSegmentId {
-    fn from(v: (D, u64)) -> Self {
-        SnapshotSegmentId {
-            id: v.0.to_string(),
-            offset: v.1,
-        }
+impl From<(SnapshotId, u64)> for SnapshotSegmentId {
+    fn from(v: (SnapshotId, u64)) -> Self {
+        SnapshotSegmentId { id: v.0, offset: v.1 }
     }
 }
 
@@ -73,4 +191,9 @@ pub struct StateMachineChanges {
     // TODO(xp): it does not need to be an Option
     pub last_applied: Option<LogId>,
     pub is_snapshot: bool,
+
+    /// The log id upto which, inclusive, logs can be purged, because they are included in the installed snapshot.
+    ///
+    /// If it is `None`, applied logs are purged only as `Config::max_applied_log_to_keep` specifies.
+    pub purge_upto: Option<LogId>,
 }
@@ -0,0 +1,166 @@
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+
+use crate::error::InvalidSnapshotId;
+use crate::LogId;
+use crate::SnapshotId;
+
+#[test]
+fn test_log_id_ord() -> anyhow::Result<()> {
+    // term dominates
+    assert!(LogId::new(1, 5) < LogId::new(2, 3));
+    assert!(LogId::new(2, 3) > LogId::new(1, 5));
+
+    // same term, compare index
+    assert!(LogId::new(2, 3) < LogId::new(2, 4));
+    assert_eq!(LogId::new(2, 3), LogId::new(2, 3));
+
+    assert!(LogId::new(0, 0) < LogId::new(1, 1));
+    assert_eq!(LogId::default(), LogId::new(0, 0));
+
+    assert_eq!(
+        Some(LogId::new(2, 3)),
+        [LogId::new(1, 5), LogId::new(2, 3), LogId::new(1, 9)].into_iter().max()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_log_id_cmp_index() -> anyhow::Result<()> {
+    assert_eq!(Ordering::Greater, LogId::new(1, 5).cmp_index(&LogId::new(2, 3)));
+    assert_eq!(Ordering::Less, LogId::new(2, 3).cmp_index(&LogId::new(1, 5)));
+    assert_eq!(Ordering::Equal, LogId::new(1, 3).cmp_index(&LogId::new(2, 3)));
+
+    // cmp_index and Ord disagree when terms differ.
+    assert_ne!(
+        LogId::new(1, 5).cmp(&LogId::new(2, 3)),
+        LogId::new(1, 5).cmp_index(&LogId::new(2, 3))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_log_id_next_index() -> anyhow::Result<()> {
+    assert_eq!(1, LogId::new(0, 0).next_index());
+    assert_eq!(6, LogId::new(3, 5).next_index());
+
+    Ok(())
+}
+
+#[test]
+fn test_log_id_sentinel() -> anyhow::Result<()> {
+    assert_eq!(LogId::new(0, 0), LogId::none());
+    assert_eq!(LogId::default(), LogId::none());
+
+    assert!(LogId::none().is_sentinel());
+    assert!(!LogId::new(1, 1).is_sentinel());
+
+    assert_eq!(None, LogId::none().to_option());
+    assert_eq!(Some(LogId::new(2, 3)), LogId::new(2, 3).to_option());
+
+    // The sentinel is before any real log, in both orders.
+    assert!(LogId::none() < LogId::new(1, 1));
+    assert_eq!(Ordering::Less, LogId::none().cmp_index(&LogId::new(1, 1)));
+    assert_eq!(1, LogId::none().next_index());
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_id_display_from_str() -> anyhow::Result<()> {
+    let id = SnapshotId::new(LogId::new(1, 100), 3, 2);
+    assert_eq!("1-100-3-2", id.to_string());
+    assert_eq!(id, "1-100-3-2".parse()?);
+
+    let id = SnapshotId::new(LogId::new(0, 0), 0, 0);
+    assert_eq!("0-0-0-0", id.to_string());
+    assert_eq!(id, id.to_string().parse()?);
+
+    let id = SnapshotId::new(LogId::new(u64::MAX, u64::MAX), u64::MAX, u64::MAX);
+    assert_eq!(id, id.to_string().parse()?);
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_id_from_str_invalid() -> anyhow::Result<()> {
+    for s in [
+        "",
+        "ss1",
+        "1-100",
+        "1-100-3-2-1",
+        "1-100-3-",
+        "-1-100-3",
+        "1-100-x-2",
+        "0-5-1-2",
+        "1-0-1-2",
+    ] {
+        assert_eq!(
+            Err(InvalidSnapshotId { id: s.to_string() }),
+            s.parse::<SnapshotId>(),
+            "parse {:?}",
+            s
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_id_from_str_legacy() -> anyhow::Result<()> {
+    let id: SnapshotId = "1-100-3".parse()?;
+    assert_eq!(SnapshotId::new(LogId::new(1, 100), 3, 0), id);
+
+    // A legacy id is displayed in the canonical form, which parses back to the same id.
+    assert_eq!("1-100-3-0", id.to_string());
+    assert_eq!(id, id.to_string().parse()?);
+
+    // A legacy id that is persisted, e.g., in a snapshot meta, is deserialized too.
+    let deserialized: SnapshotId = serde_json::from_str(r#""1-100-3""#)?;
+    assert_eq!(id, deserialized);
+    assert_eq!(id, serde_json::from_str(&serde_json::to_string(&deserialized)?)?);
+
+    assert!("0-5-1".parse::<SnapshotId>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_id_serde() -> anyhow::Result<()> {
+    let id = SnapshotId::new(LogId::new(1, 100), 3, 2);
+
+    // Serialized as the canonical string.
+    let s = serde_json::to_string(&id)?;
+    assert_eq!(r#""1-100-3-2""#, s);
+    assert_eq!(id, serde_json::from_str(&s)?);
+
+    assert!(serde_json::from_str::<SnapshotId>(r#""ss1""#).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_id_unique() -> anyhow::Result<()> {
+    let log_id = LogId::new(1, 100);
+
+    // Snapshots at the same log, built by different nodes or at different times, have distinct ids.
+    let ids = [
+        SnapshotId::new(log_id, 1, 0),
+        SnapshotId::new(log_id, 2, 0),
+        SnapshotId::new(log_id, 1, 1),
+        SnapshotId::new(LogId::new(1, 10), 1, 0),
+        SnapshotId::new(LogId::new(2, 10), 1, 0),
+        SnapshotId::new(LogId::new(1, 10), 11, 0),
+    ];
+
+    let strings = ids.iter().map(|x| x.to_string()).collect::<BTreeSet<_>>();
+    assert_eq!(ids.len(), strings.len());
+
+    // Ordered by the last log id first.
+    assert!(SnapshotId::new(LogId::new(1, 10), 9, 9) < SnapshotId::new(log_id, 1, 0));
+    assert!(SnapshotId::new(log_id, 1, 0) < SnapshotId::new(log_id, 2, 0));
+
+    Ok(())
+}
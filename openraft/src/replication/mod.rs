@@ -1,8 +1,13 @@
 //! Replication stream.
 
+mod rate_limiter;
+#[cfg(test)]
+mod rate_limiter_test;
+
 use std::io::SeekFrom;
 use std::sync::Arc;
 
+use futures::future::join_all;
 use futures::future::FutureExt;
 use serde::Deserialize;
 use serde::Serialize;
@@ -13,22 +18,32 @@ use tokio::io::AsyncSeekExt;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
 use tokio::time::interval;
+use tokio::time::interval_at;
+use tokio::time::sleep;
 use tokio::time::timeout;
 use tokio::time::Duration;
+use tokio::time::Instant;
 use tokio::time::Interval;
 use tracing::Instrument;
 use tracing::Span;
 
+use crate::codec::CodecType;
 use crate::config::Config;
 use crate::config::SnapshotPolicy;
 use crate::error::LackEntry;
+use crate::log_range::normalize_range;
+use crate::metrics::SnapshotProgress;
 use crate::raft::AppendEntriesRequest;
+use crate::raft::AppendEntriesResponse;
+use crate::raft::Entry;
 use crate::raft::InstallSnapshotRequest;
+use crate::replication::rate_limiter::RateLimiter;
 use crate::storage::Snapshot;
 use crate::AppData;
 use crate::AppDataResponse;
 use crate::LogId;
 use crate::MessageSummary;
+use crate::NetworkError;
 use crate::NodeId;
 use crate::RaftNetwork;
 use crate::RaftStorage;
@@ -37,11 +52,85 @@ use crate::ReplicationError;
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ReplicationMetrics {
     pub matched: LogId,
+
+    /// What the replication stream is doing to bring the target up to date.
+    pub state: ReplicationState,
+
+    /// Progress of the last snapshot sent to this target, updated on every acknowledged chunk.
+    pub snapshot_sending: Option<SnapshotProgress>,
+
+    /// Whether the target is a standby, which never counts toward a quorum. See `Raft::add_standby()`.
+    pub standby: bool,
+
+    /// Whether the target has responded to the leader, i.e., replication to it has actually begun.
+    pub add_learner_state: AddLearnerState,
 }
 
 impl MessageSummary for ReplicationMetrics {
     fn summary(&self) -> String {
-        format!("{}", self.matched)
+        if self.standby {
+            format!("{}:{:?}:standby", self.matched, self.state)
+        } else {
+            format!("{}:{:?}", self.matched, self.state)
+        }
+    }
+}
+
+/// The state of replication to a target, from the leader's point of view.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ReplicationState {
+    /// Searching for the last log the target has in common with the leader.
+    Probe,
+    /// The matching log is found, logs are being replicated to the target.
+    Replicate,
+    /// Sending a snapshot to the target, because the logs it lacks are already purged.
+    Snapshot,
+}
+
+impl Default for ReplicationState {
+    fn default() -> Self {
+        ReplicationState::Probe
+    }
+}
+
+/// The replication progress of a follower or learner, from the leader's point of view.
+///
+/// See `Raft::replication_table()`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FollowerProgress {
+    /// The last log known to be replicated to the target.
+    pub matched: LogId,
+
+    /// The index of the first log to send to the target in the next AppendEntries RPC.
+    ///
+    /// While probing, it is the index of the last log found not to match on the target.
+    pub next_index: u64,
+
+    /// The number of logs sent to the target and not acknowledged yet.
+    pub in_flight: u64,
+
+    /// The time since the target responded last time, or `None` if it has never responded.
+    pub last_contact: Option<Duration>,
+
+    /// What the replication stream is doing to bring the target up to date.
+    pub state: ReplicationState,
+}
+
+/// Whether a replication target has responded to the leader since the replication to it is started.
+///
+/// A newly added learner is `Pending` until the leader receives a response from it. It is not safe to promote a
+/// `Pending` learner to a voter: the leader may not even be able to reach it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AddLearnerState {
+    /// No response has been received from the target yet, e.g., the target is not started or is unreachable.
+    Pending,
+    /// The target has responded to a replication RPC, and logs or a snapshot are being replicated to it.
+    Ready,
+}
+
+impl Default for AddLearnerState {
+    fn default() -> Self {
+        AddLearnerState::Pending
     }
 }
 
@@ -65,6 +154,7 @@ impl ReplicationStream {
         network: Arc<N>,
         storage: Arc<S>,
         replication_tx: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData>, Span)>,
+        idle_heartbeat: bool,
     ) -> Self {
         ReplicationCore::spawn(
             id,
@@ -76,15 +166,17 @@ impl ReplicationStream {
             network,
             storage,
             replication_tx,
+            idle_heartbeat,
         )
     }
 }
 
 /// A task responsible for sending replication events to a target follower in the Raft cluster.
 ///
-/// NOTE: we do not stack replication requests to targets because this could result in
+/// NOTE: by default we do not stack replication requests to targets because this could result in
 /// out-of-order delivery. We always buffer until we receive a success response, then send the
-/// next payload from the buffer.
+/// next payload from the buffer. With `Config::replication_streams_per_follower` several consecutive
+/// payloads are sent at a time, and one that is delivered out of order is rejected and sent again.
 struct ReplicationCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
     //////////////////////////////////////////////////////////////////////////
     // Static Fields /////////////////////////////////////////////////////////
@@ -117,6 +209,9 @@ struct ReplicationCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: Raf
     /// The target state of this replication stream.
     target_repl_state: TargetReplState,
 
+    /// The replication state reported to the Raft node as metrics.
+    state: ReplicationState,
+
     /// The index of the log entry to most recently be appended to the log by the leader.
     /// TODO(xp): remove this
     last_log_index: u64,
@@ -136,11 +231,67 @@ struct ReplicationCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: Raf
     // The last possible matching entry on a follower.
     max_possible_matched_index: u64,
 
+    /// The index to probe next, instead of bisecting between `matched` and `max_possible_matched_index`.
+    ///
+    /// A new stream probes at the leader's last log first, without sending any entry. If it conflicts, it probes at
+    /// the last log the target reports with `AppendEntriesResponse::last_log_id`. Thus a target that already has a
+    /// part of the logs, e.g., a learner taken over by a new leader, resumes from where it is instead of receiving
+    /// them again.
+    next_probe: Option<u64>,
+
+    /// The delay before retrying after the last transient network error, see `NetworkError::Transient`.
+    /// It is reset once an AppendEntries RPC gets a response.
+    backoff: Option<Duration>,
+
+    /// Whether the last log reported by the target has been used to probe. It is used only once: if it conflicts too,
+    /// the target has inconsistent logs and the matching log is found by bisecting.
+    probed_hint: bool,
+
     /// The heartbeat interval for ensuring that heartbeats are always delivered in a timely fashion.
     heartbeat: Interval,
 
+    /// Whether the heartbeat may slow down when the target has caught up, see `Config::slow_idle_learner_heartbeat`.
+    idle_heartbeat: bool,
+
+    /// Whether `heartbeat` ticks at the idle cadence.
+    heartbeat_idle: bool,
+
+    /// The learners the target relays the logs to, see `Raft::set_relay()`.
+    relay_to: Vec<NodeId>,
+
     /// The timeout for sending snapshot segment.
     install_snapshot_timeout: Duration,
+
+    /// The instance uuid the target reported last, see `AppendEntriesResponse::instance_uuid`.
+    target_instance_uuid: Option<u128>,
+
+    /// Every instance uuid the target has reported.
+    seen_instance_uuids: Vec<u128>,
+
+    /// When the target responded to a replication RPC last time, or when this stream is spawned.
+    last_ack: Instant,
+
+    /// Whether it has been reported that the target is not responding, see `Config::learner_eviction_timeout`.
+    /// It is reset once the target responds.
+    unreachable_reported: bool,
+
+    /// Whether the target has ever responded to this stream, see `AddLearnerState`.
+    responded: bool,
+
+    /// Whether sending logs to the target is paused, see `Raft::pause_replication()`.
+    paused: bool,
+
+    /// Whether the target receives the logs from a relay instead, see `Raft::set_relay()`.
+    ///
+    /// It is tracked apart from `paused`, thus setting or unsetting a relay does not resume a paused target, and
+    /// resuming a relayed one does not make the leader send it logs. Logs are sent only if neither is set.
+    relayed: bool,
+
+    /// The index of the first log to send next, reported to the Raft node, see `FollowerProgress`.
+    next_index: u64,
+
+    /// The number of logs sent and not acknowledged yet, reported to the Raft node, see `FollowerProgress`.
+    in_flight: u64,
 }
 
 impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> ReplicationCore<D, R, N, S> {
@@ -156,6 +307,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
         network: Arc<N>,
         storage: Arc<S>,
         raft_core_tx: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData>, Span)>,
+        idle_heartbeat: bool,
     ) -> ReplicationStream {
         // other component to ReplicationStream
         let (repl_tx, repl_rx) = mpsc::unbounded_channel();
@@ -171,14 +323,30 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
             config,
             marker_r: std::marker::PhantomData,
             target_repl_state: TargetReplState::LineRate,
+            state: ReplicationState::Probe,
             last_log_index: last_log.index,
             committed,
-            matched: LogId { term: 0, index: 0 },
+            matched: LogId::none(),
             max_possible_matched_index: last_log.index,
+            next_probe: Some(last_log.index),
+            probed_hint: false,
+            backoff: None,
             raft_core_tx,
             repl_rx,
             heartbeat: interval(heartbeat_timeout),
+            idle_heartbeat,
+            heartbeat_idle: false,
+            relay_to: vec![],
             install_snapshot_timeout,
+            target_instance_uuid: None,
+            seen_instance_uuids: vec![],
+            last_ack: Instant::now(),
+            unreachable_reported: false,
+            responded: false,
+            paused: false,
+            relayed: false,
+            next_index: last_log.index + 1,
+            in_flight: 0,
         };
 
         let _handle = tokio::spawn(this.main().instrument(tracing::trace_span!("spawn").or_current()));
@@ -191,11 +359,17 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
 
     #[tracing::instrument(level="trace", skip(self), fields(id=self.id, target=self.target, cluster=%self.config.cluster_name))]
     async fn main(mut self) {
+        // A new replication stream always starts with probing the matching log on the target.
+        self.report_state();
+
         loop {
             // If it returns Ok(), always go back to LineRate state.
             let res = match &self.target_repl_state {
                 TargetReplState::LineRate => self.line_rate_loop().await,
-                TargetReplState::Snapshotting => self.replicate_snapshot().await,
+                TargetReplState::Snapshotting => {
+                    self.update_state(ReplicationState::Snapshot);
+                    self.replicate_snapshot().await
+                }
                 TargetReplState::Shutdown => return,
             };
 
@@ -245,22 +419,317 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 ReplicationError::Network { .. } => {
                     // nothing to do
                 }
+                ReplicationError::NoFullSnapshot { .. } => {
+                    // Asking for a snapshot again right away would get the same delta snapshot. Wait for a heartbeat
+                    // interval, in which a new full snapshot may be built, while still handling events from raft core.
+                    self.set_target_repl_state(TargetReplState::Snapshotting);
+                    if let Err(err) = self.wait_heartbeat_tick().await {
+                        tracing::info!(error=%err, "replication is closed while waiting for a full snapshot");
+                        self.set_target_repl_state(TargetReplState::Shutdown);
+                    }
+                }
             };
         }
     }
 
+    /// Wait for the next heartbeat tick, while handling the events from raft core.
+    async fn wait_heartbeat_tick(&mut self) -> Result<(), ReplicationError> {
+        loop {
+            tokio::select! {
+                _ = self.heartbeat.tick() => {
+                    return Ok(());
+                }
+
+                event_span = self.repl_rx.recv() => {
+                    match event_span {
+                        Some((event, _span)) => {
+                            self.process_raft_event(event)?;
+                        },
+                        None => {
+                            return Err(ReplicationError::Closed);
+                        },
+                    }
+                }
+            }
+        }
+    }
+
+    /// Wait for `delay` before retrying a failed RPC, while events from RaftCore are still processed, e.g., a new
+    /// commit index or a termination.
+    async fn back_off(&mut self, delay: Duration) -> Result<(), ReplicationError> {
+        let deadline = sleep(delay);
+        tokio::pin!(deadline);
+
+        loop {
+            tokio::select! {
+                _ = &mut deadline => {
+                    return Ok(());
+                }
+
+                event_span = self.repl_rx.recv() => {
+                    match event_span {
+                        Some((event, _span)) => {
+                            self.process_raft_event(event)?;
+                        },
+                        None => {
+                            tracing::debug!("received: RaftEvent::Terminate: closed");
+                            return Err(ReplicationError::Closed);
+                        },
+                    }
+                }
+            }
+        }
+    }
+
     /// Send an AppendEntries RPC to the target.
     ///
     /// This request will timeout if no response is received within the
     /// configured heartbeat interval.
     #[tracing::instrument(level = "debug", skip(self))]
     async fn send_append_entries(&mut self) -> Result<(), ReplicationError> {
-        // find the mid position aligning to 8
-        let diff = self.max_possible_matched_index - self.matched.index;
-        let mut prev_index = self.matched.index + diff / 16 * 8;
+        if self.can_pipeline() {
+            return self.send_append_entries_pipelined().await;
+        }
+
+        // Fast path for a heartbeat to a target that is caught up: there is nothing to send, and the prev log id is the
+        // matched one, thus no storage I/O is needed.
+        let caught_up = self.next_probe.is_none()
+            && self.matched.index == self.last_log_index
+            && self.max_possible_matched_index == self.matched.index;
+
+        let (prev_log_id, logs) = if caught_up {
+            (self.matched, vec![])
+        } else {
+            // find the mid position aligning to 8
+            let diff = self.max_possible_matched_index - self.matched.index;
+            let prev_index = match self.next_probe {
+                Some(x) => x,
+                None => self.matched.index + diff / 16 * 8,
+            };
+
+            self.load_log_entries(prev_index).await?
+        };
+
+        self.update_progress(prev_log_id.index + 1, logs.len() as u64);
+
+        // Build the heartbeat frame to be sent to the follower.
+        let payload = AppendEntriesRequest {
+            term: self.term,
+            leader_id: self.id,
+            prev_log_id,
+            leader_commit: self.committed,
+            relay_to: self.relay_to.clone(),
+            entries: logs,
+        };
+
+        let append_resp = match self.send_rpc(payload).await {
+            Ok(x) => x,
+            Err(err) => {
+                self.update_progress(self.next_index, 0);
+                return Err(err);
+            }
+        };
+
+        self.backoff = None;
+        self.ack();
+
+        self.check_instance_uuid(append_resp.instance_uuid);
+        self.next_probe = None;
+
+        // Handle success conditions.
+        if append_resp.success() {
+            let matched = append_resp.matched.unwrap();
+            self.update_matched(matched);
+            self.update_line_rate_state();
+            self.update_progress(self.matched.index + 1, 0);
+
+            return Ok(());
+        }
+
+        // Failed
 
-        // TODO(xp): make this part a job of StorageAdaptor.
-        let (prev_log_id, logs) = loop {
+        // Replication was not successful, if a newer term has been returned, revert to follower.
+        if append_resp.term > self.term {
+            tracing::debug!({ append_resp.term }, "append entries failed, reverting to follower");
+
+            return Err(ReplicationError::HigherTerm {
+                higher: append_resp.term,
+                mine: self.term,
+            });
+        }
+
+        // Replication was not successful, handle conflict optimization record, else decrement `next_index`.
+        let conflict = append_resp.conflict.unwrap();
+
+        tracing::debug!(
+            ?conflict,
+            append_resp.term,
+            "append entries failed, handling conflict opt"
+        );
+
+        assert_eq!(conflict, prev_log_id, "if conflict, it is always the prev_log_id");
+
+        self.update_progress(conflict.index, 0);
+
+        // Continue to find the matching log id on follower.
+        self.max_possible_matched_index = conflict.index - 1;
+
+        if !self.probed_hint {
+            if let Some(last) = append_resp.last_log_id {
+                let probe = std::cmp::min(last.index, self.max_possible_matched_index);
+                self.next_probe = Some(std::cmp::max(probe, self.matched.index));
+                self.probed_hint = true;
+            }
+        }
+
+        self.update_line_rate_state();
+
+        Ok(())
+    }
+
+    /// Whether to send several payloads concurrently, see `Config::replication_streams_per_follower`.
+    ///
+    /// It is only when the matching log is found and there is more than one payload to send.
+    fn can_pipeline(&self) -> bool {
+        self.config.replication_streams_per_follower > 1
+            && self.next_probe.is_none()
+            && self.matched.index == self.max_possible_matched_index
+            && self.last_log_index > self.matched.index + self.config.max_payload_entries
+    }
+
+    /// Send up to `Config::replication_streams_per_follower` consecutive payloads to the target concurrently.
+    ///
+    /// The `prev_log_id` of a payload is the last log of the preceding one, thus the target appends them only in
+    /// order. A payload that arrives before the preceding one is rejected by the target; it is not a conflict and is
+    /// sent again from the new `matched`.
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn send_append_entries_pipelined(&mut self) -> Result<(), ReplicationError> {
+        let mut payloads = vec![];
+        let mut prev_index = self.matched.index;
+
+        while (payloads.len() as u64) < self.config.replication_streams_per_follower && prev_index < self.last_log_index
+        {
+            let (prev_log_id, logs) = self.load_log_entries(prev_index).await?;
+
+            let last = match logs.last() {
+                Some(x) => x.log_id,
+                None => break,
+            };
+            prev_index = last.index;
+
+            payloads.push(AppendEntriesRequest {
+                term: self.term,
+                leader_id: self.id,
+                prev_log_id,
+                leader_commit: self.committed,
+                relay_to: self.relay_to.clone(),
+                entries: logs,
+            });
+        }
+
+        tracing::debug!(n = payloads.len(), "send payloads concurrently");
+
+        let n_entries: u64 = payloads.iter().map(|x| x.entries.len() as u64).sum();
+        self.update_progress(self.matched.index + 1, n_entries);
+
+        let results = join_all(payloads.into_iter().map(|payload| self.send_rpc(payload))).await;
+
+        let mut first_err = None;
+        let mut responded = false;
+
+        for res in results {
+            let append_resp = match res {
+                Ok(x) => x,
+                Err(err) => {
+                    first_err.get_or_insert(err);
+                    continue;
+                }
+            };
+
+            responded = true;
+            self.backoff = None;
+            self.ack();
+            self.check_instance_uuid(append_resp.instance_uuid);
+
+            if append_resp.success() {
+                self.update_matched(append_resp.matched.unwrap());
+                continue;
+            }
+
+            if append_resp.term > self.term {
+                tracing::debug!({ append_resp.term }, "append entries failed, reverting to follower");
+
+                return Err(ReplicationError::HigherTerm {
+                    higher: append_resp.term,
+                    mine: self.term,
+                });
+            }
+
+            tracing::debug!(conflict=?append_resp.conflict, "payload arrived out of order, will be sent again");
+        }
+
+        self.update_progress(self.matched.index + 1, 0);
+
+        if !responded {
+            if let Some(err) = first_err {
+                return Err(err);
+            }
+        }
+
+        self.update_line_rate_state();
+
+        Ok(())
+    }
+
+    /// Send an AppendEntries RPC to the target and wait for the response for at most a heartbeat interval.
+    async fn send_rpc(&self, payload: AppendEntriesRequest<D>) -> Result<AppendEntriesResponse, ReplicationError> {
+        tracing::debug!(
+            payload=%payload.summary(),
+            "start sending append_entries, timeout: {:?}",
+            self.config.heartbeat_interval
+        );
+
+        let the_timeout = Duration::from_millis(self.config.heartbeat_interval);
+        let start = Instant::now();
+        let res = timeout(the_timeout, self.network.send_append_entries(self.target, payload)).await;
+
+        if self.config.enable_tick_metrics {
+            let _ = self.raft_core_tx.send((
+                ReplicaEvent::UpdateRpcLatency {
+                    target: self.target,
+                    latency: start.elapsed(),
+                },
+                tracing::debug_span!("CH"),
+            ));
+        }
+
+        let append_resp = match res {
+            Ok(append_res) => match append_res {
+                Ok(res) => res,
+                Err(err) => {
+                    tracing::warn!(error=%err, "error sending AppendEntries RPC to target");
+                    return Err(ReplicationError::Network { source: err });
+                }
+            },
+            Err(timeout_err) => {
+                tracing::warn!(error=%timeout_err, "timeout while sending AppendEntries RPC to target");
+                return Err(ReplicationError::Timeout {
+                    id: self.id,
+                    target: self.target,
+                    timeout: the_timeout,
+                });
+            }
+        };
+
+        tracing::debug!("append_entries resp: {:?}", append_resp);
+
+        Ok(append_resp)
+    }
+
+    /// Load the entries to send after `prev_index`, and the log id at `prev_index`.
+    // TODO(xp): make this part a job of StorageAdaptor.
+    async fn load_log_entries(&mut self, mut prev_index: u64) -> Result<(LogId, Vec<Entry<D>>), ReplicationError> {
+        loop {
             // It is last_applied_id or the id of the first present log.
             let first_log_id = self.storage.first_known_log_id().await?;
 
@@ -271,7 +740,12 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
             }
 
             let start = prev_index + 1;
-            let end = std::cmp::min(start + self.config.max_payload_entries, self.last_log_index + 1);
+            let range = normalize_range(
+                &(start..start.saturating_add(self.config.max_payload_entries)),
+                start,
+                self.last_log_index,
+            );
+            let end = range.end;
 
             tracing::debug!(
                 "load entries: matched: {}, send_prev_log_index: {} first_log: {} prev_index: {}, end: {}",
@@ -300,94 +774,137 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
             let logs = if start == end {
                 vec![]
             } else {
-                let logs = self.storage.try_get_log_entries(start..end).await?;
-                if !logs.is_empty() && logs[0].log_id.index > prev_log_id.index + 1 {
+                let mut logs = self.storage.try_get_log_entries(range).await?;
+                if !logs.is_empty() && logs[0].log_id.index > prev_log_id.next_index() {
                     // There is still chance the first log is removed.
                     // log entry is just deleted after fetching first_log_id.
                     // Without consecutive logs, we have to retry loading.
                     continue;
                 }
 
+                if let Some(max_bytes) = self.config.max_payload_bytes {
+                    logs.truncate(Self::count_within_bytes(&logs, max_bytes, &self.config.codec));
+                }
+
                 logs
             };
 
-            break (prev_log_id, logs);
-        };
-
-        // Build the heartbeat frame to be sent to the follower.
-        let payload = AppendEntriesRequest {
-            term: self.term,
-            leader_id: self.id,
-            prev_log_id,
-            leader_commit: self.committed,
-            entries: logs,
-        };
-
-        // Send the payload.
-        tracing::debug!(
-            payload=%payload.summary(),
-            "start sending append_entries, timeout: {:?}",
-            self.config.heartbeat_interval
-        );
-
-        let the_timeout = Duration::from_millis(self.config.heartbeat_interval);
-        let res = timeout(the_timeout, self.network.send_append_entries(self.target, payload)).await;
+            return Ok((prev_log_id, logs));
+        }
+    }
 
-        let append_resp = match res {
-            Ok(append_res) => match append_res {
-                Ok(res) => res,
-                Err(err) => {
-                    tracing::warn!(error=%err, "error sending AppendEntries RPC to target");
-                    return Err(ReplicationError::Network { source: err });
-                }
-            },
-            Err(timeout_err) => {
-                tracing::warn!(error=%timeout_err, "timeout while sending AppendEntries RPC to target");
-                return Err(ReplicationError::Timeout {
-                    id: self.id,
-                    target: self.target,
-                    timeout: the_timeout,
-                });
+    /// Returns how long to wait before retrying a failed AppendEntries RPC, according to the class of the error.
+    ///
+    /// The backoff ranges from 1/5 to 10 times of the heartbeat interval. It returns `None` for an error that is not
+    /// a `NetworkError`.
+    fn retry_delay(&mut self, err: &anyhow::Error) -> Option<Duration> {
+        let heartbeat = Duration::from_millis(self.config.heartbeat_interval);
+        let min = heartbeat / 5;
+        let max = heartbeat * 10;
+
+        let delay = match err.downcast_ref::<NetworkError>()? {
+            NetworkError::Transient(_) => {
+                let d = match self.backoff {
+                    None => min,
+                    Some(prev) => std::cmp::min(prev * 2, max),
+                };
+                self.backoff = Some(d);
+                d
             }
+            NetworkError::Unrecoverable(_) => max,
+            NetworkError::RemoteError(_) => min,
         };
 
-        tracing::debug!("append_entries resp: {:?}", append_resp);
+        Some(delay)
+    }
 
-        // Handle success conditions.
-        if append_resp.success() {
-            let matched = append_resp.matched.unwrap();
-            self.update_matched(matched);
+    /// Record that the target responded to a replication RPC.
+    fn ack(&mut self) {
+        self.last_ack = Instant::now();
+        self.unreachable_reported = false;
 
-            return Ok(());
+        if !self.responded {
+            self.responded = true;
+            let _ = self.raft_core_tx.send((
+                ReplicaEvent::Responded { target: self.target },
+                tracing::debug_span!("CH"),
+            ));
         }
+    }
 
-        // Failed
+    /// Report to the leader if the target has not responded for `Config::learner_eviction_timeout`.
+    ///
+    /// It is reported only once until the target responds again. Whether to evict the target is decided by the leader.
+    fn check_unreachable(&mut self) {
+        // A paused target is expected not to respond, e.g., it is in maintenance.
+        if self.paused {
+            return;
+        }
 
-        // Replication was not successful, if a newer term has been returned, revert to follower.
-        if append_resp.term > self.term {
-            tracing::debug!({ append_resp.term }, "append entries failed, reverting to follower");
+        let eviction_timeout = match self.config.learner_eviction_timeout {
+            None => return,
+            Some(x) => Duration::from_millis(x),
+        };
 
-            return Err(ReplicationError::HigherTerm {
-                higher: append_resp.term,
-                mine: self.term,
-            });
+        if self.unreachable_reported {
+            return;
         }
 
-        // Replication was not successful, handle conflict optimization record, else decrement `next_index`.
-        let conflict = append_resp.conflict.unwrap();
+        let elapsed = self.last_ack.elapsed();
+        if elapsed < eviction_timeout {
+            return;
+        }
 
-        tracing::debug!(
-            ?conflict,
-            append_resp.term,
-            "append entries failed, handling conflict opt"
+        tracing::info!(
+            target = self.target,
+            ?elapsed,
+            "target has not responded since eviction timeout"
         );
 
-        assert_eq!(conflict, prev_log_id, "if conflict, it is always the prev_log_id");
+        self.unreachable_reported = true;
+        let _ = self.raft_core_tx.send((
+            ReplicaEvent::Unreachable {
+                target: self.target,
+                elapsed,
+            },
+            tracing::debug_span!("CH"),
+        ));
+    }
 
-        // Continue to find the matching log id on follower.
-        self.max_possible_matched_index = conflict.index - 1;
+    /// Track the instance uuid reported by the target, to detect more than one process running with the target id.
+    ///
+    /// The target reports a new instance uuid when it restarts. But if an instance uuid that has been replaced shows
+    /// up again, there are more than one process responding as the target.
+    fn check_instance_uuid(&mut self, instance_uuid: Option<u128>) {
+        let uuid = match instance_uuid {
+            None => return,
+            Some(x) => x,
+        };
 
-        Ok(())
+        if self.target_instance_uuid == Some(uuid) {
+            return;
+        }
+
+        if self.seen_instance_uuids.contains(&uuid) {
+            tracing::error!(
+                target = self.target,
+                instance_uuid = %uuid,
+                prev = ?self.target_instance_uuid,
+                "duplicate node id: more than one process responds as the target"
+            );
+
+            let _ = self.raft_core_tx.send((
+                ReplicaEvent::DuplicateNodeId { target: self.target },
+                tracing::debug_span!("CH"),
+            ));
+        } else {
+            if let Some(prev) = self.target_instance_uuid {
+                tracing::info!(target = self.target, instance_uuid = %uuid, prev = %prev, "target restarted");
+            }
+            self.seen_instance_uuids.push(uuid);
+        }
+
+        self.target_instance_uuid = Some(uuid);
     }
 
     /// max_possible_matched_index is the least index for `prev_log_id` to form a consecutive log sequence
@@ -404,12 +921,86 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
         Ok(())
     }
 
+    /// Returns the number of leading entries in `logs` whose total payload size does not exceed `max_bytes`.
+    ///
+    /// It is at least 1 if `logs` is not empty, so that an entry larger than `max_bytes` can still be replicated.
+    fn count_within_bytes(logs: &[Entry<D>], max_bytes: u64, codec: &CodecType) -> usize {
+        let mut total = 0;
+
+        for (i, ent) in logs.iter().enumerate() {
+            total += ent.payload_size_hint(codec);
+            if total > max_bytes {
+                return std::cmp::max(i, 1);
+            }
+        }
+
+        logs.len()
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
     fn set_target_repl_state(&mut self, state: TargetReplState) {
         tracing::debug!(?state, "set_target_repl_state");
         self.target_repl_state = state;
     }
 
+    /// Update the replication state when replicating logs.
+    ///
+    /// It is `Probe` until the left and right cursor of the bsearch meet, i.e., the matching log is found.
+    /// Heartbeats sent while streaming a snapshot do not change it.
+    fn update_line_rate_state(&mut self) {
+        if self.target_repl_state != TargetReplState::LineRate {
+            return;
+        }
+
+        if self.matched.index >= self.max_possible_matched_index {
+            self.update_state(ReplicationState::Replicate);
+        } else {
+            self.update_state(ReplicationState::Probe);
+        }
+    }
+
+    /// Update the replication state and report it to RaftCore if it changed.
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn update_state(&mut self, state: ReplicationState) {
+        if self.state == state {
+            return;
+        }
+
+        tracing::debug!(from=?self.state, to=?state, target=%self.target, "update replication state");
+
+        self.state = state;
+        self.report_state();
+    }
+
+    fn report_state(&self) {
+        let _ = self.raft_core_tx.send((
+            ReplicaEvent::UpdateReplicationState {
+                target: self.target,
+                state: self.state,
+            },
+            tracing::debug_span!("CH"),
+        ));
+    }
+
+    /// Update the logs being sent to the target and report them to RaftCore, along with the last time it responded.
+    fn update_progress(&mut self, next_index: u64, in_flight: u64) {
+        self.next_index = next_index;
+        self.in_flight = in_flight;
+        self.report_progress();
+    }
+
+    fn report_progress(&self) {
+        let _ = self.raft_core_tx.send((
+            ReplicaEvent::UpdateProgress {
+                target: self.target,
+                next_index: self.next_index,
+                in_flight: self.in_flight,
+                last_ack: if self.responded { Some(self.last_ack) } else { None },
+            },
+            tracing::debug_span!("CH"),
+        ));
+    }
+
     /// Update the `matched` and `max_possible_matched_index`, which both are for tracking
     /// follower replication(the left and right cursor in a bsearch).
     /// And also report the matched log id to RaftCore to commit an entry etc.
@@ -454,6 +1045,8 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 tracing::trace!("snapshot needed: {}", needs_snap);
                 needs_snap
             }
+            // Logs are replicated no matter how far behind the target is, unless they are purged.
+            SnapshotPolicy::Never => false,
         }
     }
 
@@ -501,16 +1094,74 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 committed: commit_index,
             } => {
                 self.committed = commit_index;
+                self.restore_heartbeat();
             }
 
             RaftEvent::Replicate { appended, committed } => {
                 self.committed = committed;
                 self.last_log_index = appended.index;
+                self.restore_heartbeat();
+            }
+
+            RaftEvent::SetIdleHeartbeat { idle_heartbeat } => {
+                self.idle_heartbeat = idle_heartbeat;
+                if !idle_heartbeat {
+                    self.restore_heartbeat();
+                }
+            }
+
+            RaftEvent::SetRelayTo { relay_to } => {
+                self.relay_to = relay_to;
+                // The next heartbeat brings it to the target, do not wait for an idle one.
+                self.restore_heartbeat();
+            }
+
+            RaftEvent::SetPaused { paused } => {
+                if self.paused && !paused {
+                    // The time being paused does not count for eviction.
+                    self.last_ack = Instant::now();
+                    self.unreachable_reported = false;
+                }
+                self.paused = paused;
+            }
+
+            RaftEvent::SetRelayed { relayed } => {
+                self.relayed = relayed;
             }
         }
 
         Ok(())
     }
+
+    /// Slow the heartbeat down to 10 times the heartbeat interval if the target has every log, and it is allowed, see
+    /// `Config::slow_idle_learner_heartbeat`.
+    ///
+    /// It is called when the heartbeat ticks: the heartbeat about to be sent brings the latest committed log id to
+    /// the target, after which there is nothing new to tell it.
+    fn slow_down_idle_heartbeat(&mut self) {
+        if !self.idle_heartbeat || self.heartbeat_idle || self.matched.index < self.last_log_index {
+            return;
+        }
+
+        tracing::debug!(target = self.target, "target is idle, slow down heartbeat");
+
+        let period = Duration::from_millis(self.config.heartbeat_interval) * 10;
+        self.heartbeat = interval_at(Instant::now() + period, period);
+        self.heartbeat_idle = true;
+    }
+
+    /// Restore the heartbeat interval once there is something new to send to the target. The next heartbeat is sent
+    /// at once, e.g., to deliver a new committed log id.
+    fn restore_heartbeat(&mut self) {
+        if !self.heartbeat_idle {
+            return;
+        }
+
+        tracing::debug!(target = self.target, "target is no longer idle, restore heartbeat");
+
+        self.heartbeat = interval(Duration::from_millis(self.config.heartbeat_interval));
+        self.heartbeat_idle = false;
+    }
 }
 
 //////////////////////////////////////////////////////////////////////////////////////////////////
@@ -544,6 +1195,14 @@ pub(crate) enum RaftEvent {
         /// The index of the highest log entry which is known to be committed in the cluster.
         committed: LogId,
     },
+    /// A message from Raft to pause or resume sending logs to the target.
+    SetPaused { paused: bool },
+    /// A message from Raft indicating whether the target receives the logs from a relay instead.
+    SetRelayed { relayed: bool },
+    /// A message from Raft to allow or disallow slowing down the heartbeat when the target is idle.
+    SetIdleHeartbeat { idle_heartbeat: bool },
+    /// A message from Raft to set the learners the target relays the logs to.
+    SetRelayTo { relay_to: Vec<NodeId> },
 }
 
 impl MessageSummary for RaftEvent {
@@ -557,6 +1216,18 @@ impl MessageSummary for RaftEvent {
             } => {
                 format!("UpdateCommitIndex: commit_index: {}", commit_index)
             }
+            RaftEvent::SetPaused { paused } => {
+                format!("SetPaused: {}", paused)
+            }
+            RaftEvent::SetRelayed { relayed } => {
+                format!("SetRelayed: {}", relayed)
+            }
+            RaftEvent::SetIdleHeartbeat { idle_heartbeat } => {
+                format!("SetIdleHeartbeat: {}", idle_heartbeat)
+            }
+            RaftEvent::SetRelayTo { relay_to } => {
+                format!("SetRelayTo: {:?}", relay_to)
+            }
         }
     }
 }
@@ -586,6 +1257,58 @@ where S: AsyncRead + AsyncSeek + Send + Unpin + 'static
         /// The response channel for delivering the snapshot data.
         tx: oneshot::Sender<Snapshot<S>>,
     },
+    /// An event from a replication stream reporting a change of the replication state.
+    UpdateReplicationState {
+        /// The ID of the target node.
+        target: NodeId,
+        /// The new replication state.
+        state: ReplicationState,
+    },
+    /// An event from a replication stream reporting the logs being sent to the target.
+    UpdateProgress {
+        /// The ID of the target node.
+        target: NodeId,
+        /// The index of the first log to send next.
+        next_index: u64,
+        /// The number of logs sent and not acknowledged yet.
+        in_flight: u64,
+        /// When the target responded last time, or `None` if it has never responded.
+        last_ack: Option<Instant>,
+    },
+    /// An event from a replication stream reporting how many bytes of a snapshot have been sent to the target.
+    UpdateSnapshotProgress {
+        /// The ID of the target node to which the snapshot is being sent.
+        target: NodeId,
+        /// The sending progress.
+        progress: SnapshotProgress,
+    },
+    /// An event from a replication stream reporting that more than one process responds as the target.
+    DuplicateNodeId {
+        /// The ID of the target node.
+        target: NodeId,
+    },
+    /// An event from a replication stream reporting how long an AppendEntries RPC took, including a failed one.
+    ///
+    /// It is sent only when `Config::enable_tick_metrics` is set.
+    UpdateRpcLatency {
+        /// The ID of the target node.
+        target: NodeId,
+        /// The time the RPC took.
+        latency: Duration,
+    },
+    /// An event from a replication stream reporting that the target responded for the first time.
+    Responded {
+        /// The ID of the target node.
+        target: NodeId,
+    },
+    /// An event from a replication stream reporting that the target has not responded for
+    /// `Config::learner_eviction_timeout`.
+    Unreachable {
+        /// The ID of the target node.
+        target: NodeId,
+        /// The time since the target responded last time.
+        elapsed: Duration,
+    },
     /// Some critical error has taken place, and Raft needs to shutdown.
     Shutdown,
 }
@@ -605,6 +1328,48 @@ impl<S: AsyncRead + AsyncSeek + Send + Unpin + 'static> MessageSummary for Repli
             ReplicaEvent::NeedsSnapshot { ref target, .. } => {
                 format!("NeedsSnapshot: target: {}", target)
             }
+            ReplicaEvent::UpdateReplicationState { ref target, ref state } => {
+                format!("UpdateReplicationState: target: {}, state: {:?}", target, state)
+            }
+            ReplicaEvent::UpdateProgress {
+                ref target,
+                ref next_index,
+                ref in_flight,
+                ..
+            } => {
+                format!(
+                    "UpdateProgress: target: {}, next_index: {}, in_flight: {}",
+                    target, next_index, in_flight
+                )
+            }
+            ReplicaEvent::UpdateSnapshotProgress {
+                ref target,
+                ref progress,
+            } => {
+                format!(
+                    "UpdateSnapshotProgress: target: {}, progress: {}",
+                    target,
+                    progress.summary()
+                )
+            }
+            ReplicaEvent::DuplicateNodeId { ref target } => {
+                format!("DuplicateNodeId: target: {}", target)
+            }
+            ReplicaEvent::UpdateRpcLatency {
+                ref target,
+                ref latency,
+            } => {
+                format!("UpdateRpcLatency: target: {}, latency: {:?}", target, latency)
+            }
+            ReplicaEvent::Responded { ref target } => {
+                format!("Responded: target: {}", target)
+            }
+            ReplicaEvent::Unreachable {
+                ref target,
+                ref elapsed,
+            } => {
+                format!("Unreachable: target: {}, elapsed: {:?}", target, elapsed)
+            }
             ReplicaEvent::Shutdown => "Shutdown".to_string(),
         }
     }
@@ -614,6 +1379,10 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
     #[tracing::instrument(level = "debug", skip(self))]
     pub async fn line_rate_loop(&mut self) -> Result<(), ReplicationError> {
         loop {
+            if self.holds_logs() {
+                self.paused_loop().await?;
+            }
+
             loop {
                 tracing::debug!(
                     "current matched: {} send_prev_log_index: {}",
@@ -629,9 +1398,15 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                     // For transport error, just keep retrying.
                     match err {
                         ReplicationError::Timeout { .. } => {
+                            self.check_unreachable();
                             break;
                         }
-                        ReplicationError::Network { .. } => {
+                        ReplicationError::Network { source } => {
+                            self.check_unreachable();
+                            if let Some(delay) = self.retry_delay(&source) {
+                                tracing::debug!(?delay, target = self.target, "back off before retrying");
+                                self.back_off(delay).await?;
+                            }
                             break;
                         }
                         _ => {
@@ -666,6 +1441,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
             tokio::select! {
                 _ = self.heartbeat.tick() => {
                     tracing::debug!("heartbeat triggered");
+                    self.slow_down_idle_heartbeat();
                     // continue
                 }
 
@@ -685,8 +1461,82 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
         }
     }
 
+    /// Returns whether no log is sent to the target, because it is paused or it is relayed.
+    fn holds_logs(&self) -> bool {
+        self.paused || self.relayed
+    }
+
+    /// Send only heartbeats without any log to the target until replication to it is resumed, and it is not relayed.
+    #[tracing::instrument(level = "debug", skip(self), fields(state = "paused"))]
+    async fn paused_loop(&mut self) -> Result<(), ReplicationError> {
+        while self.holds_logs() {
+            tokio::select! {
+                _ = self.heartbeat.tick() => {
+                    match self.send_heartbeat().await {
+                        Ok(_) => {}
+                        Err(ReplicationError::Timeout { .. }) | Err(ReplicationError::Network { .. }) => {
+                            // A paused target may well be down.
+                        }
+                        Err(err) => {
+                            return Err(err);
+                        }
+                    }
+                }
+
+                event_span = self.repl_rx.recv() => {
+                    match event_span {
+                        Some((event, _span)) => {
+                            self.process_raft_event(event)?;
+                            self.try_drain_raft_rx().await?;
+                        },
+                        None => {
+                            tracing::debug!("received: RaftEvent::Terminate: closed");
+                            return Err(ReplicationError::Closed);
+                        },
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send an AppendEntries RPC without any log, to keep a paused target from starting an election.
+    ///
+    /// The prev log id is the matched one, which is always accepted by the target. No storage I/O is needed.
+    async fn send_heartbeat(&mut self) -> Result<(), ReplicationError> {
+        let payload = AppendEntriesRequest {
+            term: self.term,
+            leader_id: self.id,
+            prev_log_id: self.matched,
+            leader_commit: self.committed,
+            relay_to: self.relay_to.clone(),
+            entries: vec![],
+        };
+
+        let append_resp = self.send_rpc(payload).await?;
+
+        self.ack();
+        self.check_instance_uuid(append_resp.instance_uuid);
+        self.report_progress();
+
+        if append_resp.term > self.term {
+            return Err(ReplicationError::HigherTerm {
+                higher: append_resp.term,
+                mine: self.term,
+            });
+        }
+
+        Ok(())
+    }
+
     #[tracing::instrument(level = "debug", skip(self), fields(state = "snapshotting"))]
     pub async fn replicate_snapshot(&mut self) -> Result<(), ReplicationError> {
+        // A paused or relayed target does not receive a snapshot either.
+        if self.holds_logs() {
+            self.paused_loop().await?;
+        }
+
         let snapshot = self.wait_for_snapshot().await?;
         self.stream_snapshot(snapshot).await?;
 
@@ -726,7 +1576,11 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 tokio::select! {
                     _ = self.heartbeat.tick() => {
                         // TODO(xp): just heartbeat:
-                        let res = self.send_append_entries().await;
+                        let res = if self.holds_logs() {
+                            self.send_heartbeat().await
+                        } else {
+                            self.send_append_entries().await
+                        };
                         match res {
                             Ok(_) => {
                                 //
@@ -780,26 +1634,66 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
         }
     }
 
+    /// The max size of a snapshot chunk to send.
+    ///
+    /// When snapshot transfer is throttled, a chunk is not larger than what can be sent in one heartbeat interval, so
+    /// that the target keeps receiving RPCs from the leader frequently enough not to start an election.
+    fn snapshot_chunk_size(&self) -> u64 {
+        let max = self.config.snapshot_max_chunk_size;
+
+        match self.config.snapshot_transfer_bytes_per_sec {
+            None => max,
+            Some(bytes_per_sec) => {
+                let per_heartbeat = bytes_per_sec.saturating_mul(self.config.heartbeat_interval) / 1000;
+                std::cmp::min(max, std::cmp::max(1, per_heartbeat))
+            }
+        }
+    }
+
     #[tracing::instrument(level = "trace", skip(self, snapshot))]
     async fn stream_snapshot(&mut self, mut snapshot: Snapshot<S::SnapshotData>) -> Result<(), ReplicationError> {
-        let end = snapshot.snapshot.seek(SeekFrom::End(0)).await?;
+        let mut end = snapshot.snapshot.seek(SeekFrom::End(0)).await?;
 
         let mut offset = 0;
 
-        let mut buf = Vec::with_capacity(self.config.snapshot_max_chunk_size as usize);
+        let chunk_size = self.snapshot_chunk_size();
+        let mut buf = Vec::with_capacity(chunk_size as usize);
+
+        let mut rate_limiter = self
+            .config
+            .snapshot_transfer_bytes_per_sec
+            .map(|bytes_per_sec| RateLimiter::new(bytes_per_sec, chunk_size, Instant::now()));
 
         loop {
+            // A pause stops the transfer. It starts over from the first chunk after replication is resumed.
+            self.try_drain_raft_rx().await?;
+            if self.holds_logs() {
+                tracing::info!(offset, end, "replication is paused or relayed, stop sending snapshot");
+                return Ok(());
+            }
+
             // Build the RPC.
             snapshot.snapshot.seek(SeekFrom::Start(offset)).await?;
             let n_read = snapshot.snapshot.read_buf(&mut buf).await?;
 
+            // Throttle before sending. The wait is not counted in `install_snapshot_timeout`.
+            if let Some(rl) = &mut rate_limiter {
+                let wait = rl.acquire(n_read as u64, Instant::now());
+                if wait > Duration::from_secs(0) {
+                    tracing::debug!(?wait, "throttle snapshot transfer");
+                    sleep(wait).await;
+                }
+            }
+
             let done = (offset + n_read as u64) == end; // If bytes read == 0, then we're done.
+            let data = Vec::from(&buf[..n_read]);
             let req = InstallSnapshotRequest {
                 term: self.term,
                 leader_id: self.id,
                 meta: snapshot.meta.clone(),
                 offset,
-                data: Vec::from(&buf[..n_read]),
+                checksum: InstallSnapshotRequest::checksum_of(&data),
+                data,
                 done,
             };
             buf.clear();
@@ -824,15 +1718,19 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                     Ok(res) => res,
                     Err(err) => {
                         tracing::warn!(error=%err, "error sending InstallSnapshot RPC to target");
+                        self.check_unreachable();
                         continue;
                     }
                 },
                 Err(err) => {
                     tracing::warn!(error=%err, "timeout while sending InstallSnapshot RPC to target");
+                    self.check_unreachable();
                     continue;
                 }
             };
 
+            self.ack();
+
             // Handle response conditions.
             if res.term > self.term {
                 return Err(ReplicationError::HigherTerm {
@@ -841,6 +1739,44 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 });
             }
 
+            // The target does not have the base of a delta snapshot. Start over with a full snapshot.
+            if res.need_full_snapshot {
+                let full = self.storage.get_full_snapshot().await?;
+
+                match full {
+                    Some(full) if full.meta.base_snapshot_id.is_none() => {
+                        tracing::info!(
+                            delta = %snapshot.meta.snapshot_id,
+                            full = %full.meta.snapshot_id,
+                            "target lacks the base snapshot, send full snapshot instead"
+                        );
+
+                        snapshot = full;
+                        end = snapshot.snapshot.seek(SeekFrom::End(0)).await?;
+                        offset = 0;
+                        continue;
+                    }
+                    _ => {
+                        return Err(ReplicationError::NoFullSnapshot {
+                            target: self.target,
+                            delta: snapshot.meta.snapshot_id.clone(),
+                        });
+                    }
+                }
+            }
+
+            let _ = self.raft_core_tx.send((
+                ReplicaEvent::UpdateSnapshotProgress {
+                    target: self.target,
+                    progress: SnapshotProgress {
+                        meta: snapshot.meta.clone(),
+                        transferred: offset + n_read as u64,
+                        total: Some(end),
+                    },
+                },
+                tracing::debug_span!("CH"),
+            ));
+
             // If we just sent the final chunk of the snapshot, then transition to lagging state.
             if done {
                 tracing::debug!(
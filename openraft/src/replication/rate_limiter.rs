@@ -0,0 +1,48 @@
+use tokio::time::Duration;
+use tokio::time::Instant;
+
+/// A token bucket that limits the rate of sending bytes.
+///
+/// Tokens are refilled at `bytes_per_sec` up to `capacity`.
+/// Acquiring more tokens than available puts the bucket in debt, which is paid off by waiting.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    bytes_per_sec: u64,
+
+    /// The max number of tokens the bucket holds, i.e., the max burst size.
+    capacity: u64,
+
+    /// Available tokens. Negative if the bucket is in debt.
+    tokens: f64,
+
+    /// When the tokens are refilled last time.
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(bytes_per_sec: u64, capacity: u64, now: Instant) -> Self {
+        Self {
+            bytes_per_sec,
+            capacity,
+            tokens: capacity as f64,
+            refilled_at: now,
+        }
+    }
+
+    /// Take `n` bytes from the bucket and returns how long to wait before sending them.
+    pub(crate) fn acquire(&mut self, n: u64, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.refilled_at = now;
+
+        self.tokens += elapsed.as_secs_f64() * self.bytes_per_sec as f64;
+        self.tokens = self.tokens.min(self.capacity as f64);
+
+        self.tokens -= n as f64;
+
+        if self.tokens >= 0.0 {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.bytes_per_sec as f64)
+        }
+    }
+}
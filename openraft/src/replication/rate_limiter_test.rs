@@ -0,0 +1,47 @@
+use tokio::time::Duration;
+use tokio::time::Instant;
+
+use crate::replication::rate_limiter::RateLimiter;
+
+#[test]
+fn test_rate_limiter_burst_then_wait() -> anyhow::Result<()> {
+    let now = Instant::now();
+    let mut rl = RateLimiter::new(1000, 100, now);
+
+    // The initial burst is allowed.
+    assert_eq!(Duration::from_millis(0), rl.acquire(100, now));
+
+    // Then it has to wait for tokens to refill.
+    assert_eq!(Duration::from_millis(100), rl.acquire(100, now));
+
+    // Debt is paid off by waiting.
+    let now = now + Duration::from_millis(100);
+    assert_eq!(Duration::from_millis(100), rl.acquire(100, now));
+
+    Ok(())
+}
+
+#[test]
+fn test_rate_limiter_refill_upto_capacity() -> anyhow::Result<()> {
+    let now = Instant::now();
+    let mut rl = RateLimiter::new(1000, 100, now);
+
+    assert_eq!(Duration::from_millis(0), rl.acquire(100, now));
+
+    // A long idle time does not accumulate more tokens than the capacity.
+    let now = now + Duration::from_secs(10);
+    assert_eq!(Duration::from_millis(0), rl.acquire(100, now));
+    assert_eq!(Duration::from_millis(50), rl.acquire(50, now));
+
+    Ok(())
+}
+
+#[test]
+fn test_rate_limiter_acquire_more_than_capacity() -> anyhow::Result<()> {
+    let now = Instant::now();
+    let mut rl = RateLimiter::new(1000, 100, now);
+
+    assert_eq!(Duration::from_millis(400), rl.acquire(500, now));
+
+    Ok(())
+}
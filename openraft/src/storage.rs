@@ -1,5 +1,6 @@
 //! The Raft storage interface and data types.
 
+use std::any::Any;
 use std::fmt::Debug;
 use std::ops::RangeBounds;
 
@@ -30,6 +31,14 @@ pub struct SnapshotMeta {
     /// To identify a snapshot when transferring.
     /// Caveat: even when two snapshot is built with the same `last_log_id`, they still could be different in bytes.
     pub snapshot_id: SnapshotId,
+
+    /// The id of the snapshot this snapshot is built upon, if it is a delta snapshot.
+    ///
+    /// A delta snapshot contains only the changes since its base snapshot,
+    /// and can only be installed on a node whose current snapshot is the base.
+    /// It is `None` for a full snapshot.
+    #[serde(default)]
+    pub base_snapshot_id: Option<SnapshotId>,
 }
 
 /// The data associated with the current snapshot.
@@ -43,6 +52,57 @@ where S: AsyncRead + AsyncSeek + Send + Unpin + 'static
     pub snapshot: Box<S>,
 }
 
+/// A consistent point-in-time view of the state machine, to build a snapshot from while logs are applied to the
+/// live state machine.
+///
+/// It is returned by `RaftStorage::begin_checkpoint()` and consumed by `RaftStorage::do_log_compaction_from()`. The
+/// view is opaque to Raft: a store puts in whatever it reads from, e.g., a storage engine snapshot or a copy of the
+/// state machine, and takes it out with `into_view()`.
+pub struct CheckpointHandle {
+    last_applied: Option<LogId>,
+    view: Option<Box<dyn Any + Send + Sync>>,
+}
+
+impl CheckpointHandle {
+    /// Create a handle to a view of the state machine in which `last_applied` is the last applied log.
+    pub fn new<T: Any + Send + Sync>(last_applied: LogId, view: T) -> Self {
+        Self {
+            last_applied: Some(last_applied),
+            view: Some(Box::new(view)),
+        }
+    }
+
+    /// Create a handle without a view, for a store that does not support checkpoints.
+    ///
+    /// A snapshot is then built from the live state machine, as `do_log_compaction()` does.
+    pub fn blocking() -> Self {
+        Self {
+            last_applied: None,
+            view: None,
+        }
+    }
+
+    /// The last applied log in the view, or None if it is a handle without a view.
+    pub fn last_applied(&self) -> Option<LogId> {
+        self.last_applied
+    }
+
+    /// Take the view out of the handle, or None if there is no view or it is not a `T`.
+    pub fn into_view<T: Any>(self) -> Option<T> {
+        let view = self.view?;
+        view.downcast::<T>().ok().map(|x| *x)
+    }
+}
+
+impl Debug for CheckpointHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CheckpointHandle")
+            .field("last_applied", &self.last_applied)
+            .field("has_view", &self.view.is_some())
+            .finish()
+    }
+}
+
 /// A record holding the hard state of a Raft node.
 ///
 /// This model derives serde's traits for easily (de)serializing this
@@ -79,14 +139,14 @@ impl InitialState {
     /// The ID of the Raft node.
     pub fn new_initial(id: NodeId) -> Self {
         Self {
-            last_log_id: LogId { term: 0, index: 0 },
-            last_applied: LogId { term: 0, index: 0 },
+            last_log_id: LogId::none(),
+            last_applied: LogId::none(),
             hard_state: HardState {
                 current_term: 0,
                 voted_for: None,
             },
             last_membership: EffectiveMembership {
-                log_id: LogId { term: 0, index: 0 },
+                log_id: LogId::none(),
                 membership: Membership::new_initial(id),
             },
         }
@@ -112,6 +172,9 @@ where
     type SnapshotData: AsyncRead + AsyncWrite + AsyncSeek + Send + Sync + Unpin + 'static;
 
     /// Returns the last membership config found in log or state machine.
+    ///
+    /// The membership found in log may not be committed yet. A caller that must only act upon a committed membership
+    /// should use `get_committed_membership()` instead.
     async fn get_membership(&self) -> Result<Option<EffectiveMembership>, StorageError> {
         let (_, sm_mem) = self.last_applied_state().await?;
 
@@ -129,10 +192,22 @@ where
         return Ok(sm_mem);
     }
 
+    /// Returns the last membership config applied to the state machine.
+    ///
+    /// A membership is applied only after it is committed, thus unlike `get_membership()`, which scans the log, it
+    /// never returns an uncommitted membership. It returns `None` if no membership has been applied.
+    ///
+    /// By default it is the membership returned by `last_applied_state()`.
+    async fn get_committed_membership(&self) -> Result<Option<EffectiveMembership>, StorageError> {
+        let (_, sm_mem) = self.last_applied_state().await?;
+        Ok(sm_mem)
+    }
+
     /// Get the latest membership config found in the log.
     ///
     /// This method should returns membership with the greatest log index which is `>=since_index`.
-    /// If no such membership log is found, it returns `None`, e.g., when logs are cleaned after being applied.
+    /// If no such membership log is found, it returns `None`, e.g., when logs are cleaned after being applied, or
+    /// when there is no log at all.
     #[tracing::instrument(level = "trace", skip(self))]
     async fn last_membership_in_log(&self, since_index: u64) -> Result<Option<EffectiveMembership>, StorageError> {
         let last_log_id = self.last_id_in_log().await?;
@@ -146,14 +221,25 @@ where
             Some(x) => x,
         };
 
-        let mut end = last_log_id.index + 1;
+        if last_log_id.is_sentinel() {
+            // There is only the sentinel, which is not a real log.
+            return Ok(None);
+        }
+
+        let mut end = last_log_id.next_index();
         let start = std::cmp::max(first_log_id.index, since_index);
         let step = 64;
 
+        // Scan backward by index, a window at a time.
+        // Membership logs are searched by position, not by log id order.
         while start < end {
-            let entries = self.try_get_log_entries(start..end).await?;
+            let window_start = std::cmp::max(start, end.saturating_sub(step));
+            let entries = self.try_get_log_entries(window_start..end).await?;
 
             for ent in entries.iter().rev() {
+                if ent.log_id.is_sentinel() {
+                    continue;
+                }
                 if let EntryPayload::Membership(ref mem) = ent.payload {
                     return Ok(Some(EffectiveMembership {
                         log_id: ent.log_id,
@@ -177,11 +263,33 @@ where
 
     /// Save Raft's hard-state.
     ///
+    /// Raft never saves a `current_term` lower than the one returned by `read_hard_state()`: such an attempt is
+    /// rejected by raft core before calling this method, and the node shuts down.
+    ///
     /// Errors returned from this method will cause Raft to go into shutdown.
     async fn save_hard_state(&self, hs: &HardState) -> Result<(), StorageError>;
 
     async fn read_hard_state(&self) -> Result<Option<HardState>, StorageError>;
 
+    /// Save application defined node-local metadata, e.g., a role tag or a data-center id.
+    ///
+    /// Raft never reads it. It is provided so that an application can persist a small amount of data durably along
+    /// with `HardState`, in the same store, instead of maintaining a separate file that may diverge from raft state
+    /// after a crash. It is not replicated to other nodes.
+    ///
+    /// The default impl does nothing.
+    async fn save_node_metadata(&self, meta: &[u8]) -> Result<(), StorageError> {
+        let _ = meta;
+        Ok(())
+    }
+
+    /// Read the node-local metadata saved by `save_node_metadata()`.
+    ///
+    /// It returns `None` if no metadata has been saved. The default impl always returns `None`.
+    async fn read_node_metadata(&self) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(None)
+    }
+
     /// Get a series of log entries from storage.
     ///
     /// The start value is inclusive in the search and the stop value is non-inclusive: `[start, stop)`.
@@ -192,6 +300,23 @@ where
         range: RNG,
     ) -> Result<Vec<Entry<D>>, StorageError>;
 
+    /// Get a series of log entries from storage, in descending index order, e.g., for a consumer that wants the most
+    /// recent logs first.
+    ///
+    /// It returns the same entries as `get_log_entries(range)` does, in reverse order. To read only committed logs,
+    /// bound the range with `RaftMetrics::committed`.
+    ///
+    /// The default implementation reverses the result of `get_log_entries()` in memory. A store with a reverse
+    /// iterator, e.g., a `BTreeMap` or an LSM engine, should override it to scan backward.
+    async fn get_log_entries_rev<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
+        &self,
+        range: RNG,
+    ) -> Result<Vec<Entry<D>>, StorageError> {
+        let mut entries = self.get_log_entries(range).await?;
+        entries.reverse();
+        Ok(entries)
+    }
+
     /// Get a series of log entries from storage.
     ///
     /// Entry not found is allowed
@@ -204,6 +329,16 @@ where
     /// It does not return an error if in defensive mode and the log entry at `log_index` is not found.
     async fn try_get_log_entry(&self, log_index: u64) -> Result<Option<Entry<D>>, StorageError>;
 
+    /// Returns the log id of the log at `log_index`, or `None` if there is no log at this index.
+    ///
+    /// It is a light-weight version of `try_get_log_entry()`: a store that keeps the term separately from the payload
+    /// should override it to avoid loading and deserializing the payload.
+    /// The default implementation reads the entire entry with `try_get_log_entry()`.
+    async fn get_log_id(&self, log_index: u64) -> Result<Option<LogId>, StorageError> {
+        let entry = self.try_get_log_entry(log_index).await?;
+        Ok(entry.map(|x| x.log_id))
+    }
+
     /// Returns the first log id in log.
     ///
     /// The impl should not consider the applied log id in state machine.
@@ -216,8 +351,47 @@ where
     /// The impl should not consider the applied log id in state machine.
     async fn last_id_in_log(&self) -> Result<LogId, StorageError>;
 
+    /// Returns an estimate of the size in bytes of the logs in storage, reported in `RaftMetrics::log_bytes`.
+    ///
+    /// Raft calls it at startup, after every batch of logs is applied, which may purge applied logs, and after a
+    /// snapshot is installed. Thus it has to be cheap, e.g., a counter maintained along with the logs, instead of a
+    /// scan of them. The default implementation returns `None`, i.e., unknown.
+    async fn log_size_bytes(&self) -> Result<Option<u64>, StorageError> {
+        Ok(None)
+    }
+
+    /// Returns a checksum of the state machine, if its last applied log id is `log_id`.
+    ///
+    /// It is used to detect divergence of replicas: replicas that have applied upto the same log must have the same
+    /// checksum. Raft calls it on startup and on `Raft::state_checksum()`, when no logs are being applied, and reports
+    /// the result in `RaftMetrics::state_checksum`.
+    ///
+    /// It should return `None` if the state machine is not at `log_id`. The default implementation returns `None`,
+    /// i.e., unknown.
+    async fn state_checksum_at(&self, log_id: LogId) -> Result<Option<u64>, StorageError> {
+        let _ = log_id;
+        Ok(None)
+    }
+
+    /// Replace the payload of the log at `log_id` with `payload`, e.g., to redact data for compliance.
+    ///
+    /// Raft calls it only from `Raft::rewrite_log_entry()`, after checking that the log is applied, is included in the
+    /// current snapshot, and is a normal log with exactly this `log_id`. The store must keep the log id, i.e., the term
+    /// and the index, unchanged, and must not touch the state machine.
+    ///
+    /// It returns `false` if the store does not support rewriting a log, or if the log with exactly `log_id` is not in
+    /// the store, e.g., it is purged meanwhile. The default implementation returns `false`.
+    async fn rewrite_log_entry(&self, log_id: LogId, payload: D) -> Result<bool, StorageError> {
+        let _ = (log_id, payload);
+        Ok(false)
+    }
+
     /// Returns the last applied log id which is recorded in state machine, and the last applied membership log id and
     /// membership config.
+    ///
+    /// Raft calls it only on startup and after installing a snapshot, and caches the result in memory afterwards.
+    /// Thus the store must still persist the last applied log id and membership durably along with the state machine,
+    /// so that they are consistent with the state machine data after a restart.
     async fn last_applied_state(&self) -> Result<(LogId, Option<EffectiveMembership>), StorageError>;
 
     /// Delete all logs in a `range`.
@@ -228,6 +402,20 @@ where
         range: RNG,
     ) -> Result<(), StorageError>;
 
+    /// Validate the application data of a client write request before it is appended to the log.
+    ///
+    /// It is called only on the leader, before `append_to_log()`. An error rejects the request: it is returned to the
+    /// client as `ClientWriteError::InvalidEntry` and the entry is never appended or replicated.
+    /// E.g., an application can reject an entry that breaks its schema or is too large.
+    ///
+    /// Unlike errors from other methods, an error returned from this method does not shut down Raft.
+    ///
+    /// The default impl accepts every entry.
+    async fn validate_entry(&self, data: &D) -> Result<(), StorageError> {
+        let _ = data;
+        Ok(())
+    }
+
     /// Append a payload of entries to the log.
     ///
     /// Though the entries will always be presented in order, each entry's index should be used to
@@ -236,6 +424,21 @@ where
     /// Errors returned from this method will cause Raft to go into shutdown.
     async fn append_to_log(&self, entries: &[&Entry<D>]) -> Result<(), StorageError>;
 
+    /// Append a payload of entries to the log and save Raft's hard-state, atomically.
+    ///
+    /// Raft calls it instead of `save_hard_state()` and `append_to_log()` when both have to be durable together, e.g.,
+    /// a follower that learns a new term from an AppendEntries RPC with entries. A store that supports transactions
+    /// should override it to write both in one transaction, so that a crash never leaves one without the other.
+    ///
+    /// The default implementation saves the hard-state first and then appends the entries. A crash in between leaves a
+    /// new term without the entries, which is safe, while entries of a term newer than the saved one are not.
+    ///
+    /// Errors returned from this method will cause Raft to go into shutdown.
+    async fn append_and_save_hard_state(&self, entries: &[&Entry<D>], hs: &HardState) -> Result<(), StorageError> {
+        self.save_hard_state(hs).await?;
+        self.append_to_log(entries).await
+    }
+
     /// Apply the given payload of entries to the state machine.
     ///
     /// The Raft protocol guarantees that only logs which have been _committed_, that is, logs which
@@ -249,9 +452,15 @@ where
     /// An impl should do:
     /// - Deal with the EntryPayload::Normal() log, which is business logic log.
     /// - Deal with EntryPayload::Membership
-    /// - A EntryPayload::SnapshotPointer log should never be seen.
+    /// - Update the last applied log id for an EntryPayload::Blank log, which has nothing else to apply.
     ///
-    /// Errors returned from this method will cause Raft to go into shutdown.
+    /// There is no snapshot pointer log: logs included in a snapshot are purged from the log, they are never replaced
+    /// with a log pointing to the snapshot. Thus the variants above are all an impl sees.
+    ///
+    /// Errors returned from this method will cause Raft to go into shutdown, unless it is a transient error, see
+    /// `StorageError::is_transient()`, and `Config::apply_retry` allows retrying: then the same entries are applied
+    /// again. Thus an impl that returns a transient error has to apply a log idempotently by its log id: the logs
+    /// applied before the failure must not change the state machine again.
     async fn apply_to_state_machine(&self, entries: &[&Entry<D>]) -> Result<Vec<R>, StorageError>;
 
     /// Perform log compaction, returning a handle to the generated snapshot.
@@ -263,9 +472,78 @@ where
     /// the value of that export's last applied log as the metadata indicating the breadth of the
     /// log covered by the snapshot.
     ///
+    /// It is called on a dedicated task, concurrently with `apply_to_state_machine()`. To not stall applying logs, an
+    /// impl should build the snapshot from a consistent read view of the state machine, e.g., a storage engine
+    /// checkpoint or a copy-on-write clone, instead of holding a lock that applying logs needs during the whole build.
+    /// Raft installs the new snapshot only when it is completely built.
+    ///
+    /// An impl may build a delta snapshot, which contains only the changes since the current snapshot, by setting
+    /// `SnapshotMeta::base_snapshot_id` to the id of the current snapshot. Such an impl has to implement
+    /// `get_full_snapshot()` too, for a follower that does not have the base snapshot.
+    ///
     /// Errors returned from this method will be logged and retried.
     async fn do_log_compaction(&self) -> Result<Snapshot<Self::SnapshotData>, StorageError>;
 
+    /// Take a consistent point-in-time view of the state machine to build a snapshot from.
+    ///
+    /// Raft calls it when a snapshot is triggered, then passes the returned handle to `do_log_compaction_from()`,
+    /// while logs are still applied to the live state machine. A store backed by an LSM engine can implement it
+    /// cheaply with an engine snapshot.
+    ///
+    /// The default implementation returns `CheckpointHandle::blocking()`, i.e., the snapshot is built from the live
+    /// state machine by `do_log_compaction()`.
+    async fn begin_checkpoint(&self) -> Result<CheckpointHandle, StorageError> {
+        Ok(CheckpointHandle::blocking())
+    }
+
+    /// Perform log compaction from a checkpoint returned by `begin_checkpoint()`, returning a handle to the generated
+    /// snapshot.
+    ///
+    /// The snapshot must reflect exactly the state in the checkpoint, i.e., its `last_log_id` is
+    /// `checkpoint.last_applied()`, no matter how many logs are applied since the checkpoint is taken.
+    ///
+    /// The default implementation ignores the checkpoint and calls `do_log_compaction()`. It is correct only for the
+    /// handle returned by the default `begin_checkpoint()`, thus the two methods have to be overridden together.
+    ///
+    /// Errors returned from this method will be logged and retried.
+    async fn do_log_compaction_from(
+        &self,
+        checkpoint: CheckpointHandle,
+    ) -> Result<Snapshot<Self::SnapshotData>, StorageError> {
+        let _ = checkpoint;
+        self.do_log_compaction().await
+    }
+
+    /// Perform log compaction at `upto`, which has to be the last applied log id, returning a handle to the generated
+    /// snapshot.
+    ///
+    /// Unlike `do_log_compaction()`, which decides the breadth of the snapshot by itself, the snapshot built by it
+    /// covers exactly the logs upto `upto`, e.g., to build snapshots at the same log on every node for a backup.
+    ///
+    /// The default implementation takes a checkpoint with `begin_checkpoint()` and builds the snapshot from it with
+    /// `do_log_compaction_from()`. Thus it only supports `upto` being the last applied log in the checkpoint: any other
+    /// `upto`, either not yet applied or applied before the last one, is refused with
+    /// `StorageError::CompactTargetMismatch` before a snapshot is built. An impl that is able to build a snapshot at an
+    /// earlier log, e.g., with a versioned state machine, should override it.
+    ///
+    /// A store that does not implement `begin_checkpoint()` has no pinned view to build from: `upto` is checked
+    /// against `last_applied_state()`, and the snapshot covers the logs applied meanwhile, if there are any. Its
+    /// `last_log_id` tells.
+    async fn compact_to(&self, upto: LogId) -> Result<Snapshot<Self::SnapshotData>, StorageError> {
+        let checkpoint = self.begin_checkpoint().await?;
+
+        let last_applied = match checkpoint.last_applied() {
+            Some(x) => x,
+            None => self.last_applied_state().await?.0,
+        };
+
+        if upto != last_applied {
+            return Err(StorageError::CompactTargetMismatch { upto, last_applied });
+        }
+
+        self.do_log_compaction_from(checkpoint).await
+    }
+
     /// Create a new blank snapshot, returning a writable handle to the snapshot object.
     ///
     /// Raft will use this handle to receive snapshot data.
@@ -284,6 +562,14 @@ where
     /// ### snapshot
     /// A snapshot created from an earlier call to `begin_receiving_snapshot` which provided the snapshot.
     ///
+    /// If `meta.base_snapshot_id` is not `None`, the snapshot is a delta and should be applied onto the current state
+    /// machine. Raft only receives a delta snapshot if the current snapshot is its base.
+    ///
+    /// ### purge
+    /// The returned `StateMachineChanges::purge_upto` tells Raft upto which log the logs are subsumed by the snapshot.
+    /// Raft then purges them, no matter how many applied logs `Config::max_applied_log_to_keep` keeps. It is usually
+    /// `meta.last_log_id`.
+    ///
     /// Errors returned from this method will cause Raft to go into shutdown.
     async fn finalize_snapshot_installation(
         &self,
@@ -305,6 +591,25 @@ where
     ///
     /// Errors returned from this method will cause Raft to go into shutdown.
     async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData>>, StorageError>;
+
+    /// Get a readable handle to a full snapshot, i.e., one that does not depend on a base snapshot.
+    ///
+    /// It is used to replicate to a follower that can not install a delta snapshot because it does not have the base.
+    /// The default impl returns the current snapshot, which is correct for a store that never builds a delta snapshot.
+    async fn get_full_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData>>, StorageError> {
+        self.get_current_snapshot().await
+    }
+
+    /// Returns the metadata of every snapshot in the store, including the current one.
+    ///
+    /// Raft does not call it. It is for operators and tests to audit that old snapshots are cleaned up, e.g., only
+    /// the installed one is left after `finalize_snapshot_installation()`. A snapshot that is being built or received
+    /// should not be listed.
+    ///
+    /// The default implementation returns an empty list, i.e., the store does not support listing.
+    async fn list_snapshots(&self) -> Result<Vec<SnapshotMeta>, StorageError> {
+        Ok(vec![])
+    }
 }
 
 /// APIs for debugging a store.
@@ -128,6 +128,20 @@ pub enum StorageError {
         #[backtrace]
         source: StorageIOError,
     },
+
+    /// `RaftStorage::get_log_entries()` returned entries that do not exactly cover the requested range: a log is
+    /// missing, is at a wrong position, or is out of the range.
+    #[error("short read of logs [{start}, {end}): expect log index {expected:?}, got {got:?}")]
+    ShortRead {
+        start: u64,
+        end: u64,
+        expected: Option<u64>,
+        got: Option<u64>,
+    },
+
+    /// `RaftStorage::compact_to()` can not build a snapshot that covers exactly the logs upto `upto`.
+    #[error("can not compact to {upto}: last applied is {last_applied}")]
+    CompactTargetMismatch { upto: LogId, last_applied: LogId },
 }
 
 impl StorageError {
@@ -144,6 +158,15 @@ impl StorageError {
             _ => None,
         }
     }
+
+    /// Returns true if it is an io error caused by a `TransientStorageError`, i.e., the operation may succeed if it
+    /// is retried.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            StorageError::IO { source } => source.is_transient(),
+            _ => false,
+        }
+    }
 }
 
 /// Error that occurs when operating the store.
@@ -170,4 +193,18 @@ impl StorageIOError {
             backtrace: Backtrace::capture(),
         }
     }
+
+    /// Returns true if the source of this error is a `TransientStorageError`.
+    pub fn is_transient(&self) -> bool {
+        self.source.downcast_ref::<TransientStorageError>().is_some()
+    }
 }
+
+/// A storage failure that may go away if the operation is retried, e.g., a timeout of a network attached disk.
+///
+/// It is the source of a `StorageIOError`, wrapped in an `anyhow::Error`, e.g.,
+/// `StorageIOError::new(subject, verb, TransientStorageError(e).into())`, and raft finds it with `downcast_ref()`.
+/// See `Config::apply_retry`.
+#[derive(Debug, thiserror::Error)]
+#[error("transient storage error: {0}")]
+pub struct TransientStorageError(pub anyhow::Error);
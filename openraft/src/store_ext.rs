@@ -1,10 +1,13 @@
 use std::fmt::Debug;
 use std::marker::PhantomData;
 use std::ops::RangeBounds;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use std::sync::RwLock;
 
 use crate::async_trait::async_trait;
 use crate::raft::Entry;
+use crate::storage::CheckpointHandle;
 use crate::storage::HardState;
 use crate::storage::InitialState;
 use crate::storage::Snapshot;
@@ -27,6 +30,7 @@ use crate::Wrapper;
 /// And it provides more APIs.
 pub struct StoreExt<D, R, T> {
     defensive: RwLock<bool>,
+    last_applied_state_calls: AtomicU64,
     inner: T,
     p: PhantomData<(D, R)>,
 }
@@ -36,10 +40,16 @@ impl<D, R, T> StoreExt<D, R, T> {
     pub fn new(inner: T) -> Self {
         StoreExt {
             defensive: RwLock::new(false),
+            last_applied_state_calls: AtomicU64::new(0),
             inner,
             p: Default::default(),
         }
     }
+
+    /// Returns the number of times `last_applied_state()` has been called through this store.
+    pub fn last_applied_state_calls(&self) -> u64 {
+        self.last_applied_state_calls.load(Ordering::Relaxed)
+    }
 }
 
 impl<D, R, T> Wrapper<T> for StoreExt<D, R, T> {
@@ -91,6 +101,11 @@ where
         self.inner().last_membership_in_log(since_index).await
     }
 
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn get_committed_membership(&self) -> Result<Option<EffectiveMembership>, StorageError> {
+        self.inner().get_committed_membership().await
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
     async fn get_initial_state(&self) -> Result<InitialState, StorageError> {
         self.defensive_no_dirty_log().await?;
@@ -108,6 +123,21 @@ where
         self.inner().read_hard_state().await
     }
 
+    #[tracing::instrument(level = "trace", skip(self, meta))]
+    async fn save_node_metadata(&self, meta: &[u8]) -> Result<(), StorageError> {
+        self.inner().save_node_metadata(meta).await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn read_node_metadata(&self) -> Result<Option<Vec<u8>>, StorageError> {
+        self.inner().read_node_metadata().await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, data))]
+    async fn validate_entry(&self, data: &D) -> Result<(), StorageError> {
+        self.inner().validate_entry(data).await
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
     async fn get_log_entries<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
         &self,
@@ -122,6 +152,23 @@ where
         Ok(res)
     }
 
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn get_log_entries_rev<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
+        &self,
+        range: RNG,
+    ) -> Result<Vec<Entry<D>>, StorageError> {
+        self.defensive_nonempty_range(range.clone()).await?;
+
+        let res = self.inner().get_log_entries_rev(range.clone()).await?;
+
+        if self.is_defensive() {
+            let ascending = res.iter().rev().cloned().collect::<Vec<_>>();
+            self.defensive_range_hits_logs(range, &ascending).await?;
+        }
+
+        Ok(res)
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
     async fn try_get_log_entries<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
         &self,
@@ -137,6 +184,11 @@ where
         self.inner().try_get_log_entry(log_index).await
     }
 
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn get_log_id(&self, log_index: u64) -> Result<Option<LogId>, StorageError> {
+        self.inner().get_log_id(log_index).await
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
     async fn first_id_in_log(&self) -> Result<Option<LogId>, StorageError> {
         self.inner().first_id_in_log().await
@@ -152,8 +204,24 @@ where
         self.inner().last_id_in_log().await
     }
 
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn log_size_bytes(&self) -> Result<Option<u64>, StorageError> {
+        self.inner().log_size_bytes().await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn state_checksum_at(&self, log_id: LogId) -> Result<Option<u64>, StorageError> {
+        self.inner().state_checksum_at(log_id).await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, payload))]
+    async fn rewrite_log_entry(&self, log_id: LogId, payload: D) -> Result<bool, StorageError> {
+        self.inner().rewrite_log_entry(log_id, payload).await
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
     async fn last_applied_state(&self) -> Result<(LogId, Option<EffectiveMembership>), StorageError> {
+        self.last_applied_state_calls.fetch_add(1, Ordering::Relaxed);
         self.inner().last_applied_state().await
     }
 
@@ -178,6 +246,17 @@ where
         self.inner().append_to_log(entries).await
     }
 
+    #[tracing::instrument(level = "trace", skip(self, entries), fields(entries=%entries.summary()))]
+    async fn append_and_save_hard_state(&self, entries: &[&Entry<D>], hs: &HardState) -> Result<(), StorageError> {
+        self.defensive_incremental_hard_state(hs).await?;
+        self.defensive_nonempty_input(entries).await?;
+        self.defensive_consecutive_input(entries).await?;
+        self.defensive_append_log_index_is_last_plus_one(entries).await?;
+        self.defensive_append_log_id_gt_last(entries).await?;
+
+        self.inner().append_and_save_hard_state(entries, hs).await
+    }
+
     #[tracing::instrument(level = "trace", skip(self, entries), fields(entries=%entries.summary()))]
     async fn apply_to_state_machine(&self, entries: &[&Entry<D>]) -> Result<Vec<R>, StorageError> {
         self.defensive_nonempty_input(entries).await?;
@@ -192,6 +271,24 @@ where
         self.inner().do_log_compaction().await
     }
 
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn begin_checkpoint(&self) -> Result<CheckpointHandle, StorageError> {
+        self.inner().begin_checkpoint().await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn do_log_compaction_from(
+        &self,
+        checkpoint: CheckpointHandle,
+    ) -> Result<Snapshot<Self::SnapshotData>, StorageError> {
+        self.inner().do_log_compaction_from(checkpoint).await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn compact_to(&self, upto: LogId) -> Result<Snapshot<Self::SnapshotData>, StorageError> {
+        self.inner().compact_to(upto).await
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
     async fn begin_receiving_snapshot(&self) -> Result<Box<Self::SnapshotData>, StorageError> {
         self.inner().begin_receiving_snapshot().await
@@ -210,4 +307,14 @@ where
     async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData>>, StorageError> {
         self.inner().get_current_snapshot().await
     }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn get_full_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData>>, StorageError> {
+        self.inner().get_full_snapshot().await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn list_snapshots(&self) -> Result<Vec<SnapshotMeta>, StorageError> {
+        self.inner().list_snapshots().await
+    }
 }
@@ -0,0 +1,2078 @@
+//! A test suite for `RaftStorage` implementations, enabled by the `test-utils` feature.
+//!
+//! It codifies the contract of `RaftStorage` that raft core relies on but the type system can not express. A
+//! downstream crate runs it against its own store with a [`StoreBuilder`] that builds a new empty store for a node:
+//!
+//! ```ignore
+//! struct MyStoreBuilder {}
+//!
+//! #[async_trait]
+//! impl StoreBuilder<MyRequest, MyResponse, MyStore> for MyStoreBuilder {
+//!     async fn build(&self, id: NodeId) -> MyStore {
+//!         MyStore::open_temp(id).await
+//!     }
+//! }
+//!
+//! #[test]
+//! pub fn test_my_store() -> anyhow::Result<()> {
+//!     Suite::test_store(&MyStoreBuilder {})
+//! }
+//! ```
+//!
+//! `Suite::test_store_defensive()` additionally asserts that a store with defensive check enabled, e.g., one wrapped
+//! in a `StoreExt` with `set_defensive(true)`, rejects invalid input.
+//!
+//! The suite uses only blank and membership logs, so that it does not depend on the application data. A hard state is
+//! read back from the same store instance: durability across a restart depends on how a store is reopened, which is
+//! up to the store's own tests.
+
+use std::collections::Bound;
+use std::future::Future;
+use std::io::SeekFrom;
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+use maplit::btreeset;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncSeekExt;
+use tokio::io::AsyncWriteExt;
+
+use crate::raft::Entry;
+use crate::raft::EntryPayload;
+use crate::raft::Membership;
+use crate::storage::HardState;
+use crate::AppData;
+use crate::AppDataResponse;
+use crate::CheckpointHandle;
+use crate::DefensiveError;
+use crate::EffectiveMembership;
+use crate::ErrorSubject;
+use crate::LogId;
+use crate::NodeId;
+use crate::RaftStorage;
+use crate::StorageError;
+use crate::Violation;
+
+const NODE_ID: u64 = 0;
+
+/// Builds a new empty store for the test suite.
+#[async_trait]
+pub trait StoreBuilder<D, R, S>: Send + Sync
+where
+    D: AppData,
+    R: AppDataResponse,
+    S: RaftStorage<D, R>,
+{
+    async fn build(&self, id: NodeId) -> S;
+}
+
+/// Block until a future is finished.
+/// The future will be running in a clean tokio runtime, to prevent an unfinished task affecting the test.
+pub fn run_fut<F>(f: F) -> anyhow::Result<()>
+where F: Future<Output = anyhow::Result<()>> {
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(f)?;
+    Ok(())
+}
+
+/// Test suite to ensure a `RaftStore` impl works as expected.
+pub struct Suite<D, R, S, B>
+where
+    D: AppData,
+    R: AppDataResponse,
+    S: RaftStorage<D, R>,
+    B: StoreBuilder<D, R, S>,
+{
+    p: PhantomData<(D, R, S, B)>,
+}
+
+impl<D, R, S, B> Suite<D, R, S, B>
+where
+    D: AppData,
+    R: AppDataResponse,
+    S: RaftStorage<D, R>,
+    B: StoreBuilder<D, R, S>,
+{
+    pub fn test_store(builder: &B) -> anyhow::Result<()> {
+        run_fut(Self::last_membership_in_log_initial(builder))?;
+        run_fut(Self::last_membership_in_log(builder))?;
+        run_fut(Self::last_membership_in_log_empty(builder))?;
+        run_fut(Self::get_membership_initial(builder))?;
+        run_fut(Self::get_membership_from_log_and_sm(builder))?;
+        run_fut(Self::get_committed_membership_initial(builder))?;
+        run_fut(Self::get_committed_membership_ignores_log(builder))?;
+        run_fut(Self::get_initial_state_default(builder))?;
+        run_fut(Self::get_initial_state_membership_from_log_and_sm(builder))?;
+        run_fut(Self::get_initial_state_with_state(builder))?;
+        run_fut(Self::get_initial_state_last_log_gt_sm(builder))?;
+        run_fut(Self::get_initial_state_last_log_lt_sm(builder))?;
+        run_fut(Self::save_hard_state(builder))?;
+        run_fut(Self::save_node_metadata(builder))?;
+        run_fut(Self::get_log_entries(builder))?;
+        run_fut(Self::get_log_entries_rev(builder))?;
+        run_fut(Self::try_get_log_entry(builder))?;
+        run_fut(Self::get_log_id(builder))?;
+        run_fut(Self::initial_logs(builder))?;
+        run_fut(Self::first_known_log_id(builder))?;
+        run_fut(Self::first_id_in_log(builder))?;
+        run_fut(Self::last_id_in_log(builder))?;
+        run_fut(Self::last_applied_state(builder))?;
+        run_fut(Self::delete_logs_from(builder))?;
+        run_fut(Self::append_to_log(builder))?;
+        run_fut(Self::append_and_save_hard_state(builder))?;
+        run_fut(Self::apply_single(builder))?;
+        run_fut(Self::apply_multi(builder))?;
+        run_fut(Self::compact_to(builder))?;
+        run_fut(Self::compact_from_checkpoint(builder))?;
+        run_fut(Self::snapshot_round_trip(builder))?;
+
+        Ok(())
+    }
+
+    pub async fn last_membership_in_log_initial(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let membership = store.last_membership_in_log(0).await?;
+
+        assert!(membership.is_none());
+
+        Ok(())
+    }
+
+    pub async fn last_membership_in_log_empty(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        tracing::info!("--- only the sentinel log, it is not a real log");
+        {
+            let log_id = store.last_id_in_log().await?;
+            assert!(log_id.is_sentinel());
+            assert_eq!(None, log_id.to_option());
+
+            let mem = store.last_membership_in_log(0).await?;
+            assert!(mem.is_none());
+        }
+
+        tracing::info!("--- no log at all");
+        {
+            store.delete_logs_from(0..).await?;
+
+            assert_eq!(None, store.first_id_in_log().await?);
+            assert!(store.last_id_in_log().await?.is_sentinel());
+
+            let mem = store.last_membership_in_log(0).await?;
+            assert!(mem.is_none());
+
+            let mem = store.last_membership_in_log(1).await?;
+            assert!(mem.is_none());
+        }
+
+        Ok(())
+    }
+
+    pub async fn last_membership_in_log(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        tracing::info!("--- no log, do not read membership from state machine");
+        {
+            store
+                .apply_to_state_machine(&[
+                    &Entry {
+                        log_id: LogId { term: 1, index: 1 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Blank,
+                    },
+                    &Entry {
+                        log_id: LogId { term: 1, index: 2 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Membership(Membership::new_single(btreeset! {3,4,5})),
+                    },
+                ])
+                .await?;
+
+            let mem = store.last_membership_in_log(0).await?;
+
+            assert!(mem.is_none());
+        }
+
+        tracing::info!("--- membership presents in log, smaller than last_applied, read from log");
+        {
+            store
+                .append_to_log(&[&Entry {
+                    log_id: (1, 1).into(),
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2,3})),
+                }])
+                .await?;
+
+            let mem = store.last_membership_in_log(0).await?;
+            let mem = mem.unwrap();
+            assert_eq!(Membership::new_single(btreeset! {1, 2, 3}), mem.membership,);
+
+            let mem = store.last_membership_in_log(1).await?;
+            let mem = mem.unwrap();
+            assert_eq!(Membership::new_single(btreeset! {1, 2, 3}), mem.membership,);
+
+            let mem = store.last_membership_in_log(2).await?;
+            assert!(mem.is_none());
+        }
+
+        tracing::info!("--- membership presents in log and > sm.last_applied, read from log");
+        {
+            store
+                .append_to_log(&[
+                    &Entry {
+                        log_id: LogId { term: 1, index: 3 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Membership(Membership::new_single(btreeset! {7,8,9})),
+                    },
+                    &Entry {
+                        log_id: LogId { term: 1, index: 4 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Blank,
+                    },
+                ])
+                .await?;
+
+            let mem = store.last_membership_in_log(0).await?;
+            let mem = mem.unwrap();
+
+            assert_eq!(Membership::new_single(btreeset! {7,8,9},), mem.membership,);
+        }
+
+        tracing::info!("--- membership presents in log and > sm.last_applied, read from log but since_index is greater than the last");
+        {
+            let mem = store.last_membership_in_log(4).await?;
+            assert!(mem.is_none());
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_membership_initial(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let membership = store.get_membership().await?;
+
+        assert!(membership.is_none());
+
+        Ok(())
+    }
+
+    pub async fn get_membership_from_log_and_sm(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        tracing::info!("--- no log, read membership from state machine");
+        {
+            store
+                .apply_to_state_machine(&[
+                    &Entry {
+                        log_id: LogId { term: 1, index: 1 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Blank,
+                    },
+                    &Entry {
+                        log_id: LogId { term: 1, index: 2 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Membership(Membership::new_single(btreeset! {3,4,5})),
+                    },
+                ])
+                .await?;
+
+            let mem = store.get_membership().await?;
+            let mem = mem.unwrap();
+
+            assert_eq!(Membership::new_single(btreeset! {3,4,5}), mem.membership,);
+        }
+
+        tracing::info!("--- membership presents in log, but smaller than last_applied, read from state machine");
+        {
+            store
+                .append_to_log(&[&Entry {
+                    log_id: (1, 1).into(),
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2,3})),
+                }])
+                .await?;
+
+            let mem = store.get_membership().await?;
+
+            let mem = mem.unwrap();
+
+            assert_eq!(Membership::new_single(btreeset! {3, 4, 5}), mem.membership,);
+        }
+
+        tracing::info!("--- membership presents in log and > sm.last_applied, read from log");
+        {
+            store
+                .append_to_log(&[&Entry {
+                    log_id: LogId { term: 1, index: 3 },
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Membership(Membership::new_single(btreeset! {7,8,9})),
+                }])
+                .await?;
+
+            let mem = store.get_membership().await?;
+
+            let mem = mem.unwrap();
+
+            assert_eq!(Membership::new_single(btreeset! {7,8,9},), mem.membership,);
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_committed_membership_initial(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let membership = store.get_committed_membership().await?;
+
+        assert!(membership.is_none());
+
+        Ok(())
+    }
+
+    pub async fn get_committed_membership_ignores_log(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        tracing::info!("--- apply a membership to state machine");
+        {
+            let entries = [
+                &Entry {
+                    log_id: LogId { term: 1, index: 1 },
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+                &Entry {
+                    log_id: LogId { term: 1, index: 2 },
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2,3})),
+                },
+            ];
+            store.append_to_log(&entries).await?;
+            store.apply_to_state_machine(&entries).await?;
+
+            let mem = store.get_committed_membership().await?.unwrap();
+
+            assert_eq!(LogId { term: 1, index: 2 }, mem.log_id);
+            assert_eq!(Membership::new_single(btreeset! {1,2,3}), mem.membership);
+        }
+
+        tracing::info!("--- an uncommitted joint membership in log is not returned");
+        {
+            store
+                .append_to_log(&[&Entry {
+                    log_id: LogId { term: 1, index: 3 },
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Membership(Membership::new_multi(vec![
+                        btreeset! {1,2,3},
+                        btreeset! {3,4,5},
+                    ])),
+                }])
+                .await?;
+
+            let mem = store.get_membership().await?.unwrap();
+            assert_eq!(LogId { term: 1, index: 3 }, mem.log_id);
+            assert!(mem.membership.is_joint());
+
+            let mem = store.get_committed_membership().await?.unwrap();
+            assert_eq!(LogId { term: 1, index: 2 }, mem.log_id);
+            assert_eq!(Membership::new_single(btreeset! {1,2,3}), mem.membership);
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_initial_state_default(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let expected_hs = HardState {
+            current_term: 0,
+            voted_for: None,
+        };
+
+        let initial = store.get_initial_state().await?;
+
+        assert_eq!(
+            initial.last_log_id,
+            LogId { term: 0, index: 0 },
+            "unexpected default value for last log"
+        );
+        assert_eq!(
+            initial.last_applied,
+            LogId { term: 0, index: 0 },
+            "unexpected value for last applied log"
+        );
+
+        assert_eq!(
+            Membership::new_single(btreeset! {NODE_ID}),
+            initial.last_membership.membership,
+        );
+
+        assert_eq!(
+            initial.hard_state, expected_hs,
+            "unexpected value for default hard state"
+        );
+        Ok(())
+    }
+
+    pub async fn get_initial_state_with_state(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+        Self::default_hard_state(&store).await?;
+
+        store
+            .append_to_log(&[&Entry {
+                log_id: (3, 2).into(),
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            }])
+            .await?;
+
+        store
+            .apply_to_state_machine(&[&Entry {
+                log_id: LogId { term: 3, index: 1 },
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            }])
+            .await?;
+
+        let initial = store.get_initial_state().await?;
+
+        assert_eq!(
+            initial.last_log_id,
+            LogId { term: 3, index: 2 },
+            "state machine has higher log"
+        );
+        assert_eq!(
+            initial.last_applied,
+            LogId { term: 3, index: 1 },
+            "unexpected value for last applied log"
+        );
+        assert_eq!(
+            HardState {
+                current_term: 1,
+                voted_for: Some(NODE_ID),
+            },
+            initial.hard_state,
+            "unexpected value for default hard state"
+        );
+        Ok(())
+    }
+
+    pub async fn get_initial_state_membership_from_log_and_sm(builder: &B) -> anyhow::Result<()> {
+        // It should never return membership from logs that are included in state machine present.
+
+        let store = builder.build(NODE_ID).await;
+        Self::default_hard_state(&store).await?;
+
+        // copy the test from get_membership_config
+
+        tracing::info!("--- no log, read membership from state machine");
+        {
+            store
+                .apply_to_state_machine(&[
+                    &Entry {
+                        log_id: LogId { term: 1, index: 1 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Blank,
+                    },
+                    &Entry {
+                        log_id: LogId { term: 1, index: 2 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Membership(Membership::new_single(btreeset! {3,4,5})),
+                    },
+                ])
+                .await?;
+
+            let initial = store.get_initial_state().await?;
+
+            assert_eq!(
+                Membership::new_single(btreeset! {3,4,5}),
+                initial.last_membership.membership,
+            );
+        }
+
+        tracing::info!("--- membership presents in log, but smaller than last_applied, read from state machine");
+        {
+            store
+                .append_to_log(&[&Entry {
+                    log_id: (1, 1).into(),
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2,3})),
+                }])
+                .await?;
+
+            let initial = store.get_initial_state().await?;
+
+            assert_eq!(
+                Membership::new_single(btreeset! {3,4,5}),
+                initial.last_membership.membership,
+            );
+        }
+
+        tracing::info!("--- membership presents in log and > sm.last_applied, read from log");
+        {
+            store
+                .append_to_log(&[&Entry {
+                    log_id: LogId { term: 1, index: 3 },
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2,3})),
+                }])
+                .await?;
+
+            let initial = store.get_initial_state().await?;
+
+            assert_eq!(
+                Membership::new_single(btreeset! {1,2,3}),
+                initial.last_membership.membership,
+            );
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_initial_state_last_log_gt_sm(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+        Self::default_hard_state(&store).await?;
+
+        store
+            .append_to_log(&[&Entry {
+                log_id: (2, 1).into(),
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            }])
+            .await?;
+
+        store
+            .apply_to_state_machine(&[
+                &Entry {
+                    log_id: LogId { term: 1, index: 1 },
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+                &Entry {
+                    log_id: LogId { term: 1, index: 2 },
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+            ])
+            .await?;
+
+        let initial = store.get_initial_state().await?;
+
+        assert_eq!(
+            initial.last_log_id,
+            LogId { term: 2, index: 1 },
+            "state machine has higher log"
+        );
+        Ok(())
+    }
+
+    pub async fn get_initial_state_last_log_lt_sm(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+        Self::default_hard_state(&store).await?;
+
+        store
+            .append_to_log(&[&Entry {
+                log_id: (1, 2).into(),
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            }])
+            .await?;
+
+        store
+            .apply_to_state_machine(&[&Entry {
+                log_id: LogId { term: 3, index: 1 },
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            }])
+            .await?;
+
+        let initial = store.get_initial_state().await?;
+
+        assert_eq!(
+            initial.last_log_id,
+            LogId { term: 3, index: 1 },
+            "state machine has higher log"
+        );
+        Ok(())
+    }
+
+    pub async fn save_hard_state(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        store
+            .save_hard_state(&HardState {
+                current_term: 100,
+                voted_for: Some(NODE_ID),
+            })
+            .await?;
+
+        let post = store.get_initial_state().await?;
+
+        assert_eq!(
+            HardState {
+                current_term: 100,
+                voted_for: Some(NODE_ID),
+            },
+            post.hard_state,
+        );
+        Ok(())
+    }
+
+    pub async fn save_node_metadata(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        assert_eq!(None, store.read_node_metadata().await?);
+
+        store.save_node_metadata(b"dc-1").await?;
+        assert_eq!(Some(b"dc-1".to_vec()), store.read_node_metadata().await?);
+
+        store.save_node_metadata(b"dc-2").await?;
+        assert_eq!(Some(b"dc-2".to_vec()), store.read_node_metadata().await?);
+
+        Ok(())
+    }
+
+    pub async fn get_log_entries(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+        Self::feed_10_logs_vote_self(&store).await?;
+
+        tracing::info!("--- get start == stop");
+        {
+            let logs = store.get_log_entries(3..3).await?;
+            assert_eq!(logs.len(), 0, "expected no logs to be returned");
+        }
+
+        tracing::info!("--- get start < stop");
+        {
+            let logs = store.get_log_entries(5..7).await?;
+
+            assert_eq!(logs.len(), 2);
+            assert_eq!(logs[0].log_id, (1, 5).into());
+            assert_eq!(logs[1].log_id, (1, 6).into());
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_log_entries_rev(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+        Self::feed_10_logs_vote_self(&store).await?;
+
+        tracing::info!("--- get start == stop");
+        {
+            let logs = store.get_log_entries_rev(3..3).await?;
+            assert_eq!(logs.len(), 0, "expected no logs to be returned");
+        }
+
+        tracing::info!("--- get start < stop, in descending index order");
+        {
+            let logs = store.get_log_entries_rev(5..8).await?;
+
+            let indexes = logs.iter().map(|x| x.log_id.index).collect::<Vec<_>>();
+            assert_eq!(vec![7, 6, 5], indexes);
+        }
+
+        tracing::info!("--- the same entries as get_log_entries() in reverse");
+        {
+            let mut want = store.get_log_entries(..).await?.iter().map(|x| x.log_id).collect::<Vec<_>>();
+            want.reverse();
+
+            let got = store.get_log_entries_rev(..).await?.iter().map(|x| x.log_id).collect::<Vec<_>>();
+            assert_eq!(want, got);
+        }
+
+        Ok(())
+    }
+
+    pub async fn try_get_log_entry(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+        Self::feed_10_logs_vote_self(&store).await?;
+
+        store.delete_logs_from(0..=0).await?;
+
+        let ent = store.try_get_log_entry(3).await?;
+        assert_eq!(Some(LogId { term: 1, index: 3 }), ent.map(|x| x.log_id));
+
+        let ent = store.try_get_log_entry(0).await?;
+        assert_eq!(None, ent.map(|x| x.log_id));
+
+        let ent = store.try_get_log_entry(11).await?;
+        assert_eq!(None, ent.map(|x| x.log_id));
+
+        Ok(())
+    }
+
+    pub async fn get_log_id(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+        Self::feed_10_logs_vote_self(&store).await?;
+
+        store.delete_logs_from(0..=0).await?;
+
+        assert_eq!(Some(LogId { term: 1, index: 3 }), store.get_log_id(3).await?);
+        assert_eq!(None, store.get_log_id(0).await?);
+        assert_eq!(None, store.get_log_id(11).await?);
+
+        Ok(())
+    }
+
+    pub async fn initial_logs(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let ent = store.try_get_log_entry(0).await?.unwrap();
+        assert_eq!(
+            LogId { term: 0, index: 0 },
+            ent.log_id,
+            "store initialized with a log at 0"
+        );
+
+        tracing::info!("--- no logs, return None");
+        {
+            store.delete_logs_from(..).await?;
+
+            let ent = store.try_get_log_entry(0).await?;
+            assert!(ent.is_none());
+        }
+
+        Ok(())
+    }
+
+    pub async fn first_known_log_id(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let log_id = store.first_known_log_id().await?;
+        assert_eq!(LogId::new(0, 0), log_id, "store initialized with a log at 0");
+
+        tracing::info!("--- returns the min id");
+        {
+            store
+                .append_to_log(&[
+                    &Entry {
+                        log_id: LogId { term: 1, index: 1 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Blank,
+                    },
+                    &Entry {
+                        log_id: LogId { term: 1, index: 2 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Blank,
+                    },
+                ])
+                .await?;
+
+            store.delete_logs_from(0..2).await?;
+
+            // NOTE: it assumes non applied logs always exist.
+            let log_id = store.first_known_log_id().await?;
+            assert_eq!(LogId::new(0, 0), log_id, "last_applied is 0-0");
+
+            store
+                .apply_to_state_machine(&[&Entry {
+                    log_id: LogId { term: 1, index: 1 },
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                }])
+                .await?;
+            let log_id = store.first_known_log_id().await?;
+            assert_eq!(LogId::new(1, 1), log_id);
+
+            store
+                .apply_to_state_machine(&[&Entry {
+                    log_id: LogId { term: 1, index: 2 },
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                }])
+                .await?;
+            let log_id = store.first_known_log_id().await?;
+            assert_eq!(LogId::new(1, 2), log_id);
+
+            store
+                .apply_to_state_machine(&[&Entry {
+                    log_id: LogId { term: 1, index: 3 },
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                }])
+                .await?;
+            let log_id = store.first_known_log_id().await?;
+            assert_eq!(LogId::new(1, 2), log_id, "least id is in log");
+        }
+
+        Ok(())
+    }
+
+    pub async fn first_id_in_log(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let log_id = store.first_id_in_log().await?;
+        assert_eq!(Some(LogId::new(0, 0)), log_id, "store initialized with a log at 0");
+
+        tracing::info!("--- only logs");
+        {
+            store
+                .append_to_log(&[
+                    &Entry {
+                        log_id: LogId { term: 1, index: 1 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Blank,
+                    },
+                    &Entry {
+                        log_id: LogId { term: 1, index: 2 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Blank,
+                    },
+                ])
+                .await?;
+
+            let log_id = store.first_id_in_log().await?;
+            assert_eq!(Some(LogId::new(0, 0)), log_id);
+
+            store.delete_logs_from(0..1).await?;
+
+            let log_id = store.first_id_in_log().await?;
+            assert_eq!(Some(LogId::new(1, 1)), log_id);
+        }
+
+        tracing::info!("--- no logs, return default");
+        {
+            store.delete_logs_from(..).await?;
+
+            let log_id = store.first_id_in_log().await?;
+            assert_eq!(None, log_id);
+        }
+
+        Ok(())
+    }
+
+    pub async fn last_id_in_log(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let log_id = store.last_id_in_log().await?;
+        assert_eq!(LogId { term: 0, index: 0 }, log_id);
+
+        tracing::info!("--- only logs");
+        {
+            store
+                .append_to_log(&[
+                    &Entry {
+                        log_id: LogId { term: 1, index: 1 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Blank,
+                    },
+                    &Entry {
+                        log_id: LogId { term: 1, index: 2 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Blank,
+                    },
+                ])
+                .await?;
+
+            let log_id = store.last_id_in_log().await?;
+            assert_eq!(LogId { term: 1, index: 2 }, log_id);
+        }
+
+        tracing::info!("--- last id in logs < last applied id in sm, only return the id in logs");
+        {
+            store
+                .apply_to_state_machine(&[&Entry {
+                    log_id: LogId { term: 1, index: 3 },
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                }])
+                .await?;
+            let log_id = store.last_id_in_log().await?;
+            assert_eq!(LogId { term: 1, index: 2 }, log_id);
+        }
+
+        tracing::info!("--- no logs, return default");
+        {
+            store.delete_logs_from(..).await?;
+
+            let log_id = store.last_id_in_log().await?;
+            assert_eq!(LogId { term: 0, index: 0 }, log_id);
+        }
+
+        Ok(())
+    }
+
+    pub async fn last_applied_state(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let (applied, membership) = store.last_applied_state().await?;
+        assert_eq!(LogId { term: 0, index: 0 }, applied);
+        assert_eq!(None, membership);
+
+        tracing::info!("--- with last_applied and last_membership");
+        {
+            store
+                .apply_to_state_machine(&[&Entry {
+                    log_id: LogId { term: 1, index: 3 },
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2})),
+                }])
+                .await?;
+
+            let (applied, membership) = store.last_applied_state().await?;
+            assert_eq!(LogId { term: 1, index: 3 }, applied);
+            assert_eq!(
+                Some(EffectiveMembership {
+                    log_id: LogId { term: 1, index: 3 },
+                    membership: Membership::new_single(btreeset! {1,2})
+                }),
+                membership
+            );
+        }
+
+        tracing::info!("--- no logs, return default");
+        {
+            store
+                .apply_to_state_machine(&[&Entry {
+                    log_id: LogId { term: 1, index: 5 },
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                }])
+                .await?;
+
+            let (applied, membership) = store.last_applied_state().await?;
+            assert_eq!(LogId { term: 1, index: 5 }, applied);
+            assert_eq!(
+                Some(EffectiveMembership {
+                    log_id: LogId { term: 1, index: 3 },
+                    membership: Membership::new_single(btreeset! {1,2})
+                }),
+                membership
+            );
+        }
+
+        Ok(())
+    }
+
+    pub async fn delete_logs_from(builder: &B) -> anyhow::Result<()> {
+        tracing::info!("--- delete start == stop");
+        {
+            let store = builder.build(NODE_ID).await;
+            Self::feed_10_logs_vote_self(&store).await?;
+
+            store.delete_logs_from(1..1).await?;
+
+            let logs = store.get_log_entries(1..11).await?;
+            assert_eq!(logs.len(), 10, "expected all (10) logs to be preserved");
+        }
+
+        tracing::info!("--- delete start < stop");
+        {
+            let store = builder.build(NODE_ID).await;
+            Self::feed_10_logs_vote_self(&store).await?;
+
+            store.delete_logs_from(..=0).await?;
+
+            store.delete_logs_from(1..4).await?;
+
+            let logs = store.get_log_entries(0..100).await?;
+            assert_eq!(logs.len(), 7);
+            assert_eq!(logs[0].log_id.index, 4);
+        }
+
+        tracing::info!("--- delete start < large stop");
+        {
+            let store = builder.build(NODE_ID).await;
+            Self::feed_10_logs_vote_self(&store).await?;
+
+            store.delete_logs_from(..=0).await?;
+
+            store.delete_logs_from(1..1000).await?;
+            let logs = store.get_log_entries(0..).await?;
+
+            assert_eq!(logs.len(), 0);
+        }
+
+        tracing::info!("--- delete start, None");
+        {
+            let store = builder.build(NODE_ID).await;
+            Self::feed_10_logs_vote_self(&store).await?;
+
+            store.delete_logs_from(..=0).await?;
+
+            store.delete_logs_from(1..).await?;
+            let logs = store.get_log_entries(0..100).await?;
+
+            assert_eq!(logs.len(), 0);
+        }
+
+        Ok(())
+    }
+
+    pub async fn append_to_log(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+        Self::feed_10_logs_vote_self(&store).await?;
+
+        store.delete_logs_from(..=0).await?;
+
+        store
+            .append_to_log(&[&Entry {
+                log_id: (2, 10).into(),
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            }])
+            .await?;
+
+        let l = store.get_log_entries(0..).await?.len();
+        let last = store.get_log_entries(0..).await?.last().unwrap().clone();
+
+        assert_eq!(l, 10, "expected 10 entries to exist in the log");
+        assert_eq!(last.log_id, (2, 10).into(), "unexpected log id");
+        Ok(())
+    }
+
+    pub async fn append_and_save_hard_state(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+        Self::feed_10_logs_vote_self(&store).await?;
+
+        let hs = HardState {
+            current_term: 2,
+            voted_for: None,
+        };
+
+        store
+            .append_and_save_hard_state(
+                &[&Entry {
+                    log_id: (2, 11).into(),
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                }],
+                &hs,
+            )
+            .await?;
+
+        assert_eq!(Some(hs), store.read_hard_state().await?);
+        assert_eq!(LogId::new(2, 11), store.last_id_in_log().await?);
+        Ok(())
+    }
+
+    pub async fn apply_single(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let entry = Entry {
+            log_id: LogId { term: 3, index: 1 },
+
+            timestamp_ms: 0,
+            payload: EntryPayload::Blank,
+        };
+
+        let resp = store.apply_to_state_machine(&[&entry]).await?;
+        assert_eq!(1, resp.len(), "expected a response for every log");
+
+        let (last_applied, _) = store.last_applied_state().await?;
+
+        assert_eq!(
+            last_applied,
+            LogId { term: 3, index: 1 },
+            "expected last_applied_log to be 1, got {}",
+            last_applied
+        );
+        Ok(())
+    }
+
+    pub async fn compact_to(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let entries = (1..=3)
+            .map(|i| Entry {
+                log_id: LogId { term: 1, index: i },
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            })
+            .collect::<Vec<_>>();
+        let entry_refs = entries.iter().collect::<Vec<_>>();
+
+        store.append_to_log(&entry_refs).await?;
+        store.apply_to_state_machine(&entry_refs[..2]).await?;
+
+        tracing::info!("--- compact to the last applied log");
+        {
+            let snapshot = store.compact_to(LogId { term: 1, index: 2 }).await?;
+            assert_eq!(LogId { term: 1, index: 2 }, snapshot.meta.last_log_id);
+
+            let current = store.get_current_snapshot().await?.unwrap();
+            assert_eq!(LogId { term: 1, index: 2 }, current.meta.last_log_id);
+        }
+
+        tracing::info!("--- compact to a log that is not applied is refused");
+        {
+            let res = store.compact_to(LogId { term: 1, index: 3 }).await;
+            match res {
+                Err(StorageError::CompactTargetMismatch { upto, last_applied }) => {
+                    assert_eq!(LogId { term: 1, index: 3 }, upto);
+                    assert_eq!(LogId { term: 1, index: 2 }, last_applied);
+                }
+                _ => panic!("expect CompactTargetMismatch, got: {:?}", res.map(|x| x.meta)),
+            }
+
+            let current = store.get_current_snapshot().await?.unwrap();
+            assert_eq!(
+                LogId { term: 1, index: 2 },
+                current.meta.last_log_id,
+                "no snapshot is built for a refused target"
+            );
+        }
+
+        tracing::info!("--- compact to a log that is applied but is not the last applied is refused");
+        {
+            store.apply_to_state_machine(&entry_refs[2..]).await?;
+
+            let res = store.compact_to(LogId { term: 1, index: 2 }).await;
+            assert!(
+                matches!(res, Err(StorageError::CompactTargetMismatch { .. })),
+                "expect CompactTargetMismatch"
+            );
+
+            let current = store.get_current_snapshot().await?.unwrap();
+            assert_eq!(LogId { term: 1, index: 2 }, current.meta.last_log_id);
+
+            let snapshot = store.compact_to(LogId { term: 1, index: 3 }).await?;
+            assert_eq!(LogId { term: 1, index: 3 }, snapshot.meta.last_log_id);
+        }
+
+        Ok(())
+    }
+
+    pub async fn compact_from_checkpoint(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let entries = (1..=3)
+            .map(|i| Entry {
+                log_id: LogId { term: 1, index: i },
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            })
+            .collect::<Vec<_>>();
+        let entry_refs = entries.iter().collect::<Vec<_>>();
+
+        store.append_to_log(&entry_refs).await?;
+        store.apply_to_state_machine(&entry_refs[..2]).await?;
+
+        tracing::info!("--- the snapshot reflects the checkpoint, not the logs applied after it");
+        {
+            let checkpoint = store.begin_checkpoint().await?;
+            assert_eq!(Some(LogId { term: 1, index: 2 }), checkpoint.last_applied());
+
+            store.apply_to_state_machine(&entry_refs[2..]).await?;
+
+            let snapshot = store.do_log_compaction_from(checkpoint).await?;
+            assert_eq!(LogId { term: 1, index: 2 }, snapshot.meta.last_log_id);
+
+            let current = store.get_current_snapshot().await?.unwrap();
+            assert_eq!(LogId { term: 1, index: 2 }, current.meta.last_log_id);
+        }
+
+        tracing::info!("--- a handle without a view builds from the live state machine");
+        {
+            let snapshot = store.do_log_compaction_from(CheckpointHandle::blocking()).await?;
+            assert_eq!(LogId { term: 1, index: 3 }, snapshot.meta.last_log_id);
+        }
+
+        Ok(())
+    }
+
+    pub async fn apply_multi(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let entries = vec![
+            Entry {
+                log_id: LogId { term: 3, index: 1 },
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            },
+            Entry {
+                log_id: LogId { term: 3, index: 2 },
+                timestamp_ms: 0,
+                payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2})),
+            },
+            Entry {
+                log_id: LogId { term: 3, index: 3 },
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            },
+        ];
+
+        let resp = store.apply_to_state_machine(&entries.iter().collect::<Vec<_>>()).await?;
+        assert_eq!(3, resp.len(), "expected a response for every log");
+
+        let (last_applied, membership) = store.last_applied_state().await?;
+
+        assert_eq!(
+            last_applied,
+            LogId { term: 3, index: 3 },
+            "expected last_applied_log to be 3, got {}",
+            last_applied
+        );
+        assert_eq!(
+            Some(EffectiveMembership {
+                log_id: LogId { term: 3, index: 2 },
+                membership: Membership::new_single(btreeset! {1,2}),
+            }),
+            membership,
+            "a blank log does not change the last applied membership"
+        );
+        Ok(())
+    }
+
+    pub async fn snapshot_round_trip(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let entries = vec![
+            Entry {
+                log_id: LogId { term: 1, index: 1 },
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            },
+            Entry {
+                log_id: LogId { term: 1, index: 2 },
+                timestamp_ms: 0,
+                payload: EntryPayload::Membership(Membership::new_single(btreeset! {NODE_ID, 1})),
+            },
+            Entry {
+                log_id: LogId { term: 1, index: 3 },
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            },
+        ];
+        let entry_refs = entries.iter().collect::<Vec<_>>();
+
+        store.append_to_log(&entry_refs).await?;
+        store.apply_to_state_machine(&entry_refs).await?;
+
+        tracing::info!("--- build a snapshot, which is the current snapshot");
+        let (meta, data) = {
+            let snapshot = store.do_log_compaction().await?;
+            assert_eq!(LogId { term: 1, index: 3 }, snapshot.meta.last_log_id);
+
+            let mut current = store.get_current_snapshot().await?.expect("a snapshot is built");
+            assert_eq!(snapshot.meta, current.meta);
+
+            let mut data = vec![];
+            current.snapshot.seek(SeekFrom::Start(0)).await?;
+            current.snapshot.read_to_end(&mut data).await?;
+
+            (current.meta, data)
+        };
+
+        tracing::info!("--- install the snapshot on another store");
+        {
+            let other = builder.build(1).await;
+
+            let mut snapshot = other.begin_receiving_snapshot().await?;
+            snapshot.write_all(&data).await?;
+            snapshot.shutdown().await?;
+
+            let changes = other.finalize_snapshot_installation(&meta, snapshot).await?;
+            assert!(changes.is_snapshot);
+            assert_eq!(Some(meta.last_log_id), changes.last_applied);
+
+            assert_eq!(store.last_applied_state().await?, other.last_applied_state().await?);
+
+            let current = other.get_current_snapshot().await?.expect("the installed snapshot is the current one");
+            assert_eq!(meta.last_log_id, current.meta.last_log_id);
+            assert_eq!(meta.snapshot_id, current.meta.snapshot_id);
+        }
+
+        Ok(())
+    }
+
+    pub async fn feed_10_logs_vote_self(sto: &S) -> anyhow::Result<()> {
+        for i in 1..=10 {
+            sto.append_to_log(&[&Entry {
+                log_id: (1, i).into(),
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            }])
+            .await?;
+        }
+
+        Self::default_hard_state(sto).await?;
+
+        Ok(())
+    }
+
+    pub async fn default_hard_state(sto: &S) -> anyhow::Result<()> {
+        sto.save_hard_state(&HardState {
+            current_term: 1,
+            voted_for: Some(NODE_ID),
+        })
+        .await?;
+
+        Ok(())
+    }
+}
+
+// Defensive test:
+// If a RaftStore impl support defensive check, enable it and check if it returns errors when abnormal input is seen.
+// A RaftStore with defensive check is able to expose bugs in raft core.
+impl<D, R, S, B> Suite<D, R, S, B>
+where
+    D: AppData,
+    R: AppDataResponse,
+    S: RaftStorage<D, R>,
+    B: StoreBuilder<D, R, S>,
+{
+    pub fn test_store_defensive(builder: &B) -> anyhow::Result<()> {
+        run_fut(Self::df_get_membership_config_dirty_log(builder))?;
+        run_fut(Self::df_get_initial_state_dirty_log(builder))?;
+        run_fut(Self::df_save_hard_state_ascending(builder))?;
+        run_fut(Self::df_get_log_entries(builder))?;
+        run_fut(Self::df_delete_logs_from_nonempty_range(builder))?;
+        run_fut(Self::df_append_to_log_nonempty_input(builder))?;
+        run_fut(Self::df_append_to_log_nonconsecutive_input(builder))?;
+        run_fut(Self::df_append_to_log_term_descending_input(builder))?;
+        run_fut(Self::df_append_and_save_hard_state_nonconsecutive_input(builder))?;
+        run_fut(Self::df_append_to_log_eq_last_plus_one(builder))?;
+        run_fut(Self::df_append_to_log_eq_last_applied_plus_one(builder))?;
+        run_fut(Self::df_append_to_log_gt_last_log_id(builder))?;
+        run_fut(Self::df_append_to_log_gt_last_applied_id(builder))?;
+        run_fut(Self::df_apply_nonempty_input(builder))?;
+        run_fut(Self::df_apply_index_eq_last_applied_plus_one(builder))?;
+        run_fut(Self::df_apply_gt_last_applied_id(builder))?;
+
+        Ok(())
+    }
+
+    pub async fn df_get_membership_config_dirty_log(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        tracing::info!("--- dirty log: log.index > last_applied.index && log < last_applied");
+        {
+            store
+                .append_to_log(&[
+                    &Entry {
+                        log_id: LogId { term: 1, index: 1 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Blank,
+                    },
+                    &Entry {
+                        log_id: LogId { term: 1, index: 2 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Blank,
+                    },
+                    &Entry {
+                        log_id: LogId { term: 1, index: 3 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2,3})),
+                    },
+                ])
+                .await?;
+            store
+                .apply_to_state_machine(&[
+                    &Entry {
+                        log_id: LogId { term: 2, index: 1 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Blank,
+                    },
+                    &Entry {
+                        log_id: LogId { term: 2, index: 2 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Membership(Membership::new_single(btreeset! {3,4,5})),
+                    },
+                ])
+                .await?;
+
+            let res = store.get_membership().await;
+
+            let e = res.unwrap_err().into_defensive().unwrap();
+            assert!(matches!(e, DefensiveError {
+                subject: ErrorSubject::Log(LogId { term: 1, index: 3 }),
+                violation: Violation::DirtyLog {
+                    higher_index_log_id: LogId { term: 1, index: 3 },
+                    lower_index_log_id: LogId { term: 2, index: 2 },
+                },
+                ..
+            }))
+        }
+
+        Ok(())
+    }
+
+    pub async fn df_get_initial_state_dirty_log(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        tracing::info!("--- dirty log: log.index > last_applied.index && log < last_applied");
+        {
+            store
+                .append_to_log(&[
+                    &Entry {
+                        log_id: LogId { term: 1, index: 1 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Blank,
+                    },
+                    &Entry {
+                        log_id: LogId { term: 1, index: 2 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Blank,
+                    },
+                    &Entry {
+                        log_id: LogId { term: 1, index: 3 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2,3})),
+                    },
+                ])
+                .await?;
+
+            store
+                .apply_to_state_machine(&[
+                    &Entry {
+                        log_id: LogId { term: 2, index: 1 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Blank,
+                    },
+                    &Entry {
+                        log_id: LogId { term: 2, index: 2 },
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Membership(Membership::new_single(btreeset! {3,4,5})),
+                    },
+                ])
+                .await?;
+
+            let state = store.get_initial_state().await;
+            let e = state.unwrap_err().into_defensive().unwrap();
+
+            assert!(matches!(e, DefensiveError {
+                subject: ErrorSubject::Log(LogId { term: 1, index: 3 }),
+                violation: Violation::DirtyLog {
+                    higher_index_log_id: LogId { term: 1, index: 3 },
+                    lower_index_log_id: LogId { term: 2, index: 2 },
+                },
+                ..
+            }))
+        }
+
+        Ok(())
+    }
+
+    pub async fn df_save_hard_state_ascending(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        store
+            .save_hard_state(&HardState {
+                current_term: 10,
+                voted_for: Some(NODE_ID),
+            })
+            .await?;
+
+        tracing::info!("--- lower term is rejected");
+        {
+            let res = store
+                .save_hard_state(&HardState {
+                    current_term: 9,
+                    voted_for: Some(NODE_ID),
+                })
+                .await;
+
+            let e = res.unwrap_err().into_defensive().unwrap();
+            assert!(matches!(e, DefensiveError {
+                subject: ErrorSubject::HardState,
+                violation: Violation::TermNotAscending { curr: 10, to: 9 },
+                ..
+            }));
+
+            let state = store.get_initial_state().await?;
+
+            assert_eq!(
+                HardState {
+                    current_term: 10,
+                    voted_for: Some(NODE_ID),
+                },
+                state.hard_state,
+            );
+        }
+
+        tracing::info!("--- same term can not reset to None");
+        {
+            let res = store
+                .save_hard_state(&HardState {
+                    current_term: 10,
+                    voted_for: None,
+                })
+                .await;
+
+            let e = res.unwrap_err().into_defensive().unwrap();
+            assert!(matches!(e, DefensiveError {
+                subject: ErrorSubject::HardState,
+                violation: Violation::VotedForChanged {
+                    curr: HardState {
+                        current_term: 10,
+                        voted_for: Some(NODE_ID)
+                    },
+                    to: HardState {
+                        current_term: 10,
+                        voted_for: None
+                    }
+                },
+                ..
+            }));
+
+            let state = store.get_initial_state().await?;
+
+            assert_eq!(
+                HardState {
+                    current_term: 10,
+                    voted_for: Some(NODE_ID),
+                },
+                state.hard_state,
+            );
+        }
+
+        tracing::info!("--- same term can not change voted_for");
+        {
+            let res = store
+                .save_hard_state(&HardState {
+                    current_term: 10,
+                    voted_for: Some(1000),
+                })
+                .await;
+
+            let e = res.unwrap_err().into_defensive().unwrap();
+            assert!(matches!(e, DefensiveError {
+                subject: ErrorSubject::HardState,
+                violation: Violation::VotedForChanged {
+                    curr: HardState {
+                        current_term: 10,
+                        voted_for: Some(NODE_ID)
+                    },
+                    to: HardState {
+                        current_term: 10,
+                        voted_for: Some(1000)
+                    }
+                },
+                ..
+            }));
+
+            let state = store.get_initial_state().await?;
+
+            assert_eq!(
+                HardState {
+                    current_term: 10,
+                    voted_for: Some(NODE_ID),
+                },
+                state.hard_state,
+            );
+        }
+
+        Ok(())
+    }
+
+    pub async fn df_get_log_entries(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+        Self::feed_10_logs_vote_self(&store).await?;
+
+        store.delete_logs_from(..=0).await?;
+
+        store.get_log_entries(..).await?;
+        store.get_log_entries(5..).await?;
+        store.get_log_entries(..5).await?;
+        store.get_log_entries(5..7).await?;
+
+        // mismatched bound.
+
+        let res = store.get_log_entries(11..).await;
+        let e = res.unwrap_err().into_defensive().unwrap();
+        assert!(matches!(e, DefensiveError {
+            subject: ErrorSubject::LogIndex(11),
+            violation: Violation::LogIndexNotFound { want: 11, got: None },
+            ..
+        }));
+
+        let res = store.get_log_entries(1..1).await;
+        let e = res.unwrap_err().into_defensive().unwrap();
+        assert!(matches!(e, DefensiveError {
+            subject: ErrorSubject::Logs,
+            violation: Violation::RangeEmpty {
+                start: Some(1),
+                end: Some(0)
+            },
+            ..
+        }));
+
+        let res = store.get_log_entries(0..1).await;
+        let e = res.unwrap_err().into_defensive().unwrap();
+        assert!(matches!(e, DefensiveError {
+            subject: ErrorSubject::LogIndex(0),
+            violation: Violation::LogIndexNotFound { want: 0, got: None },
+            ..
+        }));
+
+        let res = store.get_log_entries(0..2).await;
+        let e = res.unwrap_err().into_defensive().unwrap();
+        assert!(matches!(e, DefensiveError {
+            subject: ErrorSubject::LogIndex(0),
+            violation: Violation::LogIndexNotFound { want: 0, got: Some(1) },
+            ..
+        }));
+
+        let res = store.get_log_entries(10..12).await;
+        let e = res.unwrap_err().into_defensive().unwrap();
+        assert!(matches!(e, DefensiveError {
+            subject: ErrorSubject::LogIndex(11),
+            violation: Violation::LogIndexNotFound {
+                want: 11,
+                got: Some(10)
+            },
+            ..
+        }));
+
+        Ok(())
+    }
+
+    pub async fn df_delete_logs_from_nonempty_range(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+        Self::feed_10_logs_vote_self(&store).await?;
+
+        let res = store.delete_logs_from(10..10).await;
+
+        let e = res.unwrap_err().into_defensive().unwrap();
+        assert_eq!(ErrorSubject::Logs, e.subject);
+        assert_eq!(
+            Violation::RangeEmpty {
+                start: Some(10),
+                end: Some(9),
+            },
+            e.violation
+        );
+
+        let res = store.delete_logs_from(1..5).await;
+
+        let e = res.unwrap_err().into_defensive().unwrap();
+        assert_eq!(ErrorSubject::Logs, e.subject);
+        assert_eq!(
+            Violation::RangeNotHalfOpen {
+                start: Bound::Included(1),
+                end: Bound::Excluded(5),
+            },
+            e.violation
+        );
+
+        Ok(())
+    }
+
+    pub async fn df_append_to_log_nonempty_input(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let res = store.append_to_log(Vec::<&Entry<_>>::new().as_slice()).await;
+
+        let e = res.unwrap_err().into_defensive().unwrap();
+        assert_eq!(ErrorSubject::Logs, e.subject);
+        assert_eq!(Violation::LogsEmpty, e.violation);
+
+        Ok(())
+    }
+
+    pub async fn df_append_to_log_nonconsecutive_input(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let res = store
+            .append_to_log(&[
+                &Entry {
+                    log_id: (1, 1).into(),
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+                &Entry {
+                    log_id: (1, 3).into(),
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+            ])
+            .await;
+
+        let e = res.unwrap_err().into_defensive().unwrap();
+        assert_eq!(ErrorSubject::Logs, e.subject);
+        assert_eq!(
+            Violation::LogsNonConsecutive {
+                prev: LogId { term: 1, index: 1 },
+                next: LogId { term: 1, index: 3 },
+            },
+            e.violation
+        );
+
+        Ok(())
+    }
+
+    pub async fn df_append_to_log_term_descending_input(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let res = store
+            .append_to_log(&[
+                &Entry {
+                    log_id: (2, 1).into(),
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+                &Entry {
+                    log_id: (1, 2).into(),
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+            ])
+            .await;
+
+        let e = res.unwrap_err().into_defensive().unwrap();
+        assert_eq!(ErrorSubject::Logs, e.subject);
+        assert_eq!(
+            Violation::LogsNonConsecutive {
+                prev: LogId { term: 2, index: 1 },
+                next: LogId { term: 1, index: 2 },
+            },
+            e.violation
+        );
+
+        Ok(())
+    }
+
+    pub async fn df_append_and_save_hard_state_nonconsecutive_input(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let res = store
+            .append_and_save_hard_state(
+                &[
+                    &Entry {
+                        log_id: (1, 1).into(),
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Blank,
+                    },
+                    &Entry {
+                        log_id: (1, 3).into(),
+                        timestamp_ms: 0,
+                        payload: EntryPayload::Blank,
+                    },
+                ],
+                &HardState {
+                    current_term: 1,
+                    voted_for: Some(NODE_ID),
+                },
+            )
+            .await;
+
+        let e = res.unwrap_err().into_defensive().unwrap();
+        assert_eq!(ErrorSubject::Logs, e.subject);
+        assert_eq!(
+            Violation::LogsNonConsecutive {
+                prev: LogId { term: 1, index: 1 },
+                next: LogId { term: 1, index: 3 },
+            },
+            e.violation
+        );
+
+        assert!(store.last_id_in_log().await?.is_sentinel(), "nothing is appended");
+
+        Ok(())
+    }
+
+    pub async fn df_append_to_log_eq_last_plus_one(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        tracing::info!("-- log_id <= last_applied");
+        tracing::info!("-- nonconsecutive log");
+        tracing::info!("-- overlapping log");
+
+        store
+            .append_to_log(&[
+                &Entry {
+                    log_id: (1, 1).into(),
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+                &Entry {
+                    log_id: (1, 2).into(),
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+            ])
+            .await?;
+
+        store
+            .apply_to_state_machine(&[&Entry {
+                log_id: LogId { term: 1, index: 1 },
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            }])
+            .await?;
+
+        let res = store
+            .append_to_log(&[&Entry {
+                log_id: (3, 4).into(),
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            }])
+            .await;
+
+        let e = res.unwrap_err().into_defensive().unwrap();
+        assert_eq!(ErrorSubject::Log(LogId { term: 3, index: 4 }), e.subject);
+        assert_eq!(
+            Violation::LogsNonConsecutive {
+                prev: LogId { term: 1, index: 2 },
+                next: LogId { term: 3, index: 4 },
+            },
+            e.violation
+        );
+
+        Ok(())
+    }
+
+    pub async fn df_append_to_log_eq_last_applied_plus_one(builder: &B) -> anyhow::Result<()> {
+        // last_log: 1,1
+        // last_applied: 1,2
+        // append_to_log: 1,4
+        let store = builder.build(NODE_ID).await;
+
+        tracing::info!("-- log_id <= last_applied");
+        tracing::info!("-- nonconsecutive log");
+        tracing::info!("-- overlapping log");
+
+        store
+            .append_to_log(&[
+                &Entry {
+                    log_id: (1, 1).into(),
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+                &Entry {
+                    log_id: (1, 2).into(),
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+            ])
+            .await?;
+
+        store
+            .apply_to_state_machine(&[
+                &Entry {
+                    log_id: LogId { term: 1, index: 1 },
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+                &Entry {
+                    log_id: LogId { term: 1, index: 2 },
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+            ])
+            .await?;
+
+        store.delete_logs_from(1..).await?;
+
+        let res = store
+            .append_to_log(&[&Entry {
+                log_id: (1, 4).into(),
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            }])
+            .await;
+
+        let e = res.unwrap_err().into_defensive().unwrap();
+        assert_eq!(ErrorSubject::Log(LogId { term: 1, index: 4 }), e.subject);
+        assert_eq!(
+            Violation::LogsNonConsecutive {
+                prev: LogId { term: 1, index: 2 },
+                next: LogId { term: 1, index: 4 },
+            },
+            e.violation
+        );
+
+        Ok(())
+    }
+
+    pub async fn df_append_to_log_gt_last_log_id(builder: &B) -> anyhow::Result<()> {
+        // last_log: 2,2
+        // append_to_log: 1,3: index == last + 1 but term is lower
+        let store = builder.build(NODE_ID).await;
+
+        store
+            .append_to_log(&[
+                &Entry {
+                    log_id: (2, 1).into(),
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+                &Entry {
+                    log_id: (2, 2).into(),
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+            ])
+            .await?;
+
+        let res = store
+            .append_to_log(&[&Entry {
+                log_id: (1, 3).into(),
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            }])
+            .await;
+
+        let e = res.unwrap_err().into_defensive().unwrap();
+        assert_eq!(ErrorSubject::Log(LogId { term: 1, index: 3 }), e.subject);
+        assert_eq!(
+            Violation::LogsNonConsecutive {
+                prev: LogId { term: 2, index: 2 },
+                next: LogId { term: 1, index: 3 },
+            },
+            e.violation
+        );
+
+        Ok(())
+    }
+
+    pub async fn df_append_to_log_gt_last_applied_id(builder: &B) -> anyhow::Result<()> {
+        // last_log: 2,1
+        // last_applied: 2,2
+        // append_to_log: 1,3: index == last + 1 but term is lower
+        let store = builder.build(NODE_ID).await;
+
+        store
+            .append_to_log(&[
+                &Entry {
+                    log_id: (2, 1).into(),
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+                &Entry {
+                    log_id: (2, 2).into(),
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+            ])
+            .await?;
+
+        store
+            .apply_to_state_machine(&[
+                &Entry {
+                    log_id: LogId { term: 2, index: 1 },
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+                &Entry {
+                    log_id: LogId { term: 2, index: 2 },
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                },
+            ])
+            .await?;
+
+        store.delete_logs_from(1..).await?;
+
+        let res = store
+            .append_to_log(&[&Entry {
+                log_id: (1, 3).into(),
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            }])
+            .await;
+
+        let e = res.unwrap_err().into_defensive().unwrap();
+        assert_eq!(ErrorSubject::Log(LogId { term: 1, index: 3 }), e.subject);
+        assert_eq!(
+            Violation::LogsNonConsecutive {
+                prev: LogId { term: 2, index: 2 },
+                next: LogId { term: 1, index: 3 },
+            },
+            e.violation
+        );
+
+        Ok(())
+    }
+
+    pub async fn df_apply_nonempty_input(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let res = store.apply_to_state_machine(Vec::<&Entry<_>>::new().as_slice()).await;
+
+        let e = res.unwrap_err().into_defensive().unwrap();
+        assert_eq!(ErrorSubject::Logs, e.subject);
+        assert_eq!(Violation::LogsEmpty, e.violation);
+
+        Ok(())
+    }
+
+    pub async fn df_apply_index_eq_last_applied_plus_one(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let entry = Entry {
+            log_id: LogId { term: 3, index: 1 },
+
+            timestamp_ms: 0,
+            payload: EntryPayload::Blank,
+        };
+
+        store.apply_to_state_machine(&[&entry]).await?;
+
+        tracing::info!("--- re-apply 1th");
+        {
+            let res = store.apply_to_state_machine(&[&entry]).await;
+
+            let e = res.unwrap_err().into_defensive().unwrap();
+            assert_eq!(ErrorSubject::Apply(LogId { term: 3, index: 1 }), e.subject);
+            assert_eq!(
+                Violation::ApplyNonConsecutive {
+                    prev: LogId { term: 3, index: 1 },
+                    next: LogId { term: 3, index: 1 },
+                },
+                e.violation
+            );
+        }
+
+        tracing::info!("--- apply 3rd when there is only 1st");
+        {
+            let entry = Entry {
+                log_id: LogId { term: 3, index: 3 },
+
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            };
+            let res = store.apply_to_state_machine(&[&entry]).await;
+
+            let e = res.unwrap_err().into_defensive().unwrap();
+            assert_eq!(ErrorSubject::Apply(LogId { term: 3, index: 3 }), e.subject);
+            assert_eq!(
+                Violation::ApplyNonConsecutive {
+                    prev: LogId { term: 3, index: 1 },
+                    next: LogId { term: 3, index: 3 },
+                },
+                e.violation
+            );
+        }
+
+        Ok(())
+    }
+
+    pub async fn df_apply_gt_last_applied_id(builder: &B) -> anyhow::Result<()> {
+        let store = builder.build(NODE_ID).await;
+
+        let entry = Entry {
+            log_id: LogId { term: 3, index: 1 },
+            timestamp_ms: 0,
+            payload: EntryPayload::Blank,
+        };
+
+        store.apply_to_state_machine(&[&entry]).await?;
+
+        tracing::info!("--- next apply with last_index+1 but lower term");
+        {
+            let entry = Entry {
+                log_id: LogId { term: 2, index: 2 },
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            };
+            let res = store.apply_to_state_machine(&[&entry]).await;
+            assert!(res.is_err());
+
+            let e = res.unwrap_err().into_defensive().unwrap();
+            assert_eq!(ErrorSubject::Apply(LogId { term: 2, index: 2 }), e.subject);
+            assert_eq!(
+                Violation::ApplyNonConsecutive {
+                    prev: LogId { term: 3, index: 1 },
+                    next: LogId { term: 2, index: 2 },
+                },
+                e.violation
+            );
+        }
+
+        Ok(())
+    }
+}
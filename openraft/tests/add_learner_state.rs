@@ -0,0 +1,85 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::AddLearnerState;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+/// Pending and ready learner state test.
+///
+/// What does this test do?
+///
+/// - bring on a single node cluster.
+/// - add a learner that the leader can not reach, without blocking.
+/// - asserts the learner stays `Pending` in the leader's replication metrics.
+/// - make the learner reachable.
+/// - asserts the learner becomes `Ready` and receives the logs.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn add_learner_state() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- add an unreachable learner");
+    {
+        router.new_raft_node(1).await;
+        router.isolate_node(1).await;
+
+        router.add_learner_with_blocking(0, 1, false).await?;
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(
+                |x| match x.leader_metrics {
+                    Some(ref m) => m.replication.contains_key(&1),
+                    None => false,
+                },
+                "learner added",
+            )
+            .await?;
+    }
+
+    tracing::info!("--- the learner stays pending while it does not respond");
+    {
+        tokio::time::sleep(Duration::from_millis(config.heartbeat_interval * 5)).await;
+
+        let metrics = router.get_raft_handle(&0).await?.metrics().borrow().clone();
+        let repl = &metrics.leader_metrics.unwrap().replication[&1];
+        assert_eq!(AddLearnerState::Pending, repl.add_learner_state);
+    }
+
+    tracing::info!("--- the learner becomes ready once it responds");
+    {
+        router.restore_node(1).await;
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(
+                |x| match x.leader_metrics {
+                    Some(ref m) => m.replication.get(&1).map(|r| r.add_learner_state) == Some(AddLearnerState::Ready),
+                    None => false,
+                },
+                "learner ready",
+            )
+            .await?;
+
+        router.wait_for_log(&btreeset![1], n_logs, timeout(), "learner receives logs").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
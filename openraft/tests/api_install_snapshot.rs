@@ -47,11 +47,13 @@ async fn snapshot_ge_half_threshold() -> Result<()> {
         term: 1,
         leader_id: 0,
         meta: SnapshotMeta {
-            snapshot_id: "ss1".into(),
-            last_log_id: LogId { term: 1, index: 0 },
+            snapshot_id: "1-1024-1-0".parse()?,
+            last_log_id: LogId { term: 1, index: 1024 },
+            base_snapshot_id: None,
         },
         offset: 0,
         data: vec![1, 2, 3],
+        checksum: InstallSnapshotRequest::checksum_of(&[1, 2, 3]),
         done: false,
     };
 
@@ -60,7 +62,7 @@ async fn snapshot_ge_half_threshold() -> Result<()> {
         let mut req = req0.clone();
         req.offset = 2;
         let res = n.0.install_snapshot(req).await;
-        assert_eq!("expect: ss1+0, got: ss1+2", res.unwrap_err().to_string());
+        assert_eq!("expect: 1-1024-1-0+0, got: 1-1024-1-0+2", res.unwrap_err().to_string());
     }
 
     tracing::info!("--- install and write ss1:[0,3)");
@@ -73,21 +75,21 @@ async fn snapshot_ge_half_threshold() -> Result<()> {
     {
         let mut req = req0.clone();
         req.offset = 3;
-        req.meta.snapshot_id = "ss2".into();
+        req.meta.snapshot_id = "1-1024-2-0".parse()?;
         let res = n.0.install_snapshot(req).await;
-        assert_eq!("expect: ss1+3, got: ss2+3", res.unwrap_err().to_string());
+        assert_eq!("expect: 1-1024-1-0+3, got: 1-1024-2-0+3", res.unwrap_err().to_string());
     }
 
     tracing::info!("-- write from offset=0 with different id, create a new session");
     {
         let mut req = req0.clone();
         req.offset = 0;
-        req.meta.snapshot_id = "ss2".into();
+        req.meta.snapshot_id = "1-1024-2-0".parse()?;
         n.0.install_snapshot(req).await?;
 
         let mut req = req0.clone();
         req.offset = 3;
-        req.meta.snapshot_id = "ss2".into();
+        req.meta.snapshot_id = "1-1024-2-0".parse()?;
         n.0.install_snapshot(req).await?;
     }
 
@@ -95,7 +97,7 @@ async fn snapshot_ge_half_threshold() -> Result<()> {
     {
         let mut req = req0.clone();
         req.offset = 8;
-        req.meta.snapshot_id = "ss2".into();
+        req.meta.snapshot_id = "1-1024-2-0".parse()?;
         n.0.install_snapshot(req).await?;
     }
     Ok(())
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::raft::InstallSnapshotRequest;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::SnapshotId;
+use openraft::SnapshotMeta;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// API test: install_snapshot with a snapshot that is not newer than the last applied log.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster and write some logs.
+/// - send install_snapshot requests whose `last_log_id` is less than or equal to the last applied log id.
+/// - assert that the requests succeed without touching the state machine.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn install_snapshot_already_applied() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = 0;
+
+    tracing::info!("--- initializing cluster");
+    {
+        router.new_raft_node(0).await;
+
+        router.wait_for_log(&btreeset![0], n_logs, None, "empty").await?;
+        router.wait_for_state(&btreeset![0], State::Learner, None, "empty").await?;
+
+        router.initialize_from_single_node(0).await?;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![0], n_logs, None, "init leader").await?;
+        router.assert_stable_cluster(Some(1), Some(n_logs)).await;
+    }
+
+    tracing::info!("--- write some logs");
+    {
+        router.client_request_many(0, "0", 10).await;
+        n_logs += 10;
+
+        router.wait_for_log(&btreeset![0], n_logs, None, "write logs").await?;
+    }
+
+    let (n, sto) = router.remove_node(0).await.ok_or_else(|| anyhow::anyhow!("node not found"))?;
+
+    tracing::info!("--- install stale snapshots, the data is garbage and must never be decoded");
+    {
+        for last_log_id in [LogId::new(1, 5), LogId::new(1, n_logs)] {
+            let req = InstallSnapshotRequest {
+                term: 1,
+                leader_id: 0,
+                meta: SnapshotMeta {
+                    snapshot_id: SnapshotId::new(last_log_id, 1, 0),
+                    last_log_id,
+                    base_snapshot_id: None,
+                },
+                offset: 0,
+                data: b"not a snapshot".to_vec(),
+                checksum: InstallSnapshotRequest::checksum_of(b"not a snapshot"),
+                done: true,
+            };
+            n.install_snapshot(req).await?;
+        }
+    }
+
+    tracing::info!("--- state machine and snapshot are not changed");
+    {
+        let (last_applied, _) = sto.last_applied_state().await?;
+        assert_eq!(LogId::new(1, n_logs), last_applied);
+
+        let snapshot = sto.get_current_snapshot().await?;
+        assert!(snapshot.is_none());
+
+        let metrics = n.metrics().borrow().clone();
+        assert_ne!(State::Shutdown, metrics.state);
+        assert_eq!(n_logs, metrics.last_applied);
+        assert_eq!(LogId::new(0, 0), metrics.snapshot);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::raft::InstallSnapshotRequest;
+use openraft::Config;
+use openraft::LogId;
+use openraft::SnapshotMeta;
+
+#[macro_use]
+mod fixtures;
+
+/// API test: install_snapshot with corrupted chunks.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster.
+/// - send install_snapshot requests whose data does not match the checksum.
+/// - asserts a corrupted chunk is rejected and resending the good chunk carries the transfer on.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn api_install_snapshot_corrupted_chunk() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    let (n, _sto) = router.remove_node(0).await.ok_or_else(|| anyhow::anyhow!("node not found"))?;
+
+    assert_eq!(0xCBF4_3926, InstallSnapshotRequest::checksum_of(b"123456789"));
+
+    let req0 = InstallSnapshotRequest {
+        term: 1,
+        leader_id: 0,
+        meta: SnapshotMeta {
+            snapshot_id: "1-1024-1-0".parse()?,
+            last_log_id: LogId { term: 1, index: 1024 },
+            base_snapshot_id: None,
+        },
+        offset: 0,
+        data: vec![1, 2, 3],
+        checksum: InstallSnapshotRequest::checksum_of(&[1, 2, 3]),
+        done: false,
+    };
+
+    tracing::info!("--- corrupted first chunk is rejected");
+    {
+        let mut req = req0.clone();
+        req.data[1] = 9;
+        let res = n.install_snapshot(req).await;
+        let err = res.unwrap_err().to_string();
+        assert!(err.contains("snapshot chunk 1-1024-1-0+0 checksum mismatch"), "{}", err);
+    }
+
+    tracing::info!("--- resend the first chunk, write ss1:[0,3)");
+    {
+        n.install_snapshot(req0.clone()).await?;
+    }
+
+    tracing::info!("--- corrupted chunk at offset 3 is rejected");
+    {
+        let mut req = req0.clone();
+        req.offset = 3;
+        req.data = vec![4, 5, 6];
+        let res = n.install_snapshot(req).await;
+        let err = res.unwrap_err().to_string();
+        assert!(err.contains("snapshot chunk 1-1024-1-0+3 checksum mismatch"), "{}", err);
+    }
+
+    tracing::info!("--- resend the chunk at offset 3, the stream is still at ss1+3");
+    {
+        let mut req = req0.clone();
+        req.offset = 3;
+        req.data = vec![4, 5, 6];
+        req.checksum = InstallSnapshotRequest::checksum_of(&req.data);
+        n.install_snapshot(req).await?;
+    }
+
+    tracing::info!("--- the stream is still alive, a chunk with another id is out of order");
+    {
+        let mut req = req0.clone();
+        req.offset = 6;
+        req.meta.snapshot_id = "1-1024-2-0".parse()?;
+        let res = n.install_snapshot(req).await;
+        assert_eq!("expect: 1-1024-1-0+6, got: 1-1024-2-0+6", res.unwrap_err().to_string());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use maplit::hashmap;
+use memstore::MemStoreStateMachine;
+use openraft::raft::InstallSnapshotRequest;
+use openraft::raft::Membership;
+use openraft::Config;
+use openraft::EffectiveMembership;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::RaftStorageDebug;
+use openraft::SnapshotMeta;
+
+#[macro_use]
+mod fixtures;
+
+/// API test: install a delta snapshot.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster.
+/// - send a delta snapshot to it, assert it asks for a full snapshot because it does not have the base.
+/// - install a full snapshot as the base, then install a delta snapshot upon it.
+/// - assert the state machine contains the base with the delta applied.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn install_snapshot_delta() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = 0;
+
+    tracing::info!("--- initializing cluster");
+    {
+        router.new_raft_node(0).await;
+
+        router.initialize_from_single_node(0).await?;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![0], n_logs, None, "init leader").await?;
+    }
+
+    let (n, sto) = router.remove_node(0).await.ok_or_else(|| anyhow::anyhow!("node not found"))?;
+
+    let membership = Some(EffectiveMembership {
+        log_id: LogId::new(1, 1),
+        membership: Membership::new_single(btreeset! {0}),
+    });
+
+    let base_sm = MemStoreStateMachine {
+        last_applied_log: LogId::new(1, 100),
+        last_membership: membership.clone(),
+        client_status: hashmap! {"a".to_string() => "a1".to_string(), "b".to_string() => "b1".to_string()},
+        ..Default::default()
+    };
+    let base_data = serde_json::to_vec(&base_sm)?;
+    let base = InstallSnapshotRequest {
+        term: 1,
+        leader_id: 0,
+        meta: SnapshotMeta {
+            last_log_id: LogId::new(1, 100),
+            snapshot_id: "1-100-1-0".parse()?,
+            base_snapshot_id: None,
+        },
+        offset: 0,
+        checksum: InstallSnapshotRequest::checksum_of(&base_data),
+        data: base_data,
+        done: true,
+    };
+
+    let delta_sm = MemStoreStateMachine {
+        last_applied_log: LogId::new(1, 200),
+        last_membership: membership.clone(),
+        client_status: hashmap! {"b".to_string() => "b2".to_string(), "c".to_string() => "c2".to_string()},
+        ..Default::default()
+    };
+    let delta_data = serde_json::to_vec(&delta_sm)?;
+    let delta = InstallSnapshotRequest {
+        term: 1,
+        leader_id: 0,
+        meta: SnapshotMeta {
+            last_log_id: LogId::new(1, 200),
+            snapshot_id: "1-200-2-0".parse()?,
+            base_snapshot_id: Some("1-100-1-0".parse()?),
+        },
+        offset: 0,
+        checksum: InstallSnapshotRequest::checksum_of(&delta_data),
+        data: delta_data,
+        done: true,
+    };
+
+    tracing::info!("--- delta snapshot without base: ask for a full snapshot");
+    {
+        let resp = n.install_snapshot(delta.clone()).await?;
+        assert!(resp.need_full_snapshot);
+
+        assert!(sto.get_current_snapshot().await?.is_none());
+        let (last_applied, _) = sto.last_applied_state().await?;
+        assert_eq!(LogId::new(1, n_logs), last_applied);
+    }
+
+    tracing::info!("--- install the base snapshot");
+    {
+        let resp = n.install_snapshot(base).await?;
+        assert!(!resp.need_full_snapshot);
+
+        let snap = sto.get_current_snapshot().await?.unwrap();
+        assert_eq!("1-100-1-0", snap.meta.snapshot_id.to_string());
+    }
+
+    tracing::info!("--- install the delta snapshot upon the base");
+    {
+        let resp = n.install_snapshot(delta).await?;
+        assert!(!resp.need_full_snapshot);
+
+        let snap = sto.get_current_snapshot().await?.unwrap();
+        assert_eq!("1-200-2-0", snap.meta.snapshot_id.to_string());
+        assert_eq!(
+            None, snap.meta.base_snapshot_id,
+            "the installed delta is stored as a full snapshot"
+        );
+
+        let sm = sto.get_state_machine().await;
+        assert_eq!(LogId::new(1, 200), sm.last_applied_log);
+        assert_eq!(
+            hashmap! {
+                "a".to_string() => "a1".to_string(),
+                "b".to_string() => "b2".to_string(),
+                "c".to_string() => "c2".to_string(),
+            },
+            sm.client_status
+        );
+
+        let metrics = n.metrics().borrow().clone();
+        assert_eq!(LogId::new(1, 200), metrics.snapshot);
+        assert_eq!(200, metrics.last_applied);
+    }
+
+    Ok(())
+}
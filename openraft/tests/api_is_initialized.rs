@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::error::ClientWriteError;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// API test: Raft::is_initialized() and writing to an uninitialized node.
+///
+/// What does this test do?
+///
+/// - brings 1 node online with empty storage.
+/// - asserts it is not initialized and a client write returns `NotInitialized`.
+/// - initialize the cluster.
+/// - asserts it is initialized and accepts client writes.
+/// - restart the node with the same store.
+/// - asserts it is still initialized, and `Raft::is_initialized()` agrees with `RaftMetrics::initialized`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn api_is_initialized() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_raft_node(0).await;
+
+    let mut n_logs = 0;
+
+    tracing::info!("--- uninitialized node");
+    {
+        router.wait_for_state(&btreeset![0], State::Learner, None, "empty").await?;
+
+        let n = router.get_raft_handle(&0).await?;
+        assert!(!n.is_initialized());
+        assert!(!n.metrics().borrow().initialized);
+
+        let res = router
+            .send_client_request(0, ClientRequest {
+                client: "foo".to_string(),
+                serial: 1,
+                status: "bar".to_string(),
+            })
+            .await;
+
+        match res {
+            Err(ClientWriteError::NotInitialized(e)) => {
+                assert_eq!(0, e.node_id);
+            }
+            Err(e) => panic!("expect NotInitialized, got: {:?}", e),
+            Ok(x) => panic!("expect NotInitialized, got: {:?}", x),
+        }
+    }
+
+    tracing::info!("--- initialize");
+    {
+        router.initialize_with(0, btreeset![0]).await?;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![0], n_logs, None, "init").await?;
+
+        let n = router.get_raft_handle(&0).await?;
+        assert!(n.is_initialized());
+
+        router.client_request(0, "foo", 1).await;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![0], n_logs, None, "write").await?;
+    }
+
+    tracing::info!("--- restarted node is still initialized");
+    {
+        let (node, sto) = router.remove_node(0).await.ok_or_else(|| anyhow::anyhow!("node not found"))?;
+        node.shutdown().await?;
+
+        router.new_raft_node_with_sto(0, sto.clone()).await;
+        router.wait_for_state(&btreeset![0], State::Leader, None, "restarted").await?;
+
+        let n = router.get_raft_handle(&0).await?;
+        assert!(n.is_initialized());
+        assert!(n.metrics().borrow().initialized);
+    }
+
+    Ok(())
+}
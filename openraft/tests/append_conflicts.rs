@@ -50,6 +50,7 @@ async fn append_conflicts() -> Result<()> {
         prev_log_id: LogId::new(0, 0),
         entries: vec![],
         leader_commit: LogId::new(1, 2),
+        relay_to: vec![],
     };
 
     let resp = r0.append_entries(req.clone()).await?;
@@ -67,6 +68,7 @@ async fn append_conflicts() -> Result<()> {
         entries: vec![ent(1, 1), ent(1, 2), ent(1, 3), ent(1, 4)],
         // this set the last_applied to 2
         leader_commit: LogId::new(1, 2),
+        relay_to: vec![],
     };
 
     let resp = r0.append_entries(req.clone()).await?;
@@ -92,6 +94,7 @@ async fn append_conflicts() -> Result<()> {
         prev_log_id: LogId::new(1, 1),
         entries: vec![ent(1, 2)],
         leader_commit: LogId::new(1, 2),
+        relay_to: vec![],
     };
 
     let resp = r0.append_entries(req).await?;
@@ -109,6 +112,7 @@ async fn append_conflicts() -> Result<()> {
         entries: vec![ent(2, 3)],
         // this set the last_applied to 2
         leader_commit: LogId::new(1, 2),
+        relay_to: vec![],
     };
 
     let resp = r0.append_entries(req).await?;
@@ -124,6 +128,7 @@ async fn append_conflicts() -> Result<()> {
         prev_log_id: LogId::new(1, 2000),
         entries: vec![],
         leader_commit: LogId::new(1, 2),
+        relay_to: vec![],
     };
 
     let resp = r0.append_entries(req).await?;
@@ -140,6 +145,7 @@ async fn append_conflicts() -> Result<()> {
         prev_log_id: LogId::new(3, 3),
         entries: vec![],
         leader_commit: LogId::new(1, 2),
+        relay_to: vec![],
     };
 
     let resp = r0.append_entries(req).await?;
@@ -157,6 +163,7 @@ async fn append_conflicts() -> Result<()> {
         prev_log_id: LogId::new(1, 2),
         entries: vec![ent(2, 3), ent(2, 4), ent(2, 5)],
         leader_commit: LogId::new(1, 2),
+        relay_to: vec![],
     };
 
     let resp = r0.append_entries(req).await?;
@@ -173,6 +180,7 @@ async fn append_conflicts() -> Result<()> {
         prev_log_id: LogId::new(2, 3),
         entries: vec![ent(3, 4)],
         leader_commit: LogId::new(1, 2),
+        relay_to: vec![],
     };
 
     let resp = r0.append_entries(req).await?;
@@ -190,6 +198,7 @@ async fn append_conflicts() -> Result<()> {
         prev_log_id: LogId::new(1, 200),
         entries: vec![],
         leader_commit: LogId::new(1, 2),
+        relay_to: vec![],
     };
 
     let resp = r0.append_entries(req).await?;
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::NetworkError;
+
+#[macro_use]
+mod fixtures;
+
+/// AppendEntries backoff test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of a leader and a learner.
+/// - make the next several AppendEntries RPC to the learner fail with `NetworkError::Transient` and write a log.
+/// - asserts the learner receives the log once the failures are over, after backing off exponentially.
+/// - do the same with `NetworkError::RemoteError`, asserts the learner receives the log sooner.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn append_entries_backoff() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let heartbeat_interval = 50;
+    let n_failures = 5;
+
+    let config = Arc::new(
+        Config {
+            heartbeat_interval,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
+
+    tracing::info!("--- transient errors are retried with exponential backoff");
+    let transient_elapsed = {
+        let start = Instant::now();
+
+        router.fail_append_entries(1, n_failures, NetworkError::Transient);
+        router.client_request(0, "0", 0).await;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![1], n_logs, timeout(), "learner receives log").await?;
+        let elapsed = start.elapsed();
+
+        tracing::info!(?elapsed, "transient errors");
+        assert_eq!(0, router.pending_append_entries_failures(1));
+
+        // Backoff starts at 1/5 heartbeat interval and doubles: 10 + 20 + 40 + 80 + 160 ms.
+        let min_wait = Duration::from_millis(heartbeat_interval / 5 * ((1 << n_failures) - 1));
+        assert!(elapsed >= min_wait, "backed off: {:?} >= {:?}", elapsed, min_wait);
+
+        elapsed
+    };
+
+    tracing::info!("--- remote errors are retried quickly");
+    {
+        let start = Instant::now();
+
+        router.fail_append_entries(1, n_failures, NetworkError::RemoteError);
+        router.client_request(0, "0", 1).await;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![1], n_logs, timeout(), "learner receives log").await?;
+        let elapsed = start.elapsed();
+
+        tracing::info!(?elapsed, "remote errors");
+        assert_eq!(0, router.pending_append_entries_failures(1));
+
+        assert!(
+            elapsed < transient_elapsed,
+            "remote errors are retried faster: {:?} < {:?}",
+            elapsed,
+            transient_elapsed
+        );
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
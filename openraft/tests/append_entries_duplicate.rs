@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::raft::AppendEntriesRequest;
+use openraft::Config;
+use openraft::LogId;
+use openraft::State;
+
+use crate::fixtures::ent;
+
+#[macro_use]
+mod fixtures;
+
+/// Duplicate append-entries test.
+///
+/// What does this test do?
+///
+/// - bring up a learner and send to it an append-entries request.
+/// - send the same request again, asserts the entries are not appended again.
+/// - send a request that overlaps the appended entries, asserts only the new entries are appended.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn append_entries_duplicate() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+
+    router.wait_for_log(&btreeset![0], 0, None, "empty").await?;
+    router.wait_for_state(&btreeset![0], State::Learner, None, "empty").await?;
+
+    let (r0, sto0) = router.remove_node(0).await.unwrap();
+
+    let req = AppendEntriesRequest {
+        term: 1,
+        leader_id: 1,
+        prev_log_id: LogId::new(0, 0),
+        entries: vec![ent(1, 1), ent(1, 2), ent(1, 3), ent(1, 4)],
+        leader_commit: LogId::new(1, 2),
+        relay_to: vec![],
+    };
+
+    tracing::info!("--- append entries");
+    {
+        let resp = r0.append_entries(req.clone()).await?;
+        assert!(resp.success());
+        assert_eq!(Some(LogId::new(1, 4)), resp.matched);
+        assert_eq!(4, sto0.inner().log_append_count());
+    }
+
+    tracing::info!("--- the same request is delivered again, nothing is appended");
+    {
+        let resp = r0.append_entries(req.clone()).await?;
+        assert!(resp.success());
+        assert_eq!(Some(LogId::new(1, 4)), resp.matched);
+        assert_eq!(4, sto0.inner().log_append_count());
+    }
+
+    tracing::info!("--- an overlapping request appends only the absent entries");
+    {
+        let req = AppendEntriesRequest {
+            term: 1,
+            leader_id: 1,
+            prev_log_id: LogId::new(1, 2),
+            entries: vec![ent(1, 3), ent(1, 4), ent(1, 5), ent(1, 6)],
+            leader_commit: LogId::new(1, 4),
+            relay_to: vec![],
+        };
+
+        let resp = r0.append_entries(req).await?;
+        assert!(resp.success());
+        assert_eq!(Some(LogId::new(1, 6)), resp.matched);
+        assert_eq!(6, sto0.inner().log_append_count());
+    }
+
+    Ok(())
+}
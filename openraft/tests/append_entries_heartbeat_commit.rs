@@ -0,0 +1,61 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+/// Commit index propagation on heartbeat test.
+///
+/// What does this test do?
+///
+/// - create a stable cluster of 3 voters and 1 learner.
+/// - write several logs, each returns when it is committed and applied on the leader.
+/// - go idle, with no more writes.
+/// - asserts every follower and learner applies all of the logs within one heartbeat interval.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn append_entries_heartbeat_commit() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            heartbeat_interval: 200,
+            election_timeout_min: 1000,
+            election_timeout_max: 2000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {3}).await?;
+
+    tracing::info!("--- write logs, they are committed on the leader");
+    {
+        router.client_request_many(0, "foo", 10).await;
+        n_logs += 10;
+
+        let metrics = router.get_raft_handle(&0).await?.metrics().borrow().clone();
+        assert_eq!(n_logs, metrics.last_applied);
+    }
+
+    tracing::info!("--- idle, every node applies the committed logs within one heartbeat interval");
+    {
+        let timeout = Some(Duration::from_millis(config.heartbeat_interval));
+
+        for id in 1..4 {
+            router
+                .wait(&id, timeout)
+                .await?
+                .metrics(|x| x.last_applied == n_logs, "applied all committed logs")
+                .await?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+/// AppendEntries max payload bytes test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of a leader and a learner, with `max_payload_bytes` set.
+/// - isolate the learner and write logs of variable size, including one that is larger than `max_payload_bytes`.
+/// - restore the learner, thus the leader replicates the logs in several batches.
+/// - asserts every batch with more than one entry is within `max_payload_bytes`, and the large entry is sent alone.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn append_entries_max_payload_bytes() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let max_payload_bytes = 1024;
+
+    let config = Arc::new(
+        Config {
+            max_payload_bytes: Some(max_payload_bytes),
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
+
+    tracing::info!("--- isolate the learner and write logs of variable size");
+    {
+        router.isolate_node(1).await;
+        router.take_sent_batches(1);
+
+        let n = 50;
+        for serial in 0..n {
+            let size = if serial == n / 2 {
+                max_payload_bytes as usize * 2
+            } else {
+                (serial as usize * 37) % 300
+            };
+            router.send_client_request(0, req(serial, size)).await?;
+        }
+        n_logs += n;
+
+        router.wait_for_log(&btreeset![0], n_logs, timeout(), "leader writes logs").await?;
+    }
+
+    tracing::info!("--- restore the learner and replicate logs in batches");
+    {
+        router.restore_node(1).await;
+        router.wait_for_log(&btreeset![1], n_logs, timeout(), "learner caught up").await?;
+    }
+
+    tracing::info!("--- every batch respects max_payload_bytes");
+    {
+        let batches = router.take_sent_batches(1);
+        tracing::info!("sent batches: {:?}", batches);
+
+        assert!(batches.len() > 1, "logs are sent in more than one batch: {:?}", batches);
+
+        let mut large_sent = false;
+        for batch in batches.iter() {
+            let total = batch.iter().sum::<u64>();
+
+            if batch.len() > 1 {
+                assert!(total <= max_payload_bytes, "batch within limit: {:?}", batch);
+            } else if total > max_payload_bytes {
+                large_sent = true;
+            }
+        }
+        assert!(large_sent, "an entry larger than the limit is sent alone");
+    }
+
+    Ok(())
+}
+
+fn req(serial: u64, size: usize) -> ClientRequest {
+    ClientRequest {
+        client: "0".to_string(),
+        serial,
+        status: "x".repeat(size),
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
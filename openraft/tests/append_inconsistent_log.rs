@@ -56,12 +56,14 @@ async fn append_inconsistent_log() -> Result<()> {
     for i in n_logs + 1..=100 {
         sto0.append_to_log(&[&Entry {
             log_id: LogId { term: 2, index: i },
+            timestamp_ms: 0,
             payload: EntryPayload::Blank,
         }])
         .await?;
 
         sto2.append_to_log(&[&Entry {
             log_id: LogId { term: 3, index: i },
+            timestamp_ms: 0,
             payload: EntryPayload::Blank,
         }])
         .await?;
@@ -0,0 +1,111 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::Entry;
+use openraft::raft::EntryPayload;
+use openraft::AppData;
+use openraft::Config;
+use openraft::LogId;
+use openraft::State;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// Append entries and save hard state atomically test.
+///
+/// What does this test do?
+///
+/// - bring up a learner, and keep sampling what a crash would leave in its store.
+/// - send it an append-entries request with a new term and several entries.
+/// - asserts the new term and the entries are saved together: no sample sees one without the other.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn append_saves_hard_state_atomically() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+
+    router.wait_for_log(&btreeset![0], 0, timeout(), "empty").await?;
+    router.wait_for_state(&btreeset![0], State::Learner, timeout(), "empty").await?;
+
+    let (r0, sto0) = router.remove_node(0).await.unwrap();
+
+    tracing::info!("--- sample the store while appending entries of a new term");
+    let samples = {
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let sampler = {
+            let stop = stop.clone();
+            let sto0 = sto0.clone();
+            tokio::spawn(async move {
+                let mut samples = vec![];
+                while !stop.load(Ordering::Relaxed) {
+                    let s = sto0.inner().crash_state().await;
+                    if samples.last() != Some(&s) {
+                        samples.push(s);
+                    }
+                    tokio::task::yield_now().await;
+                }
+                samples
+            })
+        };
+
+        let req = AppendEntriesRequest {
+            term: 3,
+            leader_id: 1,
+            prev_log_id: LogId::new(0, 0),
+            entries: vec![ent(3, 1), ent(3, 2), ent(3, 3)],
+            leader_commit: LogId::new(0, 0),
+            relay_to: vec![],
+        };
+
+        let resp = r0.append_entries(req).await?;
+        assert!(resp.success());
+
+        stop.store(true, Ordering::Relaxed);
+        let mut samples = sampler.await?;
+        samples.push(sto0.inner().crash_state().await);
+        samples
+    };
+
+    tracing::info!("--- the new term and the entries are never seen apart: {:?}", samples);
+    {
+        for (hs, last_log_id) in samples.iter() {
+            let new_term = hs.as_ref().map(|x| x.current_term) == Some(3);
+            let new_entries = *last_log_id == LogId::new(3, 3);
+            assert_eq!(
+                new_term, new_entries,
+                "term and entries are saved atomically: {:?} {}",
+                hs, last_log_id
+            );
+        }
+
+        let (hs, last_log_id) = samples.last().unwrap();
+        assert_eq!(Some(3), hs.as_ref().map(|x| x.current_term));
+        assert_eq!(LogId::new(3, 3), *last_log_id);
+    }
+
+    Ok(())
+}
+
+/// Create a blank log entry for test.
+fn ent<T: AppData>(term: u64, index: u64) -> Entry<T> {
+    Entry {
+        log_id: LogId { term, index },
+        timestamp_ms: 0,
+        payload: EntryPayload::Blank,
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
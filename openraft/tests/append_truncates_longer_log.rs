@@ -0,0 +1,131 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorage;
+
+#[macro_use]
+mod fixtures;
+
+/// A follower with a log entirely ahead of the leader test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters, leader 0.
+/// - isolate 1 and 2, write logs to 0 that can not be committed.
+/// - isolate 0, restore 1 and 2, a new leader is elected with a shorter log, and write a few logs.
+/// - asserts the last log of 0 is still ahead of the new leader.
+/// - restore 0, asserts its uncommitted suffix is truncated and replaced with the logs of the new leader.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn append_truncates_longer_log() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- write uncommitted logs to the old leader");
+    let n_uncommitted = 10;
+    {
+        router.isolate_node(1).await;
+        router.isolate_node(2).await;
+
+        let r0 = router.get_raft_handle(&0).await?;
+        for i in 0..n_uncommitted {
+            let r0 = r0.clone();
+            tokio::spawn(async move {
+                let res = r0
+                    .client_write(ClientWriteRequest::new(ClientRequest {
+                        client: "old".to_string(),
+                        serial: i,
+                        status: format!("old-{}", i),
+                    }))
+                    .await;
+                tracing::info!("uncommitted write {}: {:?}", i, res.map(|x| x.log_id));
+            });
+        }
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|x| x.last_log_index == n_logs + n_uncommitted, "logs appended")
+            .await?;
+    }
+
+    tracing::info!("--- elect a new leader with a shorter log");
+    {
+        router.isolate_node(0).await;
+        router.restore_node(1).await;
+        router.restore_node(2).await;
+
+        let leader = loop {
+            if let Some(l) = router.leader().await {
+                if l != 0 {
+                    break l;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+
+        router.client_request_many(leader, "new", 3).await;
+
+        let metrics = router.get_raft_handle(&leader).await?.metrics().borrow().clone();
+        // The blank log of the new leader and 3 logs, there may be more than one election.
+        assert!(metrics.last_log_index >= n_logs + 1 + 3);
+        router.wait_for_log(&btreeset![1, 2], metrics.last_log_index, timeout(), "new leader logs").await?;
+
+        let sto0 = router.get_storage_handle(&0).await?;
+        assert!(
+            sto0.last_id_in_log().await?.index > metrics.last_log_index,
+            "the log of the old leader is entirely ahead"
+        );
+    }
+
+    tracing::info!("--- restore the old leader, its divergent suffix is replaced");
+    {
+        router.restore_node(0).await;
+
+        // The old leader may start an election when it rejoins, which appends one more blank log on a new leader.
+        let start = tokio::time::Instant::now();
+        let got = loop {
+            assert!(
+                start.elapsed() < timeout().unwrap(),
+                "timeout waiting for the old leader to catch up"
+            );
+            tokio::time::sleep(Duration::from_millis(config.heartbeat_interval * 3)).await;
+
+            let got = log_ids(&router, 0).await?;
+            if got == log_ids(&router, 1).await? && got == log_ids(&router, 2).await? {
+                break got;
+            }
+        };
+        let last = *got.last().unwrap();
+
+        for log_id in got.iter().skip(n_logs as usize) {
+            assert!(log_id.term > 1, "uncommitted log of term 1 is removed: {}", log_id);
+        }
+        let sto0 = router.get_storage_handle(&0).await?;
+        assert_eq!(last, sto0.last_id_in_log().await?);
+    }
+
+    Ok(())
+}
+
+/// Returns the ids of the logs of a node, excluding the initial log at index 0.
+async fn log_ids(router: &Arc<RaftRouter>, id: u64) -> Result<Vec<LogId>> {
+    let sto = router.get_storage_handle(&id).await?;
+    let logs = sto.get_log_entries(1..).await?;
+    Ok(logs.iter().map(|x| x.log_id).collect())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -49,16 +49,19 @@ async fn append_updates_membership() -> Result<()> {
                 ent(1, 1),
                 Entry {
                     log_id: LogId { term: 1, index: 2 },
+                    timestamp_ms: 0,
                     payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2})),
                 },
                 ent(1, 3),
                 Entry {
                     log_id: LogId { term: 1, index: 4 },
+                    timestamp_ms: 0,
                     payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2,3,4})),
                 },
                 ent(1, 5),
             ],
             leader_commit: LogId::new(0, 0),
+            relay_to: vec![],
         };
 
         let resp = r0.append_entries(req.clone()).await?;
@@ -76,6 +79,7 @@ async fn append_updates_membership() -> Result<()> {
             prev_log_id: LogId::new(1, 2),
             entries: vec![ent(2, 3)],
             leader_commit: LogId::new(0, 0),
+            relay_to: vec![],
         };
 
         let resp = r0.append_entries(req.clone()).await?;
@@ -92,6 +96,7 @@ async fn append_updates_membership() -> Result<()> {
 fn ent<T: AppData>(term: u64, index: u64) -> Entry<T> {
     Entry {
         log_id: LogId { term, index },
+        timestamp_ms: 0,
         payload: EntryPayload::Blank,
     }
 }
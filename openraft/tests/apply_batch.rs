@@ -0,0 +1,122 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// Config::max_apply_batch test.
+///
+/// What does this test do?
+///
+/// - bring on 2 clusters of 3 voters, one with `max_apply_batch` and one without, with a state machine on the leader
+///   that takes a fixed time for every call to apply logs.
+/// - send a burst of concurrent writes to both.
+/// - asserts the cluster with adaptive batching applies them with far fewer calls to the state machine, while the other
+///   one applies the logs one by one.
+/// - send writes one by one to the cluster with adaptive batching.
+/// - asserts every write is applied by a call of its own, without waiting for more logs.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn apply_batch() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let apply_delay = Duration::from_millis(10);
+    let n_burst: u64 = 100;
+
+    let unbatched = {
+        let config = Arc::new(Config::default().validate()?);
+        Arc::new(RaftRouter::new(config))
+    };
+    let batched = {
+        let config = Arc::new(
+            Config {
+                max_apply_batch: Some(64),
+                ..Default::default()
+            }
+            .validate()?,
+        );
+        Arc::new(RaftRouter::new(config))
+    };
+
+    let mut n_logs = unbatched.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+    batched.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    for router in [&unbatched, &batched] {
+        router.get_storage_handle(&0).await?.inner().set_apply_delay(apply_delay);
+    }
+
+    tracing::info!("--- a burst of writes");
+    {
+        let unbatched_calls = burst(&unbatched, n_burst).await?;
+        let batched_calls = burst(&batched, n_burst).await?;
+        n_logs += n_burst;
+
+        tracing::info!(unbatched_calls, batched_calls, "apply {} logs", n_burst);
+
+        assert_eq!(n_burst, unbatched_calls, "applied one by one");
+        assert!(
+            batched_calls * 2 < unbatched_calls,
+            "batched: {}, unbatched: {}",
+            batched_calls,
+            unbatched_calls
+        );
+
+        for router in [&unbatched, &batched] {
+            router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "burst").await?;
+        }
+    }
+
+    tracing::info!("--- a trickle of writes is applied at once");
+    {
+        let raft = batched.get_raft_handle(&0).await?;
+        let sto = batched.get_storage_handle(&0).await?;
+        for i in 0..10 {
+            let before = sto.inner().apply_call_count();
+            raft.client_write(ClientWriteRequest::new(req(i))).await?;
+
+            assert_eq!(
+                before + 1,
+                sto.inner().apply_call_count(),
+                "write {} is applied by a call of its own",
+                i
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Send `n` concurrent writes to the leader, node 0, and returns the number of calls to apply them to its state
+/// machine.
+async fn burst(router: &Arc<RaftRouter>, n: u64) -> Result<u64> {
+    let raft = router.get_raft_handle(&0).await?;
+    let sto = router.get_storage_handle(&0).await?;
+
+    let before = sto.inner().apply_call_count();
+    let writes = (0..n).map(|i| raft.client_write(ClientWriteRequest::new(req(i))));
+    for res in futures::future::join_all(writes).await {
+        res?;
+    }
+
+    Ok(sto.inner().apply_call_count() - before)
+}
+
+fn req(serial: u64) -> ClientRequest {
+    ClientRequest {
+        client: "0".to_string(),
+        serial,
+        status: format!("request-{}", serial),
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
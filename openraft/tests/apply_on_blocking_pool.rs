@@ -0,0 +1,176 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorageDebug;
+use openraft::State;
+use openraft::Wrapper;
+use tokio::time::Instant;
+
+#[macro_use]
+mod fixtures;
+
+/// Apply on blocking pool test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters on a single threaded runtime, with `apply_on_blocking_pool` set and a state machine
+///   that blocks the thread for a while in every apply.
+/// - write logs, while a ticker measures how long the runtime thread is not available.
+/// - asserts the runtime thread is never blocked by an apply, no election happens, and every log is applied, in order,
+///   which the defensive store checks.
+#[tokio::test(flavor = "current_thread")]
+async fn apply_on_blocking_pool() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            apply_on_blocking_pool: true,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let busy = Duration::from_millis(config.election_timeout_min / 2);
+    for id in 0..3 {
+        router.get_storage_handle(&id).await?.inner().set_apply_busy(busy);
+    }
+
+    let term = router.get_raft_handle(&0).await?.metrics().borrow().current_term;
+
+    tracing::info!("--- write logs with a CPU heavy state machine");
+    let max_gap = Arc::new(Mutex::new(Duration::from_millis(0)));
+    {
+        let ticker = {
+            let max_gap = max_gap.clone();
+            tokio::spawn(async move {
+                let mut last = Instant::now();
+                loop {
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                    let now = Instant::now();
+                    let mut g = max_gap.lock().unwrap();
+                    *g = std::cmp::max(*g, now - last);
+                    last = now;
+                }
+            })
+        };
+
+        router.client_request_many(0, "0", 10).await;
+        n_logs += 10;
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write logs").await?;
+
+        ticker.abort();
+    }
+
+    tracing::info!("--- the runtime thread is not blocked by applying");
+    {
+        let max_gap = *max_gap.lock().unwrap();
+        assert!(max_gap < busy, "runtime thread blocked for {:?}", max_gap);
+    }
+
+    tracing::info!("--- no election happens and every log is applied");
+    {
+        for m in router.latest_metrics().await {
+            assert_eq!(term, m.current_term, "node {}", m.id);
+            assert_eq!(Some(0), m.current_leader, "node {}", m.id);
+            assert_eq!(n_logs, m.last_applied, "node {}", m.id);
+            if m.id == 0 {
+                assert_eq!(State::Leader, m.state);
+            }
+
+            let sto = router.get_storage_handle(&m.id).await?;
+            let sm = sto.get_state_machine().await;
+            assert_eq!(LogId::new(term, n_logs), sm.last_applied_log, "node {}", m.id);
+        }
+    }
+
+    Ok(())
+}
+
+/// Follower long apply test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters, with a follower whose state machine takes longer than the election timeout to
+///   apply logs.
+/// - write logs one by one, so that AppendEntries requests arrive at the follower while it is applying.
+/// - asserts the follower answers every AppendEntries while it is still applying: the leader sees it has replicated all
+///   logs to it before they are applied.
+/// - asserts every log is eventually applied, and no election happens.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn follower_long_apply_does_not_start_election() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            apply_on_blocking_pool: true,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let delay = Duration::from_millis(config.election_timeout_max * 2);
+    router.get_storage_handle(&1).await?.inner().set_apply_delay(delay);
+
+    let term = router.get_raft_handle(&0).await?.metrics().borrow().current_term;
+
+    tracing::info!("--- write logs while the follower is applying");
+    {
+        for i in 0..3 {
+            router.client_request(0, "0", i).await;
+            n_logs += 1;
+        }
+    }
+
+    tracing::info!("--- the leader has replicated every log to the follower before it applies them");
+    {
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(
+                |x| match x.leader_metrics {
+                    Some(ref m) => m.replication.values().all(|r| r.matched.index == n_logs),
+                    None => false,
+                },
+                "replication metrics updated",
+            )
+            .await?;
+
+        let m = router.get_raft_handle(&1).await?.metrics().borrow().clone();
+        assert!(
+            m.last_applied < n_logs,
+            "follower is still applying: {}",
+            m.last_applied
+        );
+    }
+
+    tracing::info!("--- every log is applied and no election happens");
+    {
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "logs applied").await?;
+
+        for m in router.latest_metrics().await {
+            assert_eq!(term, m.current_term, "node {}", m.id);
+            assert_eq!(Some(0), m.current_leader, "node {}", m.id);
+            assert_eq!(n_logs, m.last_applied, "node {}", m.id);
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
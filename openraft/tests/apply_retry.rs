@@ -0,0 +1,81 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::ApplyRetry;
+use openraft::Config;
+use openraft::State;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// Retry applying logs on a transient storage error test.
+///
+/// What does this test do?
+///
+/// - build a cluster of 1 voter and 1 learner, with `apply_retry` allowing 3 retries.
+/// - make the next 2 calls to apply logs fail with a transient error on both nodes and write a log.
+/// - asserts both nodes stay up and apply the log exactly once.
+/// - make more calls fail than the retries allow on the learner and write a log.
+/// - asserts the learner shuts down.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn apply_retry() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            apply_retry: ApplyRetry::Backoff {
+                max_retries: 3,
+                backoff_ms: 10,
+            },
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
+
+    let sto0 = router.get_storage_handle(&0).await?;
+    let sto1 = router.get_storage_handle(&1).await?;
+
+    tracing::info!("--- transient apply faults are retried");
+    {
+        let count0 = sto0.inner().apply_count();
+        let count1 = sto1.inner().apply_count();
+
+        sto0.inner().set_apply_faults(2);
+        sto1.inner().set_apply_faults(2);
+
+        router.client_request_many(0, "0", 1).await;
+        n_logs += 1;
+        router.wait_for_log(&btreeset![0, 1], n_logs, timeout(), "write a log").await?;
+
+        assert_eq!(count0 + 1, sto0.inner().apply_count(), "applied exactly once on node 0");
+        assert_eq!(count1 + 1, sto1.inner().apply_count(), "applied exactly once on node 1");
+
+        router.wait(&0, timeout()).await?.state(State::Leader, "node 0 stays up").await?;
+        router.wait(&1, timeout()).await?.state(State::Learner, "node 1 stays up").await?;
+    }
+
+    tracing::info!("--- a node shuts down when retries are exhausted");
+    {
+        sto1.inner().set_apply_faults(10);
+
+        router.client_request_many(0, "0", 1).await;
+        n_logs += 1;
+        router.wait_for_log(&btreeset![0], n_logs, timeout(), "write a log").await?;
+
+        router.wait(&1, timeout()).await?.state(State::Shutdown, "node 1 shuts down").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,105 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::error::AuditFollowerError;
+use openraft::raft::EntryPayload;
+use openraft::Config;
+use openraft::RaftStorage;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// Raft::audit_follower() test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters and write some logs.
+/// - asserts the audit finds no divergence on either follower.
+/// - overwrite the payload of a log on one follower, keeping the log id, as a silent corruption.
+/// - asserts the audit reports the corrupted log as the first divergent one, and the other follower is still fine.
+/// - asserts auditing on a non-leader, or auditing a node that is not replicated to, is rejected.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn audit_follower() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            // Compare in several chunks.
+            max_payload_entries: 4,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", 20).await;
+    n_logs += 20;
+    router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write logs").await?;
+
+    let leader = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- no divergence");
+    {
+        for id in [1, 2] {
+            let report = leader.audit_follower(id).await?;
+            assert_eq!(id, report.target);
+            assert!(report.start <= 1, "node {}: start: {}", id, report.start);
+            assert_eq!(n_logs + 1, report.end, "node {}", id);
+            assert_eq!(None, report.first_divergent, "node {}", id);
+        }
+    }
+
+    let corrupted = n_logs - 5;
+
+    tracing::info!("--- corrupt log {} on node 2", corrupted);
+    {
+        let sto = router.get_storage_handle(&2).await?;
+        let mut ent = sto.try_get_log_entry(corrupted).await?.unwrap();
+        ent.payload = EntryPayload::Normal(ClientRequest {
+            client: "0".to_string(),
+            serial: 1000,
+            status: "corrupted".to_string(),
+        });
+
+        // Bypass the defensive checks, which reject re-appending a log.
+        sto.inner().append_to_log(&[&ent]).await?;
+    }
+
+    tracing::info!("--- the audit finds the corrupted log");
+    {
+        let report = leader.audit_follower(2).await?;
+        assert_eq!(Some(corrupted), report.first_divergent);
+
+        let report = leader.audit_follower(1).await?;
+        assert_eq!(None, report.first_divergent);
+    }
+
+    tracing::info!("--- audit is rejected on a non-leader or for an unknown node");
+    {
+        let res = router.get_raft_handle(&1).await?.audit_follower(2).await;
+        match res {
+            Err(AuditFollowerError::ForwardToLeader(e)) => assert_eq!(Some(0), e.leader_id),
+            other => panic!("expect ForwardToLeader, got: {:?}", other),
+        }
+
+        let res = leader.audit_follower(9).await;
+        match res {
+            Err(AuditFollowerError::NotFound(9)) => {}
+            other => panic!("expect NotFound, got: {:?}", other),
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
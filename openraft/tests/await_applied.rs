@@ -0,0 +1,71 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+
+#[macro_use]
+mod fixtures;
+
+/// Raft::await_applied() test.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster.
+/// - asserts awaiting an applied log resolves at once and awaiting a future log times out.
+/// - wait for a future log in background, asserts it resolves only when the log is applied.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn await_applied() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    let raft = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- already applied log resolves at once");
+    {
+        raft.await_applied(LogId::new(1, n_logs), Duration::from_millis(0)).await?;
+        raft.await_applied(LogId::new(0, 0), Duration::from_millis(0)).await?;
+    }
+
+    tracing::info!("--- not applied log times out");
+    {
+        let res = raft.await_applied(LogId::new(1, n_logs + 1), Duration::from_millis(200)).await;
+        assert!(res.is_err());
+    }
+
+    tracing::info!("--- wait for a future log in background");
+    {
+        let target = LogId::new(1, n_logs + 3);
+
+        let r = raft.clone();
+        let mut h = tokio::spawn(async move {
+            r.await_applied(target, Duration::from_millis(5000)).await?;
+            let last_applied = r.metrics().borrow().last_applied;
+            Ok::<_, anyhow::Error>(last_applied)
+        });
+
+        router.client_request_many(0, "foo", 2).await;
+        n_logs += 2;
+        router.wait_for_log(&btreeset![0], n_logs, None, "write 2 logs").await?;
+
+        let res = tokio::time::timeout(Duration::from_millis(200), &mut h).await;
+        assert!(res.is_err(), "log {} is not yet applied", target);
+
+        router.client_request_many(0, "foo", 1).await;
+        n_logs += 1;
+
+        let last_applied = h.await??;
+        assert!(last_applied >= target.index);
+        assert_eq!(n_logs, target.index);
+    }
+
+    Ok(())
+}
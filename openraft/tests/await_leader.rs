@@ -0,0 +1,70 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Raft::await_leader() test.
+///
+/// What does this test do?
+///
+/// - bring 3 pristine nodes online and initialize the cluster.
+/// - asserts `await_leader()` on every node resolves to the node that is the leader.
+/// - bring on a cluster of 3 voters and isolate every node, thus no leader can be elected.
+/// - asserts `await_leader()` returns an error on timeout.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn await_leader() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+
+    tracing::info!("--- resolves to the leader after initialize");
+    {
+        let router = Arc::new(RaftRouter::new(config.clone()));
+        for id in 0..3 {
+            router.new_raft_node(id).await;
+        }
+        router.wait_for_state(&btreeset![0, 1, 2], State::Learner, timeout(), "empty").await?;
+
+        router.initialize_from_single_node(0).await?;
+
+        for id in 0..3 {
+            let leader = router.get_raft_handle(&id).await?.await_leader(Duration::from_millis(5_000)).await?;
+            assert_eq!(router.leader().await, Some(leader), "node {} sees the leader", id);
+        }
+
+        let leader = router.leader().await.unwrap();
+        let m = router.get_raft_handle(&leader).await?.metrics().borrow().clone();
+        assert_eq!(State::Leader, m.state);
+    }
+
+    tracing::info!("--- errors on timeout if no leader can form");
+    {
+        let router = Arc::new(RaftRouter::new(config.clone()));
+        router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+        // Members have to be reachable to initialize a cluster, thus isolate them after that.
+        for id in 0..3 {
+            router.isolate_node(id).await;
+        }
+        router
+            .wait_for_state(&btreeset![1], State::Candidate, timeout(), "node 1 starts an election")
+            .await?;
+
+        let res = router.get_raft_handle(&1).await?.await_leader(Duration::from_millis(1_000)).await;
+        assert!(res.is_err(), "no leader without a quorum: {:?}", res);
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::error::ClientReadError;
+use openraft::raft::ClientWriteRequest;
+use openraft::raft::VoteRequest;
+use openraft::ClientWriteError;
+use openraft::Config;
+use openraft::LogId;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Client request errors on a non-leader test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters.
+/// - asserts a write and a read on a follower are rejected with `ForwardToLeader` and the leader id.
+/// - isolate a write on the leader so that it can not be committed, then make the leader step down with a vote request
+///   of a greater term.
+/// - asserts the pending write is rejected with `LeaderStepped`.
+/// - isolate 2 nodes so that the third one can not elect a leader.
+/// - asserts a write and a read on it are rejected with `NoLeader`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn client_not_leader_errors() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- write and read on a follower");
+    {
+        let r1 = router.get_raft_handle(&1).await?;
+
+        let res = r1.client_write(ClientWriteRequest::new(req(1))).await;
+        match res {
+            Err(ClientWriteError::ForwardToLeader(e)) => assert_eq!(Some(0), e.leader_id),
+            _ => panic!("expect ForwardToLeader, got: {:?}", res),
+        }
+
+        let res = r1.client_read().await;
+        match res {
+            Err(ClientReadError::ForwardToLeader(e)) => assert_eq!(Some(0), e.leader_id),
+            _ => panic!("expect ForwardToLeader, got: {:?}", res),
+        }
+    }
+
+    tracing::info!("--- the leader steps down while a write is pending");
+    {
+        router.isolate_node(1).await;
+        router.isolate_node(2).await;
+
+        let r0 = router.get_raft_handle(&0).await?;
+        let pending = {
+            let r0 = r0.clone();
+            tokio::spawn(async move { r0.client_write(ClientWriteRequest::new(req(2))).await })
+        };
+
+        n_logs += 1;
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|x| x.last_log_index == n_logs, "the write is appended")
+            .await?;
+
+        // A vote request of a greater term reverts the leader to follower.
+        r0.vote(VoteRequest {
+            term: 100,
+            candidate_id: 1,
+            last_log_id: LogId::new(100, 100),
+        })
+        .await?;
+
+        let res = pending.await?;
+        match res {
+            Err(ClientWriteError::LeaderStepped(e)) => {
+                assert_eq!(0, e.node_id);
+                assert_eq!(1, e.term);
+            }
+            _ => panic!("expect LeaderStepped, got: {:?}", res),
+        }
+    }
+
+    tracing::info!("--- write and read on a node that can not elect a leader");
+    {
+        router.isolate_node(0).await;
+        router.restore_node(2).await;
+
+        router
+            .wait_for_state(&btreeset![2], State::Candidate, timeout(), "node 2 becomes candidate")
+            .await?;
+
+        let r2 = router.get_raft_handle(&2).await?;
+
+        let res = r2.client_write(ClientWriteRequest::new(req(3))).await;
+        match res {
+            Err(ClientWriteError::NoLeader(e)) => assert_eq!(2, e.node_id),
+            _ => panic!("expect NoLeader, got: {:?}", res),
+        }
+
+        let res = r2.client_read().await;
+        match res {
+            Err(ClientReadError::NoLeader(e)) => assert_eq!(2, e.node_id),
+            _ => panic!("expect NoLeader, got: {:?}", res),
+        }
+    }
+
+    Ok(())
+}
+
+fn req(serial: u64) -> ClientRequest {
+    ClientRequest {
+        client: "0".to_string(),
+        serial,
+        status: format!("request-{}", serial),
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use fixtures::StoreWithDefensive;
+use maplit::btreeset;
+use openraft::error::ClientReadError;
+use openraft::raft::AppliedView;
+use openraft::raft::EntryPayload;
+use openraft::Config;
+use openraft::RaftStorage;
+use openraft::RaftStorageDebug;
+
+#[macro_use]
+mod fixtures;
+
+/// Raft::read_snapshot() test.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster and write some logs.
+/// - open a view on the leader, assert it sees every log acked before it.
+/// - write more logs between two reads under the same view, assert both reads observe the state at the view, not the
+///   state after the writes.
+/// - open a view on a follower, assert it is forwarded to the leader.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn client_read_snapshot() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.client_request_many(0, "foo", 10).await;
+    n_logs += 10;
+
+    let leader = router.get_raft_handle(&0).await?;
+    let sto = router.get_storage_handle(&0).await?;
+
+    tracing::info!("--- a view sees every acked write");
+    let view = {
+        let view = leader.read_snapshot().await?;
+
+        assert_eq!(n_logs, view.read_index.index);
+        assert!(view.last_applied >= view.read_index.index);
+        assert_eq!(leader.metrics().borrow().current_term, view.term);
+
+        view
+    };
+
+    tracing::info!("--- two reads under one view observe the same version");
+    {
+        let first = read_at(&view, &sto, "foo").await?;
+        assert_eq!(Some("request-9".to_string()), first, "the last write before the view");
+
+        router.client_request_many(0, "foo", 5).await;
+        n_logs += 5;
+        router.wait_for_log(&btreeset![0], n_logs, None, "write between reads").await?;
+
+        let second = read_at(&view, &sto, "foo").await?;
+        assert_eq!(first, second);
+
+        let sm = sto.get_state_machine().await;
+        assert!(
+            sm.last_applied_log.index > view.last_applied,
+            "the state machine moves on"
+        );
+        assert_eq!(
+            Some(&"request-4".to_string()),
+            sm.client_status.get("foo"),
+            "the write after the view"
+        );
+    }
+
+    tracing::info!("--- a new view observes the newer version");
+    {
+        let newer = leader.read_snapshot().await?;
+        assert_eq!(n_logs, newer.read_index.index);
+        assert!(newer.last_applied > view.last_applied);
+
+        let status = read_at(&newer, &sto, "foo").await?;
+        assert_eq!(Some("request-4".to_string()), status);
+    }
+
+    tracing::info!("--- a view on a follower is forwarded to the leader");
+    {
+        let follower = router.get_raft_handle(&1).await?;
+        let res = follower.read_snapshot().await;
+        match res {
+            Err(ClientReadError::ForwardToLeader(e)) => {
+                assert_eq!(Some(0), e.leader_id);
+            }
+            _ => panic!("expect ForwardToLeader, got: {:?}", res),
+        }
+    }
+
+    Ok(())
+}
+
+/// Emulates a query on an MVCC state machine: it returns the status of `client` at the version pinned by the view, by
+/// replaying the logs upto `AppliedView::last_applied`, no matter how many logs are applied since.
+async fn read_at(view: &AppliedView, sto: &Arc<StoreWithDefensive>, client: &str) -> Result<Option<String>> {
+    let sm = sto.get_state_machine().await;
+    assert!(sm.last_applied_log.index >= view.last_applied);
+
+    let logs = sto.get_log_entries(1..=view.last_applied).await?;
+    let status = logs
+        .iter()
+        .filter_map(|ent| match &ent.payload {
+            EntryPayload::Normal(req) if req.client == client => Some(req.status.clone()),
+            _ => None,
+        })
+        .last();
+
+    Ok(status)
+}
@@ -0,0 +1,84 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+/// Client response term test.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster.
+/// - write and read on the leader and record the term in the responses.
+/// - isolate the leader to elect a new one.
+/// - write and read on the new leader, assert the reported term increases.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn client_term_after_leader_change() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- write and read on the first leader");
+    let (write_term, read_term) = {
+        let raft = router.get_raft_handle(&0).await?;
+
+        let resp = raft.client_write(ClientWriteRequest::new(req("0", 0))).await?;
+        let read = raft.client_read().await?;
+
+        assert_eq!(resp.log_id.term, resp.term);
+        assert_eq!(resp.term, read.term);
+        (resp.term, read.term)
+    };
+
+    tracing::info!("--- isolate the leader and wait for a new one");
+    let new_leader = {
+        router.isolate_node(0).await;
+
+        let metrics = router
+            .wait(&1, timeout())
+            .await?
+            .metrics(
+                |x| x.current_leader.is_some() && x.current_leader != Some(0),
+                "new leader elected",
+            )
+            .await?;
+        metrics.current_leader.unwrap()
+    };
+
+    tracing::info!("--- write and read on the new leader, the term increases");
+    {
+        let raft = router.get_raft_handle(&new_leader).await?;
+
+        let resp = raft.client_write(ClientWriteRequest::new(req("0", 1))).await?;
+        let read = raft.client_read().await?;
+
+        assert!(resp.term > write_term, "{} > {}", resp.term, write_term);
+        assert!(read.term > read_term, "{} > {}", read.term, read_term);
+        assert_eq!(resp.term, read.term);
+    }
+
+    Ok(())
+}
+
+fn req(client: &str, serial: u64) -> ClientRequest {
+    ClientRequest {
+        client: client.to_string(),
+        serial,
+        status: format!("request-{}", serial),
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
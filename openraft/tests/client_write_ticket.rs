@@ -0,0 +1,96 @@
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::error::ClientWriteError;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// Raft::client_write_ticket() test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters, with a slow state machine on the leader.
+/// - submit a write with `client_write_ticket()`.
+/// - asserts the ticket is returned before the write is applied, and it is the log id of the appended entry.
+/// - asserts the future resolves to a response with the same log id, and the ticket is then known to be committed.
+/// - submit a write to a follower.
+/// - asserts it is refused with `ForwardToLeader` and no ticket.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn client_write_ticket() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let apply_delay = Duration::from_millis(1000);
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let sto = router.get_storage_handle(&0).await?;
+    sto.inner().set_apply_delay(apply_delay);
+
+    let raft = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- a ticket is returned once the write is appended");
+    {
+        let start = Instant::now();
+        let (ticket, done) = raft.client_write_ticket(ClientWriteRequest::new(req(0))).await?;
+        let latency = start.elapsed();
+        n_logs += 1;
+
+        tracing::info!(?latency, "ticket: {}", ticket);
+
+        assert_eq!(n_logs, ticket.index);
+        assert!(latency < apply_delay, "do not wait for apply: {:?}", latency);
+        assert_eq!(Some(ticket), raft.get_log_id(ticket.index).await?);
+
+        let resp = done.await?;
+        assert_eq!(ticket, resp.log_id);
+        assert!(raft.is_committed(ticket).await?);
+
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "ticket write is applied").await?;
+        for id in 1..3 {
+            let committed = router.get_raft_handle(&id).await?.is_committed(ticket).await?;
+            assert!(committed, "node {}", id);
+        }
+    }
+
+    tracing::info!("--- a write to a follower gets no ticket");
+    {
+        let follower = router.get_raft_handle(&1).await?;
+        let res = follower.client_write_ticket(ClientWriteRequest::new(req(1))).await;
+
+        match res {
+            Err(ClientWriteError::ForwardToLeader(e)) => assert_eq!(Some(0), e.leader_id),
+            Err(err) => panic!("expect ForwardToLeader, got: {:?}", err),
+            Ok((ticket, _)) => panic!("expect ForwardToLeader, got a ticket: {}", ticket),
+        }
+
+        let metrics = follower.metrics().borrow().clone();
+        assert_eq!(n_logs, metrics.last_log_index);
+    }
+
+    Ok(())
+}
+
+fn req(serial: u64) -> ClientRequest {
+    ClientRequest {
+        client: "0".to_string(),
+        serial,
+        status: format!("request-{}", serial),
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,94 @@
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::AckOn;
+use openraft::Config;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// Client write ack-on-commit test.
+///
+/// What does this test do?
+///
+/// - build a single node cluster with a slow state machine, for each of `AckOn::Apply` and `AckOn::Commit`.
+/// - send a client write to each of them and measure the latency.
+/// - asserts that with `AckOn::Commit` the write returns before it is applied, without application data.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn client_writes_ack_on_commit() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let apply_delay = Duration::from_millis(500);
+
+    tracing::info!("--- ack on apply");
+    {
+        let (latency, data) = write_with_slow_apply(AckOn::Apply, apply_delay).await?;
+        tracing::info!(?latency, "ack on apply");
+
+        assert!(latency >= apply_delay, "ack on apply waits for apply: {:?}", latency);
+        assert!(data.is_some());
+    }
+
+    tracing::info!("--- ack on commit");
+    {
+        let (latency, data) = write_with_slow_apply(AckOn::Commit, apply_delay).await?;
+        tracing::info!(?latency, "ack on commit");
+
+        assert!(
+            latency < apply_delay,
+            "ack on commit does not wait for apply: {:?}",
+            latency
+        );
+        assert!(data.is_none());
+    }
+
+    Ok(())
+}
+
+/// Write one request to a single node cluster whose state machine is slow. Returns the latency and response data.
+async fn write_with_slow_apply(
+    ack: AckOn,
+    apply_delay: Duration,
+) -> Result<(Duration, Option<memstore::ClientResponse>)> {
+    let config = Arc::new(
+        Config {
+            client_write_ack: ack,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    let sto = router.get_storage_handle(&0).await?;
+    sto.inner().set_apply_delay(apply_delay);
+
+    let raft = router.get_raft_handle(&0).await?;
+
+    let start = Instant::now();
+    let resp = raft
+        .client_write(ClientWriteRequest::new(ClientRequest {
+            client: "0".to_string(),
+            serial: 0,
+            status: "request-0".to_string(),
+        }))
+        .await?;
+    let latency = start.elapsed();
+    n_logs += 1;
+
+    assert_eq!(n_logs, resp.log_id.index);
+
+    // The log is applied eventually, in either mode.
+    router.wait_for_log(&btreeset![0], n_logs, Some(Duration::from_millis(5000)), "applied").await?;
+
+    Ok((latency, resp.data))
+}
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::error::ClientWriteError;
+use openraft::Config;
+use openraft::RaftStorageDebug;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Client write rejected by the state machine.
+///
+/// What does this test do?
+///
+/// - brings 2 nodes online: one leader and one learner.
+/// - write a request that the state machine rejects.
+/// - asserts the client receives a `ClientWriteError::ApplicationError`, the entry is still applied on every node and
+///   the cluster keeps serving writes.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn client_writes_application_error() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_raft_node(0).await;
+    router.new_raft_node(1).await;
+
+    let mut n_logs = 0;
+
+    tracing::info!("--- initializing single node cluster");
+    {
+        router.initialize_with(0, btreeset![0]).await?;
+        n_logs += 1;
+        router.wait_for_state(&btreeset![0], State::Leader, None, "init").await?;
+
+        router.add_learner(0, 1).await?;
+        router.wait_for_log(&btreeset![0, 1], n_logs, None, "add learner").await?;
+    }
+
+    tracing::info!("--- write a request that is rejected by the state machine");
+    {
+        let res = router
+            .send_client_request(0, ClientRequest {
+                client: "foo".to_string(),
+                serial: 1,
+                status: "".to_string(),
+            })
+            .await;
+        n_logs += 1;
+
+        match res {
+            Err(ClientWriteError::ApplicationError(e)) => {
+                tracing::info!("rejected: {}", e);
+                assert!(e.to_string().contains("empty status"));
+            }
+            Err(e) => panic!("expect ApplicationError, got: {:?}", e),
+            Ok(x) => panic!("expect ApplicationError, got: {:?}", x),
+        }
+
+        router.wait_for_log(&btreeset![0, 1], n_logs, None, "rejected entry is applied").await?;
+
+        for node_id in 0..2 {
+            let sto = router.get_storage_handle(&node_id).await?;
+            assert!(sto.get_state_machine().await.client_status.get("foo").is_none());
+        }
+    }
+
+    tracing::info!("--- the node is still healthy and accepts writes");
+    {
+        router.client_request(0, "foo", 2).await;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![0, 1], n_logs, None, "write after rejection").await?;
+        router.wait_for_state(&btreeset![0], State::Leader, None, "still leader").await?;
+
+        for node_id in 0..2 {
+            let sto = router.get_storage_handle(&node_id).await?;
+            assert!(sto.get_state_machine().await.client_status.get("foo").is_some());
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::error::ClientWriteError;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// Cancelable client write test.
+///
+/// What does this test do?
+///
+/// - build a single node cluster with a slow state machine.
+/// - send a client write and cancel it before it is applied.
+/// - asserts the write returns `Cancelled` with `maybe_committed` at once, and the entry is still applied.
+/// - send a client write with a cancellation signal that is already fired.
+/// - asserts the write returns `Cancelled` without `maybe_committed`, and nothing is appended.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn client_writes_cancelable() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let apply_delay = Duration::from_millis(2000);
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    let sto = router.get_storage_handle(&0).await?;
+    sto.inner().set_apply_delay(apply_delay);
+
+    let raft = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- cancel a write that is waiting to be applied");
+    {
+        let start = Instant::now();
+        let res = raft
+            .client_write_cancelable(
+                ClientWriteRequest::new(req(0)),
+                tokio::time::sleep(Duration::from_millis(200)),
+            )
+            .await;
+        let latency = start.elapsed();
+        n_logs += 1;
+
+        tracing::info!(?latency, "canceled write: {:?}", res);
+
+        match res {
+            Err(ClientWriteError::Cancelled(e)) => {
+                assert!(e.maybe_committed);
+            }
+            _ => {
+                panic!("expect ClientWriteError::Cancelled, got: {:?}", res);
+            }
+        }
+        assert!(latency < apply_delay, "stop waiting once canceled: {:?}", latency);
+
+        // The entry is not aborted.
+        router.wait_for_log(&btreeset![0], n_logs, timeout(), "canceled write is applied").await?;
+    }
+
+    tracing::info!("--- a write canceled before it is sent is never appended");
+    {
+        let res = raft.client_write_cancelable(ClientWriteRequest::new(req(1)), std::future::ready(())).await;
+
+        match res {
+            Err(ClientWriteError::Cancelled(e)) => {
+                assert!(!e.maybe_committed);
+            }
+            _ => {
+                panic!("expect ClientWriteError::Cancelled, got: {:?}", res);
+            }
+        }
+
+        let metrics = raft.metrics().borrow().clone();
+        assert_eq!(n_logs, metrics.last_log_index);
+    }
+
+    Ok(())
+}
+
+fn req(serial: u64) -> ClientRequest {
+    ClientRequest {
+        client: "0".to_string(),
+        serial,
+        status: format!("request-{}", serial),
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
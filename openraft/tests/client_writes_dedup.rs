@@ -0,0 +1,110 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// Config::dedup_window test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters with `dedup_window`, and a slow state machine on the leader.
+/// - send a write with a request id, and a retry of it before the first one is applied.
+/// - asserts both are answered with the same log id, and only one log is appended.
+/// - retry it again after it is applied.
+/// - asserts it is answered with the same log id, and no log is appended.
+/// - retry it after the window passes.
+/// - asserts it is appended again.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn client_writes_dedup() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let dedup_window = 2000;
+
+    let config = Arc::new(
+        Config {
+            dedup_window: Some(dedup_window),
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let sto = router.get_storage_handle(&0).await?;
+    sto.inner().set_apply_delay(Duration::from_millis(500));
+
+    let raft = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- a retry before the write is applied waits for it");
+    let first_log_id = {
+        let r = raft.clone();
+        let first =
+            tokio::spawn(async move { r.client_write(ClientWriteRequest::new(req(0)).with_request_id("x")).await });
+
+        // Let the first one be appended first.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let retry = raft.client_write(ClientWriteRequest::new(req(0)).with_request_id("x")).await?;
+
+        let first = first.await??;
+        n_logs += 1;
+
+        assert_eq!(first.log_id, retry.log_id);
+        assert_eq!(n_logs, first.log_id.index);
+
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "a single log for 2 writes").await?;
+        first.log_id
+    };
+
+    tracing::info!("--- a retry after the write is applied is answered at once");
+    {
+        let retry = raft.client_write(ClientWriteRequest::new(req(0)).with_request_id("x")).await?;
+        assert_eq!(first_log_id, retry.log_id);
+        assert!(retry.data.is_some());
+
+        let metrics = raft.metrics().borrow().clone();
+        assert_eq!(n_logs, metrics.last_log_index, "no log is appended for a retry");
+    }
+
+    tracing::info!("--- a write with another request id is appended");
+    {
+        let resp = raft.client_write(ClientWriteRequest::new(req(1)).with_request_id("y")).await?;
+        n_logs += 1;
+        assert_eq!(n_logs, resp.log_id.index);
+    }
+
+    tracing::info!("--- a retry out of the window is appended again");
+    {
+        tokio::time::sleep(Duration::from_millis(dedup_window)).await;
+
+        let retry = raft.client_write(ClientWriteRequest::new(req(0)).with_request_id("x")).await?;
+        n_logs += 1;
+        assert_eq!(n_logs, retry.log_id.index);
+
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "retry out of window").await?;
+    }
+
+    Ok(())
+}
+
+fn req(serial: u64) -> ClientRequest {
+    ClientRequest {
+        client: "0".to_string(),
+        serial,
+        status: format!("request-{}", serial),
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,102 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::raft::EntryPayload;
+use openraft::ClientWriteError;
+use openraft::Config;
+use openraft::RaftStorage;
+
+#[macro_use]
+mod fixtures;
+
+/// Client write entry size limit test.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster with `max_entry_size_bytes` set.
+/// - write an oversized entry, asserts it is rejected with `EntryTooLarge` and the log does not grow.
+/// - write an entry within the limit, asserts it is replicated and the oversized one never appears in any log.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn client_writes_entry_too_large() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let limit: u64 = 256;
+
+    let config = Arc::new(
+        Config {
+            max_entry_size_bytes: Some(limit),
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let leader = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- an oversized entry is rejected before append");
+    {
+        let big = req(0, &"x".repeat(limit as usize));
+        let size = EntryPayload::Normal(big.clone()).size_hint(&config.codec);
+        assert!(size > limit);
+
+        let res = leader.client_write(ClientWriteRequest::new(big)).await;
+        match res {
+            Err(ClientWriteError::EntryTooLarge(e)) => {
+                assert_eq!(size, e.size);
+                assert_eq!(limit, e.limit);
+            }
+            _ => panic!("expect EntryTooLarge, got: {:?}", res),
+        }
+
+        let metrics = leader.metrics().borrow().clone();
+        assert_eq!(n_logs, metrics.last_log_index, "the log does not grow");
+    }
+
+    tracing::info!("--- an entry within the limit is replicated");
+    {
+        leader.client_write(ClientWriteRequest::new(req(1, "ok"))).await?;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "valid entry").await?;
+    }
+
+    tracing::info!("--- the oversized entry never appears in any log");
+    {
+        for id in 0..3 {
+            let sto = router.get_storage_handle(&id).await?;
+            let logs = sto.get_log_entries(1..=n_logs).await?;
+
+            let statuses = logs
+                .iter()
+                .filter_map(|ent| match &ent.payload {
+                    EntryPayload::Normal(r) => Some(r.status.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(vec!["ok".to_string()], statuses, "node {}", id);
+        }
+    }
+
+    Ok(())
+}
+
+fn req(serial: u64, status: &str) -> ClientRequest {
+    ClientRequest {
+        client: "0".to_string(),
+        serial,
+        status: status.to_string(),
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
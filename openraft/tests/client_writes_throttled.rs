@@ -0,0 +1,118 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::ClientWriteError;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+/// Client write back-pressure test.
+///
+/// What does this test do?
+///
+/// - build a 2 voters cluster with `max_uncommitted_entries` set.
+/// - isolate the follower so that the leader can not commit any entry.
+/// - write until the leader has `max_uncommitted_entries` uncommitted entries.
+/// - asserts the next write is throttled and the log of the leader does not grow.
+/// - restore the follower, asserts the pending writes are committed and new writes are accepted.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn client_writes_throttled() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let max_uncommitted = 5;
+
+    // A large election timeout keeps the isolated follower from starting an election during the test.
+    let config = Arc::new(
+        Config {
+            max_uncommitted_entries: Some(max_uncommitted),
+            election_timeout_min: 10_000,
+            election_timeout_max: 12_000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1}, btreeset! {}).await?;
+
+    let leader = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- isolate the follower, writes can not be committed");
+    let pending = {
+        router.isolate_node(1).await;
+
+        let mut pending = vec![];
+        for i in 0..max_uncommitted {
+            let l = leader.clone();
+            pending.push(tokio::spawn(async move {
+                l.client_write(ClientWriteRequest::new(req("pending", i))).await
+            }));
+        }
+        n_logs += max_uncommitted;
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|x| x.last_log_index == n_logs, "uncommitted entries appended")
+            .await?;
+
+        pending
+    };
+
+    tracing::info!("--- the leader throttles new writes");
+    {
+        for i in 0..3 {
+            let res = leader.client_write(ClientWriteRequest::new(req("throttled", i))).await;
+            match res {
+                Err(ClientWriteError::Throttled(e)) => {
+                    assert_eq!(max_uncommitted, e.uncommitted);
+                    assert_eq!(max_uncommitted, e.max);
+                }
+                _ => panic!("expect Throttled, got: {:?}", res),
+            }
+        }
+
+        let metrics = leader.metrics().borrow().clone();
+        assert_eq!(n_logs, metrics.last_log_index, "the log does not grow");
+        assert_eq!(n_logs - max_uncommitted, metrics.last_applied);
+    }
+
+    tracing::info!("--- restore the follower, pending writes are committed");
+    {
+        router.restore_node(1).await;
+
+        for h in pending {
+            h.await??;
+        }
+        router.wait_for_log(&btreeset![0, 1], n_logs, timeout(), "pending writes committed").await?;
+    }
+
+    tracing::info!("--- new writes are accepted");
+    {
+        leader.client_write(ClientWriteRequest::new(req("accepted", 0))).await?;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![0, 1], n_logs, timeout(), "write accepted").await?;
+    }
+
+    Ok(())
+}
+
+fn req(client: &str, serial: u64) -> ClientRequest {
+    ClientRequest {
+        client: client.to_string(),
+        serial,
+        status: format!("request-{}", serial),
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
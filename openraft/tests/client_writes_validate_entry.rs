@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::raft::EntryPayload;
+use openraft::ClientWriteError;
+use openraft::Config;
+use openraft::RaftStorage;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// Client write entry validation test.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster, the store of the leader rejects large entries.
+/// - write an oversized entry, asserts it is rejected with `InvalidEntry` and the log does not grow.
+/// - write a valid entry, asserts it is replicated and the oversized one never appears in any log.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn client_writes_validate_entry() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let sto0 = router.get_storage_handle(&0).await?;
+    sto0.inner().set_max_status_size(Some(16));
+
+    let leader = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- an oversized entry is rejected before append");
+    {
+        let res = leader.client_write(ClientWriteRequest::new(req(0, &"x".repeat(17)))).await;
+        match res {
+            Err(ClientWriteError::InvalidEntry(e)) => {
+                assert!(e.to_string().contains("status too large: 17 > 16"), "{}", e);
+            }
+            _ => panic!("expect InvalidEntry, got: {:?}", res),
+        }
+
+        let metrics = leader.metrics().borrow().clone();
+        assert_eq!(n_logs, metrics.last_log_index, "the log does not grow");
+    }
+
+    tracing::info!("--- a valid entry is replicated");
+    {
+        leader.client_write(ClientWriteRequest::new(req(1, "ok"))).await?;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "valid entry").await?;
+    }
+
+    tracing::info!("--- the oversized entry never appears in any log");
+    {
+        for id in 0..3 {
+            let sto = router.get_storage_handle(&id).await?;
+            let logs = sto.get_log_entries(1..=n_logs).await?;
+
+            let statuses = logs
+                .iter()
+                .filter_map(|ent| match &ent.payload {
+                    EntryPayload::Normal(r) => Some(r.status.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(vec!["ok".to_string()], statuses, "node {}", id);
+        }
+    }
+
+    Ok(())
+}
+
+fn req(serial: u64, status: &str) -> ClientRequest {
+    ClientRequest {
+        client: "0".to_string(),
+        serial,
+        status: status.to_string(),
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::CommitAdvance;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+/// Eager commit advancement test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters with `CommitAdvance::Eager` and a long heartbeat interval.
+/// - write a log, asserts it is committed on the leader and applied on every follower well within a heartbeat interval,
+///   i.e., within one round of acknowledgements.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn commit_advance_eager() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let (router, config, mut n_logs) = setup(CommitAdvance::Eager).await?;
+    let half_heartbeat = Duration::from_millis(config.heartbeat_interval / 2);
+
+    tracing::info!("--- a write is committed within one ack round");
+    {
+        let start = tokio::time::Instant::now();
+        router.client_request(0, "0", 1).await;
+        n_logs += 1;
+        assert!(start.elapsed() < half_heartbeat, "committed in {:?}", start.elapsed());
+
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, Some(half_heartbeat), "followers commit").await?;
+    }
+
+    Ok(())
+}
+
+/// Batched commit advancement test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters with `CommitAdvance::Batched`.
+/// - write logs, asserts they are committed and applied on every node.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn commit_advance_batched() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let (router, _config, mut n_logs) = setup(CommitAdvance::Batched).await?;
+
+    tracing::info!("--- writes are committed with the next tick");
+    {
+        router.client_request_many(0, "0", 3).await;
+        n_logs += 3;
+
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "followers commit").await?;
+    }
+
+    Ok(())
+}
+
+async fn setup(commit_advance: CommitAdvance) -> Result<(Arc<RaftRouter>, Arc<Config>, u64)> {
+    let config = Arc::new(
+        Config {
+            heartbeat_interval: 500,
+            election_timeout_min: 3000,
+            election_timeout_max: 4000,
+            commit_advance,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    Ok((router, config, n_logs))
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -81,6 +81,7 @@ async fn compaction() -> Result<()> {
     let sto1 = router.new_store(1).await;
     sto1.append_to_log(&[&Entry {
         log_id: LogId { term: 1, index: 1 },
+        timestamp_ms: 0,
         payload: EntryPayload::Blank,
     }])
     .await?;
@@ -126,6 +127,7 @@ async fn compaction() -> Result<()> {
                 prev_log_id: LogId::new(1, 2),
                 entries: vec![],
                 leader_commit: LogId::new(0, 0),
+                relay_to: vec![],
             })
             .await?;
 
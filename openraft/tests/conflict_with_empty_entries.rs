@@ -67,10 +67,12 @@ async fn conflict_with_empty_entries() -> Result<()> {
         entries: vec![
             Entry {
                 log_id: (1, 1).into(),
+                timestamp_ms: 0,
                 payload: EntryPayload::Blank,
             },
             Entry {
                 log_id: (1, 2).into(),
+                timestamp_ms: 0,
                 payload: EntryPayload::Normal(ClientRequest {
                     client: "foo".to_string(),
                     serial: 1,
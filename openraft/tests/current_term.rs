@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::NodeId;
+
+#[macro_use]
+mod fixtures;
+
+/// Raft::current_term() test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters.
+/// - force several elections by isolating the leader, and restoring it after another leader is elected.
+/// - sample `current_term()` of every node meanwhile.
+/// - asserts the term of a node never decreases, and every new leader is elected in a greater term.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn current_term() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let mut last_terms = BTreeMap::new();
+    for id in 0..3 {
+        let term = router.get_raft_handle(&id).await?.current_term();
+        assert_eq!(1, term, "node {}", id);
+        last_terms.insert(id, term);
+    }
+
+    let mut leader = 0;
+    let mut leader_term = 1;
+
+    for i in 0..3 {
+        tracing::info!("--- round {}: isolate leader {} to force an election", i, leader);
+        router.isolate_node(leader).await;
+
+        let start = Instant::now();
+        let new_leader = loop {
+            assert!(start.elapsed() < timeout().unwrap(), "timeout waiting for a new leader");
+            sample_terms(&router, &mut last_terms).await?;
+
+            if let Some(l) = router.leader().await {
+                if l != leader {
+                    break l;
+                }
+            }
+
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        };
+
+        let new_term = router.get_raft_handle(&new_leader).await?.current_term();
+        tracing::info!("--- round {}: new leader {} in term {}", i, new_leader, new_term);
+        assert!(new_term > leader_term, "a new leader has a greater term");
+
+        router.restore_node(leader).await;
+
+        // The deposed leader learns the new term from the new leader.
+        router
+            .wait(&leader, timeout())
+            .await?
+            .metrics(|x| x.current_term >= new_term, "deposed leader learns the new term")
+            .await?;
+        sample_terms(&router, &mut last_terms).await?;
+        assert!(router.get_raft_handle(&leader).await?.current_term() >= new_term);
+
+        leader = new_leader;
+        leader_term = new_term;
+    }
+
+    Ok(())
+}
+
+/// Assert the term of every node does not go backward since the last sample.
+async fn sample_terms(router: &Arc<RaftRouter>, last_terms: &mut BTreeMap<NodeId, u64>) -> Result<()> {
+    for (id, last) in last_terms.iter_mut() {
+        let term = router.get_raft_handle(id).await?.current_term();
+        assert!(term >= *last, "node {} term never decreases: {} >= {}", id, term, last);
+        *last = term;
+    }
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
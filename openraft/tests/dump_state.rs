@@ -0,0 +1,120 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Raft::dump_state() test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters and write some logs.
+/// - asserts the dump of the leader and of a follower have the documented fields with the values in metrics.
+/// - asserts the JSON rendering contains every documented field.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn dump_state() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", 10).await;
+    n_logs += 10;
+    router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write logs").await?;
+
+    tracing::info!("--- dump the leader");
+    {
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(
+                |x| match x.leader_metrics {
+                    Some(ref m) => m.replication.values().all(|r| r.matched.index == n_logs),
+                    None => false,
+                },
+                "replication metrics updated",
+            )
+            .await?;
+
+        let dump = router.get_raft_handle(&0).await?.dump_state().await?;
+
+        assert_eq!(0, dump.id);
+        assert_eq!(State::Leader, dump.state);
+        assert_eq!(1, dump.current_term);
+        assert_eq!(Some(0), dump.current_leader);
+        assert_eq!(LogId::new(1, n_logs), dump.committed);
+        assert_eq!(LogId::new(1, n_logs), dump.last_applied);
+        assert_eq!(LogId::new(1, n_logs), dump.last_log_id);
+        assert_eq!(&btreeset! {0,1,2}, dump.effective_membership.membership.all_nodes());
+        assert_eq!(None, dump.pending_membership);
+
+        assert_eq!(
+            btreeset! {1,2},
+            dump.replication.keys().cloned().collect::<BTreeSet<_>>()
+        );
+        for (id, repl) in dump.replication.iter() {
+            assert_eq!(LogId::new(1, n_logs), repl.matched, "replication to {}", id);
+        }
+
+        let last = dump.state_transitions.last().unwrap();
+        assert_eq!(State::Leader, last.to);
+        assert_eq!(1, last.term);
+        assert!(last.unix_time_ms > 0);
+    }
+
+    tracing::info!("--- dump a follower");
+    {
+        let dump = router.get_raft_handle(&1).await?.dump_state().await?;
+
+        assert_eq!(1, dump.id);
+        assert_eq!(State::Follower, dump.state);
+        assert_eq!(1, dump.current_term);
+        assert_eq!(Some(0), dump.current_leader);
+        assert_eq!(LogId::new(1, n_logs), dump.last_log_id);
+        assert_eq!(&btreeset! {0,1,2}, dump.effective_membership.membership.all_nodes());
+        assert!(dump.replication.is_empty(), "a follower replicates to no one");
+        assert_eq!(Some(State::Follower), dump.state_transitions.last().map(|t| t.to));
+    }
+
+    tracing::info!("--- the JSON blob contains every field");
+    {
+        let dump = router.get_raft_handle(&0).await?.dump_state().await?;
+        let json: serde_json::Value = serde_json::from_str(&dump.to_json()?)?;
+
+        for field in [
+            "id",
+            "state",
+            "current_term",
+            "voted_for",
+            "current_leader",
+            "committed",
+            "last_applied",
+            "last_log_id",
+            "snapshot_last_log_id",
+            "effective_membership",
+            "pending_membership",
+            "replication",
+            "state_transitions",
+        ] {
+            assert!(json.get(field).is_some(), "field {} in {}", field, json);
+        }
+        assert_eq!(json["current_term"], 1);
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
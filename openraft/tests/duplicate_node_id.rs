@@ -0,0 +1,82 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+/// Duplicate node id detection test.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster.
+/// - detach node 1 from the network, keep it running, and start another process with id 1.
+/// - asserts the leader takes the new process as a restart of node 1, not a duplicate.
+/// - attach the first process with id 1 again, so that both respond as node 1.
+/// - asserts the leader detects the duplicate node id and reports it in metrics.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn duplicate_node_id() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // A large election timeout keeps the detached node from starting an election during the test.
+    let config = Arc::new(
+        Config {
+            heartbeat_interval: 100,
+            election_timeout_min: 3000,
+            election_timeout_max: 4000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- replace node 1 with another process with id 1");
+    let (first, first_sto) = {
+        let (node, sto) = router.remove_node(1).await.ok_or_else(|| anyhow::anyhow!("node not found"))?;
+
+        router.new_raft_node(1).await;
+        router
+            .wait_for_log(
+                &btreeset![1],
+                n_logs,
+                timeout(),
+                "the second process with id 1 catches up",
+            )
+            .await?;
+
+        let metrics = router.get_raft_handle(&0).await?.metrics().borrow().clone();
+        assert_eq!(
+            None, metrics.duplicate_node_id,
+            "a new process with id 1 looks like a restart"
+        );
+
+        (node, sto)
+    };
+
+    tracing::info!("--- attach the first process with id 1 again");
+    {
+        let (second, _) = router.remove_node(1).await.ok_or_else(|| anyhow::anyhow!("node not found"))?;
+        router.insert_node(1, first, first_sto).await;
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|x| x.duplicate_node_id == Some(1), "duplicate node id detected")
+            .await?;
+
+        second.shutdown().await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(2000))
+}
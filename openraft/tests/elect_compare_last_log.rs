@@ -7,6 +7,8 @@ use maplit::btreeset;
 use openraft::raft::Entry;
 use openraft::raft::EntryPayload;
 use openraft::raft::Membership;
+use openraft::raft::VoteRejection;
+use openraft::raft::VoteRequest;
 use openraft::storage::HardState;
 use openraft::Config;
 use openraft::LogId;
@@ -42,6 +44,7 @@ async fn elect_compare_last_log() -> Result<()> {
 
         sto0.append_to_log(&[&Entry {
             log_id: LogId { term: 2, index: 1 },
+            timestamp_ms: 0,
             payload: EntryPayload::Membership(Membership::new_single(btreeset! {0,1})),
         }])
         .await?;
@@ -58,10 +61,12 @@ async fn elect_compare_last_log() -> Result<()> {
         sto1.append_to_log(&[
             &Entry {
                 log_id: LogId { term: 1, index: 1 },
+                timestamp_ms: 0,
                 payload: EntryPayload::Membership(Membership::new_single(btreeset! {0,1})),
             },
             &Entry {
                 log_id: LogId { term: 1, index: 2 },
+                timestamp_ms: 0,
                 payload: EntryPayload::Blank,
             },
         ])
@@ -80,6 +85,110 @@ async fn elect_compare_last_log() -> Result<()> {
     Ok(())
 }
 
+/// A vote is granted only to a candidate whose last log is at least as up-to-date as the voter's.
+///
+/// What does this test do?
+///
+/// - fake a store with last log {2,2} and bring up a follower on it, which never receives a heartbeat.
+/// - send vote requests from candidates with a lower last log term but a greater index, and with the same last log term
+///   but a lower index.
+/// - asserts both votes are denied for a stale log, and the voter does not persist a vote for them.
+/// - send a vote request from a candidate with the same last log.
+/// - asserts the vote is granted.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn vote_rejects_stale_log() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // A long election timeout keeps node 1 a follower during the test.
+    let config = Arc::new(
+        Config {
+            election_timeout_min: 10_000,
+            election_timeout_max: 10_001,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let sto1 = router.new_store(1).await;
+
+    tracing::info!("--- fake store: sto1: last log: 2,2");
+    {
+        sto1.save_hard_state(&HardState {
+            current_term: 2,
+            voted_for: None,
+        })
+        .await?;
+
+        sto1.append_to_log(&[
+            &Entry {
+                log_id: LogId { term: 1, index: 1 },
+                timestamp_ms: 0,
+                payload: EntryPayload::Membership(Membership::new_single(btreeset! {0,1})),
+            },
+            &Entry {
+                log_id: LogId { term: 2, index: 2 },
+                timestamp_ms: 0,
+                payload: EntryPayload::Blank,
+            },
+        ])
+        .await?;
+    }
+
+    router.new_raft_node_with_sto(1, sto1.clone()).await;
+    router.wait_for_state(&btreeset! {1}, State::Follower, timeout(), "node 1 is a follower").await?;
+
+    let node = router.get_raft_handle(&1).await?;
+
+    tracing::info!("--- candidates with a stale last log are denied");
+    {
+        for last_log_id in [LogId::new(1, 5), LogId::new(2, 1)] {
+            let resp = node
+                .vote(VoteRequest {
+                    term: 3,
+                    candidate_id: 0,
+                    last_log_id,
+                })
+                .await?;
+
+            assert!(!resp.vote_granted, "candidate last log: {}", last_log_id);
+            assert_eq!(Some(VoteRejection::StaleLog), resp.reject_reason);
+            assert_eq!(3, resp.term);
+            assert_eq!(LogId::new(2, 2), resp.last_log_id, "the voter returns its last log");
+        }
+
+        let hs = sto1.read_hard_state().await?.unwrap();
+        assert_eq!(
+            HardState {
+                current_term: 3,
+                voted_for: None,
+            },
+            hs,
+            "the greater term is saved but no vote is"
+        );
+    }
+
+    tracing::info!("--- a candidate with an equal last log is granted");
+    {
+        let resp = node
+            .vote(VoteRequest {
+                term: 3,
+                candidate_id: 0,
+                last_log_id: LogId::new(2, 2),
+            })
+            .await?;
+
+        assert!(resp.vote_granted);
+        assert_eq!(None, resp.reject_reason);
+
+        let hs = sto1.read_hard_state().await?.unwrap();
+        assert_eq!(Some(0), hs.voted_for);
+    }
+
+    Ok(())
+}
+
 fn timeout() -> Option<Duration> {
     Some(Duration::from_millis(5000))
 }
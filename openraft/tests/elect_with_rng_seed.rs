@@ -0,0 +1,114 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use fixtures::StoreWithDefensive;
+use maplit::btreeset;
+use openraft::raft::Entry;
+use openraft::raft::EntryPayload;
+use openraft::raft::Membership;
+use openraft::storage::HardState;
+use openraft::Config;
+use openraft::LogId;
+use openraft::NodeId;
+use openraft::RaftStorage;
+use openraft::State;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+
+#[macro_use]
+mod fixtures;
+
+/// With `Config::election_rng_seed` set, elections are reproducible.
+///
+/// - Choose a seed with which exactly one node draws the shortest initial election timeout.
+/// - Fake a cluster of three nodes with identical logs.
+/// - Bring up the cluster and assert the chosen node becomes leader.
+/// - Repeat it with a brand new cluster and assert the same node becomes leader again.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn elect_with_rng_seed() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let members = btreeset! {0,1,2};
+
+    // A restarted follower waits for `rng.gen_range(1..3) * heartbeat_interval` before its first election.
+    // The rng of a node is seeded with `seed + node_id`.
+    let (seed, want_leader) = (0..u64::MAX)
+        .find_map(|seed| {
+            let shortest = members
+                .iter()
+                .filter(|id| StdRng::seed_from_u64(seed.wrapping_add(**id)).gen_range(1..3) == 1)
+                .collect::<Vec<_>>();
+
+            if shortest.len() == 1 {
+                Some((seed, *shortest[0]))
+            } else {
+                None
+            }
+        })
+        .unwrap();
+
+    tracing::info!("--- seed: {}, expected leader: {}", seed, want_leader);
+
+    let config = Arc::new(
+        Config {
+            election_rng_seed: Some(seed),
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    for round in 0..2 {
+        tracing::info!("--- round {}: bring up cluster and elect", round);
+
+        let router = Arc::new(RaftRouter::new(config.clone()));
+
+        for id in members.iter() {
+            let sto = router.new_store(*id).await;
+            fake_restarted_store(&sto, members.clone()).await?;
+            router.new_raft_node_with_sto(*id, sto).await;
+        }
+
+        router
+            .wait_for_state(
+                &btreeset! {want_leader},
+                State::Leader,
+                timeout(),
+                "seeded leader elected",
+            )
+            .await?;
+
+        for id in members.iter() {
+            let r = router.get_raft_handle(id).await?;
+            r.shutdown().await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Fill a store as if the node was a member of a running cluster.
+async fn fake_restarted_store(sto: &Arc<StoreWithDefensive>, members: BTreeSet<NodeId>) -> Result<()> {
+    sto.save_hard_state(&HardState {
+        current_term: 1,
+        voted_for: None,
+    })
+    .await?;
+
+    sto.append_to_log(&[&Entry {
+        log_id: LogId { term: 1, index: 1 },
+        timestamp_ms: 0,
+        payload: EntryPayload::Membership(Membership::new_single(members)),
+    }])
+    .await?;
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
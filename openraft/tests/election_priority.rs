@@ -0,0 +1,114 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreemap;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Voter priority test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters and give node 2 the highest priority.
+/// - asserts the priorities are committed on every node.
+/// - make the leader step down several times, asserts node 2 wins most of the elections.
+/// - isolate node 2, asserts a voter with a lower priority still becomes the leader.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn election_priority() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- set priorities");
+    {
+        let resp = router.get_raft_handle(&0).await?.set_priorities(btreemap! {2=>10}).await?;
+        n_logs += 1;
+        assert_eq!(n_logs, resp.log_id.index);
+
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "priorities committed").await?;
+
+        for id in 0..3 {
+            let metrics = router.get_raft_handle(&id).await?.metrics().borrow().clone();
+            assert_eq!(&btreeset! {0,1,2}, metrics.membership_config.membership.all_nodes());
+            assert_eq!(10, metrics.membership_config.membership.priority(&2), "node {}", id);
+        }
+    }
+
+    tracing::info!("--- the preferred node wins most of the elections");
+    {
+        let rounds = 5;
+        let mut preferred_wins = 0;
+
+        for round in 0..rounds {
+            let leader = router.leader().await.unwrap();
+            let term = router.get_raft_handle(&leader).await?.metrics().borrow().current_term;
+
+            router.get_raft_handle(&leader).await?.step_down().await?;
+
+            let new_leader = wait_for_new_leader(&router, term).await?;
+            tracing::info!(round, new_leader, "new leader elected");
+
+            if new_leader == 2 {
+                preferred_wins += 1;
+            }
+
+            // The blank log of the new leader.
+            n_logs += 1;
+            router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "new leader log").await?;
+        }
+
+        assert!(
+            preferred_wins >= rounds - 1,
+            "node 2 wins {} of {}",
+            preferred_wins,
+            rounds
+        );
+    }
+
+    tracing::info!("--- a voter with a lower priority becomes the leader if the preferred one is down");
+    {
+        let leader = router.leader().await.unwrap();
+        let term = router.get_raft_handle(&leader).await?.metrics().borrow().current_term;
+
+        router.isolate_node(2).await;
+        if leader != 2 {
+            router.get_raft_handle(&leader).await?.step_down().await?;
+        }
+
+        // An isolated leader stops sending heartbeats, thus another voter times out and elects itself.
+        let new_leader = wait_for_new_leader(&router, term).await?;
+        assert_ne!(2, new_leader);
+    }
+
+    Ok(())
+}
+
+/// Wait until some node becomes the leader in a term greater than `term` and return its id.
+async fn wait_for_new_leader(router: &Arc<RaftRouter>, term: u64) -> Result<u64> {
+    let start = tokio::time::Instant::now();
+    loop {
+        assert!(start.elapsed() < timeout().unwrap(), "timeout waiting for a new leader");
+
+        for m in router.latest_metrics().await {
+            if m.state == State::Leader && m.current_term > term {
+                return Ok(m.id);
+            }
+        }
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::RaftStorage;
+
+#[macro_use]
+mod fixtures;
+
+/// Entry timestamp test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters and 1 learner, and write some logs.
+/// - asserts every log appended by the leader carries the wall-clock time of the leader when it is appended.
+/// - asserts every replica has the same timestamp for each log as the leader.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn entry_timestamp() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {3}).await?;
+
+    let before = now_ms();
+    router.client_request_many(0, "0", 10).await;
+    let after = now_ms();
+
+    let first = n_logs + 1;
+    n_logs += 10;
+    router.wait_for_log(&btreeset![0, 1, 2, 3], n_logs, timeout(), "write logs").await?;
+
+    tracing::info!("--- the leader assigns its wall-clock time");
+    let leader_logs = router.get_storage_handle(&0).await?.get_log_entries(first..=n_logs).await?;
+    {
+        assert_eq!(10, leader_logs.len());
+
+        for ent in leader_logs.iter() {
+            assert!(
+                ent.timestamp_ms >= before && ent.timestamp_ms <= after,
+                "log {}: timestamp {} not in [{}, {}]",
+                ent.log_id,
+                ent.timestamp_ms,
+                before,
+                after
+            );
+        }
+    }
+
+    tracing::info!("--- replicas preserve the timestamp");
+    {
+        for id in 1..4 {
+            let logs = router.get_storage_handle(&id).await?.get_log_entries(first..=n_logs).await?;
+            let got = logs.iter().map(|x| (x.log_id, x.timestamp_ms)).collect::<Vec<_>>();
+            let want = leader_logs.iter().map(|x| (x.log_id, x.timestamp_ms)).collect::<Vec<_>>();
+            assert_eq!(want, got, "node {}", id);
+        }
+    }
+
+    Ok(())
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,79 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::FatalStorageErrorHandler;
+use openraft::State;
+use openraft::StorageError;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// Fatal storage error handler test.
+///
+/// What does this test do?
+///
+/// - build a cluster of 1 voter and 1 learner, with a handler that records every fatal storage error.
+/// - inject a storage error on the learner by making its store drop a log when reading logs.
+/// - asserts the handler is called exactly once with the injected error, by the time the learner is shut down.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn fatal_storage_error_handler() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let reported = Arc::new(Mutex::new(Vec::new()));
+
+    let config = {
+        let reported = reported.clone();
+        Arc::new(
+            Config {
+                on_fatal_storage_error: Some(FatalStorageErrorHandler::new(move |err: &StorageError| {
+                    reported.lock().unwrap().push(err.to_string());
+                })),
+                ..Default::default()
+            }
+            .validate()?,
+        )
+    };
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
+
+    tracing::info!("--- isolate the learner and write logs, the middle one will be dropped when it is read");
+    {
+        let sto1 = router.get_storage_handle(&1).await?;
+
+        router.isolate_node(1).await;
+        sto1.inner().set_hidden_log_index(Some(n_logs + 2));
+
+        router.client_request_many(0, "foo", 3).await;
+        n_logs += 3;
+        router.wait_for_log(&btreeset![0], n_logs, timeout(), "write 3 logs").await?;
+    }
+
+    tracing::info!("--- restore the learner, the handler is called before it shuts down");
+    {
+        router.restore_node(1).await;
+
+        router.wait(&1, timeout()).await?.state(State::Shutdown, "shutdown on short read").await?;
+
+        let reported = reported.lock().unwrap().clone();
+        assert_eq!(1, reported.len(), "called exactly once: {:?}", reported);
+        assert!(
+            reported[0].contains("short read"),
+            "the injected error: {}",
+            reported[0]
+        );
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
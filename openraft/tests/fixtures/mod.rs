@@ -29,12 +29,16 @@ use openraft::metrics::Wait;
 use openraft::raft::AddLearnerResponse;
 use openraft::raft::AppendEntriesRequest;
 use openraft::raft::AppendEntriesResponse;
+use openraft::raft::ClientReadResponse;
 use openraft::raft::ClientWriteRequest;
 use openraft::raft::ClientWriteResponse;
 use openraft::raft::Entry;
 use openraft::raft::EntryPayload;
+use openraft::raft::InitialRole;
 use openraft::raft::InstallSnapshotRequest;
 use openraft::raft::InstallSnapshotResponse;
+use openraft::raft::LogAuditRequest;
+use openraft::raft::LogAuditResponse;
 use openraft::raft::VoteRequest;
 use openraft::raft::VoteResponse;
 use openraft::storage::RaftStorage;
@@ -42,6 +46,7 @@ use openraft::AppData;
 use openraft::Config;
 use openraft::DefensiveCheck;
 use openraft::LogId;
+use openraft::NetworkError;
 use openraft::NodeId;
 use openraft::Raft;
 use openraft::RaftMetrics;
@@ -58,6 +63,8 @@ use tracing_appender::non_blocking::WorkerGuard;
 use crate::fixtures::logging::init_file_logging;
 
 pub mod logging;
+#[cfg(feature = "test-utils")]
+pub mod store_ops;
 
 macro_rules! func_name {
     () => {{
@@ -122,6 +129,24 @@ pub struct RaftRouter {
     /// To enumlate network delay for sending, in milli second.
     /// 0 means no delay.
     send_delay: u64,
+
+    /// The indexes of log entries delivered to every target with AppendEntries RPC.
+    sent_entries: Mutex<BTreeMap<NodeId, Vec<u64>>>,
+
+    /// The payload sizes of log entries in every AppendEntries RPC delivered to every target.
+    sent_batches: Mutex<BTreeMap<NodeId, Vec<Vec<u64>>>>,
+
+    /// The number of AppendEntries RPC without any log delivered to every target.
+    sent_heartbeats: Mutex<BTreeMap<NodeId, u64>>,
+
+    /// The number of AppendEntries RPC to every target to fail, and how to build the error.
+    append_entries_failures: Mutex<BTreeMap<NodeId, (u64, fn(anyhow::Error) -> NetworkError)>>,
+
+    /// The delay of every RPC on a link `(from, to)`.
+    link_delays: Mutex<BTreeMap<(NodeId, NodeId), Duration>>,
+
+    /// The probability an RPC on a link `(from, to)` is delivered once more, out of order, later.
+    link_reorders: Mutex<BTreeMap<(NodeId, NodeId), f64>>,
 }
 
 pub struct Builder {
@@ -141,6 +166,12 @@ impl Builder {
             routing_table: Default::default(),
             isolated_nodes: Default::default(),
             send_delay: self.send_delay,
+            sent_entries: Default::default(),
+            sent_batches: Default::default(),
+            sent_heartbeats: Default::default(),
+            append_entries_failures: Default::default(),
+            link_delays: Default::default(),
+            link_reorders: Default::default(),
         }
     }
 }
@@ -169,6 +200,38 @@ impl RaftRouter {
         tokio::time::sleep(timeout).await;
     }
 
+    /// Delay every RPC sent from `from` to `to` by `delay`, before it is delivered.
+    pub fn set_link_delay(&self, from: NodeId, to: NodeId, delay: Duration) {
+        self.link_delays.lock().unwrap().insert((from, to), delay);
+    }
+
+    /// Reorder AppendEntries and RequestVote RPCs sent from `from` to `to`.
+    ///
+    /// With the given probability, an RPC is delivered as usual, and a copy of it is delivered once more after a random
+    /// delay upto 2 heartbeat intervals, i.e., after newer RPCs on the same link. The response to the late copy is
+    /// dropped. Install-snapshot RPCs are never reordered.
+    pub fn set_reorder(&self, from: NodeId, to: NodeId, probability: f64) {
+        self.link_reorders.lock().unwrap().insert((from, to), probability);
+    }
+
+    async fn link_delay(&self, from: NodeId, to: NodeId) {
+        let delay = self.link_delays.lock().unwrap().get(&(from, to)).cloned();
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Returns the delay to deliver a late copy of an RPC from `from` to `to` after, or None if it is not reordered.
+    fn reorder_delay(&self, from: NodeId, to: NodeId) -> Option<Duration> {
+        let probability = self.link_reorders.lock().unwrap().get(&(from, to)).cloned().unwrap_or_default();
+        if rand::random::<f64>() >= probability {
+            return None;
+        }
+
+        let max = self.config.heartbeat_interval * 2;
+        Some(Duration::from_millis(rand::random::<u64>() % max + 1))
+    }
+
     /// Create a cluster: 0 is the initial leader, others are voters learners
     /// NOTE: it create a single node cluster first, then change it to a multi-voter cluster.
     #[tracing::instrument(level = "debug", skip(self))]
@@ -265,6 +328,28 @@ impl RaftRouter {
         rt.insert(id, (node, sto));
     }
 
+    /// Create and register a new Raft node bearing the given ID, with a config other than the one of the router.
+    pub async fn new_raft_node_with_config(self: &Arc<Self>, id: NodeId, config: Arc<Config>) {
+        let sto = self.new_store(id).await;
+        let node = Raft::new(id, config, self.clone(), sto.clone());
+        let mut rt = self.routing_table.write().await;
+        rt.insert(id, (node, sto));
+    }
+
+    /// Create and register a new Raft node that starts in the given role, with the given store.
+    ///
+    /// The store has to be consistent with the role, e.g., the store of a node that was removed from this router.
+    pub async fn new_raft_node_with_initial_role(
+        self: &Arc<Self>,
+        id: NodeId,
+        sto: Arc<StoreWithDefensive>,
+        role: InitialRole,
+    ) {
+        let node = Raft::new_with_initial_role(id, self.config.clone(), self.clone(), sto.clone(), role);
+        let mut rt = self.routing_table.write().await;
+        rt.insert(id, (node, sto));
+    }
+
     /// Remove the target node from the routing table & isolation.
     pub async fn remove_node(&self, id: NodeId) -> Option<(MemRaft, Arc<StoreWithDefensive>)> {
         let mut rt = self.routing_table.write().await;
@@ -275,6 +360,12 @@ impl RaftRouter {
         opt_handles
     }
 
+    /// Register a running Raft node, e.g., one removed with `remove_node()`, replacing the one with the same id.
+    pub async fn insert_node(&self, id: NodeId, node: MemRaft, sto: Arc<StoreWithDefensive>) {
+        let mut rt = self.routing_table.write().await;
+        rt.insert(id, (node, sto));
+    }
+
     /// Initialize all nodes based on the config in the routing table.
     pub async fn initialize_from_single_node(&self, node: NodeId) -> Result<()> {
         tracing::info!({ node }, "initializing cluster from single node");
@@ -316,6 +407,60 @@ impl RaftRouter {
         metrics
     }
 
+    /// Get the latest metrics of every node in the cluster, keyed by node id.
+    pub async fn all_metrics(&self) -> BTreeMap<NodeId, RaftMetrics> {
+        let rt = self.routing_table.read().await;
+        rt.iter().map(|(id, node)| (*id, node.0.metrics().borrow().clone())).collect()
+    }
+
+    /// Assert that exactly one node that is not isolated is in leader state, and return its id.
+    pub async fn assert_single_leader(&self) -> NodeId {
+        let isolated = self.isolated_nodes.read().await.clone();
+        let leaders = self
+            .all_metrics()
+            .await
+            .into_iter()
+            .filter(|(id, m)| m.state == State::Leader && !isolated.contains(id))
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+
+        assert_eq!(1, leaders.len(), "expected exactly one leader, got: {:?}", leaders);
+        leaders[0]
+    }
+
+    /// Assert that every node has the same last log index and has applied all of its logs, and return the last log
+    /// index.
+    ///
+    /// If `want_log` is given, the last log index has to be equal to it.
+    pub async fn assert_logs_converged(&self, want_log: Option<u64>) -> u64 {
+        let all = self.all_metrics().await;
+        let first = all.values().next().expect("no node in cluster");
+        let want_log = want_log.unwrap_or(first.last_log_index);
+
+        for (id, m) in all.iter() {
+            assert_eq!(
+                want_log, m.last_log_index,
+                "node {} has last_log_index {}, expected {}",
+                id, m.last_log_index, want_log
+            );
+            assert_eq!(
+                want_log, m.last_applied,
+                "node {} has last_applied {}, expected {}",
+                id, m.last_applied, want_log
+            );
+        }
+
+        want_log
+    }
+
+    /// Get a handle to the raft node.
+    pub async fn get_raft_handle(&self, node_id: &NodeId) -> Result<MemRaft> {
+        let rt = self.routing_table.read().await;
+        let addr = rt.get(node_id).with_context(|| format!("could not find node {} in routing table", node_id))?;
+        let r = addr.clone().0;
+        Ok(r)
+    }
+
     /// Get a handle to the storage backend for the target node.
     pub async fn get_storage_handle(&self, node_id: &NodeId) -> Result<Arc<StoreWithDefensive>> {
         let rt = self.routing_table.read().await;
@@ -416,12 +561,43 @@ impl RaftRouter {
         nodes.remove(&id);
     }
 
+    /// Make the next `n` AppendEntries RPC to a target fail with a `NetworkError` built by `make_err`.
+    pub fn fail_append_entries(&self, target: NodeId, n: u64, make_err: fn(anyhow::Error) -> NetworkError) {
+        self.append_entries_failures.lock().unwrap().insert(target, (n, make_err));
+    }
+
+    /// Returns the number of AppendEntries RPC to a target that are yet to fail.
+    pub fn pending_append_entries_failures(&self, target: NodeId) -> u64 {
+        self.append_entries_failures.lock().unwrap().get(&target).map(|x| x.0).unwrap_or_default()
+    }
+
+    /// Take the indexes of log entries delivered to a target with AppendEntries RPC since the last call.
+    pub fn take_sent_entries(&self, target: NodeId) -> Vec<u64> {
+        self.sent_entries.lock().unwrap().remove(&target).unwrap_or_default()
+    }
+
+    /// Take the payload sizes of entries in every non-empty AppendEntries RPC delivered to the target so far.
+    pub fn take_sent_batches(&self, target: NodeId) -> Vec<Vec<u64>> {
+        self.sent_batches.lock().unwrap().remove(&target).unwrap_or_default()
+    }
+
+    /// Take the number of AppendEntries RPC without any log delivered to the target since the last call.
+    pub fn take_sent_heartbeats(&self, target: NodeId) -> u64 {
+        self.sent_heartbeats.lock().unwrap().remove(&target).unwrap_or_default()
+    }
+
     pub async fn add_learner(&self, leader: NodeId, target: NodeId) -> Result<AddLearnerResponse, AddLearnerError> {
         let rt = self.routing_table.read().await;
         let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
         node.0.add_learner(target, true).await
     }
 
+    pub async fn add_standby(&self, leader: NodeId, target: NodeId) -> Result<AddLearnerResponse, AddLearnerError> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
+        node.0.add_standby(target, true).await
+    }
+
     pub async fn add_learner_with_blocking(
         &self,
         leader: NodeId,
@@ -455,7 +631,7 @@ impl RaftRouter {
     }
 
     /// Send a client read request to the target node.
-    pub async fn client_read(&self, target: NodeId) -> Result<(), ClientReadError> {
+    pub async fn client_read(&self, target: NodeId) -> Result<ClientReadResponse, ClientReadError> {
         let rt = self.routing_table.read().await;
         let node = rt.get(&target).unwrap_or_else(|| panic!("node with ID {} does not exist", target));
         node.0.client_read().await
@@ -488,14 +664,18 @@ impl RaftRouter {
         }
     }
 
-    async fn send_client_request(
+    /// Send a client request to the target node and return the result.
+    pub async fn send_client_request(
         &self,
         target: NodeId,
         req: MemClientRequest,
     ) -> std::result::Result<MemClientResponse, ClientWriteError> {
         let rt = self.routing_table.read().await;
         let node = rt.get(&target).unwrap_or_else(|| panic!("node '{}' does not exist in routing table", target));
-        node.0.client_write(ClientWriteRequest::new(req)).await.map(|res| res.data)
+        node.0
+            .client_write(ClientWriteRequest::new(req))
+            .await
+            .map(|res| res.data.expect("client write is acked on apply"))
     }
 
     //////////////////////////////////////////////////////////////////////////////////////////////
@@ -539,7 +719,7 @@ impl RaftRouter {
                 members
             );
             assert!(
-                !node.membership_config.membership.is_in_joint_consensus(),
+                !node.membership_config.membership.is_joint(),
                 "node {} is in joint consensus, expected uniform consensus",
                 node.id
             );
@@ -618,7 +798,7 @@ impl RaftRouter {
                 node.id, members, all_nodes
             );
             assert!(
-                !node.membership_config.membership.is_in_joint_consensus(),
+                !node.membership_config.membership.is_joint(),
                 "node {} was not in uniform consensus state",
                 node.id
             );
@@ -719,6 +899,7 @@ impl RaftNetwork<MemClientRequest> for RaftRouter {
     ) -> Result<AppendEntriesResponse> {
         tracing::debug!("append_entries to id={} {:?}", target, rpc);
         self.rand_send_delay().await;
+        self.link_delay(rpc.leader_id, target).await;
 
         let rt = self.routing_table.read().await;
         let isolated = self.isolated_nodes.read().await;
@@ -726,6 +907,37 @@ impl RaftNetwork<MemClientRequest> for RaftRouter {
         if isolated.contains(&target) || isolated.contains(&rpc.leader_id) {
             return Err(anyhow!("target node is isolated"));
         }
+
+        {
+            let mut failures = self.append_entries_failures.lock().unwrap();
+            if let Some((n, make_err)) = failures.get_mut(&target) {
+                if *n > 0 {
+                    *n -= 1;
+                    return Err(make_err(anyhow!("injected failure to {}", target)).into());
+                }
+            }
+        }
+
+        let indexes = rpc.entries.iter().map(|x| x.log_id.index);
+        self.sent_entries.lock().unwrap().entry(target).or_default().extend(indexes);
+
+        if !rpc.entries.is_empty() {
+            let sizes = rpc.entries.iter().map(|x| x.payload_size_hint(&self.config.codec)).collect();
+            self.sent_batches.lock().unwrap().entry(target).or_default().push(sizes);
+        } else {
+            *self.sent_heartbeats.lock().unwrap().entry(target).or_default() += 1;
+        }
+
+        if let Some(delay) = self.reorder_delay(rpc.leader_id, target) {
+            let raft = addr.0.clone();
+            let late = rpc.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                tracing::debug!("deliver a late append_entries: {:?}", late);
+                let _ = raft.append_entries(late).await;
+            });
+        }
+
         let resp = addr.0.append_entries(rpc).await;
 
         tracing::debug!("append_entries: recv resp from id={} {:?}", target, resp);
@@ -735,6 +947,7 @@ impl RaftNetwork<MemClientRequest> for RaftRouter {
     /// Send an InstallSnapshot RPC to the target Raft node (§7).
     async fn send_install_snapshot(&self, target: u64, rpc: InstallSnapshotRequest) -> Result<InstallSnapshotResponse> {
         self.rand_send_delay().await;
+        self.link_delay(rpc.leader_id, target).await;
 
         let rt = self.routing_table.read().await;
         let isolated = self.isolated_nodes.read().await;
@@ -748,6 +961,7 @@ impl RaftNetwork<MemClientRequest> for RaftRouter {
     /// Send a RequestVote RPC to the target Raft node (§5).
     async fn send_vote(&self, target: u64, rpc: VoteRequest) -> Result<VoteResponse> {
         self.rand_send_delay().await;
+        self.link_delay(rpc.candidate_id, target).await;
 
         let rt = self.routing_table.read().await;
         let isolated = self.isolated_nodes.read().await;
@@ -755,8 +969,33 @@ impl RaftNetwork<MemClientRequest> for RaftRouter {
         if isolated.contains(&target) || isolated.contains(&rpc.candidate_id) {
             return Err(anyhow!("target node is isolated"));
         }
+
+        if let Some(delay) = self.reorder_delay(rpc.candidate_id, target) {
+            let raft = addr.0.clone();
+            let late = VoteRequest::new(rpc.term, rpc.candidate_id, rpc.last_log_id);
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                tracing::debug!("deliver a late vote: {:?}", late);
+                let _ = raft.vote(late).await;
+            });
+        }
+
         Ok(addr.0.vote(rpc).await?)
     }
+
+    /// Send a LogAudit RPC to the target Raft node.
+    async fn send_log_audit(&self, target: u64, rpc: LogAuditRequest) -> Result<LogAuditResponse> {
+        self.rand_send_delay().await;
+        self.link_delay(rpc.leader_id, target).await;
+
+        let rt = self.routing_table.read().await;
+        let isolated = self.isolated_nodes.read().await;
+        let addr = rt.get(&target).expect("target node not found in routing table");
+        if isolated.contains(&target) || isolated.contains(&rpc.leader_id) {
+            return Err(anyhow!("target node is isolated"));
+        }
+        Ok(addr.0.log_audit(rpc).await?)
+    }
 }
 
 pub enum ValueTest<T> {
@@ -784,6 +1023,7 @@ fn timeout() -> Option<Duration> {
 pub fn ent<T: AppData>(term: u64, index: u64) -> Entry<T> {
     Entry {
         log_id: LogId { term, index },
+        timestamp_ms: 0,
         payload: EntryPayload::Blank,
     }
 }
@@ -0,0 +1,121 @@
+//! A property test harness that runs arbitrary sequences of log and state machine operations against a store.
+
+use anyhow::Context;
+use anyhow::Result;
+use arbitrary::Unstructured;
+use memstore::ClientRequest;
+use memstore::ClientResponse;
+use openraft::raft::Entry;
+use openraft::raft::EntryPayload;
+use openraft::storage::RaftStorage;
+use openraft::LogId;
+
+/// The max number of operations to run in one sequence.
+const MAX_OPS: usize = 100;
+
+/// Run a sequence of operations built from `data` against `sto`, and check the store against a model after each.
+///
+/// Every operation is a valid one: logs are appended right after the last log with non-decreasing terms, only logs
+/// that are not applied are deleted, and logs are applied in order. Thus a store, with or without defensive checks,
+/// has to accept all of them.
+pub async fn check_arbitrary_store_ops<S>(sto: &S, data: &[u8]) -> Result<()>
+where S: RaftStorage<ClientRequest, ClientResponse> {
+    let mut u = Unstructured::new(data);
+
+    // The expected logs, the log at index `i` is at `logs[i-1]`, and the number of applied logs.
+    let mut logs: Vec<Entry<ClientRequest>> = vec![];
+    let mut n_applied = 0;
+
+    for i in 0..MAX_OPS {
+        if u.is_empty() {
+            break;
+        }
+
+        match u.int_in_range(0..=2)? {
+            0 => {
+                let n = u.int_in_range(1..=5)?;
+                let mut entries = Vec::with_capacity(n);
+
+                let mut last = logs.last().map(|x| x.log_id).unwrap_or_default();
+                for _ in 0..n {
+                    let term = std::cmp::max(1, last.term + u.int_in_range(0..=1)?);
+                    let log_id = LogId::new(term, last.index + 1);
+                    entries.push(Entry {
+                        log_id,
+                        timestamp_ms: 0,
+                        payload: arbitrary_payload(&mut u)?,
+                    });
+                    last = log_id;
+                }
+
+                tracing::debug!("op-{}: append [{}, {}]", i, entries[0].log_id, last);
+
+                let entry_refs = entries.iter().collect::<Vec<_>>();
+                sto.append_to_log(&entry_refs).await.with_context(|| format!("op-{}: append", i))?;
+                logs.extend(entries);
+            }
+            1 => {
+                if logs.len() == n_applied {
+                    continue;
+                }
+                let since = u.int_in_range(n_applied + 1..=logs.len())?;
+
+                tracing::debug!("op-{}: delete logs since {}", i, since);
+
+                sto.delete_logs_from(since as u64..).await.with_context(|| format!("op-{}: delete", i))?;
+                logs.truncate(since - 1);
+            }
+            _ => {
+                if logs.len() == n_applied {
+                    continue;
+                }
+                let n = u.int_in_range(1..=logs.len() - n_applied)?;
+
+                tracing::debug!("op-{}: apply {} logs since {}", i, n, n_applied + 1);
+
+                let entry_refs = logs[n_applied..n_applied + n].iter().collect::<Vec<_>>();
+                sto.apply_to_state_machine(&entry_refs).await.with_context(|| format!("op-{}: apply", i))?;
+                n_applied += n;
+            }
+        }
+
+        check_store(sto, &logs, n_applied).await.with_context(|| format!("op-{}: check store", i))?;
+    }
+
+    Ok(())
+}
+
+/// Check the logs and the last applied log id in the store match the model.
+async fn check_store<S>(sto: &S, logs: &[Entry<ClientRequest>], n_applied: usize) -> Result<()>
+where S: RaftStorage<ClientRequest, ClientResponse> {
+    let want_last = logs.last().map(|x| x.log_id).unwrap_or_default();
+    assert_eq!(want_last, sto.last_id_in_log().await?, "last log id");
+
+    let want_ids = logs.iter().map(|x| x.log_id).collect::<Vec<_>>();
+    let got_ids = sto.try_get_log_entries(1..).await?.iter().map(|x| x.log_id).collect::<Vec<_>>();
+    assert_eq!(want_ids, got_ids, "log ids in store");
+
+    let want_applied = if n_applied == 0 {
+        LogId::default()
+    } else {
+        logs[n_applied - 1].log_id
+    };
+    let (got_applied, _) = sto.last_applied_state().await?;
+    assert_eq!(want_applied, got_applied, "last applied log id");
+
+    Ok(())
+}
+
+/// Build a payload of an entry: `ClientRequest` is defined by memstore thus it does not implement `Arbitrary`.
+fn arbitrary_payload(u: &mut Unstructured) -> Result<EntryPayload<ClientRequest>> {
+    let payload = match u.int_in_range(0..=2)? {
+        0 => EntryPayload::Blank,
+        1 => EntryPayload::Normal(ClientRequest {
+            client: format!("client-{}", u.int_in_range(0..=3)?),
+            serial: u.arbitrary()?,
+            status: u.arbitrary()?,
+        }),
+        _ => EntryPayload::Membership(u.arbitrary()?),
+    };
+    Ok(payload)
+}
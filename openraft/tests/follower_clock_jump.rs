@@ -0,0 +1,203 @@
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::Entry;
+use openraft::raft::EntryPayload;
+use openraft::Clock;
+use openraft::ClockSource;
+use openraft::Config;
+use openraft::LogId;
+use openraft::State;
+use openraft::Wrapper;
+use tokio::time::Instant;
+
+#[macro_use]
+mod fixtures;
+
+/// A clock that can be made to jump forward. Clones share the same offset.
+#[derive(Clone, Default)]
+struct JumpClock {
+    offset_ms: Arc<AtomicU64>,
+}
+
+impl JumpClock {
+    fn jump(&self, d: Duration) {
+        self.offset_ms.fetch_add(d.as_millis() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for JumpClock {
+    fn now(&self) -> Instant {
+        Instant::now() + Duration::from_millis(self.offset_ms.load(Ordering::SeqCst))
+    }
+}
+
+/// Follower clock jump test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters, the clock of node 2 can be made to jump forward.
+/// - let the clock of follower 2 jump by much more than an election timeout, as a VM pause would do.
+/// - asserts node 2 does not start an election, since it has recently heard from the leader.
+/// - isolate the leader, asserts a new leader is still elected, i.e., the election timer works after the jump.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn follower_clock_jump() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let clock = JumpClock::default();
+    let config2 = Arc::new(
+        Config {
+            clock: Some(ClockSource::new(clock.clone())),
+            ..config.as_ref().clone()
+        }
+        .validate()?,
+    );
+
+    router.new_raft_node(0).await;
+    router.new_raft_node(1).await;
+    router.new_raft_node_with_config(2, config2).await;
+    router.wait_for_state(&btreeset![0, 1, 2], State::Learner, timeout(), "empty").await?;
+
+    router.initialize_from_single_node(0).await?;
+    router.wait_for_log(&btreeset![0, 1, 2], 1, timeout(), "init").await?;
+    router.wait_for_state(&btreeset![2], State::Follower, timeout(), "node 2 is a follower").await?;
+
+    let leader = router.leader().await.unwrap();
+    let term = router.get_raft_handle(&leader).await?.metrics().borrow().current_term;
+
+    tracing::info!("--- the clock of the follower jumps forward");
+    {
+        clock.jump(Duration::from_secs(10));
+
+        tokio::time::sleep(Duration::from_millis(config.election_timeout_max * 3)).await;
+
+        for id in 0..3 {
+            let m = router.get_raft_handle(&id).await?.metrics().borrow().clone();
+            assert_eq!(term, m.current_term, "node {} did not see an election", id);
+            assert_eq!(Some(leader), m.current_leader, "node {} still follows the leader", id);
+        }
+
+        let m2 = router.get_raft_handle(&2).await?.metrics().borrow().clone();
+        assert_eq!(State::Follower, m2.state);
+    }
+
+    tracing::info!("--- a new leader is elected after the leader is gone");
+    {
+        router.isolate_node(leader).await;
+
+        for id in (0..3).filter(|x| *x != leader) {
+            router
+                .wait(&id, timeout())
+                .await?
+                .metrics(
+                    |x| x.current_term > term && x.current_leader.is_some() && x.current_leader != Some(leader),
+                    "a new leader is elected",
+                )
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Follower slow append test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 2 voters, node 1 reads an injected clock that never jumps.
+/// - isolate the leader, then send node 1 an AppendEntries whose append takes longer than the election timeout.
+/// - asserts node 1 starts an election as soon as the append is done: the time spent appending is not a clock jump, and
+///   does not push the election timeout back.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn follower_slow_append_is_not_clock_jump() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            election_timeout_min: 300,
+            election_timeout_max: 400,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let config1 = Arc::new(
+        Config {
+            clock: Some(ClockSource::new(JumpClock::default())),
+            ..config.as_ref().clone()
+        }
+        .validate()?,
+    );
+
+    router.new_raft_node(0).await;
+    router.new_raft_node_with_config(1, config1).await;
+    router.wait_for_state(&btreeset![0, 1], State::Learner, timeout(), "empty").await?;
+
+    router.initialize_from_single_node(0).await?;
+    router.wait_for_log(&btreeset![0, 1], 1, timeout(), "init").await?;
+    router.wait_for_state(&btreeset![0], State::Leader, timeout(), "node 0 is the leader").await?;
+    router.wait_for_state(&btreeset![1], State::Follower, timeout(), "node 1 is a follower").await?;
+
+    let term = router.get_raft_handle(&1).await?.metrics().borrow().current_term;
+
+    tracing::info!("--- a slow append on node 1, while the leader is gone");
+    {
+        router.isolate_node(0).await;
+
+        let delay = Duration::from_millis(config.election_timeout_max * 3 / 2);
+        router.get_storage_handle(&1).await?.inner().set_append_delay(delay);
+
+        let start = Instant::now();
+
+        let resp = router
+            .get_raft_handle(&1)
+            .await?
+            .append_entries(AppendEntriesRequest {
+                term,
+                leader_id: 0,
+                prev_log_id: LogId::new(term, 1),
+                entries: vec![Entry {
+                    log_id: LogId::new(term, 2),
+                    timestamp_ms: 0,
+                    payload: EntryPayload::Blank,
+                }],
+                leader_commit: LogId::new(term, 1),
+                relay_to: vec![],
+            })
+            .await?;
+        assert!(resp.success());
+        assert!(start.elapsed() >= delay);
+
+        router
+            .wait(&1, timeout())
+            .await?
+            .metrics(|x| x.current_term > term, "node 1 starts an election")
+            .await?;
+
+        // Had the append been taken as a clock jump, the election would be delayed by a whole election timeout.
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed < delay + Duration::from_millis(config.election_timeout_min / 2),
+            "election started {:?} after the append began",
+            elapsed
+        );
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::State;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// Short read of logs test.
+///
+/// What does this test do?
+///
+/// - build a cluster of 1 voter and 1 learner, the store of the learner drops a log in the middle when reading logs.
+/// - isolate the learner and write several logs, then restore it to let it apply them in one batch.
+/// - asserts the learner rejects the logs with a gap and shuts down, without applying any of them.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn get_log_entries_short_read() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
+
+    let sto1 = router.get_storage_handle(&1).await?;
+
+    tracing::info!("--- isolate the learner and write logs, the middle one will be dropped when it is read");
+    let applied = {
+        let applied = n_logs;
+
+        router.isolate_node(1).await;
+        sto1.inner().set_hidden_log_index(Some(n_logs + 2));
+
+        router.client_request_many(0, "foo", 3).await;
+        n_logs += 3;
+        router.wait_for_log(&btreeset![0], n_logs, timeout(), "write 3 logs").await?;
+
+        applied
+    };
+
+    tracing::info!("--- restore the learner, it finds the gap and shuts down");
+    {
+        router.restore_node(1).await;
+
+        router.wait(&1, timeout()).await?.state(State::Shutdown, "shutdown on short read").await?;
+
+        let metrics = router.get_raft_handle(&1).await?.metrics().borrow().clone();
+        assert_eq!(applied, metrics.last_applied, "no log is applied");
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,94 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::SnapshotPolicy;
+
+#[macro_use]
+mod fixtures;
+
+/// Raft::get_log_id() test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 1 voter and write some logs.
+/// - asserts the log ids of the logs, and `None` beyond the last log.
+/// - build a snapshot and write more logs, thus the logs included in the snapshot are purged.
+/// - asserts the last log id in the snapshot is still returned, `None` is returned for the other purged logs, and the
+///   log ids after the snapshot are returned.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn get_log_id() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::Never,
+            max_applied_log_to_keep: 0,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", 10).await;
+    n_logs += 10;
+    router.wait_for_log(&btreeset![0], n_logs, timeout(), "write logs").await?;
+
+    let raft = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- log ids in the log");
+    {
+        assert_eq!(Some(LogId::new(1, 1)), raft.get_log_id(1).await?);
+        assert_eq!(Some(LogId::new(1, n_logs)), raft.get_log_id(n_logs).await?);
+        assert_eq!(None, raft.get_log_id(n_logs + 1).await?);
+    }
+
+    tracing::info!("--- build a snapshot and purge the logs in it");
+    let snapshot_index = n_logs;
+    {
+        raft.trigger_snapshot().await?;
+        router
+            .wait_for_snapshot(&btreeset![0], LogId::new(1, snapshot_index), timeout(), "snapshot")
+            .await?;
+
+        router.client_request_many(0, "0", 5).await;
+        n_logs += 5;
+        router.wait_for_log(&btreeset![0], n_logs, timeout(), "write logs after snapshot").await?;
+
+        let sto = router.get_storage_handle(&0).await?;
+        assert!(
+            sto.try_get_log_entry(1).await?.is_none(),
+            "logs in the snapshot are purged"
+        );
+    }
+
+    tracing::info!("--- log ids across the snapshot boundary");
+    {
+        assert_eq!(None, raft.get_log_id(1).await?, "purged log");
+        assert_eq!(
+            Some(LogId::new(1, snapshot_index)),
+            raft.get_log_id(snapshot_index).await?,
+            "the last log in the snapshot"
+        );
+        assert_eq!(
+            Some(LogId::new(1, snapshot_index + 1)),
+            raft.get_log_id(snapshot_index + 1).await?
+        );
+        assert_eq!(Some(LogId::new(1, n_logs)), raft.get_log_id(n_logs).await?);
+        assert_eq!(None, raft.get_log_id(n_logs + 1).await?, "beyond the last log");
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
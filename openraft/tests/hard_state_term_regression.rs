@@ -0,0 +1,82 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::raft::VoteRequest;
+use openraft::storage::HardState;
+use openraft::Config;
+use openraft::DefensiveCheck;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::State;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// Hard state term regression test.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster.
+/// - write a greater term into the store behind the back of raft, to simulate stale state in raft core.
+/// - send a vote request that makes raft core save a term lower than the persisted one.
+/// - asserts the save is rejected by the defensive store, the persisted term is not changed and the node shuts down.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn hard_state_term_regression() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    let sto = router.get_storage_handle(&0).await?;
+
+    // Raft core checks the term against the one it saved itself. A term written behind its back is caught by the
+    // defensive store.
+    sto.set_defensive(true);
+
+    tracing::info!("--- persist a greater term behind the back of raft");
+    {
+        sto.inner()
+            .save_hard_state(&HardState {
+                current_term: 10,
+                voted_for: None,
+            })
+            .await?;
+    }
+
+    tracing::info!("--- vote request makes raft core save term 5");
+    {
+        let node = router.get_raft_handle(&0).await?;
+
+        let res = node
+            .vote(VoteRequest {
+                term: 5,
+                candidate_id: 1,
+                last_log_id: LogId::new(1, n_logs),
+            })
+            .await;
+
+        tracing::info!("--- vote result: {:?}", res);
+        assert!(res.is_err(), "saving a regressed term must be rejected");
+    }
+
+    tracing::info!("--- the term is not regressed and the node shuts down");
+    {
+        let hs = sto.read_hard_state().await?.unwrap();
+        assert_eq!(10, hs.current_term);
+
+        router.wait(&0, timeout()).await?.state(State::Shutdown, "shutdown on term regression").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(1000))
+}
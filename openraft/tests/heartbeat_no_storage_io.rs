@@ -0,0 +1,75 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// Heartbeat to caught-up targets reads no log test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 5 voters and 2 learners and write some logs.
+/// - wait for every target to catch up.
+/// - asserts the leader does not read any log from its store during many heartbeat intervals.
+/// - write a log, asserts it is replicated, i.e., logs are read again when there is something to send.
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn heartbeat_no_storage_io() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2,3,4}, btreeset! {5,6}).await?;
+
+    router.client_request_many(0, "0", 10).await;
+    n_logs += 10;
+    router.wait_for_log(&btreeset![0, 1, 2, 3, 4, 5, 6], n_logs, timeout(), "write logs").await?;
+
+    router
+        .wait(&0, timeout())
+        .await?
+        .metrics(
+            |x| match x.leader_metrics {
+                Some(ref m) => m.replication.len() == 6 && m.replication.values().all(|r| r.matched.index == n_logs),
+                None => false,
+            },
+            "every target caught up",
+        )
+        .await?;
+
+    tracing::info!("--- heartbeats to caught-up targets read no log");
+    {
+        let sto0 = router.get_storage_handle(&0).await?;
+
+        // Let in-flight replication settle.
+        tokio::time::sleep(Duration::from_millis(config.heartbeat_interval * 2)).await;
+
+        let before = sto0.inner().log_read_count();
+        tokio::time::sleep(Duration::from_millis(config.heartbeat_interval * 10)).await;
+        let after = sto0.inner().log_read_count();
+
+        assert_eq!(before, after, "no log is read for heartbeats");
+    }
+
+    tracing::info!("--- a new log is still replicated");
+    {
+        router.client_request_many(0, "0", 1).await;
+        n_logs += 1;
+        router
+            .wait_for_log(&btreeset![0, 1, 2, 3, 4, 5, 6], n_logs, timeout(), "write one more log")
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
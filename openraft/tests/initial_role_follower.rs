@@ -0,0 +1,92 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::raft::InitialRole;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Seeded initial role test.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster, then shutdown node 2.
+/// - restart node 2 with its store, seeded as a follower of node 0, while it is isolated.
+/// - asserts it starts as a follower that knows the leader without receiving any message.
+/// - restore node 2, asserts it receives logs without any election.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn initial_role_follower() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // A large election timeout keeps the isolated node from starting an election during the test.
+    let config = Arc::new(
+        Config {
+            election_timeout_min: 3000,
+            election_timeout_max: 4000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- shutdown node 2");
+    let sto2 = {
+        let (node, sto) = router.remove_node(2).await.ok_or_else(|| anyhow::anyhow!("node not found"))?;
+        node.shutdown().await?;
+        sto
+    };
+
+    tracing::info!("--- restart node 2 as a seeded follower of node 0, isolated");
+    {
+        router.isolate_node(2).await;
+        router.new_raft_node_with_initial_role(2, sto2, InitialRole::Follower { leader: 0 }).await;
+
+        let metrics = router
+            .wait(&2, Some(Duration::from_millis(1000)))
+            .await?
+            .metrics(
+                |x| x.state == State::Follower && x.current_leader == Some(0),
+                "seeded follower",
+            )
+            .await?;
+
+        assert_eq!(1, metrics.current_term);
+        assert_eq!(n_logs, metrics.last_log_index);
+    }
+
+    tracing::info!("--- restore node 2, it follows the leader without an election");
+    {
+        router.restore_node(2).await;
+
+        router.client_request_many(0, "foo", 5).await;
+        n_logs += 5;
+
+        router
+            .wait_for_log(
+                &btreeset![0, 1, 2],
+                n_logs,
+                timeout(),
+                "replicated to the seeded follower",
+            )
+            .await?;
+
+        for (id, m) in router.all_metrics().await {
+            assert_eq!(1, m.current_term, "no election on node {}", id);
+            assert_eq!(Some(0), m.current_leader, "node {}", id);
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(2000))
+}
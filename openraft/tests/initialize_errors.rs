@@ -0,0 +1,98 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::error::InitializeError;
+use openraft::Config;
+use openraft::LogId;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Initialize errors test.
+///
+/// What does this test do?
+///
+/// - bring 2 pristine nodes online and isolate node 1.
+/// - asserts initializing with empty members returns `EmptyMembers`, and with node 1 returns `MemberUnreachable`.
+/// - asserts node 0 is still pristine, restore node 1 and initialize the cluster.
+/// - asserts initializing the leader again returns `AlreadyInitialized`.
+/// - add node 2 as a learner, asserts initializing it returns `NotPristine`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn initialize_errors() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+    router.new_raft_node(1).await;
+
+    let mut n_logs = 0;
+
+    router.wait_for_log(&btreeset![0, 1], n_logs, timeout(), "empty").await?;
+    router.wait_for_state(&btreeset![0, 1], State::Learner, timeout(), "empty").await?;
+
+    let r0 = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- empty members or an unreachable member is refused");
+    {
+        let res = r0.initialize(btreeset! {}).await;
+        assert!(matches!(res, Err(InitializeError::EmptyMembers)), "{:?}", res);
+
+        router.isolate_node(1).await;
+
+        let res = r0.initialize(btreeset! {0,1}).await;
+        assert!(
+            matches!(res, Err(InitializeError::MemberUnreachable { node_id: 1 })),
+            "{:?}",
+            res
+        );
+
+        router.assert_pristine_cluster().await;
+        assert_eq!(State::Learner, r0.metrics().borrow().state);
+    }
+
+    tracing::info!("--- initialize once every member is reachable");
+    {
+        router.restore_node(1).await;
+
+        r0.initialize(btreeset! {0,1}).await?;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![0, 1], n_logs, timeout(), "init").await?;
+    }
+
+    tracing::info!("--- initializing an initialized node is refused");
+    {
+        let leader = router.leader().await.unwrap();
+        let res = router.get_raft_handle(&leader).await?.initialize(btreeset! {0,1}).await;
+        assert!(matches!(res, Err(InitializeError::AlreadyInitialized)), "{:?}", res);
+    }
+
+    tracing::info!("--- initializing a learner that has logs is refused");
+    {
+        let leader = router.leader().await.unwrap();
+
+        router.new_raft_node(2).await;
+        router.add_learner(leader, 2).await?;
+        router.wait_for_log(&btreeset![2], n_logs, timeout(), "learner receives logs").await?;
+
+        let res = router.get_raft_handle(&2).await?.initialize(btreeset! {0,1,2}).await;
+        match res {
+            Err(InitializeError::NotPristine { last_log_id }) => {
+                assert_eq!(LogId::new(1, n_logs), last_log_id);
+            }
+            _ => panic!("expect NotPristine, got: {:?}", res),
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
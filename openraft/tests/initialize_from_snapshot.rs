@@ -0,0 +1,108 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::InitializeError;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::RaftStorageDebug;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Raft::initialize_from_snapshot() test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters, write logs and take a snapshot of node 0 as a backup.
+/// - bring up another 3 pristine nodes, restore the cluster from the backup on one of them.
+/// - asserts every node converges to the backed up state, and the membership is committed after the snapshot.
+/// - asserts a restore on a node that is not pristine is refused.
+/// - asserts the restored cluster accepts writes.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn initialize_from_snapshot() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+
+    tracing::info!("--- take a backup of a cluster");
+    let (backup, backup_sm) = {
+        let router = Arc::new(RaftRouter::new(config.clone()));
+        let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+        router.client_request_many(0, "0", 50).await;
+        n_logs += 50;
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write logs").await?;
+
+        router.get_raft_handle(&0).await?.trigger_snapshot().await?;
+        router.wait_for_snapshot(&btreeset![0], LogId::new(1, n_logs), timeout(), "backup snapshot").await?;
+
+        let sto0 = router.get_storage_handle(&0).await?;
+        let backup = sto0.get_current_snapshot().await?.unwrap();
+        let sm = sto0.get_state_machine().await;
+        (backup, sm)
+    };
+
+    let backup_last = backup.meta.last_log_id;
+    assert_eq!(LogId::new(1, 53), backup_last);
+
+    tracing::info!("--- restore a new cluster from the backup");
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    {
+        router.new_raft_node(0).await;
+        router.new_raft_node(1).await;
+        router.new_raft_node(2).await;
+        router.wait_for_state(&btreeset![0, 1, 2], State::Learner, timeout(), "pristine").await?;
+
+        router.get_raft_handle(&0).await?.initialize_from_snapshot(btreeset! {0,1,2}, backup).await?;
+
+        // The membership log is the first log after the snapshot.
+        let n_logs = backup_last.index + 1;
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "restored").await?;
+
+        for id in 0..3 {
+            let sto = router.get_storage_handle(&id).await?;
+
+            let membership = sto.get_membership().await?.unwrap();
+            assert_eq!(n_logs, membership.log_id.index, "node {}", id);
+            assert!(membership.log_id.term > backup_last.term, "node {}", id);
+            assert_eq!(&btreeset! {0,1,2}, membership.membership.all_nodes());
+
+            let sm = sto.get_state_machine().await;
+            assert_eq!(backup_sm.client_status, sm.client_status, "node {}", id);
+            assert_eq!(
+                backup_sm.client_serial_responses, sm.client_serial_responses,
+                "node {}",
+                id
+            );
+        }
+    }
+
+    tracing::info!("--- restoring a non-pristine node is refused");
+    {
+        let sto = router.get_storage_handle(&1).await?;
+        let snapshot = sto.get_current_snapshot().await?.unwrap();
+        let res = router.get_raft_handle(&1).await?.initialize_from_snapshot(btreeset! {0,1,2}, snapshot).await;
+        assert!(matches!(res, Err(InitializeError::NotPristine { .. })), "{:?}", res);
+    }
+
+    tracing::info!("--- the restored cluster accepts writes");
+    {
+        let leader = router.leader().await.expect("a leader is elected");
+        let n_logs = router.get_raft_handle(&leader).await?.metrics().borrow().last_log_index;
+
+        router.client_request_many(leader, "1", 5).await;
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs + 5, timeout(), "write logs").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
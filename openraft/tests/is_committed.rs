@@ -0,0 +1,132 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::LogId;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Raft::is_committed() test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters.
+/// - isolate the leader and write a log to it, which can not be committed.
+/// - asserts the uncommitted log is not reported as committed.
+/// - wait for the other 2 nodes to elect a new leader, then restore the old leader, thus its uncommitted log is
+///   overridden by the new leader.
+/// - asserts the overridden log id is not committed, while the log id that overrides it is, on every node.
+/// - asserts a log id beyond the commit index is not committed.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn is_committed() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let committed = LogId::new(1, n_logs);
+    let uncommitted = LogId::new(1, n_logs + 1);
+
+    tracing::info!("--- committed logs are reported as committed");
+    {
+        for id in 0..3 {
+            let raft = router.get_raft_handle(&id).await?;
+            assert!(raft.is_committed(committed).await?, "node {}", id);
+            assert!(
+                !raft.is_committed(LogId::new(0, n_logs)).await?,
+                "node {}: term mismatch",
+                id
+            );
+        }
+    }
+
+    tracing::info!("--- isolate the leader and write a log that can not be committed");
+    {
+        router.isolate_node(0).await;
+
+        let raft = router.get_raft_handle(&0).await?;
+        tokio::spawn(async move {
+            let req = ClientRequest {
+                client: "0".to_string(),
+                serial: 0,
+                status: "request-0".to_string(),
+            };
+            let res = raft.client_write(ClientWriteRequest::new(req)).await;
+            tracing::info!("write to isolated leader: {:?}", res);
+        });
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|x| x.last_log_index == n_logs + 1, "uncommitted log appended")
+            .await?;
+
+        let raft = router.get_raft_handle(&0).await?;
+        assert!(!raft.is_committed(uncommitted).await?);
+        assert!(raft.is_committed(committed).await?);
+    }
+
+    tracing::info!("--- elect a new leader that overrides the uncommitted log");
+    let override_log_id = {
+        router
+            .wait(&1, timeout())
+            .await?
+            .metrics(
+                |x| x.current_term > 1 && x.current_leader.is_some(),
+                "new leader elected",
+            )
+            .await?;
+
+        let leader = router.leader().await.expect("a new leader");
+        let term = router.get_raft_handle(&leader).await?.metrics().borrow().current_term;
+
+        router.restore_node(0).await;
+        router.wait_for_state(&btreeset![0], State::Follower, timeout(), "old leader reverts").await?;
+
+        // The new leader appends a blank log at the same index as the uncommitted one.
+        let override_log_id = LogId::new(term, n_logs + 1);
+        for id in 0..3 {
+            router
+                .wait(&id, timeout())
+                .await?
+                .metrics(
+                    |x| x.committed >= Some(override_log_id) && x.last_applied >= n_logs + 1,
+                    "override log committed",
+                )
+                .await?;
+        }
+
+        override_log_id
+    };
+
+    tracing::info!("--- the overridden log is not committed, the overriding log is");
+    {
+        for id in 0..3 {
+            let raft = router.get_raft_handle(&id).await?;
+            assert!(raft.is_committed(committed).await?, "node {}", id);
+            assert!(raft.is_committed(override_log_id).await?, "node {}", id);
+            assert!(!raft.is_committed(uncommitted).await?, "node {}: overridden", id);
+            assert!(
+                !raft.is_committed(LogId::new(override_log_id.term, n_logs + 100)).await?,
+                "node {}: beyond commit index",
+                id
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
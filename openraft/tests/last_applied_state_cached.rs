@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Cached applied state test.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster.
+/// - write a lot of data to it.
+/// - assert that no node reads `last_applied_state` from storage during the writes, since the applied state is cached
+///   by raft core.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn last_applied_state_cached() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+    router.new_raft_node(1).await;
+    router.new_raft_node(2).await;
+
+    let mut n_logs = 0;
+
+    tracing::info!("--- initializing cluster");
+    {
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, None, "empty").await?;
+        router.wait_for_state(&btreeset![0, 1, 2], State::Learner, None, "empty").await?;
+
+        router.initialize_from_single_node(0).await?;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, None, "leader init log").await?;
+        router.wait_for_state(&btreeset![0], State::Leader, None, "cluster leader").await?;
+        router.wait_for_state(&btreeset![1, 2], State::Follower, None, "cluster follower").await?;
+    }
+
+    let mut calls_before = vec![];
+    for id in 0..3 {
+        let sto = router.get_storage_handle(&id).await?;
+        calls_before.push(sto.last_applied_state_calls());
+    }
+
+    tracing::info!("--- write logs");
+    {
+        router.client_request_many(0, "0", 200).await;
+        n_logs += 200;
+
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, None, "write logs").await?;
+    }
+
+    tracing::info!("--- no last_applied_state is read from storage during writes");
+    {
+        for id in 0..3 {
+            let sto = router.get_storage_handle(&id).await?;
+            let calls = sto.last_applied_state_calls() - calls_before[id as usize];
+            assert_eq!(0, calls, "node {} reads last_applied_state {} times", id, calls);
+        }
+    }
+
+    Ok(())
+}
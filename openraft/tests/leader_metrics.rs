@@ -7,10 +7,12 @@ use futures::stream::StreamExt;
 use maplit::btreeset;
 use maplit::hashmap;
 use openraft::raft::VoteRequest;
+use openraft::AddLearnerState;
 use openraft::Config;
 use openraft::LogId;
 use openraft::RaftNetwork;
 use openraft::ReplicationMetrics;
+use openraft::ReplicationState;
 use openraft::State;
 #[allow(unused_imports)]
 use pretty_assertions::assert_eq;
@@ -107,6 +109,10 @@ async fn leader_metrics() -> Result<()> {
 
     let ww = ReplicationMetrics {
         matched: LogId { term: 1, index: n_logs },
+        state: ReplicationState::Replicate,
+        snapshot_sending: None,
+        standby: false,
+        add_learner_state: AddLearnerState::Ready,
     };
     let want_repl = hashmap! { 1=>ww.clone(), 2=>ww.clone(), 3=>ww.clone(), 4=>ww.clone(), };
     router
@@ -157,6 +163,10 @@ async fn leader_metrics() -> Result<()> {
     {
         let ww = ReplicationMetrics {
             matched: LogId { term: 1, index: n_logs },
+            state: ReplicationState::Replicate,
+            snapshot_sending: None,
+            standby: false,
+            add_learner_state: AddLearnerState::Ready,
         };
         let want_repl = hashmap! { 1=>ww.clone(), 2=>ww.clone(), 3=>ww.clone()};
         router
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+/// Learner eviction test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters and a learner, with `learner_eviction_timeout` set.
+/// - isolate the learner and one of the voters, so that they never respond to the leader.
+/// - asserts the learner is removed from the leader's replication set, not before the eviction timeout.
+/// - asserts the unreachable voter is not removed.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn learner_eviction() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let learner_eviction_timeout = 500;
+
+    let config = Arc::new(
+        Config {
+            learner_eviction_timeout: Some(learner_eviction_timeout),
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {3}).await?;
+    router.wait_for_log(&btreeset![3], n_logs, timeout(), "learner caught up").await?;
+
+    tracing::info!("--- isolate the learner and a voter");
+    let start = Instant::now();
+    router.isolate_node(2).await;
+    router.isolate_node(3).await;
+
+    tracing::info!("--- the learner is evicted after the timeout");
+    {
+        let metrics = router
+            .wait(&0, timeout())
+            .await?
+            .metrics(
+                |x| match x.leader_metrics {
+                    Some(ref m) => !m.replication.contains_key(&3),
+                    None => false,
+                },
+                "learner evicted",
+            )
+            .await?;
+
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed >= Duration::from_millis(learner_eviction_timeout),
+            "evicted after timeout: {:?}",
+            elapsed
+        );
+
+        let leader_metrics = metrics.leader_metrics.unwrap();
+        assert!(
+            leader_metrics.replication.contains_key(&2),
+            "voter is never evicted: {:?}",
+            leader_metrics
+        );
+    }
+
+    tracing::info!("--- the voter is still replicated to long after the timeout");
+    {
+        tokio::time::sleep(Duration::from_millis(learner_eviction_timeout * 2)).await;
+
+        let metrics = router.get_raft_handle(&0).await?.metrics().borrow().clone();
+        let leader_metrics = metrics.leader_metrics.unwrap();
+        assert!(leader_metrics.replication.contains_key(&2));
+        assert!(!leader_metrics.replication.contains_key(&3));
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
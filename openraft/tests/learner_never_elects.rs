@@ -0,0 +1,61 @@
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Learner never elects test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of a single voter and a learner.
+/// - isolate the leader, so that the learner loses contact with it.
+/// - wait for several election timeouts, sampling metrics of the learner meanwhile.
+/// - asserts the learner stays a learner and never starts an election, i.e., its term never changes.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn learner_never_elects() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
+
+    router.wait_for_log(&btreeset![1], n_logs, timeout(), "learner caught up").await?;
+    router.wait_for_state(&btreeset![1], State::Learner, timeout(), "learner").await?;
+
+    let term = router.get_raft_handle(&1).await?.metrics().borrow().current_term;
+
+    tracing::info!("--- isolate the leader");
+    router.isolate_node(0).await;
+
+    tracing::info!("--- the learner does not elect");
+    {
+        let raft = router.get_raft_handle(&1).await?;
+        let wait = Duration::from_millis(config.election_timeout_max * 5);
+
+        let start = Instant::now();
+        while start.elapsed() < wait {
+            let metrics = raft.metrics().borrow().clone();
+
+            assert_eq!(State::Learner, metrics.state, "learner never becomes a candidate");
+            assert_eq!(term, metrics.current_term, "learner never starts an election");
+
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::RaftStorage;
+
+#[macro_use]
+mod fixtures;
+
+/// Learner resumes replication after leader change test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters and 1 learner, and replicate some logs to the learner.
+/// - isolate the learner and write more logs, so that the learner lags behind.
+/// - isolate the leader to elect a new one, and add the learner to the new leader.
+/// - asserts the learner keeps its logs, and the new leader only sends the logs the learner does not have.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn learner_resume_after_leader_change() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {3}).await?;
+
+    tracing::info!("--- replicate logs to the learner");
+    {
+        router.client_request_many(0, "0", 50).await;
+        n_logs += 50;
+
+        router.wait_for_log(&btreeset![0, 1, 2, 3], n_logs, timeout(), "replicated to learner").await?;
+    }
+
+    let learner_last = n_logs;
+
+    tracing::info!("--- isolate the learner and write more logs");
+    {
+        router.isolate_node(3).await;
+
+        router.client_request_many(0, "0", 20).await;
+        n_logs += 20;
+
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "learner lags behind").await?;
+    }
+
+    tracing::info!("--- isolate the leader and wait for a new one");
+    let new_leader = {
+        router.isolate_node(0).await;
+
+        let metrics = router
+            .wait(&1, timeout())
+            .await?
+            .metrics(
+                |x| x.current_leader.is_some() && x.current_leader != Some(0),
+                "new leader elected",
+            )
+            .await?;
+
+        // The new leader appends a blank log.
+        n_logs += 1;
+
+        metrics.current_leader.unwrap()
+    };
+
+    tracing::info!("--- add the learner to the new leader, it resumes catching up");
+    {
+        router.take_sent_entries(3);
+        router.restore_node(3).await;
+
+        router.add_learner(new_leader, 3).await?;
+        router.wait_for_log(&btreeset![3], n_logs, timeout(), "learner caught up").await?;
+
+        let sent = router.take_sent_entries(3);
+        tracing::info!(?sent, "entries sent to the learner");
+
+        assert!(!sent.is_empty());
+        assert!(
+            sent.iter().all(|index| *index > learner_last),
+            "logs the learner already has are not sent again: {:?}",
+            sent
+        );
+    }
+
+    tracing::info!("--- the learner keeps its logs");
+    {
+        let sto = router.get_storage_handle(&3).await?;
+        let logs = sto.try_get_log_entries(1..=n_logs).await?;
+        assert_eq!(n_logs as usize, logs.len());
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -4,7 +4,12 @@ mod fixtures;
 
 mod t00_learner_restart;
 mod t10_add_learner;
+mod t11_add_learners_single_step;
 mod t20_change_membership;
+mod t21_change_membership_dry_run;
+mod t22_change_membership_empty;
+mod t23_add_voter;
+mod t24_demote_voter;
 mod t25_elect_with_new_config;
 mod t30_commit_joint_config;
 mod t40_removed_follower;
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::raft::Membership;
+use openraft::Config;
+
+use crate::fixtures::RaftRouter;
+
+/// Add learners without joint consensus test.
+///
+/// What does this test do?
+///
+/// - build a single node cluster and add three learners in one call.
+/// - asserts no membership log is appended and the learners receive the logs.
+/// - change membership with the voter set unchanged, asserts a single uniform membership log is appended.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn add_learners_single_step() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    let leader = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- add three learners in one call");
+    {
+        router.new_raft_node(1).await;
+        router.new_raft_node(2).await;
+        router.new_raft_node(3).await;
+
+        let res = leader.add_learners(btreeset! {1,2,3}, true).await?;
+        assert_eq!(vec![1, 2, 3], res.keys().cloned().collect::<Vec<_>>());
+
+        let metrics = leader.metrics().borrow().clone();
+        assert_eq!(n_logs, metrics.last_log_index, "no membership log is appended");
+        assert_eq!(
+            Membership::new_single(btreeset! {0}),
+            metrics.membership_config.membership
+        );
+
+        router.wait_for_log(&btreeset![1, 2, 3], n_logs, timeout(), "learners are up to date").await?;
+    }
+
+    tracing::info!("--- change membership with unchanged voters, no joint step");
+    {
+        let resp = leader.change_membership(btreeset! {0}, true).await?;
+        n_logs += 1;
+
+        assert_eq!(n_logs, resp.log_id.index);
+        assert_eq!(Some(Membership::new_single(btreeset! {0})), resp.membership);
+
+        router.wait_for_log(&btreeset![0, 1, 2, 3], n_logs, timeout(), "a single membership log").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use maplit::btreeset;
+use openraft::raft::Membership;
+use openraft::Config;
+
+use crate::fixtures::RaftRouter;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn change_membership_dry_run_with_lagging_learner() -> anyhow::Result<()> {
+    // Dry-run a membership change with a lagging learner and a node that is not a learner.
+    // Expect the plan reports both of them, and the cluster is not changed.
+
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let lag_threshold = 1;
+
+    let config = Arc::new(
+        Config {
+            replication_lag_threshold: lag_threshold,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
+
+    tracing::info!("--- stop replication by isolating node 1");
+    {
+        router.isolate_node(1).await;
+    }
+
+    tracing::info!("--- write up to 100 logs");
+    {
+        router.client_request_many(0, "dry_run", 100 - n_logs as usize).await;
+        n_logs = 100;
+
+        router.wait(&0, timeout()).await?.log(n_logs, "received 100 logs").await?;
+    }
+
+    let metrics_before = router.get_raft_handle(&0).await?.metrics().borrow().clone();
+
+    tracing::info!("--- dry-run changing membership to {{0,1,2}}");
+    {
+        router.new_raft_node(2).await;
+
+        let raft = router.get_raft_handle(&0).await?;
+        let plan = raft.change_membership_dry_run(btreeset! {0,1,2}).await?;
+
+        tracing::info!("--- got plan: {:?}", plan);
+
+        assert_eq!(Membership::new_single(btreeset! {0}), plan.current);
+        assert_eq!(
+            vec![
+                Membership::new_multi(vec![btreeset! {0}, btreeset! {0,1,2}]),
+                Membership::new_single(btreeset! {0,1,2}),
+            ],
+            plan.steps
+        );
+        assert_eq!(btreeset! {2}, plan.unreachable);
+        assert_eq!(vec![1], plan.lagging.keys().cloned().collect::<Vec<_>>());
+        assert!(plan.lagging[&1].index < n_logs);
+        assert!(!plan.is_ready());
+    }
+
+    tracing::info!("--- the cluster is not changed");
+    {
+        let metrics = router.get_raft_handle(&0).await?.metrics().borrow().clone();
+
+        assert_eq!(n_logs, metrics.last_log_index);
+        assert_eq!(metrics_before.membership_config, metrics.membership_config);
+
+        let replication = &metrics.leader_metrics.as_ref().unwrap().replication;
+        assert!(!replication.contains_key(&2), "node 2 must not be added as learner");
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_micros(500))
+}
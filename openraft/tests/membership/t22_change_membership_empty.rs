@@ -0,0 +1,83 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use maplit::btreeset;
+use openraft::error::ChangeMembershipError;
+use openraft::error::ClientWriteError;
+use openraft::Config;
+use openraft::State;
+
+use crate::fixtures::RaftRouter;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn change_membership_to_empty() -> anyhow::Result<()> {
+    // Change membership to a config without voter.
+    // Expect it fails with `EmptyMembership`, and the cluster keeps working with the prior config.
+
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let metrics_before = router.get_raft_handle(&0).await?.metrics().borrow().clone();
+
+    tracing::info!("--- change membership to {{}}");
+    {
+        let res = router.change_membership(0, btreeset! {}).await;
+        tracing::info!("--- got: {:?}", res);
+
+        match res {
+            Err(ClientWriteError::ChangeMembershipError(ChangeMembershipError::EmptyMembership)) => {}
+            _ => {
+                panic!("expect ChangeMembershipError::EmptyMembership, got: {:?}", res);
+            }
+        }
+    }
+
+    tracing::info!("--- dry-run changing membership to {{}}");
+    {
+        let raft = router.get_raft_handle(&0).await?;
+        let res = raft.change_membership_dry_run(btreeset! {}).await;
+
+        assert!(
+            matches!(
+                res,
+                Err(ClientWriteError::ChangeMembershipError(
+                    ChangeMembershipError::EmptyMembership
+                ))
+            ),
+            "dry-run fails too: {:?}",
+            res
+        );
+    }
+
+    tracing::info!("--- the prior config is intact");
+    {
+        let metrics = router.get_raft_handle(&0).await?.metrics().borrow().clone();
+        assert_eq!(metrics_before.membership_config, metrics.membership_config);
+        assert_eq!(
+            metrics_before.last_log_index, metrics.last_log_index,
+            "no log is appended"
+        );
+        assert_eq!(State::Leader, metrics.state);
+
+        router.client_request_many(0, "0", 10).await;
+        n_logs += 10;
+
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "cluster keeps working").await?;
+
+        for id in 0..3 {
+            let m = router.get_raft_handle(&id).await?.metrics().borrow().clone();
+            assert_eq!(&btreeset! {0,1,2}, m.membership_config.membership.all_nodes());
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use maplit::btreeset;
+use openraft::error::AddVoterError;
+use openraft::Config;
+use openraft::State;
+
+use crate::fixtures::RaftRouter;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn add_voter() -> anyhow::Result<()> {
+    // Add a new node as a voter to a cluster of 3 with one call.
+    // Expect it catches up and the committed config on every node includes it, as a uniform config.
+
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- write some logs");
+    {
+        router.client_request_many(0, "add_voter", 50).await;
+        n_logs += 50;
+        router.wait_for_log(&btreeset! {0,1,2}, n_logs, timeout(), "write logs").await?;
+    }
+
+    tracing::info!("--- add node 3 as a voter");
+    {
+        router.new_raft_node(3).await;
+
+        let raft = router.get_raft_handle(&0).await?;
+        let resp = raft.add_voter(3, Duration::from_millis(5000)).await?;
+
+        let membership = resp.membership.unwrap();
+        assert!(!membership.is_joint());
+        assert_eq!(&btreeset! {0,1,2,3}, membership.all_nodes());
+
+        n_logs += 2; // the joint and the uniform membership logs
+        assert_eq!(n_logs, resp.log_id.index);
+    }
+
+    tracing::info!("--- every node has the new config committed");
+    {
+        router.wait_for_log(&btreeset! {0,1,2,3}, n_logs, timeout(), "membership logs").await?;
+
+        for id in 0..4 {
+            let metrics = router.get_raft_handle(&id).await?.metrics().borrow().clone();
+            assert!(!metrics.membership_config.membership.is_joint(), "node {}", id);
+            assert_eq!(
+                &btreeset! {0,1,2,3},
+                metrics.membership_config.membership.all_nodes(),
+                "node {}",
+                id
+            );
+        }
+
+        router.wait(&3, timeout()).await?.state(State::Follower, "node 3 becomes a voter").await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn add_voter_catch_up_timeout() -> anyhow::Result<()> {
+    // Add an unreachable node as a voter.
+    // Expect it fails with a catch-up timeout and the membership is not changed.
+
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- add an isolated node 3 as a voter");
+    {
+        router.new_raft_node(3).await;
+        router.isolate_node(3).await;
+
+        let raft = router.get_raft_handle(&0).await?;
+        let res = raft.add_voter(3, Duration::from_millis(500)).await;
+
+        match res {
+            Err(AddVoterError::CatchUpTimeout(e)) => {
+                assert_eq!(3, e.node_id);
+            }
+            _ => panic!("expect CatchUpTimeout, got: {:?}", res),
+        }
+    }
+
+    tracing::info!("--- the membership is not changed");
+    {
+        let metrics = router.get_raft_handle(&0).await?.metrics().borrow().clone();
+
+        assert_eq!(n_logs, metrics.last_log_index);
+        assert_eq!(&btreeset! {0,1,2}, metrics.membership_config.membership.all_nodes());
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
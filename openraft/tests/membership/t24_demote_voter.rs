@@ -0,0 +1,83 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use maplit::btreeset;
+use openraft::error::ChangeMembershipError;
+use openraft::error::ClientWriteError;
+use openraft::Config;
+use openraft::State;
+
+use crate::fixtures::RaftRouter;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn demote_voter() -> anyhow::Result<()> {
+    // Demote one of 3 voters to a learner.
+    // Expect the remaining 2 voters form the quorum on their own, and the demoted node still receives logs.
+
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let leader = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- the leader and a non-voter can not be demoted");
+    {
+        for id in [0, 9] {
+            let res = leader.demote_voter(id).await;
+            match res {
+                Err(ClientWriteError::ChangeMembershipError(ChangeMembershipError::CanNotDemote { node_id })) => {
+                    assert_eq!(id, node_id);
+                }
+                _ => panic!("expect CanNotDemote, got: {:?}", res),
+            }
+        }
+    }
+
+    tracing::info!("--- demote node 2");
+    {
+        let resp = leader.demote_voter(2).await?;
+
+        let membership = resp.membership.unwrap();
+        assert!(!membership.is_joint());
+        assert_eq!(&btreeset! {0,1}, membership.all_nodes());
+
+        n_logs += 2; // the joint and the uniform membership logs
+        assert_eq!(n_logs, resp.log_id.index);
+
+        router.wait_for_log(&btreeset! {0,1,2}, n_logs, timeout(), "membership logs").await?;
+        router.wait(&2, timeout()).await?.state(State::Learner, "node 2 becomes a learner").await?;
+
+        let metrics = leader.metrics().borrow().clone();
+        assert_eq!(2, metrics.voter_count);
+        assert_eq!(2, metrics.quorum_size);
+    }
+
+    tracing::info!("--- the demoted node still receives logs");
+    {
+        router.client_request_many(0, "demote_voter", 10).await;
+        n_logs += 10;
+        router.wait_for_log(&btreeset! {0,1,2}, n_logs, timeout(), "replicate to demoted node").await?;
+    }
+
+    tracing::info!("--- the remaining 2 voters commit logs without the demoted node");
+    {
+        router.isolate_node(2).await;
+
+        router.client_request_many(0, "demote_voter", 10).await;
+        n_logs += 10;
+        router.wait_for_log(&btreeset! {0,1}, n_logs, timeout(), "commit without the demoted node").await?;
+
+        router.restore_node(2).await;
+        router.wait_for_log(&btreeset! {2}, n_logs, timeout(), "demoted node catches up").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
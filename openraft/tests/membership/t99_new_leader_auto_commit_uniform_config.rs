@@ -40,6 +40,7 @@ async fn new_leader_auto_commit_uniform_config() -> Result<()> {
                 term: 1,
                 index: n_logs + 1,
             },
+            timestamp_ms: 0,
             payload: EntryPayload::Membership(Membership::new_multi(vec![btreeset! {0}, btreeset! {0,1,2}])),
         }])
         .await?;
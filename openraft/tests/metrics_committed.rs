@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftMetrics;
+
+#[macro_use]
+mod fixtures;
+
+/// Committed metrics test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters.
+/// - write logs in the background, and sample metrics of every node meanwhile.
+/// - asserts `last_applied <= committed <= last_log_index` holds in every sample.
+/// - asserts `committed` reaches the last log on every node.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn metrics_committed() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- write logs and sample metrics");
+    {
+        let n = 100;
+
+        let r = router.clone();
+        let mut handle = tokio::spawn(async move {
+            r.client_request_many(0, "0", n).await;
+        });
+
+        loop {
+            tokio::select! {
+                res = &mut handle => {
+                    res?;
+                    break;
+                }
+                _ = tokio::time::sleep(Duration::from_millis(1)) => {
+                    for (_id, m) in router.all_metrics().await {
+                        assert_watermarks(&m);
+                    }
+                }
+            }
+        }
+        n_logs += n as u64;
+    }
+
+    tracing::info!("--- committed reaches the last log");
+    {
+        for id in 0..3 {
+            let m = router
+                .wait(&id, timeout())
+                .await?
+                .metrics(
+                    |x| x.committed == Some(LogId::new(1, n_logs)) && x.last_applied == n_logs,
+                    "committed and applied",
+                )
+                .await?;
+            assert_watermarks(&m);
+        }
+    }
+
+    Ok(())
+}
+
+fn assert_watermarks(m: &RaftMetrics) {
+    match m.committed {
+        None => {
+            assert_eq!(0, m.last_applied, "{:?}", m);
+        }
+        Some(committed) => {
+            assert!(m.last_applied <= committed.index, "last_applied <= committed: {:?}", m);
+            assert!(
+                committed.index <= m.last_log_index,
+                "committed <= last_log_index: {:?}",
+                m
+            );
+        }
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
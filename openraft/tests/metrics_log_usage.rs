@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::RaftStorage;
+use tokio::time::sleep;
+
+#[macro_use]
+mod fixtures;
+
+/// Log usage metrics test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of a leader and a learner, which purges applied logs aggressively.
+/// - write logs so that applied logs are purged.
+/// - asserts `log_entry_count` equals the number of logs retained in storage, and `log_bytes` is reported.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn metrics_log_usage() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let max_keep = 3;
+
+    let config = Arc::new(
+        Config {
+            max_applied_log_to_keep: max_keep,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
+
+    tracing::info!("--- write logs to purge applied logs");
+    {
+        let count = 10 - n_logs;
+        for idx in 0..count {
+            router.client_request(0, "0", idx).await;
+            // Send slowly, otherwise logs are purged before being replicated and a snapshot is sent to the learner.
+            sleep(Duration::from_millis(50)).await;
+        }
+        n_logs = 10;
+
+        router.wait_for_log(&btreeset! {0,1}, n_logs, timeout(), "write upto 10 logs").await?;
+    }
+
+    tracing::info!("--- log_entry_count equals the number of retained logs");
+    {
+        for node_id in 0..2 {
+            let metrics = router
+                .wait(&node_id, timeout())
+                .await?
+                .metrics(|x| x.log_entry_count == max_keep, "log usage updated")
+                .await?;
+
+            let sto = router.get_storage_handle(&node_id).await?;
+            let logs = sto.try_get_log_entries(..).await?;
+
+            assert_eq!(logs.len() as u64, metrics.log_entry_count, "node {}", node_id);
+
+            let log_bytes = metrics.log_bytes.unwrap();
+            assert!(log_bytes > 0, "node {}", node_id);
+            assert_eq!(Some(log_bytes), sto.log_size_bytes().await?, "node {}", node_id);
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// RaftMetrics::voted_for test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 4 voters.
+/// - asserts the leader reports it voted for itself, and the followers, which joined as learners, did not vote.
+/// - isolate the leader and node 3, and delay every vote request between node 1 and node 2.
+/// - node 1 and node 2 become candidates that can never win a majority.
+/// - asserts the metrics reveal the split vote: node 1 voted for itself and node 2 voted for itself.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn metrics_voted_for() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2,3}, btreeset! {}).await?;
+
+    tracing::info!("--- the leader voted for itself, the followers joined without voting");
+    {
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(
+                |x| x.current_term == 1 && x.voted_for == Some(0),
+                "leader voted for itself in term 1",
+            )
+            .await?;
+
+        for id in 1..4 {
+            router
+                .wait(&id, timeout())
+                .await?
+                .metrics(
+                    |x| x.current_term == 1 && x.voted_for.is_none(),
+                    "follower did not vote in term 1",
+                )
+                .await?;
+        }
+    }
+
+    tracing::info!("--- split the vote between node 1 and node 2");
+    {
+        router.isolate_node(0).await;
+        router.isolate_node(3).await;
+
+        // Vote requests between the two candidates are never delivered during this test.
+        router.set_link_delay(1, 2, Duration::from_secs(3600));
+        router.set_link_delay(2, 1, Duration::from_secs(3600));
+
+        for id in [1, 2] {
+            let m = router
+                .wait(&id, timeout())
+                .await?
+                .metrics(
+                    |x| x.state == State::Candidate && x.current_term > 1 && x.voted_for == Some(id),
+                    "candidate voted for itself",
+                )
+                .await?;
+
+            tracing::info!("node {} term: {}, voted_for: {:?}", id, m.current_term, m.voted_for);
+        }
+
+        let m1 = router.get_raft_handle(&1).await?.metrics().borrow().clone();
+        let m2 = router.get_raft_handle(&2).await?.metrics().borrow().clone();
+        assert_ne!(m1.voted_for, m2.voted_for, "conflicting votes");
+        assert_eq!(None, m1.current_leader);
+        assert_eq!(None, m2.current_leader);
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
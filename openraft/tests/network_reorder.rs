@@ -0,0 +1,106 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorage;
+
+#[macro_use]
+mod fixtures;
+
+/// Reordered and delayed RPC test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters.
+/// - delay and reorder RPCs on every link, i.e., stale AppendEntries and RequestVote RPCs arrive after newer ones.
+/// - write logs, then force an election by isolating the leader and write logs to the new leader.
+/// - asserts every node ends up with the same logs, and nothing committed is lost.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn network_reorder() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- delay and reorder RPCs on every link");
+    for from in 0..3 {
+        for to in 0..3 {
+            if from != to {
+                router.set_link_delay(from, to, Duration::from_millis(from + to));
+                router.set_reorder(from, to, 0.3);
+            }
+        }
+    }
+
+    tracing::info!("--- write logs with reordered AppendEntries");
+    {
+        router.client_request_many(0, "0", 50).await;
+        n_logs += 50;
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write logs").await?;
+    }
+
+    tracing::info!("--- elect a new leader with reordered votes");
+    {
+        router.isolate_node(0).await;
+
+        let leader = loop {
+            if let Some(l) = router.leader().await {
+                if l != 0 {
+                    break l;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+
+        // The blank log of the new leader.
+        n_logs += 1;
+        router.wait_for_log(&btreeset![1, 2], n_logs, timeout(), "new leader").await?;
+
+        router.client_request_many(leader, "0", 50).await;
+        n_logs += 50;
+        router.wait_for_log(&btreeset![1, 2], n_logs, timeout(), "write logs to the new leader").await?;
+    }
+
+    tracing::info!("--- the old leader rejoins and the logs converge");
+    {
+        let committed = log_ids(&router, 1).await?;
+        router.restore_node(0).await;
+
+        // The old leader may start an election when it rejoins, which appends one more blank log.
+        let start = tokio::time::Instant::now();
+        loop {
+            assert!(
+                start.elapsed() < timeout().unwrap(),
+                "timeout waiting for logs to converge"
+            );
+            tokio::time::sleep(Duration::from_millis(config.heartbeat_interval * 3)).await;
+
+            let logs = log_ids(&router, 0).await?;
+            if logs == log_ids(&router, 1).await? && logs == log_ids(&router, 2).await? {
+                assert!(logs.len() >= n_logs as usize);
+                assert_eq!(&committed[..], &logs[..committed.len()], "committed logs are not lost");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the ids of the logs of a node, excluding the initial log at index 0.
+async fn log_ids(router: &Arc<RaftRouter>, id: u64) -> Result<Vec<LogId>> {
+    let sto = router.get_storage_handle(&id).await?;
+    let logs = sto.get_log_entries(1..).await?;
+    Ok(logs.iter().map(|x| x.log_id).collect())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::RaftStorage;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Node metadata restart test.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster and save node metadata in its store.
+/// - shutdown and restart the node with the same store.
+/// - asserts the metadata is read back along with the hard state.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn node_metadata_restart() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = 0;
+
+    tracing::info!("--- initializing cluster");
+    {
+        router.new_raft_node(0).await;
+
+        router.wait_for_log(&btreeset![0], n_logs, None, "empty").await?;
+        router.wait_for_state(&btreeset![0], State::Learner, None, "empty").await?;
+
+        router.initialize_from_single_node(0).await?;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![0], n_logs, None, "init leader").await?;
+    }
+
+    tracing::info!("--- save node metadata");
+    {
+        let sto = router.get_storage_handle(&0).await?;
+        sto.save_node_metadata(b"dc=east;role=primary").await?;
+    }
+
+    tracing::info!("--- restart node 0");
+    {
+        let (node, sto) = router.remove_node(0).await.ok_or_else(|| anyhow::anyhow!("node not found"))?;
+        node.shutdown().await?;
+
+        router.new_raft_node_with_sto(0, sto.clone()).await;
+        router.wait_for_state(&btreeset![0], State::Leader, None, "restarted").await?;
+    }
+
+    tracing::info!("--- metadata survives the restart along with the hard state");
+    {
+        let sto = router.get_storage_handle(&0).await?;
+
+        assert_eq!(Some(b"dc=east;role=primary".to_vec()), sto.read_node_metadata().await?);
+
+        let hs = sto.read_hard_state().await?.unwrap();
+        assert!(hs.current_term >= 1);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::State;
+use openraft::StateChangeHandler;
+
+#[macro_use]
+mod fixtures;
+
+/// Config::on_state_change test.
+///
+/// What does this test do?
+///
+/// - bring up 3 learners, node 0 with a state change handler that records every transition.
+/// - initialize the cluster on node 0, asserts the transitions of node 0 during the election end with becoming the
+///   leader.
+/// - step down node 0, asserts the next transition is from leader to follower.
+/// - asserts the recorded transitions form a chain.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn on_state_change() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let transitions = Arc::new(Mutex::new(Vec::<(State, State)>::new()));
+
+    let config = Arc::new(Config::default().validate()?);
+    let config0 = Arc::new(
+        Config {
+            on_state_change: Some(StateChangeHandler::new({
+                let transitions = transitions.clone();
+                move |old, new| transitions.lock().unwrap().push((old, new))
+            })),
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_raft_node_with_config(0, config0).await;
+    router.new_raft_node(1).await;
+    router.new_raft_node(2).await;
+    router.wait_for_state(&btreeset![0, 1, 2], State::Learner, timeout(), "learners").await?;
+
+    assert!(transitions.lock().unwrap().is_empty(), "no transition yet");
+
+    tracing::info!("--- election");
+    {
+        router.initialize_from_single_node(0).await?;
+        router.wait_for_state(&btreeset![0], State::Leader, timeout(), "node 0 becomes leader").await?;
+
+        let got = transitions.lock().unwrap().clone();
+        assert_eq!(State::Learner, got.first().unwrap().0);
+        assert_eq!(Some(&(State::Candidate, State::Leader)), got.last());
+    }
+
+    tracing::info!("--- step down");
+    {
+        let n = transitions.lock().unwrap().len();
+
+        router.get_raft_handle(&0).await?.step_down().await?;
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|x| x.state != State::Leader, "node 0 is no longer leader")
+            .await?;
+
+        let got = transitions.lock().unwrap().clone();
+        assert_eq!((State::Leader, State::Follower), got[n]);
+    }
+
+    tracing::info!("--- transitions form a chain");
+    {
+        let got = transitions.lock().unwrap().clone();
+        for pair in got.windows(2) {
+            assert_eq!(pair[0].1, pair[1].0, "{:?}", got);
+        }
+        for (old, new) in got.iter() {
+            assert_ne!(old, new);
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
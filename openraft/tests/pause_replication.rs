@@ -0,0 +1,83 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::error::PauseReplicationError;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Pause and resume replication to a follower test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters.
+/// - asserts pausing replication to an unknown node or on a non-leader is refused.
+/// - pause replication to a follower and write logs.
+/// - asserts the logs are committed by the other 2 voters, the paused follower receives none of them and does not start
+///   an election.
+/// - resume replication and asserts the follower catches up.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn pause_replication() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let r0 = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- pausing an unknown node or on a non-leader is refused");
+    {
+        let res = r0.pause_replication(9).await;
+        assert!(matches!(res, Err(PauseReplicationError::NotFound(9))), "{:?}", res);
+
+        let res = router.get_raft_handle(&1).await?.pause_replication(2).await;
+        assert!(
+            matches!(res, Err(PauseReplicationError::ForwardToLeader(ref e)) if e.leader_id == Some(0)),
+            "{:?}",
+            res
+        );
+    }
+
+    tracing::info!("--- pause replication to node 2 and write logs");
+    let n_logs_before_pause = n_logs;
+    {
+        r0.pause_replication(2).await?;
+
+        router.client_request_many(0, "0", 10).await;
+        n_logs += 10;
+        router.wait_for_log(&btreeset![0, 1], n_logs, timeout(), "logs committed without node 2").await?;
+
+        // Wait for several heartbeat intervals, longer than an election timeout.
+        tokio::time::sleep(Duration::from_millis(config.election_timeout_max * 2)).await;
+
+        let metrics = router.get_raft_handle(&2).await?.metrics().borrow().clone();
+        assert_eq!(n_logs_before_pause, metrics.last_log_index, "node 2 receives no log");
+        assert_eq!(State::Follower, metrics.state, "node 2 does not start an election");
+        assert_eq!(1, metrics.current_term);
+        assert_eq!(Some(0), metrics.current_leader);
+    }
+
+    tracing::info!("--- resume replication to node 2");
+    {
+        r0.resume_replication(2).await?;
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "node 2 catches up").await?;
+
+        router.client_request_many(0, "0", 10).await;
+        n_logs += 10;
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write logs after resume").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
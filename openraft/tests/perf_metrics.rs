@@ -0,0 +1,130 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::PerfMetrics;
+use tokio::sync::watch;
+
+#[macro_use]
+mod fixtures;
+
+/// Perf metrics test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters with `enable_tick_metrics`, and write some logs.
+/// - asserts the leader reports core loop ticks, replication RPC latency to every follower and apply durations.
+/// - asserts a follower reports core loop ticks and apply durations.
+/// - asserts all durations are plausible.
+/// - asserts nothing is reported by a cluster without `enable_tick_metrics`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn perf_metrics() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            enable_tick_metrics: true,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", 10).await;
+    n_logs += 10;
+    router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write logs").await?;
+
+    // An RPC never takes longer than its timeout, plus some scheduling delay.
+    let max_rpc = Duration::from_millis(config.heartbeat_interval * 2);
+
+    tracing::info!("--- leader reports ticks, replication RPC latency and apply durations");
+    {
+        let rx = router.get_raft_handle(&0).await?.perf_metrics();
+        let m = wait_perf(rx, |x| {
+            x.ticks > 0
+                && x.last_apply_entries > 0
+                && x.replication_rpc.contains_key(&1)
+                && x.replication_rpc.contains_key(&2)
+        })
+        .await?;
+
+        tracing::info!("leader perf metrics: {:?}", m);
+
+        assert_eq!(0, m.id);
+        assert_plausible(&m);
+        for latency in m.replication_rpc.values() {
+            assert!(*latency <= max_rpc, "rpc latency: {:?}", m);
+        }
+    }
+
+    tracing::info!("--- follower reports ticks and apply durations");
+    {
+        let rx = router.get_raft_handle(&1).await?.perf_metrics();
+        let m = wait_perf(rx, |x| x.ticks > 0 && x.last_apply_entries > 0).await?;
+
+        tracing::info!("follower perf metrics: {:?}", m);
+
+        assert_eq!(1, m.id);
+        assert_plausible(&m);
+        assert!(m.replication_rpc.is_empty(), "follower does not replicate: {:?}", m);
+    }
+
+    tracing::info!("--- nothing is reported if disabled");
+    {
+        let config = Arc::new(Config::default().validate()?);
+        let router = Arc::new(RaftRouter::new(config.clone()));
+
+        let mut n_logs = router.new_nodes_from_single(btreeset! {0,1}, btreeset! {}).await?;
+
+        router.client_request_many(0, "0", 10).await;
+        n_logs += 10;
+        router.wait_for_log(&btreeset![0, 1], n_logs, timeout(), "write logs").await?;
+
+        for id in [0, 1] {
+            let m = router.get_raft_handle(&id).await?.perf_metrics().borrow().clone();
+            assert_eq!(
+                PerfMetrics {
+                    id,
+                    ..Default::default()
+                },
+                m
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn assert_plausible(m: &PerfMetrics) {
+    assert!(m.last_tick <= m.max_tick, "last tick <= max tick: {:?}", m);
+    assert!(m.max_tick < Duration::from_secs(1), "no tick blocks the core: {:?}", m);
+    assert!(
+        m.last_apply < Duration::from_secs(1),
+        "apply is fast with memstore: {:?}",
+        m
+    );
+}
+
+async fn wait_perf(mut rx: watch::Receiver<PerfMetrics>, func: impl Fn(&PerfMetrics) -> bool) -> Result<PerfMetrics> {
+    let fu = async {
+        loop {
+            let latest = rx.borrow().clone();
+            if func(&latest) {
+                return Ok::<_, anyhow::Error>(latest);
+            }
+            rx.changed().await?;
+        }
+    };
+
+    tokio::time::timeout(timeout().unwrap(), fu).await?
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
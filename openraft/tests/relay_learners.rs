@@ -0,0 +1,177 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::error::SetRelayError;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+/// Raft::set_relay() test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 1 voter and 3 learners, and let learner 2 and 3 receive logs from learner 1.
+/// - write some logs and asserts every learner catches up.
+/// - pause replication to the relay and write more logs.
+/// - asserts the relayed learners do not receive them either, i.e., the leader does not replicate to them.
+/// - resume replication to the relay, and asserts every learner catches up.
+/// - let learner 3 receive logs from the leader again, pause the relay and write more logs.
+/// - asserts learner 3 catches up while learner 2 does not.
+/// - asserts invalid relays are rejected.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn relay_learners() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {1,2,3}).await?;
+
+    let leader = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- relay logs to learner 2 and 3 through learner 1");
+    {
+        leader.set_relay(2, Some(1)).await?;
+        leader.set_relay(3, Some(1)).await?;
+
+        router.client_request_many(0, "0", 10).await;
+        n_logs += 10;
+        router.wait_for_log(&btreeset![0, 1, 2, 3], n_logs, timeout(), "relayed logs").await?;
+    }
+
+    tracing::info!("--- relayed learners receive nothing while the relay is paused");
+    {
+        leader.pause_replication(1).await?;
+
+        router.client_request_many(0, "0", 10).await;
+        n_logs += 10;
+        router.wait_for_log(&btreeset![0], n_logs, timeout(), "write on leader").await?;
+
+        // Give a chance to replicate, if the leader still did.
+        tokio::time::sleep(Duration::from_millis(config.heartbeat_interval * 10)).await;
+        for id in 1..4 {
+            let metrics = router.get_raft_handle(&id).await?.metrics().borrow().clone();
+            assert_eq!(n_logs - 10, metrics.last_log_index, "node {} receives nothing", id);
+        }
+
+        leader.resume_replication(1).await?;
+        router.wait_for_log(&btreeset![1, 2, 3], n_logs, timeout(), "relay resumed").await?;
+    }
+
+    tracing::info!("--- learner 3 receives logs from the leader again");
+    {
+        leader.set_relay(3, None).await?;
+        leader.pause_replication(1).await?;
+
+        router.client_request_many(0, "0", 10).await;
+        n_logs += 10;
+        router.wait_for_log(&btreeset![0, 3], n_logs, timeout(), "learner 3 from leader").await?;
+
+        let metrics = router.get_raft_handle(&2).await?.metrics().borrow().clone();
+        assert_eq!(n_logs - 10, metrics.last_log_index, "learner 2 is still relayed");
+
+        leader.resume_replication(1).await?;
+        router.wait_for_log(&btreeset![1, 2], n_logs, timeout(), "relay resumed").await?;
+    }
+
+    tracing::info!("--- invalid relays are rejected");
+    {
+        let res = leader.set_relay(2, Some(0)).await;
+        match res {
+            Err(SetRelayError::NotLearner(0)) => {}
+            other => panic!("expect NotLearner, got: {:?}", other),
+        }
+
+        let res = leader.set_relay(1, Some(2)).await;
+        match res {
+            Err(SetRelayError::InvalidRelay { learner: 1, relay: 2 }) => {}
+            other => panic!("expect InvalidRelay, got: {:?}", other),
+        }
+
+        let res = leader.set_relay(9, Some(1)).await;
+        match res {
+            Err(SetRelayError::NotFound(9)) => {}
+            other => panic!("expect NotFound, got: {:?}", other),
+        }
+
+        let res = router.get_raft_handle(&1).await?.set_relay(3, Some(1)).await;
+        match res {
+            Err(SetRelayError::ForwardToLeader(e)) => assert_eq!(Some(0), e.leader_id),
+            other => panic!("expect ForwardToLeader, got: {:?}", other),
+        }
+    }
+
+    Ok(())
+}
+
+/// Relay and pause_replication() test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 1 voter and 2 learners.
+/// - pause replication to learner 2, let it receive logs from learner 1, then from the leader again.
+/// - asserts learner 2 is still paused: it receives no log until replication to it is resumed.
+/// - let learner 2 receive logs from learner 1, pause the relay and resume replication to learner 2.
+/// - asserts the leader does not send logs to learner 2 while it is relayed.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn relay_does_not_change_pause() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {1,2}).await?;
+
+    let leader = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- a paused learner stays paused after its relay is unset");
+    {
+        leader.pause_replication(2).await?;
+        leader.set_relay(2, Some(1)).await?;
+        leader.set_relay(2, None).await?;
+
+        router.client_request_many(0, "0", 10).await;
+        n_logs += 10;
+        router.wait_for_log(&btreeset![0, 1], n_logs, timeout(), "write logs").await?;
+
+        // Give a chance to replicate, if the leader still did.
+        tokio::time::sleep(Duration::from_millis(config.heartbeat_interval * 10)).await;
+        let metrics = router.get_raft_handle(&2).await?.metrics().borrow().clone();
+        assert_eq!(n_logs - 10, metrics.last_log_index, "learner 2 is still paused");
+
+        leader.resume_replication(2).await?;
+        router.wait_for_log(&btreeset![2], n_logs, timeout(), "learner 2 resumed").await?;
+    }
+
+    tracing::info!("--- the leader does not send logs to a relayed learner that is resumed");
+    {
+        leader.set_relay(2, Some(1)).await?;
+        leader.pause_replication(1).await?;
+        leader.resume_replication(2).await?;
+
+        router.client_request_many(0, "0", 10).await;
+        n_logs += 10;
+        router.wait_for_log(&btreeset![0], n_logs, timeout(), "write on leader").await?;
+
+        tokio::time::sleep(Duration::from_millis(config.heartbeat_interval * 10)).await;
+        for id in 1..3 {
+            let metrics = router.get_raft_handle(&id).await?.metrics().borrow().clone();
+            assert_eq!(n_logs - 10, metrics.last_log_index, "node {} receives nothing", id);
+        }
+
+        leader.resume_replication(1).await?;
+        router.wait_for_log(&btreeset![1, 2], n_logs, timeout(), "relay resumed").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::RaftMetrics;
+use openraft::ReplicationState;
+use openraft::State;
+use tokio::sync::watch;
+
+#[macro_use]
+mod fixtures;
+
+/// Replication state test.
+///
+/// What does this test do?
+///
+/// - build a single node cluster and write a lot of logs to it.
+/// - add a learner with empty log, which diverges a lot from the leader.
+/// - asserts the leader reports the replication to the learner as `Probe` and then `Replicate`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn replication_state_probe() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(5000));
+
+    let config = Arc::new(Config::default().validate()?);
+    // Slow network makes every probing round observable.
+    let router = Arc::new(RaftRouter::builder(config.clone()).send_delay(20).build());
+
+    let mut n_logs = 0;
+
+    tracing::info!("--- initializing cluster");
+    {
+        router.new_raft_node(0).await;
+
+        router.initialize_from_single_node(0).await?;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![0], n_logs, timeout, "init leader").await?;
+    }
+
+    tracing::info!("--- write logs");
+    {
+        router.client_request_many(0, "0", 200).await;
+        n_logs += 200;
+
+        router.wait_for_log(&btreeset![0], n_logs, timeout, "write logs").await?;
+    }
+
+    tracing::info!("--- add learner and collect replication states");
+    {
+        router.new_raft_node(1).await;
+
+        let states = tokio::spawn(collect_states(router.wait(&0, None).await?.rx, 1));
+
+        router.add_learner(0, 1).await?;
+        router.wait_for_log(&btreeset![1], n_logs, timeout, "learner catches up").await?;
+
+        let states = tokio::time::timeout(timeout.unwrap(), states).await???;
+        tracing::info!("replication states: {:?}", states);
+
+        assert_eq!(vec![ReplicationState::Probe, ReplicationState::Replicate], states);
+    }
+
+    Ok(())
+}
+
+/// Collect distinct replication states of `target` from the leader metrics until it becomes `Replicate`.
+async fn collect_states(mut rx: watch::Receiver<RaftMetrics>, target: u64) -> Result<Vec<ReplicationState>> {
+    let mut res = vec![];
+
+    loop {
+        let state = rx.borrow().leader_metrics.as_ref().and_then(|x| x.replication.get(&target)).map(|x| x.state);
+
+        if let Some(st) = state {
+            if res.last() != Some(&st) {
+                res.push(st);
+                if st == ReplicationState::Replicate {
+                    return Ok(res);
+                }
+            }
+        }
+
+        rx.changed().await?;
+    }
+}
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorage;
+
+#[macro_use]
+mod fixtures;
+
+/// Replication with multiple RPCs in flight test.
+///
+/// What does this test do?
+///
+/// - bring on a single node cluster with `replication_streams_per_follower=4` and small payloads, and write logs.
+/// - add a learner, with a random delay on every RPC so that payloads arrive out of order.
+/// - asserts the learner has exactly the logs of the leader, in order and without gap.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn replication_streams_ordered_append() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            replication_streams_per_follower: 4,
+            max_payload_entries: 5,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::builder(config.clone()).send_delay(20).build());
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", 200).await;
+    n_logs += 200;
+    router.wait_for_log(&btreeset![0], n_logs, timeout(), "write logs").await?;
+
+    tracing::info!("--- add a learner, payloads are delivered out of order");
+    {
+        router.new_raft_node(1).await;
+        router.add_learner(0, 1).await?;
+        router.wait_for_log(&btreeset![1], n_logs, timeout(), "learner catches up").await?;
+    }
+
+    tracing::info!("--- the learner appended every log in order");
+    {
+        let want = log_ids(&router, 0).await?;
+        let got = log_ids(&router, 1).await?;
+        assert_eq!(want, got);
+
+        for (i, log_id) in got.iter().enumerate() {
+            assert_eq!(i as u64 + 1, log_id.index, "no gap");
+        }
+    }
+
+    Ok(())
+}
+
+/// Replication throughput with multiple RPCs in flight test.
+///
+/// What does this test do?
+///
+/// - with a fixed latency on the link to a learner, measure how long it takes the learner to catch up with 1 and with 4
+///   RPCs in flight.
+/// - asserts it is faster with 4.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn replication_streams_throughput() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let single = catch_up_time(1).await?;
+    let multi = catch_up_time(4).await?;
+
+    tracing::info!(?single, ?multi, "time to catch up");
+    assert!(multi < single, "4 streams: {:?}, 1 stream: {:?}", multi, single);
+
+    Ok(())
+}
+
+/// Returns how long a new learner takes to receive 300 logs over a link with a 20 ms latency.
+async fn catch_up_time(streams: u64) -> Result<Duration> {
+    let config = Arc::new(
+        Config {
+            replication_streams_per_follower: streams,
+            max_payload_entries: 10,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", 300).await;
+    n_logs += 300;
+    router.wait_for_log(&btreeset![0], n_logs, timeout(), "write logs").await?;
+
+    router.set_link_delay(0, 1, Duration::from_millis(20));
+    router.new_raft_node(1).await;
+
+    let start = tokio::time::Instant::now();
+    router.add_learner(0, 1).await?;
+    router.wait_for_log(&btreeset![1], n_logs, timeout(), "learner catches up").await?;
+
+    Ok(start.elapsed())
+}
+
+/// Returns the ids of the logs of a node, excluding the initial log at index 0.
+async fn log_ids(router: &Arc<RaftRouter>, id: u64) -> Result<Vec<LogId>> {
+    let sto = router.get_storage_handle(&id).await?;
+    let logs = sto.get_log_entries(1..).await?;
+    Ok(logs.iter().map(|x| x.log_id).collect())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::ReplicationState;
+
+#[macro_use]
+mod fixtures;
+
+/// Raft::replication_table() test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters and 1 learner, and write some logs.
+/// - asserts the table on the leader has every follower and learner caught up, and `None` is returned on the others.
+/// - isolate a follower and write more logs.
+/// - asserts the table shows the isolated follower lagging behind and not contacted recently, while the others are
+///   caught up.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn replication_table() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {3}).await?;
+
+    router.client_request_many(0, "0", 10).await;
+    n_logs += 10;
+    router.wait_for_log(&btreeset![0, 1, 2, 3], n_logs, timeout(), "write logs").await?;
+
+    // Let the replication streams report their progress after the last response.
+    tokio::time::sleep(Duration::from_millis(config.heartbeat_interval * 3)).await;
+
+    tracing::info!("--- every target is caught up");
+    {
+        let table = router.get_raft_handle(&0).await?.replication_table().await.unwrap();
+        assert_eq!(btreeset! {1,2,3}, table.keys().cloned().collect());
+
+        for (id, progress) in table.iter() {
+            assert_eq!(LogId::new(1, n_logs), progress.matched, "node {}", id);
+            assert_eq!(n_logs + 1, progress.next_index, "node {}", id);
+            assert_eq!(0, progress.in_flight, "node {}", id);
+            assert_eq!(ReplicationState::Replicate, progress.state, "node {}", id);
+
+            let last_contact = progress.last_contact.unwrap();
+            assert!(
+                last_contact < Duration::from_millis(500),
+                "node {}: {:?}",
+                id,
+                last_contact
+            );
+        }
+    }
+
+    tracing::info!("--- no table on a non-leader");
+    {
+        for id in 1..4 {
+            assert!(
+                router.get_raft_handle(&id).await?.replication_table().await.is_none(),
+                "node {}",
+                id
+            );
+        }
+    }
+
+    tracing::info!("--- an isolated follower lags behind");
+    {
+        router.isolate_node(2).await;
+
+        router.client_request_many(0, "0", 10).await;
+        n_logs += 10;
+        router
+            .wait_for_log(
+                &btreeset![0, 1, 3],
+                n_logs,
+                timeout(),
+                "write logs with node 2 isolated",
+            )
+            .await?;
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let table = router.get_raft_handle(&0).await?.replication_table().await.unwrap();
+
+        let lagging = &table[&2];
+        assert!(lagging.matched.index < n_logs, "node 2 matched: {}", lagging.matched);
+        assert!(
+            lagging.next_index <= n_logs,
+            "node 2 next_index: {}",
+            lagging.next_index
+        );
+        assert!(
+            lagging.last_contact.unwrap() >= Duration::from_millis(500),
+            "node 2 last contact: {:?}",
+            lagging.last_contact
+        );
+
+        for id in [1, 3] {
+            assert_eq!(LogId::new(1, n_logs), table[&id].matched, "node {}", id);
+            assert!(
+                table[&id].last_contact.unwrap() < Duration::from_millis(500),
+                "node {}",
+                id
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
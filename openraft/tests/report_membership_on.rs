@@ -0,0 +1,163 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::Entry;
+use openraft::raft::EntryPayload;
+use openraft::raft::Membership;
+use openraft::Config;
+use openraft::LogId;
+use openraft::ReportMembershipOn;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Report membership on append test.
+///
+/// What does this test do?
+///
+/// - bring up a learner and send it a membership log that includes it, without committing it.
+/// - asserts the membership takes effect at once and the learner becomes a follower.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn report_membership_on_append() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let r0 = setup(ReportMembershipOn::Append).await?;
+
+    tracing::info!("--- append an uncommitted membership log");
+    {
+        append_membership(&r0).await?;
+
+        r0.wait(timeout()).members(btreeset! {0,1}, "membership is effective").await?;
+        r0.wait(timeout()).state(State::Follower, "becomes follower").await?;
+
+        let metrics = r0.metrics().borrow().clone();
+        assert_eq!(None, metrics.pending_membership);
+    }
+
+    tracing::info!("--- commit the membership log");
+    {
+        commit(&r0).await?;
+
+        r0.wait(timeout()).log(1, "membership log applied").await?;
+        let metrics = r0.metrics().borrow().clone();
+        assert_eq!(&btreeset! {0,1}, metrics.membership_config.membership.all_nodes());
+        assert_eq!(State::Follower, metrics.state);
+    }
+
+    Ok(())
+}
+
+/// Report membership on commit test.
+///
+/// What does this test do?
+///
+/// - bring up a learner and send it a membership log that includes it, without committing it.
+/// - asserts the membership is reported as pending, while the node already uses it and becomes a follower.
+/// - commit the membership log.
+/// - asserts the membership is reported as the membership config.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn report_membership_on_commit() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let r0 = setup(ReportMembershipOn::Commit).await?;
+
+    tracing::info!("--- append an uncommitted membership log");
+    {
+        append_membership(&r0).await?;
+
+        r0.wait(timeout()).metrics(|x| x.pending_membership.is_some(), "membership is pending").await?;
+
+        let metrics = r0.metrics().borrow().clone();
+        let pending = metrics.pending_membership.unwrap();
+        assert_eq!(LogId::new(1, 1), pending.log_id);
+        assert_eq!(&btreeset! {0,1}, pending.membership.all_nodes());
+
+        assert_eq!(
+            &btreeset! {0},
+            metrics.membership_config.membership.all_nodes(),
+            "the committed config is reported"
+        );
+        assert_eq!(State::Follower, metrics.state, "the latest config in the log is in use");
+    }
+
+    tracing::info!("--- commit the membership log");
+    {
+        commit(&r0).await?;
+
+        r0.wait(timeout()).members(btreeset! {0,1}, "membership is reported").await?;
+
+        let metrics = r0.metrics().borrow().clone();
+        assert_eq!(None, metrics.pending_membership);
+        assert_eq!(State::Follower, metrics.state);
+    }
+
+    Ok(())
+}
+
+/// Bring up a learner that does not start an election during a test, and remove it from the router to feed it
+/// append-entries requests directly.
+async fn setup(report_membership_on: ReportMembershipOn) -> Result<fixtures::MemRaft> {
+    let config = Arc::new(
+        Config {
+            election_timeout_min: 10_000,
+            election_timeout_max: 10_001,
+            report_membership_on,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+
+    router.wait_for_log(&btreeset![0], 0, timeout(), "empty").await?;
+    router.wait_for_state(&btreeset![0], State::Learner, timeout(), "empty").await?;
+
+    let (r0, _sto0) = router.remove_node(0).await.unwrap();
+    Ok(r0)
+}
+
+async fn append_membership(r0: &fixtures::MemRaft) -> Result<()> {
+    let req = AppendEntriesRequest {
+        term: 1,
+        leader_id: 1,
+        prev_log_id: LogId::new(0, 0),
+        entries: vec![Entry {
+            log_id: LogId::new(1, 1),
+            timestamp_ms: 0,
+            payload: EntryPayload::Membership(Membership::new_single(btreeset! {0,1})),
+        }],
+        leader_commit: LogId::new(0, 0),
+        relay_to: vec![],
+    };
+
+    let resp = r0.append_entries(req).await?;
+    assert!(resp.success());
+    Ok(())
+}
+
+/// Send a heartbeat that commits the membership log.
+async fn commit(r0: &fixtures::MemRaft) -> Result<()> {
+    let req = AppendEntriesRequest {
+        term: 1,
+        leader_id: 1,
+        prev_log_id: LogId::new(1, 1),
+        entries: vec![],
+        leader_commit: LogId::new(1, 1),
+        relay_to: vec![],
+    };
+
+    let resp = r0.append_entries(req).await?;
+    assert!(resp.success());
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::EntryPayload;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::RewriteLogError;
+use openraft::SnapshotPolicy;
+
+#[macro_use]
+mod fixtures;
+
+/// Rewrite the payload of a log test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters, write logs until every node builds a snapshot, then write a few more.
+/// - asserts rewriting a log that is not applied, not in the snapshot, not found or not a normal log is refused.
+/// - add an isolated learner, asserts the leader refuses to rewrite a log the learner has not replicated.
+/// - restore the learner, rewrite an applied log in the snapshot on every node.
+/// - asserts the payload is redacted while the log id is preserved.
+/// - asserts replication goes on: new logs are appended on every node after the rewritten one.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn rewrite_log_entry() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold: u64 = 20;
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", (snapshot_threshold - n_logs) as usize).await;
+    n_logs = snapshot_threshold;
+    router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write logs").await?;
+    router.wait_for_snapshot(&btreeset![0, 1, 2], LogId::new(1, n_logs), timeout(), "snapshot").await?;
+
+    router.client_request_many(0, "0", 5).await;
+    n_logs += 5;
+    router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write logs after snapshot").await?;
+
+    let target = LogId::new(1, snapshot_threshold - 2);
+    let redacted = ClientRequest {
+        client: "0".to_string(),
+        serial: 0,
+        status: "redacted".to_string(),
+    };
+
+    tracing::info!("--- refuse to rewrite a log that is not safe to rewrite");
+    {
+        let r0 = router.get_raft_handle(&0).await?;
+
+        let res = r0.rewrite_log_entry(LogId::new(1, n_logs + 1), redacted.clone()).await;
+        assert!(matches!(res, Err(RewriteLogError::NotApplied { .. })), "{:?}", res);
+
+        let res = r0.rewrite_log_entry(LogId::new(1, n_logs), redacted.clone()).await;
+        assert!(matches!(res, Err(RewriteLogError::NotInSnapshot { .. })), "{:?}", res);
+
+        let res = r0.rewrite_log_entry(LogId::new(2, target.index), redacted.clone()).await;
+        assert!(matches!(res, Err(RewriteLogError::NotFound(_))), "{:?}", res);
+
+        let res = r0.rewrite_log_entry(LogId::new(1, 1), redacted.clone()).await;
+        assert!(matches!(res, Err(RewriteLogError::NotNormal(_))), "{:?}", res);
+    }
+
+    tracing::info!("--- a leader refuses to rewrite a log not replicated to every target");
+    {
+        router.new_raft_node(3).await;
+        router.isolate_node(3).await;
+
+        let r0 = router.get_raft_handle(&0).await?;
+        r0.add_learner(3, false).await?;
+
+        let res = r0.rewrite_log_entry(target, redacted.clone()).await;
+        match res {
+            Err(RewriteLogError::NotReplicated { log_id, target: 3, .. }) => {
+                assert_eq!(target, log_id);
+            }
+            _ => panic!("expect NotReplicated to node 3, got: {:?}", res),
+        }
+
+        router.restore_node(3).await;
+        router.wait_for_log(&btreeset![3], n_logs, timeout(), "learner catches up").await?;
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(
+                |x| match x.leader_metrics {
+                    Some(ref m) => m.replication.get(&3).map(|r| r.matched >= target).unwrap_or(false),
+                    None => false,
+                },
+                "leader sees the learner replicated the log",
+            )
+            .await?;
+    }
+
+    tracing::info!("--- rewrite a log on every node, the log id is preserved");
+    {
+        for id in 0..3 {
+            let sto = router.get_storage_handle(&id).await?;
+            let before = sto.try_get_log_entry(target.index).await?.unwrap();
+            assert!(matches!(before.payload, EntryPayload::Normal(ref req) if req.status != "redacted"));
+
+            router.get_raft_handle(&id).await?.rewrite_log_entry(target, redacted.clone()).await?;
+
+            let after = sto.try_get_log_entry(target.index).await?.unwrap();
+            assert_eq!(target, after.log_id, "node {}", id);
+            assert!(
+                matches!(after.payload, EntryPayload::Normal(ref req) if req.status == "redacted"),
+                "node {}",
+                id
+            );
+
+            assert_eq!(LogId::new(1, n_logs), sto.last_id_in_log().await?, "node {}", id);
+        }
+    }
+
+    tracing::info!("--- replication goes on after the rewrite");
+    {
+        router.client_request_many(0, "0", 5).await;
+        n_logs += 5;
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write logs after rewrite").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::RunningState;
+use openraft::ShutdownReason;
+use openraft::State;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// RaftMetrics::running_state test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 1 voter and 3 learners.
+/// - asserts every node is running.
+/// - make applying logs fail with a transient error on learner 1, and panic on learner 2, then write a log.
+/// - asserts learner 1 shuts down with a transient storage fault, and learner 2 with the panic message.
+/// - asserts the panic is propagated to `Raft::shutdown()`.
+/// - shut down learner 3.
+/// - asserts it shuts down as requested.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn shutdown_reason() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {1,2,3}).await?;
+
+    tracing::info!("--- every node is running");
+    {
+        for id in 0..4 {
+            let metrics = router.get_raft_handle(&id).await?.metrics().borrow().clone();
+            assert_eq!(RunningState::Running, metrics.running_state, "node {}", id);
+        }
+    }
+
+    tracing::info!("--- a storage fault and a panic");
+    {
+        router.get_storage_handle(&1).await?.inner().set_apply_faults(10);
+        router.get_storage_handle(&2).await?.inner().set_apply_panic(true);
+
+        router.client_request_many(0, "0", 1).await;
+        n_logs += 1;
+        router.wait_for_log(&btreeset![0, 3], n_logs, timeout(), "write a log").await?;
+
+        for id in 1..3 {
+            router.wait(&id, timeout()).await?.state(State::Shutdown, "shut down by a fault").await?;
+        }
+
+        let metrics = router.get_raft_handle(&1).await?.metrics().borrow().clone();
+        match metrics.running_state {
+            RunningState::Shutdown(ShutdownReason::StorageFault(fault)) => {
+                assert!(fault.transient, "injected fault is transient: {:?}", fault);
+            }
+            other => panic!("expect StorageFault, got: {:?}", other),
+        }
+
+        let raft2 = router.get_raft_handle(&2).await?;
+        let metrics = raft2.metrics().borrow().clone();
+        match metrics.running_state {
+            RunningState::Shutdown(ShutdownReason::Panic(msg)) => {
+                assert!(msg.contains("injected apply panic"), "panic message: {}", msg);
+            }
+            other => panic!("expect Panic, got: {:?}", other),
+        }
+
+        assert!(raft2.shutdown().await.is_err(), "the panic is propagated");
+    }
+
+    tracing::info!("--- a requested shutdown");
+    {
+        let raft3 = router.get_raft_handle(&3).await?;
+        raft3.shutdown().await?;
+
+        let metrics = raft3.metrics().borrow().clone();
+        assert_eq!(State::Shutdown, metrics.state);
+        assert_eq!(RunningState::Shutdown(ShutdownReason::Requested), metrics.running_state);
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,84 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::Config;
+use openraft::LogId;
+use tokio::time::Instant;
+
+#[macro_use]
+mod fixtures;
+
+/// Single voter commits on append test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 1 voter and 1 learner, and delay every RPC to the learner.
+/// - write logs one by one.
+/// - asserts every write returns long before an RPC to the learner could be delivered, i.e., the write latency is
+///   `append_to_log` plus `apply`, with no commit round.
+/// - asserts the log is committed and applied on the leader without waiting for the learner.
+/// - asserts the learner still receives every log.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn single_voter_fast_commit() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
+
+    let rpc_delay = Duration::from_millis(1_000);
+    router.set_link_delay(0, 1, rpc_delay);
+
+    tracing::info!("--- every write commits without waiting for any RPC");
+    {
+        let n = 50;
+        let start = Instant::now();
+
+        for i in 0..n {
+            let req = ClientRequest {
+                client: "0".to_string(),
+                serial: i,
+                status: format!("request-{}", i),
+            };
+
+            let t = Instant::now();
+            let resp = router.send_client_request(0, req).await?;
+            let latency = t.elapsed();
+
+            n_logs += 1;
+            assert_eq!(LogId::new(1, n_logs), resp.log_id);
+            assert!(latency < rpc_delay / 2, "write {} took {:?}", i, latency);
+
+            // Metrics are reported asynchronously, but never wait for an RPC.
+            router
+                .wait(&0, Some(rpc_delay / 2))
+                .await?
+                .metrics(
+                    |x| x.committed == Some(LogId::new(1, n_logs)) && x.last_applied == n_logs,
+                    "committed and applied without replication",
+                )
+                .await?;
+        }
+
+        let elapsed = start.elapsed();
+        tracing::info!("{} writes took {:?}", n, elapsed);
+        assert!(elapsed < rpc_delay, "{} writes took {:?}", n, elapsed);
+    }
+
+    tracing::info!("--- the learner receives every log");
+    {
+        router.wait_for_log(&btreeset![1], n_logs, timeout(), "learner catches up").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
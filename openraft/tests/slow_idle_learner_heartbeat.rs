@@ -0,0 +1,72 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+/// Config::slow_idle_learner_heartbeat test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 1 voter and 1 learner, and a cluster of 2 voters, with `slow_idle_learner_heartbeat`
+///   enabled.
+/// - leave both clusters idle for a while.
+/// - asserts the learner receives far fewer heartbeats than the follower, which is sent one every heartbeat interval.
+/// - write a log to the cluster with the learner.
+/// - asserts the learner receives and applies it without waiting for an idle heartbeat.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn slow_idle_learner_heartbeat() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            slow_idle_learner_heartbeat: true,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let single = Arc::new(RaftRouter::new(config.clone()));
+    let mut n_logs = single.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
+
+    let multi = Arc::new(RaftRouter::new(config.clone()));
+    multi.new_nodes_from_single(btreeset! {0,1}, btreeset! {}).await?;
+
+    tracing::info!("--- leave both clusters idle");
+    {
+        // Let the replication streams settle down after the initial replication.
+        tokio::time::sleep(Duration::from_millis(config.heartbeat_interval * 3)).await;
+        single.take_sent_heartbeats(1);
+        multi.take_sent_heartbeats(1);
+
+        let idle = Duration::from_millis(config.heartbeat_interval * 20);
+        tokio::time::sleep(idle).await;
+
+        let to_learner = single.take_sent_heartbeats(1);
+        let to_follower = multi.take_sent_heartbeats(1);
+        tracing::info!(to_learner, to_follower, "heartbeats sent in {:?}", idle);
+
+        // About 2 at the idle cadence.
+        assert!(to_learner <= 4, "heartbeats to an idle learner: {}", to_learner);
+        // About 20 at the normal cadence.
+        assert!(to_follower >= 15, "heartbeats to an idle follower: {}", to_follower);
+    }
+
+    tracing::info!("--- a write is delivered to the idle learner at once");
+    {
+        single.client_request_many(0, "0", 1).await;
+        n_logs += 1;
+
+        // The committed log id is sent without waiting for the next idle heartbeat.
+        let within = Some(Duration::from_millis(config.heartbeat_interval * 4));
+        single.wait_for_log(&btreeset![0, 1], n_logs, within, "learner applies the write").await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::SnapshotPolicy;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// Building a snapshot concurrently with applying logs test.
+///
+/// What does this test do?
+///
+/// - build a single node cluster whose store takes a long time to build a snapshot.
+/// - send just enough logs to trigger a snapshot.
+/// - keep writing while the snapshot is being built, asserts every write is applied without waiting for the build.
+/// - asserts the snapshot is installed once it is built, at the log it is triggered.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_build_concurrent_apply() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold: u64 = 50;
+    let build_delay = Duration::from_millis(2000);
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    let sto = router.get_storage_handle(&0).await?;
+    sto.inner().set_snapshot_build_delay(build_delay);
+
+    tracing::info!("--- send just enough logs to trigger snapshot");
+    {
+        router.client_request_many(0, "0", (snapshot_threshold - n_logs) as usize).await;
+        n_logs = snapshot_threshold;
+
+        router.wait_for_log(&btreeset![0], n_logs, timeout(), "send log to trigger snapshot").await?;
+    }
+
+    tracing::info!("--- write while the snapshot is being built");
+    let start = Instant::now();
+    {
+        for i in 0..20 {
+            let write_start = Instant::now();
+            router.client_request(0, "foreground", i).await;
+            let latency = write_start.elapsed();
+
+            assert!(
+                latency < Duration::from_millis(500),
+                "apply stalls while building snapshot: {:?}",
+                latency
+            );
+        }
+        n_logs += 20;
+
+        router.wait_for_log(&btreeset![0], n_logs, timeout(), "foreground writes").await?;
+
+        let metrics = router.get_raft_handle(&0).await?.metrics().borrow().clone();
+        assert!(
+            start.elapsed() < build_delay,
+            "the writes are done before the snapshot is built"
+        );
+        assert_eq!(LogId::default(), metrics.snapshot, "the snapshot is not built yet");
+    }
+
+    tracing::info!("--- the snapshot is installed once it is built");
+    {
+        router
+            .wait_for_snapshot(
+                &btreeset![0],
+                LogId {
+                    term: 1,
+                    index: snapshot_threshold,
+                },
+                timeout(),
+                "snapshot on node 0",
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,94 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::MemStoreStateMachine;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::RaftStorageDebug;
+use openraft::SnapshotPolicy;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// Build a snapshot from a checkpoint test.
+///
+/// What does this test do?
+///
+/// - build a single node cluster whose store takes a long time to build a snapshot.
+/// - send just enough logs to trigger a snapshot, which is built from a checkpoint of the state machine.
+/// - keep writing while the snapshot is being built.
+/// - asserts the snapshot reflects exactly the state at the checkpoint: none of the writes after it is in the snapshot,
+///   while the live state machine has all of them.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_from_checkpoint() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold: u64 = 50;
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    let sto = router.get_storage_handle(&0).await?;
+    sto.inner().set_snapshot_build_delay(Duration::from_millis(1_000));
+
+    tracing::info!("--- send just enough logs to trigger snapshot");
+    {
+        router.client_request_many(0, "0", (snapshot_threshold - n_logs) as usize).await;
+        n_logs = snapshot_threshold;
+
+        router.wait_for_log(&btreeset![0], n_logs, timeout(), "send log to trigger snapshot").await?;
+    }
+
+    tracing::info!("--- write while the snapshot is being built from the checkpoint");
+    {
+        router.client_request_many(0, "foreground", 20).await;
+        n_logs += 20;
+
+        router.wait_for_log(&btreeset![0], n_logs, timeout(), "foreground writes").await?;
+    }
+
+    tracing::info!("--- the snapshot reflects exactly the checkpoint");
+    {
+        let want = LogId::new(1, snapshot_threshold);
+        router.wait_for_snapshot(&btreeset![0], want, timeout(), "snapshot on node 0").await?;
+
+        let snapshot = sto.get_current_snapshot().await?.unwrap();
+        assert_eq!(want, snapshot.meta.last_log_id);
+
+        let snapshot_sm: MemStoreStateMachine = serde_json::from_slice(snapshot.snapshot.get_ref())?;
+        assert_eq!(want, snapshot_sm.last_applied_log);
+        assert_eq!(
+            Some(&format!("request-{}", snapshot_threshold - 2)),
+            snapshot_sm.client_status.get("0")
+        );
+        assert_eq!(
+            None,
+            snapshot_sm.client_status.get("foreground"),
+            "writes after the checkpoint are not in the snapshot"
+        );
+
+        let live_sm = sto.get_state_machine().await;
+        assert_eq!(LogId::new(1, n_logs), live_sm.last_applied_log);
+        assert_eq!(Some(&"request-19".to_string()), live_sm.client_status.get("foreground"));
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
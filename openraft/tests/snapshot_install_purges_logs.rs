@@ -0,0 +1,104 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::SnapshotPolicy;
+
+#[macro_use]
+mod fixtures;
+
+/// Installing a snapshot purges logs test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of a leader and a learner. The learner keeps many applied logs, by `max_applied_log_to_keep`.
+/// - write some logs that the learner receives, then isolate the learner.
+/// - write more logs on the leader until it builds a snapshot and purges its logs, then restore the learner, thus the
+///   leader sends its snapshot to it.
+/// - asserts the logs on the learner that are subsumed by the installed snapshot are purged.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_install_purges_logs() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold: u64 = 10;
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            max_applied_log_to_keep: 2,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let learner_config = Arc::new(
+        Config {
+            max_applied_log_to_keep: 1000,
+            ..config.as_ref().clone()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- add a learner that keeps applied logs");
+    {
+        router.new_raft_node_with_config(1, learner_config).await;
+        router.add_learner(0, 1).await?;
+
+        router.client_request_many(0, "0", 5).await;
+        n_logs += 5;
+
+        router.wait_for_log(&btreeset![0, 1], n_logs, timeout(), "learner receives logs").await?;
+
+        let sto = router.get_storage_handle(&1).await?;
+        let logs = sto.try_get_log_entries(..=n_logs).await?;
+        assert_eq!(
+            n_logs as usize,
+            logs.len(),
+            "learner has logs below the snapshot to install"
+        );
+    }
+
+    tracing::info!("--- isolate the learner and write logs until the leader builds a snapshot");
+    {
+        router.isolate_node(1).await;
+
+        router.client_request_many(0, "0", (snapshot_threshold * 3 - n_logs) as usize).await;
+        n_logs = snapshot_threshold * 3;
+
+        router.wait_for_log(&btreeset![0], n_logs, timeout(), "leader writes").await?;
+        router.wait_for_snapshot(&btreeset![0], LogId::new(1, n_logs), timeout(), "leader snapshot").await?;
+    }
+
+    tracing::info!("--- restore the learner, it installs the snapshot from the leader");
+    {
+        router.restore_node(1).await;
+
+        router
+            .wait_for_snapshot(&btreeset![1], LogId::new(1, n_logs), timeout(), "install snapshot")
+            .await?;
+        router.wait_for_log(&btreeset![1], n_logs, timeout(), "learner caught up").await?;
+    }
+
+    tracing::info!("--- logs subsumed by the snapshot are purged");
+    {
+        let sto = router.get_storage_handle(&1).await?;
+
+        let logs = sto.try_get_log_entries(..=n_logs).await?;
+        assert!(logs.is_empty(), "logs upto the snapshot are purged: {:?}", logs);
+        assert_eq!(None, sto.first_id_in_log().await?);
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::SnapshotPolicy;
+
+#[macro_use]
+mod fixtures;
+
+/// List snapshots after installing a snapshot test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of a leader and a learner, and write logs until both of them build a snapshot.
+/// - isolate the learner and write more logs, so that the learner lags behind and needs a newer snapshot.
+/// - restore the learner, thus the leader sends its snapshot to it.
+/// - asserts only the installed snapshot is left in the learner's store.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_list_after_install() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold: u64 = 10;
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            max_applied_log_to_keep: 2,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
+
+    tracing::info!("--- write logs until both nodes build a snapshot");
+    {
+        router.client_request_many(0, "0", (snapshot_threshold - n_logs) as usize).await;
+        n_logs = snapshot_threshold;
+
+        router.wait_for_log(&btreeset![0, 1], n_logs, timeout(), "write upto threshold").await?;
+        router
+            .wait_for_snapshot(&btreeset![0, 1], LogId::new(1, n_logs), timeout(), "build snapshot")
+            .await?;
+
+        let sto = router.get_storage_handle(&1).await?;
+        let snapshots = sto.list_snapshots().await?;
+        assert_eq!(1, snapshots.len());
+        assert_eq!(LogId::new(1, n_logs), snapshots[0].last_log_id);
+    }
+
+    tracing::info!("--- isolate the learner and write more logs");
+    {
+        router.isolate_node(1).await;
+
+        router.client_request_many(0, "0", (snapshot_threshold * 2) as usize).await;
+        n_logs += snapshot_threshold * 2;
+
+        router.wait_for_log(&btreeset![0], n_logs, timeout(), "learner lags behind").await?;
+        router.wait_for_snapshot(&btreeset![0], LogId::new(1, n_logs), timeout(), "leader snapshot").await?;
+    }
+
+    tracing::info!("--- restore the learner, it installs the snapshot from the leader");
+    {
+        router.restore_node(1).await;
+
+        router
+            .wait_for_snapshot(&btreeset![1], LogId::new(1, n_logs), timeout(), "install snapshot")
+            .await?;
+        router.wait_for_log(&btreeset![1], n_logs, timeout(), "learner caught up").await?;
+    }
+
+    tracing::info!("--- only the installed snapshot is left");
+    {
+        let sto = router.get_storage_handle(&1).await?;
+
+        let snapshots = sto.list_snapshots().await?;
+        assert_eq!(1, snapshots.len(), "old snapshots are deleted: {:?}", snapshots);
+
+        let current = sto.get_current_snapshot().await?.unwrap();
+        assert_eq!(current.meta, snapshots[0]);
+        assert_eq!(LogId::new(1, n_logs), snapshots[0].last_log_id);
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -93,9 +93,11 @@ async fn snapshot_overrides_membership() -> Result<()> {
                 prev_log_id: LogId::new(0, 0),
                 entries: vec![Entry {
                     log_id: LogId { term: 1, index: 1 },
+                    timestamp_ms: 0,
                     payload: EntryPayload::Membership(Membership::new_single(btreeset! {2,3})),
                 }],
                 leader_commit: LogId::new(0, 0),
+                relay_to: vec![],
             };
             router.send_append_entries(1, req).await?;
 
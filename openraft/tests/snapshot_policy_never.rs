@@ -0,0 +1,98 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::SnapshotPolicy;
+
+#[macro_use]
+mod fixtures;
+
+/// Snapshot policy never test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 1 voter with `SnapshotPolicy::Never` and write a lot of logs.
+/// - asserts no snapshot is built and no log is purged.
+/// - trigger a snapshot explicitly.
+/// - asserts the snapshot is built and logs included in it are purged after more logs are applied.
+/// - add a learner, asserts it receives the snapshot and the logs after it.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_policy_never() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let max_keep: u64 = 10;
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::Never,
+            max_applied_log_to_keep: max_keep,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- sustained writes build no snapshot");
+    {
+        router.client_request_many(0, "0", 200).await;
+        n_logs += 200;
+        router.wait_for_log(&btreeset![0], n_logs, timeout(), "write logs").await?;
+
+        // Give a compaction, if any, a chance to start.
+        tokio::time::sleep(Duration::from_millis(config.heartbeat_interval * 3)).await;
+
+        let metrics = router.get_raft_handle(&0).await?.metrics().borrow().clone();
+        assert_eq!(LogId::none(), metrics.snapshot);
+
+        let sto = router.get_storage_handle(&0).await?;
+        assert!(sto.get_current_snapshot().await?.is_none());
+        assert!(sto.try_get_log_entry(1).await?.is_some(), "no log is purged");
+    }
+
+    tracing::info!("--- trigger a snapshot explicitly");
+    {
+        router.get_raft_handle(&0).await?.trigger_snapshot().await?;
+        router.wait_for_snapshot(&btreeset![0], LogId::new(1, n_logs), timeout(), "snapshot").await?;
+
+        router.client_request_many(0, "0", 5).await;
+        n_logs += 5;
+        router.wait_for_log(&btreeset![0], n_logs, timeout(), "write logs after snapshot").await?;
+
+        let sto = router.get_storage_handle(&0).await?;
+        let logs = sto.get_log_entries(..).await?;
+        assert!(
+            logs.len() as u64 <= max_keep,
+            "logs in the snapshot are purged: {}",
+            logs.len()
+        );
+        assert!(sto.try_get_log_entry(1).await?.is_none());
+    }
+
+    tracing::info!("--- a new learner receives the snapshot");
+    {
+        router.new_raft_node(1).await;
+        router.add_learner(0, 1).await.expect("failed to add new node as learner");
+
+        router.wait_for_log(&btreeset![1], n_logs, timeout(), "learner catches up").await?;
+
+        let sto1 = router.get_storage_handle(&1).await?;
+        assert!(
+            sto1.get_current_snapshot().await?.is_some(),
+            "learner installed the snapshot"
+        );
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
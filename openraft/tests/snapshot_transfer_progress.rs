@@ -0,0 +1,150 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::metrics::SnapshotProgress;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftMetrics;
+use openraft::SnapshotPolicy;
+use openraft::State;
+use tokio::sync::watch;
+
+#[macro_use]
+mod fixtures;
+
+/// Snapshot transfer progress test.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster.
+/// - send enough requests to the node that log compaction will be triggered.
+/// - add learner and collect the snapshot progress reported by both the leader and the learner.
+/// - asserts the progress is monotonic and ends at 100%.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_transfer_progress() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold: u64 = 10;
+    let timeout = Some(Duration::from_millis(5000));
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            snapshot_max_chunk_size: 10,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = 0;
+
+    tracing::info!("--- initializing cluster");
+    {
+        router.new_raft_node(0).await;
+
+        router.wait_for_log(&btreeset![0], n_logs, None, "empty").await?;
+        router.wait_for_state(&btreeset![0], State::Learner, None, "empty").await?;
+
+        router.initialize_from_single_node(0).await?;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![0], n_logs, None, "init leader").await?;
+    }
+
+    tracing::info!("--- send just enough logs to trigger snapshot");
+    {
+        router.client_request_many(0, "0", (snapshot_threshold - n_logs) as usize).await;
+        n_logs = snapshot_threshold;
+
+        router.wait_for_log(&btreeset![0], n_logs, None, "send log to trigger snapshot").await?;
+        router.wait_for_snapshot(&btreeset![0], LogId { term: 1, index: n_logs }, None, "snapshot").await?;
+    }
+
+    tracing::info!("--- add learner and collect snapshot progress on both ends");
+    {
+        router.new_raft_node(1).await;
+
+        let sending = tokio::spawn(collect_progress(router.wait(&0, None).await?.rx, |m| {
+            m.leader_metrics
+                .as_ref()
+                .and_then(|x| x.replication.get(&1))
+                .and_then(|x| x.snapshot_sending.clone())
+        }));
+        let receiving = tokio::spawn(collect_progress(router.wait(&1, None).await?.rx, |m| {
+            m.snapshot_receiving.clone()
+        }));
+
+        router.add_learner(0, 1).await.expect("failed to add new node as learner");
+        router.wait_for_snapshot(&btreeset![1], LogId { term: 1, index: n_logs }, timeout, "").await?;
+
+        let sending = tokio::time::timeout(timeout.unwrap(), sending).await???;
+        let receiving = tokio::time::timeout(timeout.unwrap(), receiving).await???;
+
+        tracing::info!("sending progress: {:?}", sending);
+        tracing::info!("receiving progress: {:?}", receiving);
+
+        assert_monotonic(&sending);
+        assert_monotonic(&receiving);
+
+        let total = sending.last().unwrap().total;
+        assert!(total.is_some());
+        assert!(
+            sending.iter().all(|x| x.total == total),
+            "sender always knows the total size"
+        );
+
+        assert_eq!(total, receiving.last().unwrap().total);
+        assert!(
+            receiving[..receiving.len() - 1].iter().all(|x| x.total.is_none()),
+            "receiver knows the total size only when done"
+        );
+    }
+
+    Ok(())
+}
+
+/// Collect distinct snapshot progress from a metrics channel until the transfer is done.
+async fn collect_progress<F>(mut rx: watch::Receiver<RaftMetrics>, get: F) -> Result<Vec<SnapshotProgress>>
+where F: Fn(&RaftMetrics) -> Option<SnapshotProgress> {
+    let mut res: Vec<SnapshotProgress> = vec![];
+
+    loop {
+        let progress = get(&rx.borrow());
+
+        if let Some(p) = progress {
+            if res.last() != Some(&p) {
+                let done = p.is_done();
+                res.push(p);
+                if done {
+                    return Ok(res);
+                }
+            }
+        }
+
+        rx.changed().await?;
+    }
+}
+
+fn assert_monotonic(progress: &[SnapshotProgress]) {
+    assert!(!progress.is_empty());
+
+    let snapshot_id = &progress[0].meta.snapshot_id;
+    assert!(progress.iter().all(|x| &x.meta.snapshot_id == snapshot_id));
+
+    for w in progress.windows(2) {
+        assert!(
+            w[0].transferred <= w[1].transferred,
+            "progress must be monotonic: {:?}",
+            progress
+        );
+    }
+
+    let last = progress.last().unwrap();
+    assert!(last.is_done());
+    assert_eq!(Some(100), last.percent());
+}
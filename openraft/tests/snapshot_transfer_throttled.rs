@@ -0,0 +1,129 @@
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::SnapshotPolicy;
+
+#[macro_use]
+mod fixtures;
+
+/// Throttled snapshot transfer test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 2 voters and 1 learner, with snapshot transfer throttled.
+/// - isolate the learner and send enough logs to trigger snapshot on the leader.
+/// - restore the learner, thus the leader starts to send a snapshot to it.
+/// - send client writes during the snapshot transfer and assert the write latency stays bounded.
+/// - assert the transfer is throttled and no node ever starts an election.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_transfer_throttled() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold: u64 = 100;
+    let bytes_per_sec: u64 = 2000;
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            snapshot_transfer_bytes_per_sec: Some(bytes_per_sec),
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1}, btreeset! {2}).await?;
+
+    tracing::info!("--- isolate node 2 and send just enough logs to trigger snapshot");
+    {
+        router.isolate_node(2).await;
+
+        // Use distinct clients to build a snapshot large enough to take a while to transfer.
+        for i in n_logs..snapshot_threshold {
+            router.client_request(0, &format!("client-{}", i), 0).await;
+        }
+        n_logs = snapshot_threshold;
+
+        router.wait_for_log(&btreeset![0, 1], n_logs, timeout(), "send log to trigger snapshot").await?;
+        router
+            .wait_for_snapshot(
+                &btreeset![0],
+                LogId { term: 1, index: n_logs },
+                timeout(),
+                "snapshot on node 0",
+            )
+            .await?;
+    }
+
+    tracing::info!("--- restore node 2 and write during the snapshot transfer");
+    let start = Instant::now();
+    {
+        router.restore_node(2).await;
+
+        for i in 0..20 {
+            let write_start = Instant::now();
+            router.client_request(0, "foreground", i).await;
+            let latency = write_start.elapsed();
+
+            assert!(
+                latency < Duration::from_millis(500),
+                "write latency is not bounded: {:?}",
+                latency
+            );
+        }
+        n_logs += 20;
+
+        router.wait_for_log(&btreeset![0, 1], n_logs, timeout(), "foreground writes").await?;
+    }
+
+    tracing::info!("--- snapshot transfer is throttled");
+    {
+        router
+            .wait_for_snapshot(
+                &btreeset![2],
+                LogId {
+                    term: 1,
+                    index: snapshot_threshold,
+                },
+                timeout(),
+                "snapshot on node 2",
+            )
+            .await?;
+        let elapsed = start.elapsed();
+
+        let metrics = router.get_raft_handle(&0).await?.metrics().borrow().clone();
+        let sending = metrics.leader_metrics.as_ref().unwrap().replication[&2].snapshot_sending.clone().unwrap();
+        let total = sending.total.unwrap();
+
+        tracing::info!(total, ?elapsed, "snapshot transferred");
+
+        let expected = Duration::from_secs_f64(total as f64 / bytes_per_sec as f64);
+        assert!(
+            elapsed >= expected / 2,
+            "transfer of {} bytes is too fast: {:?}",
+            total,
+            elapsed
+        );
+    }
+
+    tracing::info!("--- no election is started during the transfer");
+    {
+        for id in 0..3 {
+            let metrics = router.get_raft_handle(&id).await?.metrics().borrow().clone();
+            assert_eq!(1, metrics.current_term, "node {}", id);
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(10_000))
+}
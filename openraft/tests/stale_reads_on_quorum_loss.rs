@@ -0,0 +1,114 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::ClientWriteError;
+use openraft::Config;
+use openraft::LogId;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Quorum-loss read-only mode test.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster with `allow_stale_reads_on_quorum_loss` set.
+/// - isolate the leader so that it can not reach a quorum.
+/// - asserts linearizable reads fail, stale reads are served and flagged as stale, and writes are refused.
+/// - restore the old leader, asserts it leaves the read-only mode.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn stale_reads_on_quorum_loss() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            allow_stale_reads_on_quorum_loss: true,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.client_request_many(0, "foo", 5).await;
+    n_logs += 5;
+    router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write 5 logs").await?;
+
+    let leader = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- with a quorum, a stale read is not flagged");
+    {
+        let resp = leader.stale_read().await?;
+        assert!(!resp.is_stale);
+        assert_eq!(LogId::new(1, n_logs), resp.last_applied);
+        assert!(!leader.metrics().borrow().is_stale);
+    }
+
+    tracing::info!("--- isolate the leader");
+    {
+        router.isolate_node(0).await;
+    }
+
+    tracing::info!("--- linearizable reads fail and the leader enters the read-only mode");
+    {
+        let res = leader.client_read().await;
+        assert!(res.is_err(), "client_read must not be served without a quorum");
+
+        router.wait(&0, timeout()).await?.metrics(|x| x.is_stale, "is_stale").await?;
+    }
+
+    tracing::info!("--- stale reads are served and flagged");
+    {
+        let resp = leader.stale_read().await?;
+        assert!(resp.is_stale);
+        assert_eq!(1, resp.term);
+        assert_eq!(LogId::new(1, n_logs), resp.last_applied);
+    }
+
+    tracing::info!("--- writes are refused");
+    {
+        let res = leader
+            .client_write(ClientWriteRequest::new(ClientRequest {
+                client: "foo".to_string(),
+                serial: 100,
+                status: "refused".to_string(),
+            }))
+            .await;
+
+        match res {
+            Err(ClientWriteError::QuorumLost(e)) => {
+                assert_eq!(0, e.node_id);
+            }
+            _ => panic!("expect QuorumLost, got: {:?}", res),
+        }
+        assert_eq!(n_logs, leader.metrics().borrow().last_log_index);
+    }
+
+    tracing::info!("--- restore the old leader, it steps down and leaves the read-only mode");
+    {
+        router.restore_node(0).await;
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(
+                |x| x.state != State::Leader && !x.is_stale,
+                "stepped down and left read-only mode",
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::ClientWriteError;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Quorum-loss read-only mode is left on replication acks.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster with `allow_stale_reads_on_quorum_loss` set, and an election timeout long enough
+///   that the followers do not elect another leader during the test.
+/// - isolate the leader, let a read fail so that it enters the read-only mode, and asserts writes are refused.
+/// - restore the leader without any read.
+/// - asserts the leader leaves the read-only mode and accepts writes again.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn stale_writes_resume_on_quorum() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            allow_stale_reads_on_quorum_loss: true,
+            election_timeout_min: 10_000,
+            election_timeout_max: 10_001,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let leader = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- isolate the leader, it enters the read-only mode");
+    {
+        router.isolate_node(0).await;
+
+        let res = leader.client_read().await;
+        assert!(res.is_err(), "client_read must not be served without a quorum");
+
+        router.wait(&0, timeout()).await?.metrics(|x| x.is_stale, "is_stale").await?;
+
+        let res = leader.client_write(ClientWriteRequest::new(req(100))).await;
+        match res {
+            Err(ClientWriteError::QuorumLost(_)) => {}
+            _ => panic!("expect QuorumLost, got: {:?}", res),
+        }
+    }
+
+    tracing::info!("--- restore the leader, it leaves the read-only mode without a read");
+    {
+        router.restore_node(0).await;
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|x| x.state == State::Leader && !x.is_stale, "left read-only mode")
+            .await?;
+    }
+
+    tracing::info!("--- writes succeed again");
+    {
+        leader.client_write(ClientWriteRequest::new(req(101))).await?;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write after quorum is back").await?;
+    }
+
+    Ok(())
+}
+
+fn req(serial: u64) -> ClientRequest {
+    ClientRequest {
+        client: "foo".to_string(),
+        serial,
+        status: format!("request-{}", serial),
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
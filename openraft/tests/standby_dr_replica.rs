@@ -0,0 +1,136 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::Config;
+use openraft::RaftMetrics;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Standby replica test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters, and add node 3 as a standby.
+/// - asserts the standby applies every log, and the leader reports it as a standby.
+/// - isolate 2 voters, and write a log.
+/// - asserts the standby receives the log but the log is not committed, i.e., the standby never counts toward a quorum.
+/// - restore the voters, then simulate a regional failure by isolating voter 2 for good.
+/// - promote the standby to a voter in place of voter 2, and asserts it is no longer reported as a standby and does
+///   count toward a quorum.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn standby_dr_replica() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Isolated voters must not start an election before they are restored.
+    let config = Arc::new(
+        Config {
+            election_timeout_min: 2000,
+            election_timeout_max: 3000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- add node 3 as a standby");
+    {
+        router.new_raft_node(3).await;
+        router.add_standby(0, 3).await?;
+
+        router.client_request_many(0, "0", 10).await;
+        n_logs += 10;
+
+        router.wait_for_log(&btreeset![0, 1, 2, 3], n_logs, timeout(), "standby applies logs").await?;
+        router.wait_for_state(&btreeset![3], State::Learner, timeout(), "standby is a learner").await?;
+
+        let m = router.wait(&0, timeout()).await?.metrics(|x| is_standby(x, 3), "reported as standby").await?;
+        assert_eq!(3, m.voter_count);
+        assert!(!is_standby(&m, 1));
+    }
+
+    tracing::info!("--- the standby never counts toward a quorum");
+    {
+        router.isolate_node(1).await;
+        router.isolate_node(2).await;
+
+        let r = router.clone();
+        let handle = tokio::spawn(async move { r.send_client_request(0, req(100)).await });
+
+        router
+            .wait(&3, timeout())
+            .await?
+            .metrics(|x| x.last_log_index == n_logs + 1, "standby receives the log")
+            .await?;
+
+        tokio::time::sleep(Duration::from_millis(config.heartbeat_interval * 5)).await;
+
+        let m = router.get_raft_handle(&0).await?.metrics().borrow().clone();
+        assert_eq!(n_logs, m.last_applied, "not committed without a quorum of voters");
+
+        let m = router.get_raft_handle(&3).await?.metrics().borrow().clone();
+        assert_eq!(n_logs, m.last_applied, "standby does not apply an uncommitted log");
+
+        router.restore_node(1).await;
+        router.restore_node(2).await;
+
+        handle.await??;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![0, 1, 2, 3], n_logs, timeout(), "committed with voters").await?;
+    }
+
+    tracing::info!("--- region of node 2 fails, promote the standby");
+    {
+        router.isolate_node(2).await;
+
+        router.change_membership(0, btreeset! {0,1,3}).await?;
+        n_logs += 2;
+
+        router.wait_for_log(&btreeset![0, 1, 3], n_logs, timeout(), "standby promoted").await?;
+        router.wait_for_state(&btreeset![3], State::Follower, timeout(), "standby becomes follower").await?;
+
+        let m = router.wait(&0, timeout()).await?.metrics(|x| !is_standby(x, 3), "no longer standby").await?;
+        assert_eq!(&btreeset! {0,1,3}, m.membership_config.membership.all_nodes());
+    }
+
+    tracing::info!("--- the promoted node counts toward a quorum");
+    {
+        router.isolate_node(1).await;
+
+        router.client_request(0, "0", 200).await;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![0, 3], n_logs, timeout(), "committed with the promoted node").await?;
+    }
+
+    Ok(())
+}
+
+fn is_standby(m: &RaftMetrics, target: u64) -> bool {
+    m.leader_metrics
+        .as_ref()
+        .and_then(|x| x.replication.get(&target))
+        .map(|x| x.standby)
+        .unwrap_or(false)
+}
+
+fn req(serial: u64) -> ClientRequest {
+    ClientRequest {
+        client: "0".to_string(),
+        serial,
+        status: format!("request-{}", serial),
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,126 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::Entry;
+use openraft::raft::EntryPayload;
+use openraft::raft::Membership;
+use openraft::storage::HardState;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::State;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// Startup replay progress test.
+///
+/// What does this test do?
+///
+/// - pre-seed the store of a single node cluster with a large log that is not yet applied, and slow down applying.
+/// - start the node, it becomes the leader and replays the log in batches.
+/// - asserts the replay progress in metrics advances monotonically, in more than one step, to completion.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn startup_replay_progress() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let n_logs = 1000;
+    let batch_size = 100;
+
+    let config = Arc::new(
+        Config {
+            max_payload_entries: batch_size,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    tracing::info!("--- pre-seed the store with {} logs", n_logs);
+    let sto = {
+        let sto = router.new_store(0).await;
+
+        let mut entries = vec![Entry {
+            log_id: LogId::new(1, 1),
+            timestamp_ms: 0,
+            payload: EntryPayload::Membership(Membership::new_single(btreeset! {0})),
+        }];
+        for index in 2..=n_logs {
+            entries.push(Entry {
+                log_id: LogId::new(1, index),
+                timestamp_ms: 0,
+                payload: EntryPayload::Normal(ClientRequest {
+                    client: "0".to_string(),
+                    serial: index,
+                    status: format!("request-{}", index),
+                }),
+            });
+        }
+
+        sto.append_to_log(&entries.iter().collect::<Vec<_>>()).await?;
+        sto.save_hard_state(&HardState {
+            current_term: 1,
+            voted_for: Some(0),
+        })
+        .await?;
+
+        sto.inner().set_apply_delay(Duration::from_millis(20));
+        sto
+    };
+
+    tracing::info!("--- start the node and watch the replay progress");
+    {
+        router.new_raft_node_with_sto(0, sto.clone()).await;
+
+        let mut rx = router.get_raft_handle(&0).await?.metrics();
+        let mut progress = vec![];
+
+        let fu = async {
+            loop {
+                let m = rx.borrow().clone();
+                if m.startup_replay_target > 0 && progress.last() != Some(&m.startup_replay_applied) {
+                    assert_eq!(n_logs, m.startup_replay_target);
+                    progress.push(m.startup_replay_applied);
+                }
+
+                if m.startup_replay_target > 0 && m.startup_replay_applied == m.startup_replay_target {
+                    return Ok::<_, anyhow::Error>(());
+                }
+                rx.changed().await?;
+            }
+        };
+        tokio::time::timeout(timeout().unwrap(), fu).await??;
+
+        tracing::info!("replay progress: {:?}", progress);
+
+        assert!(
+            progress.len() > 2,
+            "progress is reported in several steps: {:?}",
+            progress
+        );
+        assert!(
+            progress.windows(2).all(|w| w[0] < w[1]),
+            "progress advances monotonically: {:?}",
+            progress
+        );
+        assert_eq!(Some(&n_logs), progress.last());
+    }
+
+    tracing::info!("--- the node serves after replay");
+    {
+        router.wait_for_state(&btreeset![0], State::Leader, timeout(), "leader").await?;
+        router.wait_for_log(&btreeset![0], n_logs + 1, timeout(), "replayed and blank log applied").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,111 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// State machine checksum test.
+///
+/// What does this test do?
+///
+/// - bring on a cluster of 3 voters and write some logs.
+/// - asserts every node reports the same state machine checksum at the same applied log id, in storage, by
+///   `Raft::state_checksum()` and in metrics.
+/// - asserts the checksum is not recomputed in metrics when more logs are applied.
+/// - change the state machine of one node without a log, i.e., make it diverge.
+/// - asserts the checksum of the diverged node differs from the others.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn state_checksum() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", 10).await;
+    n_logs += 10;
+    router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write logs").await?;
+
+    let log_id = LogId::new(1, n_logs);
+
+    tracing::info!("--- replicas have the same checksum");
+    let checksum = {
+        let sto = router.get_storage_handle(&0).await?;
+        let checksum = sto.state_checksum_at(log_id).await?;
+        assert!(checksum.is_some());
+
+        assert_eq!(
+            None,
+            sto.state_checksum_at(LogId::new(1, n_logs - 1)).await?,
+            "no checksum at a log id other than the last applied"
+        );
+
+        for id in 0..3 {
+            let sto = router.get_storage_handle(&id).await?;
+            assert_eq!(checksum, sto.state_checksum_at(log_id).await?, "node {}", id);
+
+            let raft = router.get_raft_handle(&id).await?;
+            let got = raft.state_checksum().await?.unwrap();
+            assert_eq!(log_id, got.log_id);
+            assert_eq!(checksum, Some(got.checksum), "node {}", id);
+
+            let metrics = raft.metrics().borrow().clone();
+            assert_eq!(Some(got), metrics.state_checksum, "checksum in metrics, node {}", id);
+        }
+
+        checksum
+    };
+
+    tracing::info!("--- the checksum is computed only on demand");
+    {
+        router.client_request_many(0, "0", 1).await;
+        n_logs += 1;
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write one more log").await?;
+
+        let raft = router.get_raft_handle(&0).await?;
+        let metrics = raft.metrics().borrow().clone();
+        assert_eq!(
+            Some(log_id),
+            metrics.state_checksum.map(|c| c.log_id),
+            "not recomputed on apply"
+        );
+
+        let got = raft.state_checksum().await?.unwrap();
+        assert_eq!(LogId::new(1, n_logs), got.log_id);
+        assert_ne!(checksum, Some(got.checksum));
+    }
+
+    tracing::info!("--- a diverged replica has a different checksum");
+    {
+        let sto = router.get_storage_handle(&2).await?;
+        sto.inner().set_client_status("0", "diverged").await;
+
+        let log_id = LogId::new(1, n_logs);
+        let checksum = router.get_storage_handle(&0).await?.state_checksum_at(log_id).await?;
+
+        let diverged = sto.state_checksum_at(log_id).await?;
+        assert!(diverged.is_some());
+        assert_ne!(checksum, diverged);
+
+        for id in 0..2 {
+            let sto = router.get_storage_handle(&id).await?;
+            assert_eq!(checksum, sto.state_checksum_at(log_id).await?, "node {}", id);
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
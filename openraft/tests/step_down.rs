@@ -0,0 +1,85 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::error::ClientWriteError;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Leader step down test.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster.
+/// - asserts `step_down()` on a follower is rejected with `ForwardToLeader`.
+/// - call `step_down()` on the leader, asserts it reverts to follower.
+/// - asserts a new leader, possibly the same node, is elected in a greater term and accepts writes.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn step_down() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- step down on a follower is rejected");
+    {
+        let res = router.get_raft_handle(&1).await?.step_down().await;
+        match res {
+            Err(ClientWriteError::ForwardToLeader(e)) => {
+                assert_eq!(Some(0), e.leader_id);
+            }
+            _ => panic!("expect ForwardToLeader, got: {:?}", res),
+        }
+    }
+
+    tracing::info!("--- step down on the leader");
+    {
+        router.get_raft_handle(&0).await?.step_down().await?;
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|x| x.state != State::Leader, "node 0 is no longer leader")
+            .await?;
+    }
+
+    tracing::info!("--- a new leader is elected in a greater term");
+    {
+        for id in 0..3 {
+            router
+                .wait(&id, timeout())
+                .await?
+                .metrics(
+                    |x| x.current_term > 1 && x.current_leader.is_some(),
+                    "new leader elected",
+                )
+                .await?;
+        }
+
+        let leader = router.leader().await.expect("a leader is elected");
+        tracing::info!("--- new leader: {}", leader);
+
+        router.client_request_many(leader, "foo", 5).await;
+
+        // Every new leader appends a blank log, there may be more than one election.
+        let metrics = router.get_raft_handle(&leader).await?.metrics().borrow().clone();
+        assert!(metrics.last_log_index >= n_logs + 1 + 5);
+        n_logs = metrics.last_log_index;
+
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write to the new leader").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(3000))
+}
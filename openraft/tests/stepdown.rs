@@ -102,7 +102,7 @@ async fn stepdown() -> Result<()> {
             metrics.last_applied
         );
         assert_eq!(
-            cfg.get_configs().clone(),
+            cfg.get_configs().to_vec(),
             vec![btreeset![1, 2, 3]],
             "expected old leader to have membership of [1, 2, 3], got {:?}",
             cfg.get_configs()
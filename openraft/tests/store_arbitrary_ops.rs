@@ -0,0 +1,44 @@
+#![cfg(feature = "test-utils")]
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use anyhow::Result;
+use fixtures::store_ops::check_arbitrary_store_ops;
+use fixtures::RaftRouter;
+use openraft::Config;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+
+#[macro_use]
+mod fixtures;
+
+/// Arbitrary store operations test.
+///
+/// What does this test do?
+///
+/// - build sequences of valid log and state machine operations from seeded random bytes.
+/// - run each of them against a new store and asserts the store matches the expected logs and last applied log id after
+///   every operation.
+///
+/// It requires feature `test-utils`: `cargo test -p openraft --features test-utils`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn store_arbitrary_ops() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    for seed in 0..100 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut data = vec![0u8; 1024];
+        rng.fill(&mut data[..]);
+
+        let sto = router.new_store(seed).await;
+        check_arbitrary_store_ops(sto.as_ref(), &data).await.with_context(|| format!("seed: {}", seed))?;
+    }
+
+    Ok(())
+}
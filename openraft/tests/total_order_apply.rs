@@ -5,6 +5,7 @@ use anyhow::Result;
 use fixtures::RaftRouter;
 use maplit::btreeset;
 use openraft::Config;
+use openraft::LogId;
 use openraft::RaftStorage;
 use openraft::State;
 use tokio::sync::watch;
@@ -63,14 +64,17 @@ async fn total_order_apply() -> Result<()> {
     h.await?;
 
     let want = n as u64;
-    router
-        .wait_for_metrics(
-            &1u64,
-            |x| x.last_applied >= want,
-            timeout(),
-            &format!("n{}.last_applied -> {}", 1, want),
-        )
-        .await?;
+    router.get_raft_handle(&1).await?.await_applied(LogId::new(1, want), timeout().unwrap()).await?;
+
+    tracing::info!("--- all logs are applied on every node");
+    {
+        // The initial membership log plus the client logs.
+        let want = n as u64 + 1;
+        router.wait_for_log(&btreeset![0, 1], want, timeout(), "all logs applied").await?;
+
+        assert_eq!(0, router.assert_single_leader().await);
+        router.assert_logs_converged(Some(want)).await;
+    }
 
     Ok(())
 }